@@ -18,6 +18,7 @@ complete -c flake-edit -n "__fish_seen_subcommand_from rm" -f -a "(__fish_comple
 complete -c flake-edit -n "__fish_seen_subcommand_from remove" -f -a "(__fish_complete_inputs)" -d Input
 complete -c flake-edit -n "__fish_seen_subcommand_from change" -f -a "(__fish_complete_inputs)" -d Input
 complete -c flake-edit -n "__fish_seen_subcommand_from c" -f -a "(__fish_complete_inputs)" -d Input
+complete -c flake-edit -n "__fish_seen_subcommand_from rename" -f -a "(__fish_complete_inputs_toplevel)" -d Input
 complete -c flake-edit -n "__fish_seen_subcommand_from pin" -f -a "(__fish_complete_inputs_toplevel)" -d Input
 complete -c flake-edit -n "__fish_seen_subcommand_from p" -f -a "(__fish_complete_inputs_toplevel)" -d Input
 complete -c flake-edit -n "__fish_seen_subcommand_from unpin" -f -a "(__fish_complete_inputs_toplevel)" -d Input
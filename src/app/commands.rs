@@ -1,12 +1,13 @@
 use ropey::Rope;
 
 use crate::change::Change;
+use crate::cli::OutputFormat;
 use crate::edit::{FlakeEdit, InputMap};
 use crate::error::Error as FlakeError;
 use crate::forge::update::Updater;
+use crate::input::Input;
 use crate::lock::FlakeLock;
 use crate::tui;
-use crate::validate;
 
 use super::editor::Editor;
 use super::error::{Error, Result};
@@ -15,35 +16,57 @@ use super::state::AppState;
 mod add;
 mod change;
 mod config;
+mod copy;
+mod doctor;
 pub mod follow;
+mod lint;
 pub mod list;
 mod pin;
+mod prune;
 mod remove;
+mod rename;
+mod status;
 mod toggle;
 mod update;
 mod uri;
+mod validate;
+mod watch;
 
-pub use add::add;
+pub use add::{AddOptions, add};
 pub use change::change;
 pub use config::config;
-pub use list::list;
+pub use copy::copy;
+pub use doctor::doctor;
+pub use lint::lint;
+pub use list::{list, list_changed_since, list_many};
 pub use pin::{pin, unpin};
+pub use prune::prune;
 pub use remove::remove;
-pub use toggle::toggle;
-pub use update::update;
+pub use rename::rename;
+pub use status::status;
+pub use toggle::{store_override, toggle};
+pub use update::{update, update_to_ref};
 pub use uri::UriOptions;
+pub use validate::validate;
+pub use watch::watch;
 
-pub(super) fn updater(editor: &Editor, inputs: InputMap) -> Updater {
-    Updater::new(Rope::from_str(&editor.text()), inputs)
+pub(super) fn updater(editor: &Editor, state: &AppState, inputs: InputMap) -> Updater {
+    Updater::new(editor.rope().clone(), inputs).with_quiet(state.quiet_success || state.dry_run)
 }
 
-/// Load `flake.lock`, using the path from `state` if provided.
-pub(super) fn load_flake_lock(state: &AppState) -> std::result::Result<FlakeLock, FlakeError> {
-    if let Some(lock_path) = &state.lock_file {
-        FlakeLock::from_file(lock_path)
-    } else {
-        FlakeLock::from_default_path()
+/// Where `input` is declared in `rope`, the text it was read from.
+/// `None` for inputs without a recorded range, such as follows-only ones.
+pub(super) fn input_location(input: &Input, rope: &Rope) -> Option<crate::validate::Location> {
+    if input.range.is_empty() {
+        return None;
     }
+    let (line, column) = input.range.line_col(rope);
+    Some(crate::validate::Location { line, column })
+}
+
+/// Load `flake.lock` from [`AppState::lock_path`].
+pub(super) fn load_flake_lock(state: &AppState) -> std::result::Result<FlakeLock, FlakeError> {
+    FlakeLock::from_file(state.lock_path())
 }
 
 /// Outcome of [`confirm_or_apply`].
@@ -115,13 +138,15 @@ pub(super) fn interactive_multi_select<F>(
     title: &str,
     prompt: &str,
     items: Vec<String>,
+    selection: tui::InitialSelection,
     make_change: F,
 ) -> Result<()>
 where
     F: Fn(&[String]) -> String,
 {
     loop {
-        let select_app = tui::App::select_many(title, prompt, items.clone(), state.diff);
+        let select_app = tui::App::select_many(title, prompt, items.clone(), state.diff)
+            .with_initial_selection(selection);
         let Some(tui::AppResult::MultiSelect(result)) = tui::run(select_app)? else {
             return Ok(());
         };
@@ -152,7 +177,9 @@ fn confirm_or_apply(
     show_diff: bool,
 ) -> Result<ConfirmResult> {
     if show_diff || state.diff {
-        let diff = crate::diff::Diff::new(&editor.text(), change).to_string_plain();
+        let diff = crate::diff::Diff::new(&editor.text(), change)
+            .with_context(state.diff_context)
+            .to_string_plain();
         let confirm_app = tui::App::confirm(context, &diff);
         let Some(tui::AppResult::Confirm(action)) = tui::run(confirm_app)? else {
             return Ok(ConfirmResult::Cancelled);
@@ -179,47 +206,48 @@ pub(super) fn apply_change(
     state: &AppState,
     change: Change,
 ) -> Result<()> {
-    let original_content = flake_edit.source_text();
     let outcome = flake_edit.apply_change(change.clone())?;
-    let resulting_change = match outcome.text {
-        Some(t) => t,
-        None => {
-            if change.is_remove() {
-                let id = change
-                    .id()
-                    .expect("bug: Change::Remove always carries an id");
-                return Err(Error::CouldNotRemove { id });
-            }
-            if change.is_follows() {
-                let id = change.id().map(|id| id.to_string()).unwrap_or_default();
-                return Err(Error::FollowsCreateFailed { id });
-            }
-            println!("Nothing changed.");
+    let Some(resulting_change) = outcome.text else {
+        // The edit reproduced the current text: skip the write (and the
+        // relock that would follow it).
+        if outcome.unchanged {
+            let message = match change.id().filter(|_| change.is_follows()) {
+                Some(id) => {
+                    let follows_str = id
+                        .follows()
+                        .map(|s| s.render())
+                        .unwrap_or_else(|| "?".to_string());
+                    let target_str = change
+                        .follows_target()
+                        .map(|t| t.to_flake_follows_string())
+                        .unwrap_or_else(|| "?".to_string());
+                    format!(
+                        "Already follows: {}.inputs.{}.follows = \"{}\"",
+                        id.input().render(),
+                        follows_str,
+                        target_str,
+                    )
+                }
+                None => "Nothing changed.".to_string(),
+            };
+            report_unchanged(state, Some(&change), &message);
             return Ok(());
         }
-    };
-
-    if change.is_follows() && resulting_change == original_content {
-        if let Some(id) = change.id() {
-            let follows_str = id
-                .follows()
-                .map(|s| s.render())
-                .unwrap_or_else(|| "?".to_string());
-            let target_str = change
-                .follows_target()
-                .map(|t| t.to_string())
-                .unwrap_or_else(|| "?".to_string());
-            println!(
-                "Already follows: {}.inputs.{}.follows = \"{}\"",
-                id.input().render(),
-                follows_str,
-                target_str,
-            );
+        if change.is_remove() {
+            let id = change
+                .id()
+                .expect("bug: Change::Remove always carries an id");
+            return Err(Error::CouldNotRemove { id });
+        }
+        if change.is_follows() {
+            let id = change.id().map(|id| id.to_string()).unwrap_or_default();
+            return Err(Error::FollowsCreateFailed { id });
         }
+        report_unchanged(state, Some(&change), "Nothing changed.");
         return Ok(());
-    }
+    };
 
-    let validation = validate::validate(&resulting_change);
+    let validation = crate::validate::validate(&resulting_change);
     if validation.has_errors() {
         for e in &validation.errors {
             tracing::error!("validation error: {e}");
@@ -229,7 +257,7 @@ pub(super) fn apply_change(
 
     editor.apply_or_diff(&resulting_change, state)?;
 
-    if !state.diff {
+    if state.writes() {
         // Cache added entries for future completions.
         if let Change::Add {
             id: Some(id),
@@ -237,21 +265,97 @@ pub(super) fn apply_change(
             ..
         } = &change
         {
-            let mut cache = crate::cache::Cache::load();
+            let mut cache = crate::cache::Cache::load().scoped(state.cache_namespace());
             cache.add_entry(id.to_string(), uri.clone());
             if let Err(e) = cache.commit() {
                 tracing::debug!("Could not write to cache: {}", e);
             }
         }
 
-        for msg in change.success_messages() {
-            println!("{}", msg);
-        }
+        report_applied(state, &change);
     }
 
     Ok(())
 }
 
+/// Report a written `change`: its success messages as prose, or a
+/// `{"status": "applied"}` object with `--output json`. Nothing with
+/// `--quiet-success`.
+pub(super) fn report_applied(state: &AppState, change: &Change) {
+    if state.quiet_success {
+        return;
+    }
+    match state.output {
+        OutputFormat::Text => {
+            for msg in change.success_messages() {
+                println!("{}", msg);
+            }
+        }
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::json!({ "status": "applied", "change": change })
+        ),
+    }
+}
+
+/// Report that flake.nix was left as it was: `message` as prose, or a
+/// `{"status": "unchanged"}` object naming `change` with `--output json`.
+/// Nothing with `--quiet-success`.
+pub(super) fn report_unchanged(state: &AppState, change: Option<&Change>, message: &str) {
+    if state.quiet_success {
+        return;
+    }
+    match state.output {
+        OutputFormat::Text => println!("{message}"),
+        OutputFormat::Json => match change {
+            Some(change) => println!(
+                "{}",
+                serde_json::json!({ "status": "unchanged", "change": change })
+            ),
+            None => println!("{}", serde_json::json!({ "status": "unchanged" })),
+        },
+    }
+}
+
+/// Apply `changes` one after another and write the result once.
+///
+/// Each change sees the text the previous one produced. Changes that
+/// leave the text alone are dropped; the rest are returned after each is
+/// reported with [`report_applied`]. With none left, nothing is written.
+pub(super) fn apply_in_sequence(
+    editor: &Editor,
+    state: &AppState,
+    changes: Vec<Change>,
+) -> Result<Vec<Change>> {
+    let mut text = editor.text();
+    let mut applied = Vec::new();
+    for change in changes {
+        let mut step = FlakeEdit::from_text(&text)?;
+        if let Some(next) = step.apply_change(change.clone())?.text {
+            text = next;
+            applied.push(change);
+        }
+    }
+
+    if applied.is_empty() {
+        report_unchanged(state, None, "Nothing changed.");
+        return Ok(applied);
+    }
+
+    let validation = crate::validate::validate(&text);
+    if validation.has_errors() {
+        return Err(Error::ValidationAfterEdit(validation.errors));
+    }
+    editor.apply_or_diff(&text, state)?;
+
+    if state.writes() {
+        for change in &applied {
+            report_applied(state, change);
+        }
+    }
+    Ok(applied)
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
@@ -3,17 +3,56 @@
 //! Three branches: scripted (id + uri), interactive TUI (with
 //! optional prefill), and infer-id (uri only, ID derived from the
 //! parsed [`FlakeRef`]).
+//!
+//! `--input-prefix` is prepended to the id in every branch. An id that
+//! already exists is an error unless `--replace-if-exists` is given, in
+//! which case the add becomes a url change. `--json-change` prints the
+//! resulting [`Change`] instead of applying it.
+//!
+//! A new input is a flake unless `--no-flake` is given, its url carries
+//! `?flake=false` (written as the attribute instead), or the `[add]`
+//! configuration says otherwise for its url and `--as-flake` is not.
+//!
+//! `--from-lock` reads the url from a `flake.lock` instead, pinned to
+//! the locked revision. Given an id it adds that one input; without one
+//! it imports every input the flake lacks in a single write.
 
-use nix_uri::FlakeRef;
+use std::path::PathBuf;
 
 use crate::change::{Change, ChangeId};
 use crate::edit::FlakeEdit;
+use crate::error::Error as FlakeError;
+use crate::follows::{AttrPath, Segment};
+use crate::lock::{FlakeLock, LockError};
 use crate::tui;
+use crate::uri::{infer_id, take_flake_param};
 
 use super::super::editor::Editor;
 use super::super::state::AppState;
-use super::uri::{BuildKind, UriOptions, apply_uri_options, build_uri_change, transform_uri};
-use super::{Error, Result, apply_change};
+use super::uri::{
+    BuildKind, UriOptions, apply_uri_options, build_uri_change, parse_flake_ref, set_git_ref,
+    transform_uri,
+};
+use super::{Error, Result, apply_change, apply_in_sequence, load_flake_lock, report_unchanged};
+
+/// Flags that only apply to `add`.
+#[derive(Default)]
+pub struct AddOptions {
+    /// Whether the new input is a flake. `None` defers to the `[add]`
+    /// configuration; `Some(false)` writes `inputs.<id>.flake = false;`.
+    pub flake: Option<bool>,
+    /// Change the url of an existing input instead of failing.
+    pub replace_if_exists: bool,
+    /// Prepended to the given or inferred input id.
+    pub input_prefix: Option<String>,
+    /// Rewrite an inferred id into a bare identifier instead of failing.
+    pub id_sanitize: bool,
+    /// Print the change as JSON instead of applying it.
+    pub json_change: bool,
+    /// Take the url from a `flake.lock`: `Some(None)` is the flake's own
+    /// lock file.
+    pub from_lock: Option<Option<PathBuf>>,
+}
 
 pub fn add(
     editor: &Editor,
@@ -21,28 +60,248 @@ pub fn add(
     state: &AppState,
     id: Option<String>,
     uri: Option<String>,
-    no_flake: bool,
+    add_opts: AddOptions,
     opts: UriOptions<'_>,
 ) -> Result<()> {
-    let change = match (id, uri, state.interactive) {
+    let AddOptions {
+        flake,
+        replace_if_exists,
+        input_prefix,
+        id_sanitize,
+        json_change,
+        from_lock,
+    } = add_opts;
+    let no_flake = flake == Some(false);
+    // A lock records whether each input is a flake, so only urls given
+    // here fall back to the configuration.
+    let by_uri = from_lock.is_none();
+    let change = match (id, uri, state.interactive, from_lock) {
+        (Some(id), None, _, Some(path)) => {
+            let lock = open_lock(state, path)?;
+            add_locked_input(&lock, id)?
+        }
+        (None, None, _, Some(path)) => {
+            if input_prefix.is_some() || replace_if_exists || json_change {
+                return Err(Error::FromLockNeedsId);
+            }
+            let lock = open_lock(state, path)?;
+            return add_from_lock(editor, flake_edit, state, &lock);
+        }
         // Both ID and URI provided: non-interactive add.
-        (Some(id_val), Some(uri_str), _) => {
+        (Some(id_val), Some(uri_str), _, _) => {
             build_uri_change(BuildKind::Add { no_flake }, id_val, uri_str, &opts)?
         }
         // Interactive: show TUI (with or without prefill).
-        (id, None, true) | (None, id, true) => {
+        (id, None, true, _) | (None, id, true, _) => {
             add_interactive(editor, state, id.as_deref(), no_flake, &opts)?
         }
         // Non-interactive with only one positional arg: infer ID from URI.
-        (Some(uri), None, false) | (None, Some(uri), false) => add_infer_id(uri, no_flake, &opts)?,
-        (None, None, false) => {
+        (Some(uri), None, false, _) | (None, Some(uri), false, _) => {
+            add_infer_id(uri, no_flake, id_sanitize, &opts)?
+        }
+        (None, None, false, _) => {
             return Err(Error::NoUri);
         }
     };
+    // `?flake=false` in the url becomes the `flake = false;` attribute,
+    // and overrides the configuration like `--no-flake` does.
+    let (change, flake_given) = match change {
+        Change::Add {
+            id,
+            uri: Some(uri),
+            flake: is_flake,
+        } if by_uri && let Some((uri, from_uri)) = take_flake_param(&uri) => {
+            let change = Change::Add {
+                id,
+                uri: Some(uri),
+                flake: is_flake && from_uri,
+            };
+            (change, true)
+        }
+        change => (change, flake.is_some()),
+    };
+    let change = match change {
+        Change::Add {
+            id,
+            uri: Some(uri),
+            flake: true,
+        } if by_uri && !flake_given => Change::Add {
+            flake: state.config.add.is_flake(&uri),
+            id,
+            uri: Some(uri),
+        },
+        change => change,
+    };
+    let change = match input_prefix {
+        Some(prefix) => prefix_id(change, &prefix)?,
+        None => change,
+    };
+    let change = if replace_if_exists {
+        replace_existing(flake_edit, change)
+    } else {
+        change
+    };
+
+    if json_change {
+        println!(
+            "{}",
+            serde_json::to_string(&change).expect("Change serializes to JSON")
+        );
+        return Ok(());
+    }
 
     apply_change(editor, flake_edit, state, change)
 }
 
+/// Load the lock at `path`, or the flake's own lock when `None`.
+fn open_lock(state: &AppState, path: Option<PathBuf>) -> Result<FlakeLock> {
+    let loaded = match &path {
+        Some(path) => FlakeLock::from_file(path),
+        None => load_flake_lock(state),
+    };
+    loaded.map_err(|source| Error::LockFile {
+        path: path.unwrap_or_else(|| state.lock_path()),
+        source,
+    })
+}
+
+/// Builds a `Change::Add` for `id` from its node in `lock`.
+///
+/// An input that follows another one in the lock has no node of its
+/// own and is an error.
+fn add_locked_input(lock: &FlakeLock, id: String) -> Result<Change> {
+    let segment = Segment::from_unquoted(id.clone()).map_err(|source| Error::InvalidInputId {
+        id,
+        source: source.into(),
+    })?;
+    let uri = lock.uri_for(&segment)?;
+    Ok(Change::Add {
+        flake: lock.input_is_flake(&segment),
+        id: Some(ChangeId::from(segment)),
+        uri: Some(uri),
+    })
+}
+
+/// Add every root input of `lock` that the flake doesn't declare yet,
+/// pinned to its locked revision.
+///
+/// The adds land in one write. Inputs that follow another input have
+/// no node to pin and are skipped.
+fn add_from_lock(
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    lock: &FlakeLock,
+) -> Result<()> {
+    let existing = flake_edit.list().clone();
+
+    let mut changes = Vec::new();
+    let mut skipped = Vec::new();
+    for id in lock.input_ids() {
+        if existing.contains_key(id.as_str()) {
+            // The add that was left out, for the JSON report. An input
+            // that follows another one has no url to name.
+            skipped.push(Change::Add {
+                id: Some(ChangeId::from(id.clone())),
+                uri: lock.uri_for(&id).ok(),
+                flake: lock.input_is_flake(&id),
+            });
+            continue;
+        }
+        let uri = match lock.uri_for(&id) {
+            Ok(uri) => uri,
+            Err(FlakeError::Lock(LockError::InputFollows { .. })) => continue,
+            Err(err) => return Err(err.into()),
+        };
+        changes.push(Change::Add {
+            id: Some(ChangeId::from(id.clone())),
+            uri: Some(uri),
+            flake: lock.input_is_flake(&id),
+        });
+    }
+
+    let applied = apply_in_sequence(editor, state, changes)?;
+    if !applied.is_empty() && state.writes() {
+        for change in &skipped {
+            let id = change.id().map(|id| id.to_string()).unwrap_or_default();
+            report_unchanged(
+                state,
+                Some(change),
+                &format!("Skipped existing input: {id}"),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Prepend `prefix` to the id of a `Change::Add`. Other changes pass
+/// through.
+///
+/// The prefixed id must be a bare Nix identifier, so a prefix with a
+/// dot or a leading digit can't silently split or quote the name.
+fn prefix_id(change: Change, prefix: &str) -> Result<Change> {
+    let Change::Add {
+        id: Some(id),
+        uri,
+        flake,
+    } = change
+    else {
+        return Ok(change);
+    };
+    let prefixed = format!("{prefix}{}", id.input().as_str());
+    let segment = Segment::from_unquoted(prefixed.clone())
+        .ok()
+        .filter(|segment| !segment.needs_quoting())
+        .ok_or_else(|| Error::InvalidInputPrefix {
+            prefix: prefix.to_string(),
+            id: prefixed,
+        })?;
+    Ok(Change::Add {
+        id: Some(ChangeId::new(AttrPath::new(segment))),
+        uri,
+        flake,
+    })
+}
+
+/// `id` as a bare identifier for `--id-sanitize`: characters other than
+/// ASCII letters, digits and `_` become `_`, and everything before the
+/// first letter is dropped.
+fn sanitize_id(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .skip_while(|c| !c.is_ascii_alphabetic())
+        .collect()
+}
+
+/// Turn a `Change::Add` whose id is already declared into a
+/// `Change::Change` of that input's url. Other changes pass through.
+///
+/// The `flake` flag of the add is dropped on replacement: an existing
+/// `flake = false;` stays as written.
+fn replace_existing(flake_edit: &mut FlakeEdit, change: Change) -> Change {
+    let Change::Add {
+        id: Some(id),
+        uri,
+        flake,
+    } = change
+    else {
+        return change;
+    };
+    if flake_edit.list().contains_key(id.input().as_str()) {
+        Change::Change {
+            id: Some(id),
+            uri,
+            rename: None,
+        }
+    } else {
+        Change::Add {
+            id: Some(id),
+            uri,
+            flake,
+        }
+    }
+}
+
 fn add_interactive(
     editor: &Editor,
     state: &AppState,
@@ -59,7 +318,8 @@ fn add_interactive(
     // CLI options override the TUI result.
     if let Change::Add { id, uri, flake } = tui_change {
         let final_uri = uri
-            .map(|u| transform_uri(u, opts.ref_or_rev, opts.shallow))
+            .map(|u| transform_uri(opts.expand(u), opts.ref_or_rev, opts.shallow))
+            .map(|u| opts.apply_git_ref(u?))
             .transpose()?;
         Ok(Change::Add {
             id,
@@ -73,24 +333,42 @@ fn add_interactive(
 
 /// Builds a `Change::Add` when only the URI is supplied, inferring
 /// the ID from the parsed flake reference.
-fn add_infer_id(uri: String, no_flake: bool, opts: &UriOptions<'_>) -> Result<Change> {
-    let (inferred_id, final_uri) = match uri.parse::<FlakeRef>() {
+fn add_infer_id(
+    uri: String,
+    no_flake: bool,
+    id_sanitize: bool,
+    opts: &UriOptions<'_>,
+) -> Result<Change> {
+    let uri = opts.expand(uri);
+    let (inferred_id, final_uri) = match parse_flake_ref(&uri) {
         Ok(flake_ref) => {
-            let flake_ref = apply_uri_options(flake_ref, opts.ref_or_rev, opts.shallow);
-            let id = flake_ref.id().map(str::to_owned);
+            let mut flake_ref = apply_uri_options(flake_ref, opts.ref_or_rev, opts.shallow);
+            if let Some(git_ref) = opts.git_ref {
+                set_git_ref(&mut flake_ref, git_ref);
+            }
+            let id = infer_id(&flake_ref);
             (id, flake_ref.into_uri())
         }
+        Err(err @ Error::MissingRepo { .. }) => return Err(err),
         Err(_) => (None, uri.clone()),
     };
 
-    let final_id = inferred_id.ok_or_else(|| Error::CouldNotInferId { uri: uri.clone() })?;
-    let final_id = ChangeId::parse(&final_id).map_err(|source| Error::InvalidInputId {
-        id: final_id,
-        source,
-    })?;
+    // A malformed url can infer a blank id (`git+https://host/a/.git`),
+    // which would otherwise be written as `inputs."".url`.
+    let final_id = inferred_id
+        .map(|id| if id_sanitize { sanitize_id(&id) } else { id })
+        .filter(|id| !id.trim().is_empty())
+        .ok_or_else(|| Error::CouldNotInferId { uri: uri.clone() })?;
+    let segment = Segment::from_unquoted(final_id.clone())
+        .ok()
+        .filter(|segment| !segment.needs_quoting())
+        .ok_or_else(|| Error::InferredIdNotIdentifier {
+            id: final_id,
+            uri: uri.clone(),
+        })?;
 
     Ok(Change::Add {
-        id: Some(final_id),
+        id: Some(ChangeId::new(AttrPath::new(segment))),
         uri: Some(final_uri),
         flake: !no_flake,
     })
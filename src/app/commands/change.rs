@@ -3,17 +3,24 @@
 //! Four branches: full interactive (pick + URI), URI-only
 //! interactive (with the ID known), scripted (id + uri), and
 //! infer-id (uri only). All route the resulting URI through
-//! [`super::uri::transform_uri`].
-
-use nix_uri::FlakeRef;
+//! [`super::uri::transform_uri`]. An existing id with `--ref-or-rev`
+//! or `--param` and no URI rewrites only those parts of the input's
+//! current URI, see [`change_ref_only`].
+//!
+//! With `--rename-output` the input also takes the id inferred from its
+//! new URI, see [`with_inferred_rename`].
 
 use crate::change::{Change, ChangeId};
 use crate::edit::{FlakeEdit, InputMap, sorted_input_ids};
+use crate::follows::Segment;
 use crate::tui;
+use crate::uri::infer_id;
 
 use super::super::editor::Editor;
 use super::super::state::AppState;
-use super::uri::{BuildKind, UriOptions, apply_uri_options, build_uri_change, transform_uri};
+use super::uri::{
+    BuildKind, UriOptions, apply_uri_options, build_uri_change, parse_flake_ref, transform_uri,
+};
 use super::{Error, Result, apply_change};
 
 pub fn change(
@@ -23,10 +30,19 @@ pub fn change(
     id: Option<String>,
     uri: Option<String>,
     opts: UriOptions<'_>,
+    rename_output: bool,
 ) -> Result<()> {
     let inputs = flake_edit.list();
 
     let change = match (id, uri, state.interactive) {
+        // ID of an existing input and a ref or params, no URI: keep the
+        // rest of the URI.
+        (Some(id), None, _)
+            if (opts.ref_or_rev.is_some() || !opts.params.is_empty())
+                && inputs.contains_key(&id) =>
+        {
+            change_ref_only(inputs, &id, &opts)?
+        }
         // Full interactive: select input, then enter URI. Also covers the
         // case where only URI was provided interactively (need to select input).
         (None, None, true) | (None, Some(_), true) => {
@@ -44,10 +60,42 @@ pub fn change(
             return Err(Error::NoId);
         }
     };
+    let change = if rename_output {
+        with_inferred_rename(change)?
+    } else {
+        change
+    };
 
     apply_change(editor, flake_edit, state, change)
 }
 
+/// Rename the input of a `Change::Change` to the id its new URI infers.
+/// An inferred id equal to the current one leaves the change as it is.
+fn with_inferred_rename(change: Change) -> Result<Change> {
+    let Change::Change {
+        id: Some(id),
+        uri: Some(uri),
+        ..
+    } = change
+    else {
+        return Ok(change);
+    };
+    let flake_ref = parse_flake_ref(&uri)?;
+    let inferred = infer_id(&flake_ref)
+        .filter(|id| !id.trim().is_empty())
+        .ok_or_else(|| Error::CouldNotInferId { uri: uri.clone() })?;
+    let to = Segment::from_unquoted(&inferred).map_err(|source| Error::InvalidInputId {
+        id: inferred.clone(),
+        source: source.into(),
+    })?;
+    let rename = (id.input() != &to).then_some(to);
+    Ok(Change::Change {
+        id: Some(id),
+        uri: Some(uri),
+        rename,
+    })
+}
+
 /// Runs the full interactive flow: pick an input from the list, then
 /// enter the new URI.
 fn change_full_interactive(
@@ -73,9 +121,16 @@ fn change_full_interactive(
     // CLI options override the TUI result.
     if let Change::Change { id, uri, .. } = tui_change {
         let final_uri = uri
-            .map(|u| transform_uri(u, opts.ref_or_rev, opts.shallow))
+            .map(|u| {
+                let uri = transform_uri(opts.expand(u), opts.ref_or_rev, opts.shallow)?;
+                opts.apply_params(uri)
+            })
             .transpose()?;
-        Ok(Change::Change { id, uri: final_uri })
+        Ok(Change::Change {
+            id,
+            uri: final_uri,
+            rename: None,
+        })
     } else {
         Ok(tui_change)
     }
@@ -109,7 +164,8 @@ fn change_uri_interactive(
         uri: Some(new_uri), ..
     } = tui_change
     {
-        let final_uri = transform_uri(new_uri, opts.ref_or_rev, opts.shallow)?;
+        let final_uri = transform_uri(opts.expand(new_uri), opts.ref_or_rev, opts.shallow)?;
+        let final_uri = opts.apply_params(final_uri)?;
         let id = ChangeId::parse(id).map_err(|source| Error::InvalidInputId {
             id: id.to_string(),
             source,
@@ -117,30 +173,57 @@ fn change_uri_interactive(
         Ok(Change::Change {
             id: Some(id),
             uri: Some(final_uri),
+            rename: None,
         })
     } else {
         Err(Error::NoUri)
     }
 }
 
+/// Builds a `Change::Change` that sets `--ref-or-rev` and `--param` on
+/// the input's current URI. Kinds without a ref slot (`path:`, `file:`,
+/// tarballs) are rejected with the reason from `nix-uri`.
+fn change_ref_only(inputs: &InputMap, id: &str, opts: &UriOptions<'_>) -> Result<Change> {
+    let url = inputs[id].url();
+    let mut flake_ref = parse_flake_ref(url)?;
+    if let Some(ref_or_rev) = opts.ref_or_rev {
+        flake_ref = flake_ref
+            .try_with_ref(Some(ref_or_rev.to_string()))
+            .map_err(|source| Error::RefNotSupported {
+                id: id.to_string(),
+                uri: url.to_string(),
+                source,
+            })?;
+    }
+    let final_uri = apply_uri_options(flake_ref, None, opts.shallow).into_uri();
+    let final_uri = opts.apply_params(final_uri)?;
+    let id = ChangeId::parse(id).map_err(|source| Error::InvalidInputId {
+        id: id.to_string(),
+        source,
+    })?;
+    Ok(Change::Change {
+        id: Some(id),
+        uri: Some(final_uri),
+        rename: None,
+    })
+}
+
 /// Builds a `Change::Change` when only the URI is supplied, inferring
 /// the ID from the parsed flake reference.
 fn change_infer_id(uri: String, opts: &UriOptions<'_>) -> Result<Change> {
-    let flake_ref: FlakeRef = uri.parse().map_err(|source| Error::InvalidUri {
-        uri: uri.clone(),
-        source,
-    })?;
+    let uri = opts.expand(uri);
+    let flake_ref = parse_flake_ref(&uri)?;
     let flake_ref = apply_uri_options(flake_ref, opts.ref_or_rev, opts.shallow);
 
-    let id = flake_ref
-        .id()
-        .map(str::to_owned)
+    let id = infer_id(&flake_ref)
+        .filter(|id| !id.trim().is_empty())
         .ok_or_else(|| Error::CouldNotInferId { uri: uri.clone() })?;
     let id = ChangeId::parse(&id).map_err(|source| Error::InvalidInputId { id, source })?;
-    let final_uri = flake_ref.into_uri();
+    let final_uri = opts.apply_params(flake_ref.into_uri())?;
 
     Ok(Change::Change {
         id: Some(id),
         uri: Some(final_uri),
+        rename: None,
     })
 }
@@ -3,19 +3,27 @@
 //!
 //! `--print-default` writes the embedded default
 //! [`DEFAULT_CONFIG_TOML`] to stdout. `--path` reports the lookup
-//! locations for the project and user config files. With neither
-//! flag the subcommand is a no-op.
+//! locations for the project and user config files. `--show` prints
+//! the configuration a run would use, annotated with the source of each
+//! value. With no flag the subcommand is a no-op.
+
+use std::path::Path;
 
 use crate::config::{Config, DEFAULT_CONFIG_TOML};
 
 use super::Result;
 
-pub fn config(print_default: bool, path: bool) -> Result<()> {
+pub fn config(print_default: bool, path: bool, show: bool, explicit: Option<&Path>) -> Result<()> {
     if print_default {
         print!("{}", DEFAULT_CONFIG_TOML);
         return Ok(());
     }
 
+    if show {
+        print!("{}", Config::render_effective(&Config::source(explicit))?);
+        return Ok(());
+    }
+
     if path {
         let project_path = Config::project_config_path();
         let user_path = Config::user_config_path();
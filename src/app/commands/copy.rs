@@ -0,0 +1,112 @@
+//! `flake-edit copy`: import an input declaration from another flake.
+//!
+//! The input's url and `flake = false` are added, then each of its
+//! follows declarations is replayed on top, all in one write. With
+//! `--force` an existing input only gets the url, and keeps its own
+//! `flake` setting. A follows
+//! whose target is not an input of this flake would dangle, so it is
+//! skipped with a warning.
+
+use std::path::Path;
+
+use crate::change::{Change, ChangeId};
+use crate::edit::FlakeEdit;
+use crate::error::Error as FlakeError;
+use crate::follows::AttrPath;
+use crate::input::Follows;
+
+use super::super::editor::Editor;
+use super::super::state::AppState;
+use super::{Error, Result, apply_in_sequence};
+
+pub fn copy(
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    source: &mut FlakeEdit,
+    source_path: &Path,
+    id: &str,
+    force: bool,
+) -> Result<()> {
+    let Some(input) = source.list().get(id).cloned() else {
+        return Err(Error::CopyUnknownInput {
+            id: id.to_string(),
+            path: source_path.to_path_buf(),
+        });
+    };
+    if input.url().is_empty() {
+        return Err(Error::CopyNoUrl {
+            id: id.to_string(),
+            path: source_path.to_path_buf(),
+        });
+    }
+    if input.is_unresolved() {
+        return Err(FlakeError::UnresolvedUrl {
+            id: id.to_string(),
+            expr: input.url().to_string(),
+        }
+        .into());
+    }
+
+    let local = flake_edit.list();
+    let exists = local.contains_key(id);
+    if exists && !force {
+        return Err(Error::CopyInputExists { id: id.to_string() });
+    }
+
+    if exists && local[id].flake != input.flake {
+        eprintln!(
+            "warning: keeping the flake = {} of the existing '{id}': --force only replaces the url",
+            local[id].flake
+        );
+    }
+
+    let change_id = ChangeId::from(input.id().clone());
+    let mut changes = vec![if exists {
+        Change::Change {
+            id: Some(change_id),
+            uri: Some(input.url().to_string()),
+            rename: None,
+        }
+    } else {
+        Change::Add {
+            id: Some(change_id),
+            uri: Some(input.url().to_string()),
+            flake: input.flake,
+        }
+    }];
+
+    for follows in input.follows() {
+        match follows {
+            Follows::Indirect {
+                path,
+                target: Some(target),
+            } if target.first() == input.id() || local.contains_key(target.first().as_str()) => {
+                let mut nested = AttrPath::new(input.id().clone());
+                for segment in path.segments() {
+                    nested.push(segment.clone());
+                }
+                changes.push(Change::Follows {
+                    input: ChangeId::new(nested),
+                    target: target.clone(),
+                });
+            }
+            Follows::Indirect {
+                path,
+                target: Some(target),
+            } => eprintln!(
+                "warning: skipping {id}.{path} -> {target}: no input named '{}' here",
+                target.first()
+            ),
+            Follows::Indirect { path, target: None } => {
+                eprintln!("warning: skipping {id}.{path}: empty follows are not copied")
+            }
+            Follows::Direct(name, _) => {
+                eprintln!("warning: skipping {id}.{name}: nested url declarations are not copied")
+            }
+        }
+    }
+
+    apply_in_sequence(editor, state, changes)?;
+    Ok(())
+}
@@ -0,0 +1,342 @@
+//! `flake-edit doctor`: report problems in `flake.nix`, and fix the safe
+//! ones with `--fix`.
+//!
+//! Safe fixes never change what an input resolves to: the later copy of
+//! a duplicate input binding is dropped (Nix rejects the file anyway),
+//! follows whose source left `flake.lock` or whose target is not an input
+//! are removed, and url parameters are sorted. Unused inputs and the other
+//! follows lints are only reported. Any problem left fails the command, so
+//! it can gate CI. With `--output json` every finding is a JSON line, and
+//! an applied fix names its equivalent [`Change`] where there is one.
+
+use ropey::Rope;
+
+use crate::change::{Change, ChangeId};
+use crate::cli::OutputFormat;
+use crate::edit::{self, FlakeEdit, sorted_input_ids};
+use crate::follows::{AttrPath, Segment};
+use crate::input::Input;
+use crate::validate::{self, Location, ValidationError};
+
+use super::super::editor::Editor;
+use super::super::state::AppState;
+use super::{Error, Result, input_location, load_flake_lock, report_unchanged};
+
+/// One problem found in `flake.nix`.
+struct Finding {
+    problem: String,
+    fix: Fix,
+}
+
+enum Fix {
+    /// Dropped by [`edit::dedup_inputs`].
+    Dedup,
+    /// A follows declaration removed by its source path.
+    Unfollow(AttrPath),
+    /// Url parameters put in canonical order: the input and its sorted
+    /// url.
+    SortParams(String, String),
+    /// Reported only.
+    Manual,
+}
+
+impl Fix {
+    /// The [`Change`] this fix amounts to, for the JSON report. `None`
+    /// for a dropped duplicate, which no change describes.
+    fn change(&self) -> Option<Change> {
+        match self {
+            Fix::Unfollow(source) => Some(Change::Remove {
+                ids: vec![ChangeId::new(source.clone())],
+            }),
+            Fix::SortParams(id, url) => Some(Change::Change {
+                id: Segment::from_unquoted(id.as_str()).ok().map(ChangeId::from),
+                uri: Some(url.clone()),
+                rename: None,
+            }),
+            Fix::Dedup | Fix::Manual => None,
+        }
+    }
+}
+
+pub fn doctor(editor: &Editor, state: &AppState, fix: bool) -> Result<()> {
+    let original = editor.text();
+    let mut findings: Vec<Finding> = edit::duplicate_bindings(&original)
+        .into_iter()
+        .map(|(dup, input)| Finding {
+            problem: dup.to_string(),
+            fix: if input { Fix::Dedup } else { Fix::Manual },
+        })
+        .collect();
+
+    // The remaining checks need the parsed inputs, which only load once
+    // every duplicate is gone.
+    let deduped = edit::dedup_inputs(&original);
+    let text = deduped.as_deref().unwrap_or(&original);
+    match FlakeEdit::from_text(text) {
+        Ok(mut flake_edit) => {
+            let origins = deduped.as_deref().map(|d| line_origins(&original, d));
+            let lines = match &origins {
+                None => Lines::Same,
+                Some(Some(origins)) => Lines::Mapped(origins),
+                Some(None) => Lines::Unknown,
+            };
+            findings.extend(inspect(&mut flake_edit, text, lines, state)?);
+        }
+        Err(err) => findings.extend(unloadable(text, err)),
+    }
+
+    let fixable = findings
+        .iter()
+        .filter(|f| !matches!(f.fix, Fix::Manual))
+        .count();
+    if fix && fixable > 0 {
+        let fixed = apply_fixes(&original, &findings)?;
+        editor.apply_or_diff(&fixed, state)?;
+    }
+
+    let applied = fix && state.writes();
+    if findings.is_empty() {
+        report_unchanged(state, None, "No problems found.");
+        return Ok(());
+    }
+    for finding in &findings {
+        report(state, finding, applied);
+    }
+
+    let count = if fix {
+        findings.len() - fixable
+    } else {
+        findings.len()
+    };
+    if count == 0 {
+        return Ok(());
+    }
+    Err(Error::DoctorProblems {
+        count,
+        fixable: if fix { 0 } else { fixable },
+    })
+}
+
+/// Print `finding` as a `<label>: <problem>` line, or as a JSON object
+/// with `--output json`. `applied` tells whether the safe fixes were
+/// written.
+fn report(state: &AppState, finding: &Finding, applied: bool) {
+    let fixed = applied && !matches!(finding.fix, Fix::Manual);
+    match state.output {
+        OutputFormat::Text => {
+            let label = match finding.fix {
+                Fix::Manual => "manual",
+                _ if applied => "fixed",
+                _ => "fixable",
+            };
+            println!("{label}: {}", finding.problem);
+        }
+        OutputFormat::Json => {
+            let status = match finding.fix {
+                Fix::Manual => "manual",
+                _ if fixed => "applied",
+                _ => "fixable",
+            };
+            let mut object = serde_json::json!({ "status": status, "problem": finding.problem });
+            if fixed && let Some(change) = finding.fix.change() {
+                object["change"] = serde_json::json!(change);
+            }
+            println!("{object}");
+        }
+    }
+}
+
+/// How the lines of the text [`inspect`] checks relate to the file.
+enum Lines<'a> {
+    /// The text is the file as read.
+    Same,
+    /// The 1-indexed file line each line of the deduplicated text came
+    /// from.
+    Mapped(&'a [usize]),
+    /// Deduplication did more than drop whole lines, so findings are
+    /// reported without a location rather than with a wrong one.
+    Unknown,
+}
+
+impl Lines<'_> {
+    /// `location` in the file, or `None` when it cannot be placed.
+    fn locate(&self, mut location: Location) -> Option<Location> {
+        match self {
+            Lines::Same => Some(location),
+            Lines::Mapped(lines) => {
+                location.line = lines[location.line - 1];
+                Some(location)
+            }
+            Lines::Unknown => None,
+        }
+    }
+}
+
+/// Follows lints, unsorted url parameters and unused inputs of the
+/// deduplicated `text`. `lines` maps its lines back to the original
+/// file, so reported locations match what the user sees.
+fn inspect(
+    flake_edit: &mut FlakeEdit,
+    text: &str,
+    lines: Lines<'_>,
+    state: &AppState,
+) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    // Without a lock the stale lints are skipped, the rest still run.
+    let lock = load_flake_lock(state).ok();
+    let validation = validate::validate_full(text, flake_edit.list(), lock.as_ref());
+    let errors: Vec<ValidationError> = validation
+        .errors
+        .into_iter()
+        .chain(validation.warnings)
+        .collect();
+    let removed: Vec<&AttrPath> = errors
+        .iter()
+        .filter_map(|err| match err {
+            ValidationError::FollowsStale { edge, .. }
+            | ValidationError::FollowsTargetNotToplevel { edge, .. } => Some(&edge.source),
+            _ => None,
+        })
+        .collect();
+    for err in &errors {
+        let fix = match err {
+            // Reported from the original text already.
+            ValidationError::DuplicateAttribute(_) | ValidationError::ParseError { .. } => {
+                continue;
+            }
+            ValidationError::FollowsStale { edge, .. }
+            | ValidationError::FollowsTargetNotToplevel { edge, .. } => {
+                Fix::Unfollow(edge.source.clone())
+            }
+            // Goes away with the declaration removed for another lint.
+            ValidationError::FollowsStaleLock { source_path, .. }
+                if removed.contains(&source_path) =>
+            {
+                Fix::Unfollow(source_path.clone())
+            }
+            _ => Fix::Manual,
+        };
+        findings.push(Finding {
+            problem: problem(err, &lines),
+            fix,
+        });
+    }
+
+    let rope = Rope::from_str(text);
+    let at = |input: &Input| {
+        input_location(input, &rope)
+            .and_then(|location| lines.locate(location))
+            .map(|location| format!(" at {location}"))
+            .unwrap_or_default()
+    };
+    let inputs = flake_edit.list();
+    for id in sorted_input_ids(inputs) {
+        let url = inputs[id].url();
+        if !inputs[id].is_unresolved()
+            && let Some(sorted) = crate::uri::sort_params(url)
+        {
+            findings.push(Finding {
+                problem: format!("unsorted url parameters{}: {id}: {url}", at(&inputs[id])),
+                fix: Fix::SortParams(id.clone(), sorted),
+            });
+        }
+    }
+
+    for id in flake_edit.unused_inputs()?.unwrap_or_default() {
+        let at = flake_edit.list().get(&id).map(at).unwrap_or_default();
+        findings.push(Finding {
+            problem: format!("unused input{at}: {id}"),
+            fix: Fix::Manual,
+        });
+    }
+    Ok(findings)
+}
+
+/// `err` as a finding, its location moved to the file by `lines`, or
+/// left out where `lines` cannot place it.
+fn problem(err: &ValidationError, lines: &Lines<'_>) -> String {
+    let mut err = err.clone();
+    let Some(location) = err.location_mut() else {
+        return err.to_string();
+    };
+    match lines.locate(location.clone()) {
+        Some(placed) => {
+            *location = placed;
+            err.to_string()
+        }
+        None => {
+            let unplaced = format!(" at {location}");
+            err.to_string().replacen(&unplaced, "", 1)
+        }
+    }
+}
+
+/// Why `text` does not load: its syntax errors, or the load error itself
+/// when the syntax is fine. Never empty together with the duplicates
+/// already reported, so a broken file fails the command.
+fn unloadable(text: &str, err: crate::Error) -> Vec<Finding> {
+    let errors = validate::validate(text).errors;
+    if errors.is_empty() {
+        return vec![Finding {
+            problem: err.to_string(),
+            fix: Fix::Manual,
+        }];
+    }
+    errors
+        .into_iter()
+        // Reported from the original text already.
+        .filter(|err| !matches!(err, ValidationError::DuplicateAttribute(_)))
+        .map(|err| Finding {
+            problem: err.to_string(),
+            fix: Fix::Manual,
+        })
+        .collect()
+}
+
+/// The 1-indexed line of `original` each line of `deduped` came from, or
+/// `None` if deduplication did not only drop whole lines.
+fn line_origins(original: &str, deduped: &str) -> Option<Vec<usize>> {
+    let mut source = original.lines().enumerate();
+    deduped
+        .lines()
+        .map(|line| {
+            source
+                .by_ref()
+                .find(|(_, candidate)| *candidate == line)
+                .map(|(index, _)| index + 1)
+        })
+        .collect()
+}
+
+/// `original` with every safe fix among `findings` applied, in the order
+/// the checks ran.
+fn apply_fixes(original: &str, findings: &[Finding]) -> Result<String> {
+    let mut text = edit::dedup_inputs(original).unwrap_or_else(|| original.to_string());
+
+    let mut unfollow: Vec<&AttrPath> = findings
+        .iter()
+        .filter_map(|f| match &f.fix {
+            Fix::Unfollow(source) => Some(source),
+            _ => None,
+        })
+        .collect();
+    unfollow.sort();
+    unfollow.dedup();
+    for source in unfollow {
+        let change = Change::Remove {
+            ids: vec![ChangeId::new(source.clone())],
+        };
+        if let Some(next) = FlakeEdit::from_text(&text)?.apply_change(change)?.text {
+            text = next;
+        }
+    }
+
+    if findings
+        .iter()
+        .any(|f| matches!(f.fix, Fix::SortParams(..)))
+        && let Some(next) = FlakeEdit::from_text(&text)?.sort_url_params()?
+    {
+        text = next;
+    }
+    Ok(text)
+}
@@ -1,8 +1,10 @@
-//! `flake-edit follow` and `flake-edit add-follow`: declare or
-//! deduplicate `inputs.<id>.follows` edges.
+//! `flake-edit follow`, `flake-edit add-follow` and `flake-edit unfollow`:
+//! declare, deduplicate or drop `inputs.<id>.follows` edges.
 //!
 //! [`add_follow`] handles the scripted `<input> <target>` form and
-//! the interactive picker fallback. [`auto`] runs the
+//! the interactive picker fallback. [`remove_follow`] drops a single
+//! declaration for `follow --remove` and `unfollow`, whose picker
+//! fallback is [`unfollow`]. [`auto`] runs the
 //! auto-deduplication planner, applier, and batch driver.
 
 pub mod auto;
@@ -18,6 +20,7 @@ use crate::tui;
 
 use super::super::editor::Editor;
 use super::super::state::AppState;
+use super::remove::{pick_removals, removable};
 use super::{Error, Result, apply_change, load_flake_lock};
 
 pub(super) struct FollowContext {
@@ -37,13 +40,8 @@ pub(super) fn load_follow_context(
     let nested_inputs: Vec<NestedInput> = match load_flake_lock(state) {
         Ok(lock) => lock.nested_inputs(),
         Err(e) => {
-            let lock_path = state
-                .lock_file
-                .as_ref()
-                .map(|p| p.display().to_string())
-                .unwrap_or_else(|| "flake.lock".to_string());
             return Err(Error::LockFile {
-                path: std::path::PathBuf::from(lock_path),
+                path: state.lock_path(),
                 source: e,
             });
         }
@@ -87,7 +85,7 @@ pub fn add_follow(
             source,
         })?;
         let target_path =
-            AttrPath::parse(&target_val).map_err(|source| Error::InvalidFollowsPath {
+            AttrPath::parse_target(&target_val).map_err(|source| Error::InvalidFollowsPath {
                 path: target_val.clone(),
                 source,
             })?;
@@ -127,3 +125,53 @@ pub fn add_follow(
 
     apply_change(editor, flake_edit, state, change)
 }
+
+/// `flake-edit unfollow`: drop one follows declaration, keeping the input.
+///
+/// Without a path, picks among the existing follows declarations in the
+/// same listing `flake-edit remove` shows, minus the inputs themselves.
+pub fn unfollow(
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    path: Option<String>,
+    selection: tui::InitialSelection,
+) -> Result<()> {
+    if let Some(path) = path {
+        return remove_follow(editor, flake_edit, state, &path);
+    }
+    if !state.interactive {
+        return Err(Error::NoId);
+    }
+    let follows = removable(flake_edit.list(), false);
+    if follows.is_empty() {
+        return Err(Error::NoFollows);
+    }
+    let Some(change) = pick_removals(editor, "Unfollow", follows, selection)? else {
+        return Ok(());
+    };
+    apply_change(editor, flake_edit, state, change)
+}
+
+/// Removes the single follows declaration at `<input>.<nested>`.
+///
+/// Goes through the same [`Change::Remove`] walker path as
+/// `flake-edit remove`, but refuses a bare input id so a missing
+/// `.<nested>` can't delete the whole input.
+pub fn remove_follow(
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    path: &str,
+) -> Result<()> {
+    let id = ChangeId::parse(path).map_err(|source| Error::InvalidFollowsPath {
+        path: path.to_string(),
+        source,
+    })?;
+    if id.follows().is_none() {
+        return Err(Error::NotAFollowsPath {
+            path: path.to_string(),
+        });
+    }
+    apply_change(editor, flake_edit, state, Change::Remove { ids: vec![id] })
+}
@@ -9,10 +9,11 @@
 use std::collections::{HashMap, HashSet};
 
 use crate::change::{Change, ChangeId};
+use crate::cli::OutputFormat;
 use crate::config::FollowConfig;
 use crate::edit::{FlakeEdit, InputMap};
 use crate::follows::{
-    AttrPath, Edge, EdgeOrigin, FollowsGraph, Segment, is_follows_reference_to_parent,
+    AttrPath, Cycle, Edge, EdgeOrigin, FollowsGraph, Segment, is_follows_reference_to_parent,
 };
 use crate::input::Range;
 use crate::lock::{FlakeLock, NestedInput};
@@ -26,8 +27,72 @@ use super::load_follow_context;
 const SENTINEL_ALREADY_DEDUPLICATED: &str = "All inputs are already deduplicated.";
 
 /// Entry point for `flake-edit follow` on a single in-memory flake.
-pub fn run(editor: &Editor, flake_edit: &mut FlakeEdit, state: &AppState) -> Result<()> {
-    run_impl(editor, flake_edit, state, false)
+///
+/// With `summary` the per-change listing and the diff give way to a
+/// single tally, see [`Tally`].
+pub fn run(
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    summary: bool,
+) -> Result<()> {
+    if !summary {
+        return run_impl(
+            editor,
+            flake_edit,
+            state,
+            state.quiet_success || state.dry_run,
+        );
+    }
+    // The tally is all that goes to stdout: the write stays silent and
+    // `--dry-run` leaves the file alone without printing it.
+    let quiet_state = state
+        .clone()
+        .with_quiet_success(true)
+        .with_diff(state.diff || state.dry_run)
+        .with_dry_run(false);
+    let tally = run_planned(editor, flake_edit, &quiet_state, true)?
+        .map(|applied| Tally::of(&applied))
+        .unwrap_or_default();
+    if !state.quiet_success {
+        tally.print(state.output);
+    }
+    Ok(())
+}
+
+/// Counts of what `follow` changed, for `--summary`.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+struct Tally {
+    followed: usize,
+    removed_stale: usize,
+    promoted: usize,
+}
+
+impl Tally {
+    fn of(applied: &AppliedPlan) -> Self {
+        Self {
+            followed: applied.applied_follows.len(),
+            removed_stale: applied.unfollowed.len(),
+            promoted: applied.promoted.len(),
+        }
+    }
+
+    fn print(&self, output: OutputFormat) {
+        let Self {
+            followed,
+            removed_stale,
+            promoted,
+        } = self;
+        match output {
+            OutputFormat::Text => println!(
+                "followed: {followed}, removed stale: {removed_stale}, promoted: {promoted}"
+            ),
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string(self).expect("Tally serializes to JSON")
+            ),
+        }
+    }
 }
 
 /// Run auto-follow against in-memory text.
@@ -88,10 +153,7 @@ pub fn run_batch(
     let mut errors: Vec<(PathBuf, Box<Error>)> = Vec::new();
 
     for flake_path in paths {
-        let lock_path = flake_path
-            .parent()
-            .map(|p| p.join("flake.lock"))
-            .unwrap_or_else(|| PathBuf::from("flake.lock"));
+        let lock_path = FlakeLock::path_beside(flake_path);
 
         let editor = match Editor::from_path(flake_path.clone()) {
             Ok(e) => e,
@@ -118,12 +180,19 @@ pub fn run_batch(
         let mut state = match AppState::new(flake_path.clone(), args.config().map(PathBuf::from)) {
             Ok(s) => s
                 .with_diff(args.diff())
+                .with_diff_context(args.context())
+                .with_dry_run(args.dry_run())
                 .with_no_lock(args.no_lock())
+                .with_relock(args.relock())
                 .with_lock_offline(true)
                 .with_interactive(false)
+                .with_quiet_success(args.quiet_success())
                 .with_lock_file(Some(lock_path))
                 .with_no_cache(args.no_cache())
-                .with_cache_path(args.cache().map(PathBuf::from)),
+                .with_cache_path(args.cache().map(PathBuf::from))
+                .with_output(args.output())
+                .with_sort_params(args.sort_params())
+                .with_no_color(args.no_color()),
             Err(e) => {
                 errors.push((flake_path.clone(), Box::new(e.into())));
                 continue;
@@ -199,6 +268,9 @@ struct FollowPlan {
     toplevel_follows: Vec<(AttrPath, AttrPath)>,
     toplevel_adds: Vec<(String, String)>,
     seen_nested: HashSet<AttrPath>,
+    /// Follows dropped by [`reject_batch_cycles`], as the cycle each one
+    /// would have closed.
+    rejected_cycles: Vec<Cycle>,
 }
 
 impl FollowPlan {
@@ -223,6 +295,10 @@ struct AppliedPlan {
     applied_follows: Vec<(AttrPath, AttrPath)>,
     /// Stale follows declarations that were removed.
     unfollowed: Vec<AttrPath>,
+    /// Nested inputs added as top-level inputs so others can follow them.
+    promoted: Vec<String>,
+    /// Cycles the plan would have closed, see [`reject_batch_cycles`].
+    rejected_cycles: Vec<Cycle>,
     /// Validation warnings observed across speculative applications, in
     /// arrival order. The caller deduplicates for display.
     warnings: Vec<validate::ValidationError>,
@@ -234,11 +310,22 @@ fn run_impl(
     state: &AppState,
     quiet: bool,
 ) -> Result<()> {
+    run_planned(editor, flake_edit, state, quiet).map(|_| ())
+}
+
+/// Plan, apply and report the deduplication. Returns what was applied,
+/// or `None` when there was nothing to plan.
+fn run_planned(
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    quiet: bool,
+) -> Result<Option<AppliedPlan>> {
     let Some(ctx) = load_follow_context(flake_edit, state)? else {
         if !quiet {
             println!("Nothing to deduplicate.");
         }
-        return Ok(());
+        return Ok(None);
     };
 
     let lock_graph = FollowsGraph::from_nested_inputs(&ctx.nested_inputs);
@@ -258,7 +345,7 @@ fn run_impl(
         if !quiet {
             println!("{SENTINEL_ALREADY_DEDUPLICATED}");
         }
-        return Ok(());
+        return Ok(None);
     };
 
     let applied = apply_plan_text(
@@ -268,7 +355,8 @@ fn run_impl(
         &lock_graph,
         &plan,
     )?;
-    render_summary(editor, state, &applied, quiet)
+    render_summary(editor, state, &applied, quiet)?;
+    Ok(Some(applied))
 }
 
 fn build_plan(
@@ -340,8 +428,10 @@ fn build_plan(
     collect_direct_candidates(&ax, &mut plan);
 
     scrub_redundant(&graph_for_discovery, &mut plan);
+    reject_batch_cycles(&graph_for_discovery, &mut plan);
 
-    if !plan.has_pending() {
+    // A rejected cycle is still reported, even when nothing else is left.
+    if !plan.has_pending() && plan.rejected_cycles.is_empty() {
         return None;
     }
 
@@ -498,6 +588,10 @@ fn resolve_direct_candidate(
     let nested_name = nested.path.last().as_str();
     let path_display = nested.path.to_string();
 
+    if !ax.follow_config.can_consider(nested_name) {
+        tracing::debug!("Skipping {}: not in follow.only", path_display);
+        return None;
+    }
     if ax.follow_config.is_ignored(&path_display, nested_name) {
         tracing::debug!("Skipping {}: ignored by config", path_display);
         return None;
@@ -609,7 +703,9 @@ fn resolve_transitive_candidate(
     let parent = nested.path.first().as_str();
     let path_display = nested.path.to_string();
 
-    if ax.follow_config.is_ignored(&path_display, nested_name) {
+    if !ax.follow_config.can_consider(nested_name)
+        || ax.follow_config.is_ignored(&path_display, nested_name)
+    {
         return None;
     }
     if ax.existing_follows.contains(&nested.path) || plan.seen_nested.contains(&nested.path) {
@@ -683,7 +779,9 @@ fn collect_direct_groups(
         let nested_name = nested.path.last().as_str();
         let path_display = nested.path.to_string();
 
-        if ax.follow_config.is_ignored(&path_display, nested_name) {
+        if !ax.follow_config.can_consider(nested_name)
+            || ax.follow_config.is_ignored(&path_display, nested_name)
+        {
             continue;
         }
         if ax.existing_follows.contains(&nested.path) || plan.seen_nested.contains(&nested.path) {
@@ -827,7 +925,8 @@ fn decide_direct_promotion(
             input: ChangeId::new(path.clone()),
             target: target_attr.clone(),
         };
-        let mut fe = FlakeEdit::from_syntax(probe_syntax.clone());
+        // The target is only added when the promotion is committed.
+        let mut fe = FlakeEdit::from_syntax(probe_syntax.clone()).with_dangling_follows(true);
         fe.apply_change(change)
             .ok()
             .and_then(|outcome| outcome.text)
@@ -916,6 +1015,42 @@ fn scrub_redundant(graph: &FollowsGraph, plan: &mut FollowPlan) {
     plan.to_follow.retain(|_| keep_iter.next().unwrap_or(true));
 }
 
+/// Drop planned follows that would close a cycle together with the ones
+/// planned before them.
+///
+/// Candidates are checked against the graph one by one during discovery,
+/// which cannot see the rest of the batch: `a.b -> b` and `b.a -> a` are
+/// each fine alone. Nix refuses a flake whose follows form a cycle, so
+/// the later follows of such a pair is dropped and its cycle recorded in
+/// [`FollowPlan::rejected_cycles`]. Checked in apply order.
+fn reject_batch_cycles(graph: &FollowsGraph, plan: &mut FollowPlan) {
+    let mut staged = graph.clone();
+    let mut rejected = Vec::new();
+    let mut check = |(source, follows): &(AttrPath, AttrPath)| {
+        let edge = Edge {
+            source: source.clone(),
+            follows: follows.clone(),
+            origin: EdgeOrigin::Declared {
+                range: Range::default(),
+            },
+        };
+        if let Some(cycle) = staged.cycle_through(&edge) {
+            tracing::debug!(
+                "Rejecting {} -> {}: closes a follows cycle",
+                source,
+                follows
+            );
+            rejected.push(cycle);
+            return false;
+        }
+        staged.add_edge(edge);
+        true
+    };
+    plan.toplevel_follows.retain(&mut check);
+    plan.to_follow.retain(&mut check);
+    plan.rejected_cycles = rejected;
+}
+
 /// Pairing `current_text` with its `ParsedSource` lets each iteration
 /// share a single rnix parse: each accepted change replaces both fields
 /// in lockstep, sparing the next phase a re-parse of identical text.
@@ -958,9 +1093,14 @@ impl PlanState {
         };
         let resulting_text = match outcome.text {
             Some(t) => t,
+            // Already in place: the current text and parse stand as they are.
+            None if outcome.unchanged => {
+                return StepOutcome::Accepted {
+                    text_changed: false,
+                };
+            }
             None => return StepOutcome::NoText,
         };
-        let text_changed = resulting_text != self.current_text;
         let resulting_parsed = validate::ParsedSource::new(&resulting_text);
         let validation = validate::validate_speculative_parsed(
             &resulting_parsed,
@@ -971,7 +1111,7 @@ impl PlanState {
             self.warnings.extend(validation.warnings);
             self.current_text = resulting_text;
             self.current_parsed = resulting_parsed;
-            StepOutcome::Accepted { text_changed }
+            StepOutcome::Accepted { text_changed: true }
         } else {
             StepOutcome::Rejected(validation.errors)
         }
@@ -1033,7 +1173,7 @@ fn apply_plan_text(
     };
 
     // Top-level adds must precede follows that name them.
-    apply_toplevel_adds(plan, &mut state, lock_graph_ref);
+    let promoted = apply_toplevel_adds(plan, &mut state, lock_graph_ref);
     let applied_follows = apply_follow_changes(plan, &mut state, lock_graph_ref);
     let unfollowed = apply_unfollow_changes(plan, &mut state, lock_graph_ref);
 
@@ -1041,15 +1181,19 @@ fn apply_plan_text(
         current_text: state.current_text,
         applied_follows,
         unfollowed,
+        promoted,
+        rejected_cycles: plan.rejected_cycles.clone(),
         warnings: state.warnings,
     })
 }
 
+/// Add the promoted top-level inputs, returning the ids that were added.
 fn apply_toplevel_adds(
     plan: &FollowPlan,
     state: &mut PlanState,
     lock_graph_ref: Option<&FollowsGraph>,
-) {
+) -> Vec<String> {
+    let mut promoted = Vec::new();
     for (id, url) in &plan.toplevel_adds {
         let change_id = match ChangeId::parse(id) {
             Ok(change_id) => change_id,
@@ -1064,7 +1208,7 @@ fn apply_toplevel_adds(
             flake: true,
         };
         match state.try_apply_one(change, lock_graph_ref) {
-            StepOutcome::Accepted { .. } => {}
+            StepOutcome::Accepted { .. } => promoted.push(id.clone()),
             StepOutcome::Rejected(errors) => {
                 for err in errors {
                     tracing::error!("could not add top-level input {id}: {err}");
@@ -1078,6 +1222,7 @@ fn apply_toplevel_adds(
             }
         }
     }
+    promoted
 }
 
 fn apply_follow_changes(
@@ -1087,7 +1232,34 @@ fn apply_follow_changes(
 ) -> Vec<(AttrPath, AttrPath)> {
     let mut follow_changes: Vec<(AttrPath, AttrPath)> = plan.toplevel_follows.clone();
     follow_changes.extend(plan.to_follow.iter().cloned());
+    if follow_changes.is_empty() {
+        return Vec::new();
+    }
+
+    // The whole plan as one `Change::AddFollows` first. Declarations
+    // already in place are left out of the summary, as below.
+    let declared: HashSet<(AttrPath, AttrPath)> = {
+        let mut temp = FlakeEdit::from_syntax(state.current_parsed.syntax.clone());
+        FollowsGraph::from_declared(temp.list())
+            .edges()
+            .map(|edge| (edge.source.clone(), edge.follows.clone()))
+            .collect()
+    };
+    let batch = Change::AddFollows {
+        entries: follow_changes
+            .iter()
+            .map(|(input_path, target)| (ChangeId::new(input_path.clone()), target.clone()))
+            .collect(),
+    };
+    if let StepOutcome::Accepted { .. } = state.try_apply_one(batch, lock_graph_ref) {
+        return follow_changes
+            .into_iter()
+            .filter(|follows| !declared.contains(follows))
+            .collect();
+    }
 
+    // A batch that fails is retried one entry at a time, so a single bad
+    // follows is reported on its own and does not hold back the rest.
     let mut applied_follows: Vec<(AttrPath, AttrPath)> = Vec::new();
     for (input_path, target) in &follow_changes {
         let change = Change::Follows {
@@ -1158,6 +1330,20 @@ fn render_summary(
             }
         }
     }
+    if !quiet {
+        for cycle in &applied.rejected_cycles {
+            eprintln!(
+                "warning: skipped {}: it would close the follows cycle {}",
+                format_follows(&cycle.edges[0]),
+                cycle
+                    .edges
+                    .iter()
+                    .map(format_follows)
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            );
+        }
+    }
 
     // Empty plans short-circuit earlier (see [`build_plan`]).
     if applied.current_text == editor.text() {
@@ -1168,9 +1354,13 @@ fn render_summary(
     }
 
     if state.diff {
-        let original = editor.text();
-        let diff = crate::diff::Diff::new(&original, &applied.current_text);
-        diff.compare();
+        if !state.quiet_success {
+            let original = editor.text();
+            let diff = crate::diff::Diff::new(&original, &applied.current_text)
+                .with_color(state.color())
+                .with_context(state.diff_context);
+            diff.compare();
+        }
         return Ok(());
     }
 
@@ -1213,8 +1403,13 @@ fn render_summary(
     Ok(())
 }
 
+fn format_follows(edge: &Edge) -> String {
+    format!("{} -> {}", edge.source, edge.follows)
+}
+
 /// Source path of the malformed declaration named by `err`, or `None`
-/// for variants that carry no source (parse errors, duplicate attributes).
+/// for variants that carry no source (parse errors, duplicate attributes,
+/// lock type mismatches).
 fn offending_source(err: &validate::ValidationError) -> Option<&AttrPath> {
     use validate::ValidationError as V;
     match err {
@@ -1224,7 +1419,7 @@ fn offending_source(err: &validate::ValidationError) -> Option<&AttrPath> {
         V::FollowsContradiction { edges, .. } => edges.first().map(|e| &e.source),
         V::FollowsCycle { cycle, .. } => cycle.edges.first().map(|e| &e.source),
         V::FollowsStaleLock { source_path, .. } => Some(source_path),
-        V::ParseError { .. } | V::DuplicateAttribute(_) => None,
+        V::ParseError { .. } | V::DuplicateAttribute(_) | V::LockTypeMismatch { .. } => None,
     }
 }
 
@@ -1937,4 +2132,63 @@ mod tests {
             "state must be untouched on a non-Accepted outcome",
         );
     }
+
+    fn plan_following(to_follow: &[(&str, &str)]) -> FollowPlan {
+        FollowPlan {
+            to_follow: to_follow.iter().map(|(s, t)| (ap(s), ap(t))).collect(),
+            ..FollowPlan::default()
+        }
+    }
+
+    #[test]
+    fn reject_batch_cycles_drops_two_node_cycle() {
+        let mut plan = plan_following(&[("a.b", "b"), ("b.a", "a"), ("a.nixpkgs", "nixpkgs")]);
+        reject_batch_cycles(&FollowsGraph::default(), &mut plan);
+        assert_eq!(
+            plan.to_follow,
+            [(ap("a.b"), ap("b")), (ap("a.nixpkgs"), ap("nixpkgs"))]
+        );
+        assert_eq!(plan.rejected_cycles.len(), 1);
+        assert_eq!(
+            plan.rejected_cycles[0].edges,
+            [declared_edge("b.a", "a"), declared_edge("a.b", "b")]
+        );
+    }
+
+    #[test]
+    fn reject_batch_cycles_drops_three_node_cycle() {
+        let mut plan = plan_following(&[("a.b", "b"), ("b.c", "c"), ("c.a", "a")]);
+        reject_batch_cycles(&FollowsGraph::default(), &mut plan);
+        assert_eq!(plan.to_follow, [(ap("a.b"), ap("b")), (ap("b.c"), ap("c"))]);
+        assert_eq!(
+            plan.rejected_cycles[0].edges,
+            [
+                declared_edge("c.a", "a"),
+                declared_edge("a.b", "b"),
+                declared_edge("b.c", "c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_batch_cycles_sees_existing_edges() {
+        // `a.b -> b` is already declared; only the new half is dropped.
+        let mut graph = FollowsGraph::default();
+        graph.add_edge(declared_edge("a.b", "b"));
+        let mut plan = plan_following(&[("b.a", "a")]);
+        reject_batch_cycles(&graph, &mut plan);
+        assert!(plan.to_follow.is_empty());
+        assert_eq!(plan.rejected_cycles.len(), 1);
+    }
+
+    #[test]
+    fn reject_batch_cycles_keeps_acyclic_batches() {
+        let mut plan = plan_following(&[
+            ("crane.nixpkgs", "nixpkgs"),
+            ("home-manager.nixpkgs", "nixpkgs"),
+        ]);
+        reject_batch_cycles(&FollowsGraph::default(), &mut plan);
+        assert_eq!(plan.to_follow.len(), 2);
+        assert!(plan.rejected_cycles.is_empty());
+    }
 }
@@ -0,0 +1,39 @@
+//! `flake-edit lint`: report inputs whose url is declared more than once.
+//!
+//! Works from the text, since a flake that mixes a flat `inputs.<id>.url`
+//! with an entry in the `inputs = { ... }` block does not load into a
+//! [`crate::edit::FlakeEdit`]. Read-only. Any duplicate fails the
+//! command, so it can gate CI, and so does a file that does not parse.
+
+use crate::edit;
+use crate::validate::ParsedSource;
+
+use super::super::editor::Editor;
+use super::super::state::AppState;
+use super::{Error, Result};
+
+pub fn lint(editor: &Editor, state: &AppState) -> Result<()> {
+    let text = editor.text();
+    let parsed = ParsedSource::new(&text);
+    if !parsed.parse_errors.is_empty() {
+        return Err(crate::Error::Validation(parsed.parse_errors).into());
+    }
+    let duplicates = edit::duplicate_inputs(&text);
+    if duplicates.is_empty() {
+        if !state.quiet_success {
+            println!("No duplicate inputs.");
+        }
+        return Ok(());
+    }
+    for (id, locations) in &duplicates {
+        let lines: Vec<String> = locations.iter().map(|l| l.line.to_string()).collect();
+        println!(
+            "duplicate input: {id} is declared {} times, at lines {}",
+            locations.len(),
+            lines.join(", ")
+        );
+    }
+    Err(Error::DuplicateInputs {
+        count: duplicates.len(),
+    })
+}
@@ -2,25 +2,157 @@
 //! formats.
 //!
 //! Owns the [`ListOutput`] / [`InputView`] / [`FollowEdge`] wire
-//! types used by the JSON formatter and the per-format renderers
-//! behind [`ListFormat`].
+//! types used by the JSON formatter, the [`ListEntry`] type behind
+//! `list --json`, and the per-format renderers behind [`ListFormat`],
+//! including the user-supplied `{field}` template.
+//! `list --changed-since` compares lockfiles instead, see
+//! [`list_changed_since`].
 
 use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use nix_uri::FlakeRef;
 use serde::Serialize;
 
 use crate::cli::ListFormat;
 use crate::edit::{FlakeEdit, InputMap, sorted_input_ids};
-use crate::input::Follows;
+use crate::error::Error as FlakeError;
+use crate::follows::AttrPath;
+use crate::input::{Follows, Input};
+use crate::lock::{FlakeLock, RevChange};
 
-use super::Result;
+use super::super::state::AppState;
+use super::{Error, Result, load_flake_lock};
 
-pub fn list(flake_edit: &mut FlakeEdit, format: &ListFormat) -> Result<()> {
-    let inputs = flake_edit.list();
-    list_inputs(inputs, format);
+/// `template` is the `--template` given on the command line, which takes
+/// precedence over `[list] template` in the configuration.
+pub fn list(
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    format: &ListFormat,
+    locked: bool,
+    template: Option<&str>,
+) -> Result<()> {
+    let lock = annotation_lock(state, format, locked);
+    let template = template.or(state.config.list.template.as_deref());
+    println!(
+        "{}",
+        render_list(
+            flake_edit.list(),
+            format,
+            lock.as_ref(),
+            locked,
+            template,
+            state.color()
+        )?
+    );
     Ok(())
 }
 
+/// `list` across several flakes, one `(path, flake, state)` per
+/// `--flake`.
+///
+/// Text formats prefix every line with the flake's path. JSON maps each
+/// path to its [`ListOutput`].
+pub fn list_many(
+    flakes: &mut [(String, FlakeEdit, AppState)],
+    format: &ListFormat,
+    locked: bool,
+    template: Option<&str>,
+) -> Result<()> {
+    if matches!(format, ListFormat::Json) {
+        let out: BTreeMap<&str, ListOutput> = flakes
+            .iter_mut()
+            .map(|(path, flake_edit, _)| (path.as_str(), flake_edit.list().into()))
+            .collect();
+        println!("{}", serde_json::to_string(&out).unwrap());
+        return Ok(());
+    }
+    if matches!(format, ListFormat::Entries) {
+        let out: BTreeMap<&str, Vec<ListEntry>> = flakes
+            .iter_mut()
+            .map(|(path, flake_edit, _)| (path.as_str(), list_entries(flake_edit.list())))
+            .collect();
+        println!("{}", serde_json::to_string(&out).unwrap());
+        return Ok(());
+    }
+
+    let mut buf = String::new();
+    for (path, flake_edit, state) in flakes.iter_mut() {
+        let lock = annotation_lock(state, format, locked);
+        let template = template.or(state.config.list.template.as_deref());
+        for line in render_list(
+            flake_edit.list(),
+            format,
+            lock.as_ref(),
+            locked,
+            template,
+            state.color(),
+        )?
+        .lines()
+        {
+            if !buf.is_empty() {
+                buf.push('\n');
+            }
+            buf.push_str(&format!("{path}: {line}"));
+        }
+    }
+    println!("{buf}");
+    Ok(())
+}
+
+/// `list --changed-since`: the inputs whose locked rev differs from the
+/// one in the lockfile at `reference`, one `id: from -> to` line each,
+/// or a JSON array of [`RevChange`] with `json`.
+pub fn list_changed_since(state: &AppState, reference: &Path, json: bool) -> Result<()> {
+    let reference = FlakeLock::from_file(reference)?;
+    let changes = load_flake_lock(state)?.changed_revs(&reference);
+    if json {
+        println!("{}", serde_json::to_string(&changes).unwrap());
+    } else {
+        let text = render_rev_changes(&changes);
+        if !text.is_empty() {
+            println!("{text}");
+        }
+    }
+    Ok(())
+}
+
+/// `id: from -> to` per change, revs shortened to seven characters and
+/// `none` for a side without one.
+fn render_rev_changes(changes: &[RevChange]) -> String {
+    let short = |rev: &Option<String>| {
+        rev.as_deref()
+            .map_or("none".to_string(), |rev| rev.chars().take(7).collect())
+    };
+    changes
+        .iter()
+        .map(|c| format!("{}: {} -> {}", c.id, short(&c.from), short(&c.to)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The lockfile behind the detailed format's input ages and `--locked`,
+/// or `None` when neither needs it.
+///
+/// A missing lockfile silently drops the annotation. A malformed one
+/// drops it too, but says so under `--locked`, since the user asked
+/// for one.
+fn annotation_lock(state: &AppState, format: &ListFormat, locked: bool) -> Option<FlakeLock> {
+    if !locked && !matches!(format, ListFormat::Detailed) {
+        return None;
+    }
+    match load_flake_lock(state) {
+        Ok(lock) => Some(lock),
+        Err(err @ FlakeError::MalformedLock { .. }) if locked => {
+            eprintln!("warning: {err}");
+            None
+        }
+        Err(_) => None,
+    }
+}
+
 /// JSON output for `flake-edit list --format json`.
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ListOutput {
@@ -108,18 +240,185 @@ impl From<&InputMap> for ListOutput {
     }
 }
 
-/// Dispatches to the renderer matching `format` and prints the
-/// result on stdout.
-pub(super) fn list_inputs(inputs: &InputMap, format: &ListFormat) {
-    match format {
-        ListFormat::Simple => list_simple(inputs),
-        ListFormat::Json => list_json(inputs),
-        ListFormat::Detailed => list_detailed(inputs),
-        ListFormat::Toplevel => list_toplevel(inputs),
+/// One element of the `flake-edit list --json` array.
+///
+/// Unlike [`ListOutput`], this shape is part of the CLI's contract:
+/// fields are only ever added. `follows` holds the input's
+/// `follows = "..."` declarations, `name` being the nested path below
+/// the input and `target` empty for `follows = ""`. `ref_or_rev` is
+/// `None` when the url carries neither or does not parse. `unresolved`
+/// marks a url that is an expression rather than a string, see
+/// [`Input::is_unresolved`]; `url` is then that expression's source.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ListEntry {
+    pub id: String,
+    pub url: String,
+    pub flake: bool,
+    pub follows: Vec<ListEntryFollows>,
+    pub ref_or_rev: Option<String>,
+    pub unresolved: bool,
+}
+
+/// One element of [`ListEntry::follows`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ListEntryFollows {
+    pub name: String,
+    pub target: String,
+}
+
+impl From<&Input> for ListEntry {
+    fn from(input: &Input) -> Self {
+        let follows = input
+            .follows()
+            .iter()
+            .filter_map(|f| match f {
+                Follows::Indirect { path, target } => Some(ListEntryFollows {
+                    name: path.to_string(),
+                    target: target
+                        .as_ref()
+                        .map(|t| t.to_flake_follows_string())
+                        .unwrap_or_default(),
+                }),
+                Follows::Direct(..) => None,
+            })
+            .collect();
+        ListEntry {
+            id: input.id().as_str().to_string(),
+            url: input.url().to_string(),
+            flake: input.flake,
+            follows,
+            ref_or_rev: input
+                .url_parsed()
+                .ok()
+                .filter(|_| !input.is_unresolved())
+                .and_then(|flake_ref| flake_ref.ref_or_rev().map(str::to_string)),
+            unresolved: input.is_unresolved(),
+        }
+    }
+}
+
+/// The inputs as [`ListEntry`]s, sorted by id.
+pub fn list_entries(inputs: &InputMap) -> Vec<ListEntry> {
+    sorted_input_ids(inputs)
+        .into_iter()
+        .map(|key| ListEntry::from(&inputs[key]))
+        .collect()
+}
+
+/// Dispatches to the renderer matching `format`. `lock` and `locked`
+/// are only consulted by the detailed format, `template` only by the
+/// template format, `color` only by the compact format.
+fn render_list(
+    inputs: &InputMap,
+    format: &ListFormat,
+    lock: Option<&FlakeLock>,
+    locked: bool,
+    template: Option<&str>,
+    color: bool,
+) -> Result<String> {
+    Ok(match format {
+        ListFormat::Simple => render_simple(inputs),
+        ListFormat::Json => render_json(inputs),
+        ListFormat::Entries => serde_json::to_string(&list_entries(inputs)).unwrap(),
+        ListFormat::Detailed => render_detailed(inputs, lock, locked, unix_now()),
+        ListFormat::Compact => render_compact(inputs, color),
+        ListFormat::Toplevel => render_toplevel(inputs),
+        ListFormat::Template => {
+            render_template(inputs, template.ok_or(Error::MissingListTemplate)?)?
+        }
+    })
+}
+
+/// Placeholders a `list` template may name: the fields of [`ListEntry`].
+const TEMPLATE_FIELDS: [&str; 6] = ["id", "url", "flake", "follows", "ref_or_rev", "unresolved"];
+
+/// A parsed `list` template: literal text and the placeholders between it.
+enum TemplatePiece<'a> {
+    Text(String),
+    Field(&'a str),
+}
+
+/// Split `template` into [`TemplatePiece`]s. `{{` and `}}` are literal
+/// braces. A placeholder outside [`TEMPLATE_FIELDS`], a `{` without its
+/// `}` or a lone `}` is an error, so a typo fails before anything is
+/// printed.
+fn parse_template(template: &str) -> Result<Vec<TemplatePiece<'_>>> {
+    let invalid = |reason: String| Error::ListTemplate {
+        reason,
+        valid: TEMPLATE_FIELDS.map(|f| format!("{{{f}}}")).join(", "),
+    };
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(index) = rest.find(['{', '}']) {
+        text.push_str(&rest[..index]);
+        let tail = &rest[index..];
+        if let Some(after) = tail.strip_prefix("{{").or_else(|| tail.strip_prefix("}}")) {
+            text.push_str(&tail[..1]);
+            rest = after;
+            continue;
+        }
+        if tail.starts_with('}') {
+            return Err(invalid("unmatched '}'".to_string()));
+        }
+        let Some(end) = tail.find('}') else {
+            return Err(invalid("unclosed '{'".to_string()));
+        };
+        let name = &tail[1..end];
+        if !TEMPLATE_FIELDS.contains(&name) {
+            return Err(invalid(format!("unknown placeholder '{{{name}}}'")));
+        }
+        if !text.is_empty() {
+            pieces.push(TemplatePiece::Text(std::mem::take(&mut text)));
+        }
+        pieces.push(TemplatePiece::Field(name));
+        rest = &tail[end + 1..];
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        pieces.push(TemplatePiece::Text(text));
     }
+    Ok(pieces)
+}
+
+/// One line per input, `template` filled in from its [`ListEntry`].
+fn render_template(inputs: &InputMap, template: &str) -> Result<String> {
+    let pieces = parse_template(template)?;
+    let lines: Vec<String> = list_entries(inputs)
+        .iter()
+        .map(|entry| {
+            pieces
+                .iter()
+                .map(|piece| match piece {
+                    TemplatePiece::Text(text) => text.clone(),
+                    TemplatePiece::Field(name) => template_field(entry, name),
+                })
+                .collect()
+        })
+        .collect();
+    Ok(lines.join("\n"))
 }
 
-fn list_simple(inputs: &InputMap) {
+/// The value of placeholder `name` for `entry`. A missing `ref_or_rev`
+/// is empty, and `follows` lists `name => target` pairs.
+fn template_field(entry: &ListEntry, name: &str) -> String {
+    match name {
+        "id" => entry.id.clone(),
+        "url" => entry.url.clone(),
+        "flake" => entry.flake.to_string(),
+        "follows" => entry
+            .follows
+            .iter()
+            .map(|f| format!("{} => {}", f.name, f.target))
+            .collect::<Vec<_>>()
+            .join(", "),
+        "ref_or_rev" => entry.ref_or_rev.clone().unwrap_or_default(),
+        "unresolved" => entry.unresolved.to_string(),
+        _ => unreachable!("placeholders are checked by parse_template"),
+    }
+}
+
+fn render_simple(inputs: &InputMap) -> String {
     let mut buf = String::new();
     for key in sorted_input_ids(inputs) {
         let input = &inputs[key];
@@ -137,15 +436,15 @@ fn list_simple(inputs: &InputMap) {
             }
         }
     }
-    println!("{buf}");
+    buf
 }
 
-fn list_json(inputs: &InputMap) {
+fn render_json(inputs: &InputMap) -> String {
     let out: ListOutput = inputs.into();
-    println!("{}", serde_json::to_string(&out).unwrap());
+    serde_json::to_string(&out).unwrap()
 }
 
-fn list_toplevel(inputs: &InputMap) {
+fn render_toplevel(inputs: &InputMap) -> String {
     let mut buf = String::new();
     for key in sorted_input_ids(inputs) {
         if !buf.is_empty() {
@@ -153,7 +452,7 @@ fn list_toplevel(inputs: &InputMap) {
         }
         buf.push_str(&key.to_string());
     }
-    println!("{buf}");
+    buf
 }
 
 /// Returns `true` when `url` is a top-level follows reference (for
@@ -163,17 +462,88 @@ fn is_toplevel_follows(url: &str) -> bool {
     !url.is_empty() && !url.contains(':') && url.contains('/') && !url.starts_with('/')
 }
 
-fn list_detailed(inputs: &InputMap) {
+/// Seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// How long before `now` the epoch timestamp `then` lies, in the largest
+/// whole unit: `3 days ago`, `1 hour ago`. Anything under a minute,
+/// including timestamps in the future, is `just now`.
+fn relative_age(then: u64, now: u64) -> String {
+    const UNITS: [(u64, &str); 5] = [
+        (365 * 24 * 60 * 60, "year"),
+        (30 * 24 * 60 * 60, "month"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+    let elapsed = now.saturating_sub(then);
+    UNITS
+        .iter()
+        .find(|(secs, _)| elapsed >= *secs)
+        .map(|(secs, unit)| {
+            let n = elapsed / secs;
+            let plural = if n == 1 { "" } else { "s" };
+            format!("{n} {unit}{plural} ago")
+        })
+        .unwrap_or_else(|| "just now".to_string())
+}
+
+/// The annotation `lock` gives `input`: ` (<age>)` from the node's
+/// `lastModified` relative to `now`, or with `rev` set,
+/// ` (locked: <rev>, <age>)` with the rev shortened to seven
+/// characters. The age is left out when the node has no timestamp.
+/// Empty when there is nothing to show.
+fn locked_annotation(lock: Option<&FlakeLock>, input: &Input, rev: bool, now: u64) -> String {
+    let Some(lock) = lock else {
+        return String::new();
+    };
+    let path = AttrPath::new(input.id().clone());
+    let age = lock
+        .last_modified_for(&path)
+        .ok()
+        .map(|then| relative_age(then, now));
+    let rev = rev
+        .then(|| lock.rev_for(&path).ok())
+        .flatten()
+        .map(|rev| rev.chars().take(7).collect::<String>());
+    match (rev, age) {
+        (Some(rev), Some(age)) => format!(" (locked: {rev}, {age})"),
+        (Some(rev), None) => format!(" (locked: {rev})"),
+        (None, Some(age)) => format!(" ({age})"),
+        (None, None) => String::new(),
+    }
+}
+
+/// `rev` adds the locked rev to the age `lock` gives each input, see
+/// [`locked_annotation`].
+fn render_detailed(inputs: &InputMap, lock: Option<&FlakeLock>, rev: bool, now: u64) -> String {
     let mut buf = String::new();
     for key in sorted_input_ids(inputs) {
         let input = &inputs[key];
         if !buf.is_empty() {
             buf.push('\n');
         }
-        let line = if is_toplevel_follows(input.url()) {
+        let line = if input.is_unresolved() {
+            format!(
+                "· {} - {} (unresolved){}",
+                input.id().as_str(),
+                input.url(),
+                locked_annotation(lock, input, rev, now)
+            )
+        } else if is_toplevel_follows(input.url()) {
             format!("· {} <= {}", input.id().as_str(), input.url())
         } else {
-            format!("· {} - {}", input.id().as_str(), input.url())
+            format!(
+                "· {} - {}{}",
+                input.id().as_str(),
+                input.url(),
+                locked_annotation(lock, input, rev, now)
+            )
         };
         buf.push_str(&line);
         for follows in input.follows() {
@@ -192,7 +562,84 @@ fn list_detailed(inputs: &InputMap) {
             }
         }
     }
-    println!("{buf}");
+    buf
+}
+
+/// One row of the compact table: `id`, `type`, `ref`, `pinned`.
+fn compact_row(input: &Input) -> [String; 4] {
+    let id = input.id().as_str().to_string();
+    let url = input.url();
+    if input.is_unresolved() {
+        return [id, "unresolved".into(), "-".into(), "no".into()];
+    }
+    if is_toplevel_follows(url) {
+        return [id, "follows".into(), url.to_string(), "no".into()];
+    }
+    let kind = match url.split_once(':') {
+        Some((scheme, _)) => scheme,
+        None if url.is_empty() => "-",
+        None => "indirect",
+    };
+    let parsed = url.parse::<FlakeRef>().ok();
+    let reference = match parsed.as_ref().and_then(FlakeRef::ref_or_rev) {
+        Some(r) if parsed.as_ref().is_some_and(FlakeRef::is_pinned_to_rev) => {
+            r.get(..7).unwrap_or(r).to_string()
+        }
+        Some(r) => r.to_string(),
+        None => "-".to_string(),
+    };
+    let pinned = if parsed.as_ref().is_some_and(FlakeRef::is_pinned_to_rev) {
+        "yes"
+    } else {
+        "no"
+    };
+    [id, kind.to_string(), reference, pinned.to_string()]
+}
+
+/// Render one line per input with columns padded to the widest cell.
+/// With `color`, the header row is bold. The last column is not padded,
+/// so lines carry no trailing whitespace.
+fn render_compact(inputs: &InputMap, color: bool) -> String {
+    const HEADER: [&str; 4] = ["id", "type", "ref", "pinned"];
+    let rows: Vec<[String; 4]> = sorted_input_ids(inputs)
+        .into_iter()
+        .map(|key| compact_row(&inputs[key]))
+        .collect();
+
+    let mut widths = HEADER.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let render_line = |cells: [&str; 4]| {
+        let last = cells.len() - 1;
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                if i == last {
+                    cell.to_string()
+                } else {
+                    format!("{cell:<width$}", width = widths[i])
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+
+    let header = render_line(HEADER);
+    let mut buf = if color {
+        format!("\x1b[1m{header}\x1b[0m")
+    } else {
+        header
+    };
+    for row in &rows {
+        buf.push('\n');
+        buf.push_str(&render_line(row.each_ref().map(String::as_str)));
+    }
+    buf
 }
 
 #[cfg(test)]
@@ -325,6 +772,117 @@ mod tests {
         assert_eq!(keys, vec!["aaa", "zzz"]);
     }
 
+    const LOCKED_FLAKE: &str = r#"{
+        inputs.nixpkgs.url = "github:nixos/nixpkgs";
+        inputs.crane.url = "github:ipetkov/crane";
+        outputs = { ... }: { };
+    }
+    "#;
+
+    const LOCKED_LOCK: &str = r#"{
+  "nodes": {
+    "nixpkgs": {
+      "locked": {
+        "owner": "nixos",
+        "repo": "nixpkgs",
+        "rev": "abc1234def5678abc1234def5678abc1234def56",
+        "type": "github"
+      },
+      "original": { "owner": "nixos", "repo": "nixpkgs", "type": "github" }
+    },
+    "root": { "inputs": { "nixpkgs": "nixpkgs" } }
+  },
+  "root": "root",
+  "version": 7
+}"#;
+
+    #[test]
+    fn detailed_with_lock_annotates_locked_inputs_only() {
+        let mut fe = FlakeEdit::from_text(LOCKED_FLAKE).unwrap();
+        let lock = FlakeLock::read_from_str(LOCKED_LOCK).unwrap();
+        let out = render_detailed(fe.list(), Some(&lock), true, 0);
+        assert_eq!(
+            out,
+            "· crane - github:ipetkov/crane\n\
+             · nixpkgs - github:nixos/nixpkgs (locked: abc1234)"
+        );
+    }
+
+    #[test]
+    fn detailed_with_lock_shows_age_of_last_modified() {
+        let mut fe = FlakeEdit::from_text(LOCKED_FLAKE).unwrap();
+        let lock = FlakeLock::read_from_str(&LOCKED_LOCK.replace(
+            r#""owner": "nixos","#,
+            r#""lastModified": 1700000000, "owner": "nixos","#,
+        ))
+        .unwrap();
+        let now = 1700000000 + 3 * 24 * 60 * 60 + 5;
+        let out = render_detailed(fe.list(), Some(&lock), true, now);
+        assert!(
+            out.contains("github:nixos/nixpkgs (locked: abc1234, 3 days ago)"),
+            "{out}"
+        );
+        let out = render_detailed(fe.list(), Some(&lock), false, now);
+        assert_eq!(
+            out,
+            "· crane - github:ipetkov/crane\n\
+             · nixpkgs - github:nixos/nixpkgs (3 days ago)"
+        );
+    }
+
+    #[test]
+    fn template_renders_each_input() {
+        let mut fe = FlakeEdit::from_text(LOCKED_FLAKE).unwrap();
+        let out =
+            render_template(fe.list(), "{id}: {url} ({ref_or_rev}) {{flake={flake}}}").unwrap();
+        assert_eq!(
+            out,
+            "crane: github:ipetkov/crane () {flake=true}\n\
+             nixpkgs: github:nixos/nixpkgs () {flake=true}"
+        );
+    }
+
+    #[test]
+    fn template_rejects_unknown_and_unbalanced_placeholders() {
+        let mut fe = FlakeEdit::from_text(LOCKED_FLAKE).unwrap();
+        for (template, reason) in [
+            ("{id} {bogus}", "unknown placeholder '{bogus}'"),
+            ("{id", "unclosed '{'"),
+            ("{id} }", "unmatched '}'"),
+        ] {
+            let err = render_template(fe.list(), template).unwrap_err();
+            assert!(
+                matches!(&err, Error::ListTemplate { reason: r, valid }
+                    if r == reason && valid.starts_with("{id}, {url}")),
+                "{template}: {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn relative_age_picks_largest_unit() {
+        let now = 2_000_000_000;
+        for (elapsed, expected) in [
+            (0, "just now"),
+            (59, "just now"),
+            (60, "1 minute ago"),
+            (2 * 60 * 60 + 59, "2 hours ago"),
+            (24 * 60 * 60, "1 day ago"),
+            (45 * 24 * 60 * 60, "1 month ago"),
+            (800 * 24 * 60 * 60, "2 years ago"),
+        ] {
+            assert_eq!(relative_age(now - elapsed, now), expected, "{elapsed}s");
+        }
+        assert_eq!(relative_age(now + 100, now), "just now");
+    }
+
+    #[test]
+    fn detailed_without_lock_has_no_annotation() {
+        let mut fe = FlakeEdit::from_text(LOCKED_FLAKE).unwrap();
+        let out = render_detailed(fe.list(), None, true, 0);
+        assert!(!out.contains("locked:"), "unexpected annotation: {out}");
+    }
+
     #[test]
     fn test_is_toplevel_follows() {
         for url in [
@@ -349,4 +907,42 @@ mod tests {
             );
         }
     }
+
+    fn compact_inputs() -> InputMap {
+        let mut inputs = InputMap::new();
+        for (id, url) in [
+            ("nixpkgs", "github:nixos/nixpkgs/nixos-unstable"),
+            ("a", "git+https://git.example.com/a"),
+            (
+                "home-manager",
+                "github:nix-community/home-manager/0123456789abcdef0123456789abcdef01234567",
+            ),
+        ] {
+            let mut input = Input::new(Segment::from_unquoted(id).unwrap());
+            input.url = url.into();
+            inputs.insert(id.into(), input);
+        }
+        inputs
+    }
+
+    #[test]
+    fn compact_aligns_columns_across_id_lengths() {
+        let out = render_compact(&compact_inputs(), false);
+        assert_eq!(
+            out,
+            "\
+id            type       ref             pinned
+a             git+https  -               no
+home-manager  github     0123456         yes
+nixpkgs       github     nixos-unstable  no",
+        );
+    }
+
+    #[test]
+    fn compact_color_only_styles_header() {
+        let out = render_compact(&compact_inputs(), true);
+        let mut lines = out.lines();
+        assert!(lines.next().unwrap().starts_with("\x1b[1mid "));
+        assert!(lines.all(|line| !line.contains('\x1b')));
+    }
 }
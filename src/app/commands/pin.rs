@@ -2,24 +2,22 @@
 //! specific revision on an input.
 //!
 //! `pin` reads `flake.lock` to default the target rev when the user
-//! does not supply one. `unpin`'s interactive picker filters to
-//! inputs whose URL already carries a `ref_or_rev`.
-
-use nix_uri::FlakeRef;
+//! does not supply one. With `--before` it asks the input's forge for
+//! the newest commit before that date on the input's ref instead.
+//! `unpin`'s interactive picker filters to inputs whose URL already
+//! carries a `ref_or_rev`.
 
+use crate::change::{Change, ChangeId};
 use crate::edit::{FlakeEdit, sorted_input_ids};
-use crate::follows::AttrPath;
+use crate::follows::{AttrPath, Segment};
+use crate::forge::commits::Date;
 
 use super::super::editor::Editor;
 use super::super::state::AppState;
-use super::{Error, Result, interactive_single_select, load_flake_lock, updater};
-
-fn lock_path_display(state: &AppState) -> std::path::PathBuf {
-    state
-        .lock_file
-        .clone()
-        .unwrap_or_else(|| std::path::PathBuf::from("flake.lock"))
-}
+use super::{
+    Error, Result, interactive_single_select, load_flake_lock, report_applied, report_unchanged,
+    updater,
+};
 
 pub fn pin(
     editor: &Editor,
@@ -27,6 +25,7 @@ pub fn pin(
     state: &AppState,
     id: Option<String>,
     rev: Option<String>,
+    before: Option<Date>,
 ) -> Result<()> {
     let inputs = flake_edit.list().clone();
     let input_ids = sorted_input_ids(&inputs)
@@ -35,34 +34,43 @@ pub fn pin(
         .collect::<Vec<_>>();
 
     if let Some(id) = id {
-        let lock = load_flake_lock(state).map_err(|source| Error::LockFile {
-            path: lock_path_display(state),
-            source,
-        })?;
+        if let Some(date) = before {
+            let mut updater = updater(editor, state, inputs);
+            let rev = updater.pin_input_before(&id, &date)?;
+            let change = Change::Pin {
+                id: input_change_id(&id)?,
+                rev,
+            };
+            return write_and_report(editor, state, &updater.get_changes(), &change);
+        }
         let target_rev = if let Some(rev) = rev {
             rev
         } else {
+            let lock = load_flake_lock(state).map_err(|source| Error::LockFile {
+                path: state.lock_path(),
+                source,
+            })?;
             let path = AttrPath::parse(&id).map_err(|source| Error::InvalidInputId {
                 id: id.clone(),
                 source,
             })?;
             lock.rev_for(&path)?
         };
-        let mut updater = updater(editor, inputs);
+        let mut updater = updater(editor, state, inputs);
         updater
             .pin_input_to_ref(&id, &target_rev)
             .map_err(|id| Error::InputNotPinnable { id })?;
-        let change = updater.get_changes();
-        editor.apply_or_diff(&change, state)?;
-        if !state.diff {
-            println!("Pinned input: {} to {}", id, target_rev);
-        }
+        let change = Change::Pin {
+            id: input_change_id(&id)?,
+            rev: target_rev,
+        };
+        write_and_report(editor, state, &updater.get_changes(), &change)?;
     } else if state.interactive {
         if input_ids.is_empty() {
             return Err(Error::NoInputs);
         }
         let lock = load_flake_lock(state).map_err(|source| Error::LockFile {
-            path: lock_path_display(state),
+            path: state.lock_path(),
             source,
         })?;
 
@@ -78,13 +86,17 @@ pub fn pin(
                     source,
                 })?;
                 let target_rev = lock.rev_for(&path)?;
-                let mut updater = updater(editor, inputs.clone());
+                let mut updater = updater(editor, state, inputs.clone());
                 updater
                     .pin_input_to_ref(id, &target_rev)
                     .map_err(|id| Error::InputNotPinnable { id })?;
-                Ok((updater.get_changes(), target_rev))
+                let change = Change::Pin {
+                    id: input_change_id(id)?,
+                    rev: target_rev,
+                };
+                Ok((updater.get_changes(), change))
             },
-            |id, target_rev| println!("Pinned input: {} to {}", id, target_rev),
+            |_, change| report_applied(state, &change),
         )?;
     } else {
         return Err(Error::NoId);
@@ -106,22 +118,20 @@ pub fn unpin(
         .collect::<Vec<_>>();
 
     if let Some(id) = id {
-        let mut updater = updater(editor, inputs);
+        let mut updater = updater(editor, state, inputs);
         updater
             .unpin_input(&id)
             .map_err(|id| Error::InputNotPinnable { id })?;
-        let change = updater.get_changes();
-        editor.apply_or_diff(&change, state)?;
-        if !state.diff {
-            println!("Unpinned input: {}", id);
-        }
+        let change = Change::Unpin {
+            id: input_change_id(&id)?,
+        };
+        write_and_report(editor, state, &updater.get_changes(), &change)?;
     } else if state.interactive {
         let pinned_ids: Vec<String> = input_ids
             .into_iter()
             .filter(|id| {
                 inputs[id]
-                    .url()
-                    .parse::<FlakeRef>()
+                    .url_parsed()
                     .is_ok_and(|f| f.ref_kind() != nix_uri::RefKind::None)
             })
             .collect();
@@ -137,13 +147,16 @@ pub fn unpin(
             "Select pinned input",
             pinned_ids,
             |id| {
-                let mut updater = updater(editor, inputs.clone());
+                let mut updater = updater(editor, state, inputs.clone());
                 updater
                     .unpin_input(id)
                     .map_err(|id| Error::InputNotPinnable { id })?;
-                Ok((updater.get_changes(), ()))
+                let change = Change::Unpin {
+                    id: input_change_id(id)?,
+                };
+                Ok((updater.get_changes(), change))
             },
-            |id, ()| println!("Unpinned input: {}", id),
+            |_, change| report_applied(state, &change),
         )?;
     } else {
         return Err(Error::NoId);
@@ -151,3 +164,32 @@ pub fn unpin(
 
     Ok(())
 }
+
+/// The [`ChangeId`] of the top-level input `id`, quoted or not.
+fn input_change_id(id: &str) -> Result<ChangeId> {
+    let bare = id
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(id);
+    Segment::from_unquoted(bare)
+        .map(ChangeId::from)
+        .map_err(|source| Error::InvalidInputId {
+            id: id.to_string(),
+            source: source.into(),
+        })
+}
+
+/// Write `text`, the result of `change`, and report it the way
+/// [`super::apply_change`] does. Nothing is written when `text` is what
+/// flake.nix already holds.
+fn write_and_report(editor: &Editor, state: &AppState, text: &str, change: &Change) -> Result<()> {
+    if text == editor.text() {
+        report_unchanged(state, Some(change), "Nothing changed.");
+        return Ok(());
+    }
+    editor.apply_or_diff(text, state)?;
+    if state.writes() {
+        report_applied(state, change);
+    }
+    Ok(())
+}
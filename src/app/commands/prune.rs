@@ -0,0 +1,54 @@
+//! `flake-edit prune`: drop inputs the outputs never use.
+//!
+//! Lists the unused inputs by default, one `{"status": "unused"}` line
+//! each with `--output json`, and removes them with `--apply`.
+//! An input counts as used when the outputs lambda names it anywhere or
+//! another input follows it. Flakes whose outputs take `...` or bind
+//! `@inputs` are refused, since those can use an input without naming it.
+
+use crate::change::{Change, ChangeId};
+use crate::cli::OutputFormat;
+use crate::edit::FlakeEdit;
+use crate::follows::Segment;
+
+use super::super::editor::Editor;
+use super::super::state::AppState;
+use super::{Error, Result, apply_change, report_unchanged};
+
+pub fn prune(
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    apply: bool,
+) -> Result<()> {
+    let unused = flake_edit.unused_inputs()?.ok_or(Error::OpenOutputs)?;
+    if unused.is_empty() {
+        report_unchanged(state, None, "No unused inputs.");
+        return Ok(());
+    }
+    if !apply {
+        for id in &unused {
+            match state.output {
+                OutputFormat::Text => println!("{id}"),
+                OutputFormat::Json => {
+                    println!("{}", serde_json::json!({ "status": "unused", "id": id }))
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    let ids = unused
+        .iter()
+        .map(|id| {
+            // Input ids are single segments: `hls-1.10` names one input.
+            Segment::from_unquoted(id.as_str())
+                .map(ChangeId::from)
+                .map_err(|source| Error::InvalidInputId {
+                    id: id.clone(),
+                    source: source.into(),
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    apply_change(editor, flake_edit, state, Change::Remove { ids })
+}
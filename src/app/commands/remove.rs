@@ -7,7 +7,7 @@
 //! before parsing back to a [`ChangeId`].
 
 use crate::change::{Change, ChangeId};
-use crate::edit::{FlakeEdit, sorted_input_ids};
+use crate::edit::{FlakeEdit, InputMap, sorted_input_ids};
 use crate::tui;
 
 use super::super::editor::Editor;
@@ -19,6 +19,7 @@ pub fn remove(
     flake_edit: &mut FlakeEdit,
     state: &AppState,
     id: Option<String>,
+    selection: tui::InitialSelection,
 ) -> Result<()> {
     let change = if let Some(id) = id {
         Change::Remove {
@@ -30,47 +31,69 @@ pub fn remove(
             ],
         }
     } else if state.interactive {
-        let inputs = flake_edit.list();
-        let mut removable: Vec<String> = Vec::new();
-        for input_id in sorted_input_ids(inputs) {
-            let input = &inputs[input_id];
-            removable.push(input_id.clone());
-            for follows in input.follows() {
-                if let crate::input::Follows::Indirect { path, target } = follows {
-                    let target_str = match target {
-                        Some(t) => t.to_string(),
-                        None => "\"\"".to_string(),
-                    };
-                    removable.push(format!("{}.{} => {}", input_id, path, target_str));
-                }
-            }
-        }
+        let removable = removable(flake_edit.list(), true);
         if removable.is_empty() {
             return Err(Error::NoInputs);
         }
-
-        let tui_app = tui::App::remove("Remove", editor.text(), removable);
-        let Some(tui::AppResult::Change(tui_change)) = tui::run(tui_app)? else {
+        let Some(change) = pick_removals(editor, "Remove", removable, selection)? else {
             return Ok(());
         };
-
-        // Strip the " => target" suffix on follows entries.
-        if let Change::Remove { ids } = tui_change {
-            let stripped_ids: Vec<_> = ids
-                .iter()
-                .filter_map(|id| {
-                    let s = id.to_string();
-                    let stripped = s.split(" => ").next().unwrap_or(&s);
-                    ChangeId::parse(stripped).ok()
-                })
-                .collect();
-            Change::Remove { ids: stripped_ids }
-        } else {
-            tui_change
-        }
+        change
     } else {
         return Err(Error::NoId);
     };
 
     apply_change(editor, flake_edit, state, change)
 }
+
+/// Picker entries for `remove`: each top-level input when `with_inputs`
+/// is set, followed by its indirect follows as `parent.nested => target`.
+pub(super) fn removable(inputs: &InputMap, with_inputs: bool) -> Vec<String> {
+    let mut removable: Vec<String> = Vec::new();
+    for input_id in sorted_input_ids(inputs) {
+        let input = &inputs[input_id];
+        if with_inputs {
+            removable.push(input_id.clone());
+        }
+        for follows in input.follows() {
+            if let crate::input::Follows::Indirect { path, target } = follows {
+                let target_str = match target {
+                    Some(t) => t.to_string(),
+                    None => "\"\"".to_string(),
+                };
+                removable.push(format!("{}.{} => {}", input_id, path, target_str));
+            }
+        }
+    }
+    removable
+}
+
+/// Run the removal picker over `removable`. `None` means the user
+/// cancelled.
+pub(super) fn pick_removals(
+    editor: &Editor,
+    title: &str,
+    removable: Vec<String>,
+    selection: tui::InitialSelection,
+) -> Result<Option<Change>> {
+    let tui_app =
+        tui::App::remove(title, editor.text(), removable).with_initial_selection(selection);
+    let Some(tui::AppResult::Change(tui_change)) = tui::run(tui_app)? else {
+        return Ok(None);
+    };
+
+    // Strip the " => target" suffix on follows entries.
+    if let Change::Remove { ids } = tui_change {
+        let stripped_ids: Vec<_> = ids
+            .iter()
+            .filter_map(|id| {
+                let s = id.to_string();
+                let stripped = s.split(" => ").next().unwrap_or(&s);
+                ChangeId::parse(stripped).ok()
+            })
+            .collect();
+        Ok(Some(Change::Remove { ids: stripped_ids }))
+    } else {
+        Ok(Some(tui_change))
+    }
+}
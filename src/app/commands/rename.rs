@@ -0,0 +1,33 @@
+//! `flake-edit rename`: change an input's id.
+//!
+//! Scripted only. Both ids are single input names, so a `.` is part of
+//! the name (`hls-1.10`) rather than a path separator.
+
+use crate::change::Change;
+use crate::edit::FlakeEdit;
+use crate::follows::Segment;
+
+use super::super::editor::Editor;
+use super::super::state::AppState;
+use super::{Error, Result, apply_change};
+
+pub fn rename(
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    from: &str,
+    to: &str,
+) -> Result<()> {
+    let change = Change::Rename {
+        from: parse_id(from)?,
+        to: parse_id(to)?,
+    };
+    apply_change(editor, flake_edit, state, change)
+}
+
+fn parse_id(id: &str) -> Result<Segment> {
+    Segment::from_source(id).map_err(|source| Error::InvalidInputId {
+        id: id.to_string(),
+        source: source.into(),
+    })
+}
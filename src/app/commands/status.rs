@@ -0,0 +1,285 @@
+//! `flake-edit status`: report drift between `flake.nix` and `flake.lock`.
+//!
+//! Compares the top-level inputs `flake.nix` declares with the root inputs
+//! of `flake.lock`: inputs missing from the lock, inputs only the lock
+//! still has, and inputs whose url no longer matches the reference the
+//! lock was created from. Read-only. Any drift fails the command, so it
+//! can gate CI. With `--output json` every drifted input is a JSON line.
+
+use std::fmt;
+
+use nix_uri::FlakeRef;
+use ropey::Rope;
+
+use crate::cli::OutputFormat;
+use crate::edit::{FlakeEdit, sorted_input_ids};
+use crate::error::Error as FlakeError;
+use crate::follows::Segment;
+use crate::lock::LockError;
+use crate::validate::Location;
+
+use super::super::state::AppState;
+use super::{Error, Result, input_location, load_flake_lock, report_unchanged};
+
+/// `text` is the flake.nix `flake_edit` was read from, for locating
+/// drifted inputs.
+pub fn status(flake_edit: &mut FlakeEdit, text: &Rope, state: &AppState) -> Result<()> {
+    let lock = load_flake_lock(state).map_err(|source| Error::LockFile {
+        path: state.lock_path(),
+        source,
+    })?;
+    let inputs = flake_edit.list();
+    let locked = lock.input_ids();
+
+    let mut drift = Vec::new();
+    for id in sorted_input_ids(inputs) {
+        let Ok(segment) = Segment::from_unquoted(id.as_str()) else {
+            continue;
+        };
+        let at = input_location(&inputs[id], text);
+        if !locked.contains(&segment) {
+            drift.push(Drift::MissingFromLock { id: id.clone(), at });
+            continue;
+        }
+        let url = inputs[id].url();
+        if url.is_empty() || inputs[id].is_unresolved() {
+            continue;
+        }
+        match lock.original_uri_for(&segment) {
+            Ok(original) if !same_reference(url, &original) => drift.push(Drift::UrlChanged {
+                id: id.clone(),
+                url: url.to_string(),
+                locked: original,
+                at,
+            }),
+            Ok(_) | Err(FlakeError::Lock(LockError::InputFollows { .. })) => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    for id in &locked {
+        if !inputs.contains_key(id.as_str()) {
+            drift.push(Drift::OnlyInLock { id: id.to_string() });
+        }
+    }
+
+    if drift.is_empty() {
+        report_unchanged(state, None, "flake.lock is in sync with flake.nix.");
+        return Ok(());
+    }
+    for drift in &drift {
+        match state.output {
+            OutputFormat::Text => println!("{drift}"),
+            OutputFormat::Json => println!("{}", drift.to_json()),
+        }
+    }
+    Err(Error::LockDrift)
+}
+
+/// One input on which flake.nix and flake.lock disagree. `at` is where
+/// flake.nix declares it.
+enum Drift {
+    MissingFromLock {
+        id: String,
+        at: Option<Location>,
+    },
+    UrlChanged {
+        id: String,
+        url: String,
+        locked: String,
+        at: Option<Location>,
+    },
+    OnlyInLock {
+        id: String,
+    },
+}
+
+impl Drift {
+    /// The `{"status": "drift"}` object `--output json` prints, with the
+    /// `kind` of drift and the fields of the prose line.
+    fn to_json(&self) -> serde_json::Value {
+        let location = |at: &Option<Location>| {
+            at.as_ref()
+                .map(|at| serde_json::json!({ "line": at.line, "column": at.column }))
+        };
+        match self {
+            Drift::MissingFromLock { id, at } => serde_json::json!({
+                "status": "drift",
+                "kind": "missing_from_lock",
+                "id": id,
+                "location": location(at),
+            }),
+            Drift::UrlChanged {
+                id,
+                url,
+                locked,
+                at,
+            } => serde_json::json!({
+                "status": "drift",
+                "kind": "url_changed",
+                "id": id,
+                "url": url,
+                "locked": locked,
+                "location": location(at),
+            }),
+            Drift::OnlyInLock { id } => serde_json::json!({
+                "status": "drift",
+                "kind": "only_in_lock",
+                "id": id,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = |at: &Option<Location>| {
+            at.as_ref()
+                .map(|at| format!(" at {at}"))
+                .unwrap_or_default()
+        };
+        match self {
+            Drift::MissingFromLock { id, at } => {
+                write!(f, "missing from flake.lock{}: {id}", suffix(at))
+            }
+            Drift::UrlChanged {
+                id,
+                url,
+                locked,
+                at,
+            } => write!(
+                f,
+                "url changed{}: {id}: {url} (flake.lock: {locked})",
+                suffix(at)
+            ),
+            Drift::OnlyInLock { id } => write!(f, "only in flake.lock: {id}"),
+        }
+    }
+}
+
+/// Whether two flake urls name the same reference, ignoring spellings
+/// Nix treats as equal: `?ref=` versus a path segment, parameter order,
+/// and the `flake:` prefix of indirect references. `shallow` only
+/// changes how the input is fetched and is ignored too.
+fn same_reference(a: &str, b: &str) -> bool {
+    canonical_reference(a) == canonical_reference(b)
+}
+
+/// Canonical form of `url` for [`same_reference`]. The canonical string
+/// drops `dir`, which still selects a different flake, so it is kept
+/// alongside.
+fn canonical_reference(url: &str) -> (String, Option<&str>) {
+    let url = url.strip_prefix("flake:").unwrap_or(url);
+    match url.parse::<FlakeRef>() {
+        Ok(mut flake_ref) => {
+            flake_ref.set_shallow(false);
+            (flake_ref.to_canonical_string(), query_param(url, "dir"))
+        }
+        Err(_) => (url.to_string(), None),
+    }
+}
+
+/// Value of the query parameter `key` in `url`.
+fn query_param<'a>(url: &'a str, key: &str) -> Option<&'a str> {
+    let query = url.split_once('?')?.1;
+    let query = query.split('#').next().unwrap_or(query);
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find_map(|(k, v)| (k == key).then_some(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_reference_ignores_equivalent_spellings() {
+        for (a, b) in [
+            (
+                "github:nixos/nixpkgs?ref=nixos-unstable",
+                "github:nixos/nixpkgs/nixos-unstable",
+            ),
+            ("nixpkgs", "flake:nixpkgs"),
+            (
+                "git+https://example.org/a?shallow=1&ref=main",
+                "git+https://example.org/a?ref=main",
+            ),
+        ] {
+            assert!(same_reference(a, b), "{a} vs {b}");
+        }
+        for (a, b) in [
+            (
+                "github:nixos/nixpkgs/nixos-unstable",
+                "github:nixos/nixpkgs",
+            ),
+            ("github:a/b?dir=lib", "github:a/b"),
+            ("github:a/b", "github:a/c"),
+        ] {
+            assert!(!same_reference(a, b), "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn same_reference_ignores_param_order() {
+        for (a, b) in [
+            ("github:a/b?dir=lib&ref=main", "github:a/b?ref=main&dir=lib"),
+            (
+                "git+https://example.org/a?rev=0000000000000000000000000000000000000000&ref=main",
+                "git+https://example.org/a?ref=main&rev=0000000000000000000000000000000000000000",
+            ),
+            (
+                "gitlab:a/b?host=git.example.org&dir=nix",
+                "gitlab:a/b?dir=nix&host=git.example.org",
+            ),
+        ] {
+            assert!(same_reference(a, b), "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn same_reference_reads_ref_and_rev_in_either_position() {
+        let rev = "0000000000000000000000000000000000000000";
+        for (a, b) in [
+            (format!("github:a/b/{rev}"), format!("github:a/b?rev={rev}")),
+            ("gitlab:a/b/main".into(), "gitlab:a/b?ref=main".into()),
+            (
+                "sourcehut:~a/b/main".into(),
+                "sourcehut:~a/b?ref=main".into(),
+            ),
+        ] {
+            assert!(same_reference(&a, &b), "{a} vs {b}");
+        }
+        // A branch and the commit it points at are different references.
+        assert!(!same_reference(
+            "github:a/b/main",
+            &format!("github:a/b/{rev}")
+        ));
+    }
+
+    // The lock records owner and repo as they were written, so a change of
+    // case is drift even though the forge would serve the same repository.
+    #[test]
+    fn same_reference_keeps_owner_and_repo_case() {
+        for (a, b) in [
+            ("github:NixOS/nixpkgs", "github:nixos/nixpkgs"),
+            ("gitlab:Foo/Bar", "gitlab:foo/bar"),
+            ("git+https://example.org/A", "git+https://example.org/a"),
+        ] {
+            assert!(!same_reference(a, b), "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn same_reference_ignores_lock_metadata_but_not_nar_hash() {
+        assert!(same_reference("github:a/b?lastModified=1", "github:a/b"));
+        assert!(same_reference("github:a/b?revCount=2", "github:a/b"));
+        assert!(!same_reference(
+            "github:a/b?narHash=sha256-AAAA",
+            "github:a/b"
+        ));
+        assert!(!same_reference(
+            "gitlab:a/b?host=git.example.org",
+            "gitlab:a/b"
+        ));
+    }
+}
@@ -4,6 +4,9 @@
 //! `toggle [INPUT] [REF]`, and any omitted coordinate is inferred. A
 //!
 //! `--remove` deletes the resolved variant's line instead of activating it.
+//!
+//! `flake-edit override` stores a variant without activating it, so the
+//! canonical url stays in effect until the input is toggled.
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -17,7 +20,11 @@ use crate::error::Error as FlakeError;
 use super::super::editor::Editor;
 use super::super::error::{RefCandidate, ToggleAction, ToggleCandidate};
 use super::super::state::AppState;
-use super::{ConfirmResult, Error, Result, apply_change, confirm_or_apply, pick_one};
+use super::uri::parse_flake_ref;
+use super::{
+    ConfirmResult, Error, Result, apply_change, confirm_or_apply, pick_one, report_applied,
+    report_unchanged,
+};
 
 pub fn toggle(
     editor: &Editor,
@@ -58,6 +65,38 @@ pub fn toggle(
     }
 }
 
+/// Store `reference` as a commented alternate of `id`, leaving the active
+/// url in place. A reference that already names one of the input's
+/// variants changes nothing.
+pub fn store_override(
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    id: &str,
+    reference: &str,
+) -> Result<()> {
+    let states = flake_edit.toggle_states()?;
+    validate_id(flake_edit, &states, id)?;
+    let ref_arg = RefArg::parse(reference, ToggleAction::Activate)?;
+    let flake_dir = flake_dir(state);
+    let stored = variants(&states[id]).any(|v| variant_equals(v, &ref_arg, &flake_dir));
+    let change = Change::ToggleStore {
+        id: ChangeId::parse(id).map_err(|source| Error::InvalidInputId {
+            id: id.to_string(),
+            source,
+        })?,
+        uri: ref_arg.store_as,
+    };
+    if stored {
+        report_unchanged(state, Some(&change), "Nothing changed.");
+        return Ok(());
+    }
+    // Only a comment line is added, so the resolved source is unchanged.
+    let mut state = state.clone();
+    state.no_lock = true;
+    apply_change(editor, flake_edit, &state, change)
+}
+
 /// Read-only resolution context shared by every invocation form.
 struct Resolve<'a> {
     /// Toggle surface per input id, from [`FlakeEdit::toggle_states`].
@@ -145,10 +184,7 @@ impl RefArg {
                 flake_ref: None,
             })
         } else {
-            let flake_ref: FlakeRef = typed.parse().map_err(|source| Error::InvalidUri {
-                uri: typed.to_string(),
-                source,
-            })?;
+            let flake_ref = parse_flake_ref(typed)?;
             Ok(Self {
                 typed: typed.to_string(),
                 store_as: typed.to_string(),
@@ -659,13 +695,13 @@ fn confirm_toggle(
     let state = effective_state(state, &change);
     let outcome = flake_edit.apply_change(change.clone())?;
     let Some(text) = outcome.text else {
-        println!("Nothing changed.");
+        report_unchanged(&state, Some(&change), "Nothing changed.");
         return Ok(Some(true));
     };
     match confirm_or_apply(editor, &state, "Toggle", &text, show_diff)? {
         ConfirmResult::Applied => {
-            for msg in change.success_messages() {
-                println!("{msg}");
+            if !state.dry_run {
+                report_applied(&state, &change);
             }
             Ok(Some(true))
         }
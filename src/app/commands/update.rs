@@ -1,30 +1,45 @@
 //! `flake-edit update`: bump inputs to the latest semver match.
 //!
-//! Three modes: scripted single-input by ID, interactive
-//! multi-select with current versions rendered for context, and a
-//! non-interactive bump-everything path. `init` toggles whether
-//! [`crate::forge::update::Updater`] seeds updates for inputs the lockfile
-//! has not yet seen.
-
-use nix_uri::FlakeRef;
+//! Four modes: scripted single-input by ID, a scripted subset via
+//! `--select`, interactive multi-select with current versions rendered
+//! for context, and a non-interactive bump-everything path. `init`
+//! toggles whether [`crate::forge::update::Updater`] seeds updates for
+//! inputs the lockfile has not yet seen.
+//!
+//! `--ref` skips the forge and moves one input to a named branch or tag
+//! instead, see [`update_to_ref`].
 
+use crate::change::{Change, ChangeId};
+use crate::cli::OutputFormat;
 use crate::edit::{FlakeEdit, sorted_input_ids};
+use crate::tui::InitialSelection;
 
 use super::super::editor::Editor;
 use super::super::state::AppState;
-use super::{Error, Result, interactive_multi_select, updater};
+use super::{Error, Result, interactive_multi_select, report_applied, report_unchanged, updater};
 
 pub fn update(
     editor: &Editor,
     flake_edit: &mut FlakeEdit,
     state: &AppState,
     id: Option<String>,
+    select: &[String],
     init: bool,
+    selection: InitialSelection,
 ) -> Result<()> {
     let inputs = flake_edit.list().clone();
 
-    if let Some(id) = id {
-        let mut updater = updater(editor, inputs);
+    if !select.is_empty() {
+        if let Some(id) = select.iter().find(|id| !inputs.contains_key(id.as_str())) {
+            return Err(Error::UpdateUnknownInput { id: id.clone() });
+        }
+        let ids: Vec<&str> = select.iter().map(String::as_str).collect();
+        let mut updater = updater(editor, state, inputs);
+        updater.update_inputs_to_latest_semver(&ids, init);
+        let change = updater.get_changes();
+        editor.apply_or_diff(&change, state)?;
+    } else if let Some(id) = id {
+        let mut updater = updater(editor, state, inputs);
         updater.update_inputs_to_latest_semver(&[id.as_str()], init);
         let change = updater.get_changes();
         editor.apply_or_diff(&change, state)?;
@@ -41,7 +56,7 @@ pub fn update(
             .iter()
             .map(|id| {
                 let input = &inputs[id];
-                let parsed = input.url().parse::<FlakeRef>().ok();
+                let parsed = input.url_parsed().ok();
                 let version = parsed.as_ref().and_then(|f| f.ref_or_rev());
                 match version {
                     Some(v) if !v.is_empty() => format!("{} - {}", id, v),
@@ -56,18 +71,19 @@ pub fn update(
             "Update",
             "Space select, U all, ^D diff",
             display_items,
+            selection,
             |selected| {
                 let ids: Vec<&str> = selected
                     .iter()
                     .map(|s| s.split(" - ").next().unwrap_or(s))
                     .collect();
-                let mut updater = updater(editor, inputs.clone());
+                let mut updater = updater(editor, state, inputs.clone());
                 updater.update_inputs_to_latest_semver(&ids, init);
                 updater.get_changes()
             },
         )?;
     } else {
-        let mut updater = updater(editor, inputs);
+        let mut updater = updater(editor, state, inputs);
         updater.update_all_to_latest_semver(init);
         let change = updater.get_changes();
         editor.apply_or_diff(&change, state)?;
@@ -75,3 +91,45 @@ pub fn update(
 
     Ok(())
 }
+
+/// `update <id> --ref <ref>`: point `id` at `ref_name`. A rev pin is only
+/// replaced with `force`. With `--output json` the move is reported as the
+/// equivalent [`Change::Change`].
+pub fn update_to_ref(
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    id: &str,
+    ref_name: &str,
+    force: bool,
+) -> Result<()> {
+    let inputs = flake_edit.list().clone();
+    let mut updater = updater(editor, state, inputs);
+    let changed = updater.update_input_to_ref(id, ref_name, force)?;
+    if !changed {
+        report_unchanged(state, None, &format!("{id} already tracks {ref_name}."));
+        return Ok(());
+    }
+    let text = updater.get_changes();
+    editor.apply_or_diff(&text, state)?;
+    if !state.writes() {
+        return Ok(());
+    }
+    match state.output {
+        OutputFormat::Text if !state.quiet_success => println!("Updated {id} to {ref_name}."),
+        OutputFormat::Text => {}
+        OutputFormat::Json => {
+            let uri = FlakeEdit::from_text(&text)?
+                .list()
+                .get(id)
+                .map(|input| input.url().to_string());
+            let change = Change::Change {
+                id: ChangeId::parse(id).ok(),
+                uri,
+                rename: None,
+            };
+            report_applied(state, &change);
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,8 @@
-use nix_uri::FlakeRef;
+use nix_uri::{FlakeRef, FlakeRefType};
 
 use crate::change::{Change, ChangeId};
+use crate::config::Shortcuts;
+use crate::uri::forge_owner_without_repo;
 
 use super::{Error, Result};
 
@@ -15,6 +17,120 @@ use super::{Error, Result};
 pub struct UriOptions<'a> {
     pub ref_or_rev: Option<&'a str>,
     pub shallow: bool,
+    /// Expanded in a user-supplied url before it is parsed.
+    pub shortcuts: Option<&'a Shortcuts>,
+    /// Branch to track, applied after `ref_or_rev`. See [`set_git_ref`].
+    pub git_ref: Option<&'a str>,
+    /// `KEY=VALUE` url parameters, applied last and in order. See
+    /// [`set_param`].
+    pub params: &'a [String],
+}
+
+impl UriOptions<'_> {
+    /// `uri` with its [`Shortcuts`] prefix expanded.
+    pub(super) fn expand(&self, uri: String) -> String {
+        match self.shortcuts {
+            Some(shortcuts) => shortcuts.expand(uri),
+            None => uri,
+        }
+    }
+
+    /// `uri` with [`Self::git_ref`] applied, or unchanged without one.
+    pub(super) fn apply_git_ref(&self, uri: String) -> Result<String> {
+        let Some(git_ref) = self.git_ref else {
+            return Ok(uri);
+        };
+        let mut flake_ref = parse_flake_ref(&uri)?;
+        set_git_ref(&mut flake_ref, git_ref);
+        Ok(flake_ref.into_uri())
+    }
+
+    /// `uri` with [`Self::params`] applied, or unchanged without any.
+    ///
+    /// The result is parsed again, so a value that leaves the url
+    /// unparseable is reported here instead of being written.
+    pub(super) fn apply_params(&self, uri: String) -> Result<String> {
+        if self.params.is_empty() {
+            return Ok(uri);
+        }
+        let mut flake_ref = parse_flake_ref(&uri)?;
+        for param in self.params {
+            let invalid = |reason: String| Error::InvalidParam {
+                param: param.clone(),
+                uri: uri.clone(),
+                reason,
+            };
+            let (key, value) = param
+                .split_once('=')
+                .ok_or_else(|| invalid("expected KEY=VALUE".to_string()))?;
+            flake_ref = set_param(flake_ref, key, value).map_err(invalid)?;
+        }
+        let uri = flake_ref.into_uri();
+        parse_flake_ref(&uri)?;
+        Ok(uri)
+    }
+}
+
+/// `flake_ref` with its `key` parameter set to `value`. An empty value
+/// clears it. `shallow` and `submodules` take `0`/`1` or
+/// `false`/`true`, and `rev` only a full commit sha, since a forge url
+/// would otherwise read a short one as a ref.
+fn set_param(
+    mut flake_ref: FlakeRef,
+    key: &str,
+    value: &str,
+) -> std::result::Result<FlakeRef, String> {
+    let text = (!value.is_empty()).then(|| value.to_string());
+    let flag = || match value {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err(format!("expected 0 or 1 for '{key}', got '{value}'")),
+    };
+    match key {
+        "ref" => {
+            flake_ref = flake_ref.try_with_ref(text).map_err(|e| e.to_string())?;
+        }
+        "rev" => {
+            if !text.as_deref().is_none_or(is_commit_sha) {
+                return Err(format!(
+                    "expected a 40-character hex commit sha for 'rev', got '{value}'"
+                ));
+            }
+            flake_ref.set_rev(text);
+        }
+        "dir" => flake_ref.set_dir(text),
+        "host" => flake_ref.set_host(text),
+        "narHash" => flake_ref.set_nar_hash(text),
+        "lastModified" => flake_ref.set_last_modified(text),
+        "revCount" => flake_ref.set_rev_count(text),
+        "shallow" => flake_ref.set_shallow(flag()?),
+        "submodules" => flake_ref.set_submodules(flag()?),
+        _ => return Err(format!("unknown parameter '{key}'")),
+    }
+    Ok(flake_ref)
+}
+
+/// Whether `rev` is a full 40-character hex commit sha.
+fn is_commit_sha(rev: &str) -> bool {
+    rev.len() == 40 && rev.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Symbolic `--git-ref` naming the remote's default branch.
+pub(super) const DEFAULT_BRANCH_REF: &str = "HEAD";
+
+/// Make `flake_ref` track the branch `git_ref`.
+///
+/// [`DEFAULT_BRANCH_REF`] is written in the form each kind reads as "the
+/// default branch": a forge reference drops its ref and rev, since
+/// `github:owner/repo` already means that, while any other kind gets
+/// `ref=HEAD`. Other values are set as the ref as given.
+pub(super) fn set_git_ref(flake_ref: &mut FlakeRef, git_ref: &str) {
+    if git_ref == DEFAULT_BRANCH_REF && matches!(flake_ref.kind(), FlakeRefType::GitForge(_)) {
+        flake_ref.set_ref(None);
+        flake_ref.set_rev(None);
+    } else {
+        flake_ref.set_ref(Some(git_ref.to_string()));
+    }
 }
 
 /// Selects which [`Change`] variant [`build_uri_change`] constructs.
@@ -31,7 +147,9 @@ pub(super) fn build_uri_change(
     uri: String,
     opts: &UriOptions<'_>,
 ) -> Result<Change> {
-    let final_uri = transform_uri(uri, opts.ref_or_rev, opts.shallow)?;
+    let final_uri = transform_uri(opts.expand(uri), opts.ref_or_rev, opts.shallow)?;
+    let final_uri = opts.apply_git_ref(final_uri)?;
+    let final_uri = opts.apply_params(final_uri)?;
     let id = ChangeId::parse(&id).map_err(|source| Error::InvalidInputId { id, source })?;
     Ok(match kind {
         BuildKind::Add { no_flake } => Change::Add {
@@ -42,6 +160,7 @@ pub(super) fn build_uri_change(
         BuildKind::Change => Change::Change {
             id: Some(id),
             uri: Some(final_uri),
+            rename: None,
         },
     })
 }
@@ -77,10 +196,7 @@ pub(super) fn transform_uri(
     ref_or_rev: Option<&str>,
     shallow: bool,
 ) -> Result<String> {
-    let flake_ref: FlakeRef = uri.parse().map_err(|source| Error::InvalidUri {
-        uri: uri.clone(),
-        source,
-    })?;
+    let flake_ref = parse_flake_ref(&uri)?;
 
     if ref_or_rev.is_none() && !shallow {
         return Ok(uri);
@@ -89,6 +205,24 @@ pub(super) fn transform_uri(
     Ok(apply_uri_options(flake_ref, ref_or_rev, shallow).into_uri())
 }
 
+/// Parse a user-supplied flake reference. A forge reference without a
+/// repository gets [`Error::MissingRepo`] so the CLI can show the full
+/// form; any other parse failure is [`Error::InvalidUri`].
+pub(super) fn parse_flake_ref(uri: &str) -> Result<FlakeRef> {
+    uri.parse()
+        .map_err(|source| match forge_owner_without_repo(uri) {
+            Some((scheme, owner)) => Error::MissingRepo {
+                uri: uri.to_string(),
+                scheme: scheme.to_string(),
+                owner: owner.to_string(),
+            },
+            None => Error::InvalidUri {
+                uri: uri.to_string(),
+                source,
+            },
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,4 +242,304 @@ mod tests {
             "expected InvalidInputId for 'a..b', got: {err:?}"
         );
     }
+
+    /// A `?ref=` branch with a slash must survive a parse and re-render on
+    /// the non-github forges too. `shallow` forces the re-render through
+    /// `into_uri` instead of returning the input verbatim.
+    #[test]
+    fn slashed_ref_query_round_trips_on_gitlab_and_sourcehut() {
+        for (uri, expected) in [
+            (
+                "gitlab:o/r?ref=release/1.2",
+                "gitlab:o/r?ref=release/1.2&shallow=1",
+            ),
+            (
+                "sourcehut:~o/r?ref=release/1.2",
+                "sourcehut:~o/r?ref=release/1.2&shallow=1",
+            ),
+        ] {
+            let rendered = transform_uri(uri.to_string(), None, true).unwrap();
+            assert_eq!(rendered, expected);
+            let reparsed: FlakeRef = rendered.parse().unwrap();
+            assert_eq!(reparsed.ref_(), Some("release/1.2"), "{uri} -> {rendered}");
+        }
+    }
+
+    /// A github path ref takes everything after `owner/repo/`, so a
+    /// branch with slashes is neither split into the repo nor cut short.
+    #[test]
+    fn slashed_path_ref_keeps_the_whole_remainder() {
+        for (uri, reference) in [
+            ("github:o/r/release/2024", "release/2024"),
+            ("github:o/r/feature/foo/bar", "feature/foo/bar"),
+        ] {
+            let rendered = transform_uri(uri.to_string(), None, true).unwrap();
+            assert_eq!(rendered, format!("{uri}?shallow=1"));
+            let reparsed: FlakeRef = rendered.parse().unwrap();
+            assert_eq!(reparsed.owner(), Some("o"), "{uri}");
+            assert_eq!(reparsed.repo(), Some("r"), "{uri}");
+            assert_eq!(reparsed.ref_(), Some(reference), "{uri}");
+        }
+    }
+
+    /// Plain-http transports (internal mirrors) keep their `+http`
+    /// prefix through a re-render instead of being upgraded or dropped.
+    #[test]
+    fn plain_http_transport_round_trips() {
+        for (uri, expected) in [
+            (
+                "git+http://mirror.internal/org/repo",
+                "git+http://mirror.internal/org/repo?shallow=1",
+            ),
+            (
+                "git+http://mirror.internal/org/repo?ref=main",
+                "git+http://mirror.internal/org/repo?ref=main&shallow=1",
+            ),
+            (
+                "hg+http://mirror.internal/org/repo",
+                "hg+http://mirror.internal/org/repo?shallow=1",
+            ),
+        ] {
+            let rendered = transform_uri(uri.to_string(), None, true).unwrap();
+            assert_eq!(rendered, expected);
+            let reparsed: FlakeRef = rendered.parse().unwrap();
+            assert_eq!(reparsed.into_uri(), expected);
+        }
+    }
+
+    /// Archive URLs, with or without the `tarball+` prefix, are tarball
+    /// inputs rather than indirect registry refs, and `add` keeps them as
+    /// typed.
+    #[test]
+    fn archive_urls_parse_as_tarballs() {
+        use nix_uri::{FlakeRefType, ResourceType};
+
+        for uri in [
+            "https://hackage.haskell.org/package/foo-1.0/foo-1.0.tar.gz",
+            "tarball+https://example.com/src.tar.gz",
+            "https://example.com/src.tgz",
+            "https://example.com/src.tar.xz",
+            "https://example.com/src.tar.bz2",
+            "https://example.com/src.zip",
+        ] {
+            let parsed: FlakeRef = uri.parse().unwrap();
+            assert!(
+                matches!(
+                    parsed.kind(),
+                    FlakeRefType::Resource(res) if res.res_type == ResourceType::Tarball
+                ),
+                "{uri} parsed as {:?}",
+                parsed.kind()
+            );
+            assert_eq!(transform_uri(uri.to_string(), None, false).unwrap(), uri);
+        }
+    }
+
+    /// Path refs re-render as `path:` with their query intact, including
+    /// the prefix-less absolute form.
+    #[test]
+    fn path_refs_round_trip() {
+        for (uri, rendered) in [
+            ("path:/abs/dir", "path:/abs/dir"),
+            ("path:/abs/dir?dir=sub", "path:/abs/dir?dir=sub"),
+            ("/abs/dir", "path:/abs/dir"),
+        ] {
+            let parsed: FlakeRef = uri.parse().unwrap();
+            assert_eq!(parsed.to_string(), rendered);
+            let reparsed: FlakeRef = rendered.parse().unwrap();
+            assert_eq!(reparsed.to_string(), rendered);
+
+            let shallow = transform_uri(uri.to_string(), None, true).unwrap();
+            let expected = if rendered.contains('?') {
+                format!("{rendered}&shallow=1")
+            } else {
+                format!("{rendered}?shallow=1")
+            };
+            assert_eq!(shallow, expected);
+        }
+    }
+
+    /// `git+` and `hg+` refs keep their transport through a parse and
+    /// re-render, including any query parameters.
+    #[test]
+    fn git_and_hg_transports_round_trip() {
+        for uri in [
+            "git+https://git.somehost.tld/user/path",
+            "git+https://git.somehost.tld/user/path?ref=main",
+            "git+ssh://git@github.com/owner/repo",
+            "git+ssh://git@github.com/owner/repo?ref=main&rev=a3f5e2c8b9d1f4e6a7b8c9d0e1f2a3b4c5d6e7f8",
+            "git+file:///home/me/repo",
+            "git+file:///home/me/repo?dir=sub",
+            "hg+https://hg.somehost.tld/user/path",
+            "hg+https://hg.somehost.tld/user/path?ref=default",
+            "hg+ssh://hg@somehost.tld/user/path",
+            "hg+file:///home/me/repo",
+        ] {
+            let parsed: FlakeRef = uri.parse().unwrap();
+            assert_eq!(parsed.to_string(), uri);
+
+            let shallow = transform_uri(uri.to_string(), None, true).unwrap();
+            let reparsed: FlakeRef = shallow.parse().unwrap();
+            assert_eq!(reparsed.to_string(), shallow, "{uri}");
+        }
+    }
+
+    /// Every query parameter survives a re-render, joined with `&` in a
+    /// fixed order.
+    #[test]
+    fn query_parameters_round_trip() {
+        let uri = "git+https://git.somehost.tld/user/path?ref=branch&rev=a3f5e2c8b9d1f4e6a7b8c9d0e1f2a3b4c5d6e7f8&submodules=1";
+        let parsed: FlakeRef = uri.parse().unwrap();
+        assert_eq!(parsed.to_string(), uri);
+
+        let shallow = transform_uri(uri.to_string(), None, true).unwrap();
+        assert_eq!(
+            shallow,
+            "git+https://git.somehost.tld/user/path?ref=branch&rev=a3f5e2c8b9d1f4e6a7b8c9d0e1f2a3b4c5d6e7f8&shallow=1&submodules=1"
+        );
+        let reparsed: FlakeRef = shallow.parse().unwrap();
+        assert_eq!(reparsed.to_string(), shallow);
+
+        let nar = "https://example.com/src.tar.gz?narHash=sha256-AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA%3D";
+        let parsed: FlakeRef = nar.parse().unwrap();
+        assert_eq!(parsed.to_string(), nar);
+    }
+
+    /// A rev path segment followed by several parameters keeps the rev
+    /// in `ref_or_rev` and the rest in the query.
+    #[test]
+    fn github_rev_with_params_round_trips() {
+        let rev = "a3f5e2c8b9d1f4e6a7b8c9d0e1f2a3b4c5d6e7f8";
+        let uri = format!("github:owner/repo/{rev}?dir=sub&submodules=1");
+        let parsed: FlakeRef = uri.parse().unwrap();
+        assert_eq!(parsed.ref_or_rev(), Some(rev));
+        assert_eq!(parsed.to_string(), uri);
+
+        let shallow = transform_uri(uri.clone(), None, true).unwrap();
+        let reparsed: FlakeRef = shallow.parse().unwrap();
+        assert_eq!(reparsed.ref_or_rev(), Some(rev));
+        assert_eq!(reparsed.to_string(), shallow);
+        assert!(shallow.contains("dir=sub"), "{shallow}");
+        assert!(shallow.contains("submodules=1"), "{shallow}");
+    }
+
+    /// GitLab subgroups are written with `%2F` in the owner. The owner is
+    /// decoded on parse and re-encoded on render.
+    #[test]
+    fn gitlab_subgroups_round_trip() {
+        use nix_uri::FlakeRefType;
+
+        for (uri, owner) in [
+            ("gitlab:veloren%2Fdev/rfcs", "veloren/dev"),
+            ("gitlab:veloren%2Fdev/rfcs/main", "veloren/dev"),
+            ("gitlab:a%2Fb%2Fc/r?dir=sub", "a/b/c"),
+        ] {
+            let parsed: FlakeRef = uri.parse().unwrap();
+            assert!(
+                matches!(parsed.kind(), FlakeRefType::GitForge(forge) if forge.owner == owner),
+                "{uri} parsed as {:?}",
+                parsed.kind()
+            );
+            assert_eq!(parsed.to_string(), uri);
+
+            let shallow = transform_uri(uri.to_string(), None, true).unwrap();
+            let reparsed: FlakeRef = shallow.parse().unwrap();
+            assert_eq!(reparsed.to_string(), shallow);
+        }
+    }
+
+    /// Sourcehut has no subgroups, so an encoded `/` in the owner is a
+    /// malformed reference rather than a nested path.
+    #[test]
+    fn sourcehut_subgroup_is_rejected() {
+        let err = transform_uri("sourcehut:~o%2Fsub/r".to_string(), None, false)
+            .expect_err("sourcehut owners cannot nest");
+        assert!(matches!(err, Error::InvalidUri { .. }), "{err:?}");
+    }
+
+    /// `ref_or_rev` reports the pinned version for every kind that can
+    /// carry one, which is what `update` shows next to each input.
+    #[test]
+    fn ref_or_rev_covers_every_kind() {
+        let rev = "a3f5e2c8b9d1f4e6a7b8c9d0e1f2a3b4c5d6e7f8";
+        for (uri, expected) in [
+            ("github:o/r/v1.0".to_string(), Some("v1.0")),
+            ("gitlab:o/r/main".to_string(), Some("main")),
+            ("sourcehut:~o/r/main".to_string(), Some("main")),
+            ("flake:nixpkgs/nixos-24.05".to_string(), Some("nixos-24.05")),
+            ("git+https://host/o/r?ref=main".to_string(), Some("main")),
+            (format!("git+https://host/o/r?rev={rev}"), Some(rev)),
+            (
+                "hg+https://host/o/r?ref=default".to_string(),
+                Some("default"),
+            ),
+            ("https://host/src.tar.gz?ref=v1".to_string(), Some("v1")),
+            ("github:o/r".to_string(), None),
+            ("https://host/src.tar.gz".to_string(), None),
+            ("path:/abs/dir".to_string(), None),
+            ("file:///abs/dir".to_string(), None),
+        ] {
+            let parsed: FlakeRef = uri.parse().unwrap();
+            assert_eq!(parsed.ref_or_rev(), expected, "{uri}");
+        }
+    }
+
+    /// Known query keys land in their typed slots even after an unknown
+    /// one, and unknown keys are kept verbatim through a re-render.
+    #[test]
+    fn unknown_params_keep_known_ones_typed() {
+        let parsed: FlakeRef = "git+https://host/o/r?unknownparam=y&ref=main"
+            .parse()
+            .unwrap();
+        assert_eq!(parsed.ref_(), Some("main"));
+
+        let parsed: FlakeRef = "github:o/r?dir=x&unknownparam=y".parse().unwrap();
+        assert_eq!(parsed.to_string(), "github:o/r?dir=x&unknownparam=y");
+    }
+
+    /// Forge owners and repos take `[A-Za-z0-9._-]`, plus a leading `~` on
+    /// sourcehut owners. Spaces, control characters and non-ASCII letters
+    /// are rejected before anything is written.
+    #[test]
+    fn forge_owner_and_repo_charset() {
+        for uri in [
+            "github:nix-community/home-manager",
+            "github:user123/repo-2",
+            "github:a.b/c_d",
+            "gitlab:gr0up/pro-ject",
+            "sourcehut:~sir-cmpwn/aerc2",
+        ] {
+            let out = transform_uri(uri.to_string(), None, false).unwrap();
+            assert_eq!(out, uri);
+        }
+        for uri in [
+            "github:own er/repo",
+            "github:own\u{7}er/repo",
+            "github:owner/re\tpo",
+            "github:ow~ner/repo",
+            "github:own\u{e9}r/repo",
+            "sourcehut:~user/re po",
+        ] {
+            let err = transform_uri(uri.to_string(), None, false)
+                .expect_err("invalid owner or repo must be rejected");
+            assert!(matches!(err, Error::InvalidUri { .. }), "{uri:?}: {err:?}");
+        }
+    }
+
+    /// Both spellings of an owner-only forge reference raise the same
+    /// error, which carries what the hint needs to show the full form.
+    #[test]
+    fn owner_without_repo_is_missing_repo() {
+        for uri in ["github:nixos", "github:nixos/"] {
+            let err = transform_uri(uri.to_string(), None, false)
+                .expect_err("a reference without a repo must be rejected");
+            assert!(
+                matches!(
+                    &err,
+                    Error::MissingRepo { scheme, owner, .. } if scheme == "github" && owner == "nixos"
+                ),
+                "{uri}: {err:?}"
+            );
+        }
+    }
 }
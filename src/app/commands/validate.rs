@@ -0,0 +1,50 @@
+//! `flake-edit validate`: check `flake.nix` for errors without editing it.
+//!
+//! Runs the syntax-level [`validate::validate`] first. A file that parses
+//! also gets the follows lints of [`validate::validate_full`], without a
+//! lock, so a dangling follows target is caught as well, and a file that
+//! parses but does not load reports why. Read-only. Any error fails the
+//! command, so it can gate a pre-commit hook.
+
+use crate::edit::FlakeEdit;
+use crate::validate::{self, ValidationResult};
+
+use super::super::editor::Editor;
+use super::super::state::AppState;
+use super::{Error, Result};
+
+pub fn validate(editor: &Editor, state: &AppState) -> Result<()> {
+    let (result, unloadable) = check(&editor.text());
+    for warning in &result.warnings {
+        println!("warning: {warning}");
+    }
+    if result.is_ok() && unloadable.is_none() {
+        if !state.quiet_success {
+            println!("flake.nix is valid.");
+        }
+        return Ok(());
+    }
+    for error in &result.errors {
+        println!("error: {error}");
+    }
+    if let Some(err) = &unloadable {
+        println!("error: {err}");
+    }
+    Err(Error::Invalid {
+        count: result.errors.len() + usize::from(unloadable.is_some()),
+    })
+}
+
+/// Syntax lints, then the follows lints once `text` loads as a flake.
+/// A file that parses but does not load, such as one declaring an input
+/// both flat and in the `inputs` block, comes back with the load error.
+fn check(text: &str) -> (ValidationResult, Option<crate::Error>) {
+    let result = validate::validate(text);
+    if !result.is_ok() {
+        return (result, None);
+    }
+    match FlakeEdit::from_text(text) {
+        Ok(mut flake_edit) => (validate::validate_full(text, flake_edit.list(), None), None),
+        Err(err) => (result, Some(err)),
+    }
+}
@@ -0,0 +1,137 @@
+//! `flake-edit watch`: re-run a read-only command whenever flake.nix
+//! changes.
+//!
+//! The file is polled rather than subscribed to, so no platform notify
+//! backend is needed and editors that save by replacing the file are
+//! seen like any other write. A change is acted on once two consecutive
+//! polls read the same content, which debounces the burst of writes a
+//! single save can produce. Errors of a run are printed and the watch
+//! goes on, since a half-written flake.nix is expected mid-edit.
+
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use ropey::Rope;
+
+use crate::cli::{ListFormat, WatchCommand};
+use crate::edit::FlakeEdit;
+
+use super::super::editor::{Editor, FlakeBuf};
+use super::super::state::AppState;
+use super::{Result, doctor, list, status, validate};
+
+pub fn watch(state: &AppState, command: &WatchCommand, interval: Duration) -> Result<()> {
+    if !state.quiet_success {
+        eprintln!("Watching {} (Ctrl-C to stop)", state.flake_path.display());
+    }
+    let mut first = true;
+    watch_file(&state.flake_path, interval, None, |text| {
+        if !std::mem::take(&mut first) && !state.quiet_success {
+            eprintln!("\n{} changed", state.flake_path.display());
+        }
+        if let Err(err) = run(state, command, text) {
+            eprintln!("error: {err}");
+        }
+    });
+    Ok(())
+}
+
+fn run(state: &AppState, command: &WatchCommand, text: &str) -> Result<()> {
+    // `doctor` and `validate` report a flake.nix that does not load
+    // themselves, so they start from the text.
+    let editor = || Editor::new(FlakeBuf::from_text(text, state.flake_path.clone()));
+    match command {
+        WatchCommand::List => list(
+            &mut FlakeEdit::from_text(text)?,
+            state,
+            &ListFormat::default(),
+            false,
+            None,
+        ),
+        WatchCommand::Status => status(
+            &mut FlakeEdit::from_text(text)?,
+            &Rope::from_str(text),
+            state,
+        ),
+        WatchCommand::Doctor => doctor(&editor(), state, false),
+        WatchCommand::Validate => validate(&editor(), state),
+    }
+}
+
+/// Call `on_change` with the content of `path` now and after every change
+/// to it, polling every `interval`. Returns after `max_runs` calls, or
+/// never without a limit.
+///
+/// A missing or unreadable file counts as a save in progress: nothing
+/// runs until it can be read again.
+fn watch_file(
+    path: &Path,
+    interval: Duration,
+    max_runs: Option<usize>,
+    mut on_change: impl FnMut(&str),
+) {
+    let read = || fs::read_to_string(path).ok();
+    let mut last = loop {
+        if let Some(text) = read() {
+            break text;
+        }
+        thread::sleep(interval);
+    };
+    let mut runs = 0;
+    loop {
+        on_change(&last);
+        runs += 1;
+        if max_runs.is_some_and(|max| runs >= max) {
+            return;
+        }
+
+        let mut current = loop {
+            thread::sleep(interval);
+            let text = read();
+            if text.as_ref() != Some(&last) {
+                break text;
+            }
+        };
+        // Settle: wait until two polls in a row agree on readable content.
+        loop {
+            thread::sleep(interval);
+            let next = read();
+            if next == current && next.is_some() {
+                break;
+            }
+            current = next;
+        }
+        last = current.expect("settled content is readable");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_file_reruns_after_a_change() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("flake.nix");
+        fs::write(&path, "{ inputs = { }; }").unwrap();
+
+        let touch = {
+            let path = path.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                fs::write(&path, "{ inputs.nixpkgs.url = \"github:nixos/nixpkgs\"; }").unwrap();
+            })
+        };
+        let mut seen = Vec::new();
+        watch_file(&path, Duration::from_millis(10), Some(2), |text| {
+            seen.push(text.to_string());
+        });
+        touch.join().unwrap();
+
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0], "{ inputs = { }; }");
+        assert!(seen[1].contains("github:nixos/nixpkgs"), "{seen:?}");
+    }
+}
@@ -1,10 +1,12 @@
 use std::fs::File;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use ropey::Rope;
 
+use crate::cli::BackupMode;
 use crate::diff::Diff;
 use crate::edit::FlakeEdit;
 use crate::error::Error;
@@ -25,6 +27,15 @@ impl FlakeBuf {
         Ok(Self { text, path })
     }
 
+    /// A buffer holding `text` as the content of `path`, without reading
+    /// the file.
+    pub fn from_text(text: &str, path: PathBuf) -> Self {
+        Self {
+            text: Rope::from_str(text),
+            path,
+        }
+    }
+
     pub fn text(&self) -> &Rope {
         &self.text
     }
@@ -33,9 +44,71 @@ impl FlakeBuf {
         &self.path
     }
 
+    /// Overwrite the file in place. A symlinked `flake.nix` is written
+    /// through, so the link itself is kept.
     pub fn write(&self, content: &str) -> io::Result<()> {
         std::fs::write(&self.path, content)
     }
+
+    /// Where a `mode` backup of the file goes, next to it.
+    pub fn backup_path(&self, mode: BackupMode) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        if mode == BackupMode::Timestamp {
+            let secs = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            name.push(format!(".{secs}"));
+        }
+        name.push(".bak");
+        self.path.with_file_name(name)
+    }
+
+    /// Copy the file as it is on disk to its `mode` backup path and
+    /// return that path.
+    pub fn backup(&self, mode: BackupMode) -> Result<PathBuf, Error> {
+        let backup = self.backup_path(mode);
+        std::fs::copy(&self.path, &backup).map_err(|source| Error::Backup {
+            path: self.path.clone(),
+            backup: backup.clone(),
+            source,
+        })?;
+        Ok(backup)
+    }
+}
+
+/// Refreshes `flake.lock` after `flake.nix` was written.
+///
+/// [`NixLockRunner`] shells out to `nix flake lock`. Tests swap in a
+/// recording runner through [`Editor::with_lock_runner`].
+pub trait LockRunner: std::fmt::Debug {
+    /// Relock the flake in `flake_dir`, passing `--offline` to nix when
+    /// `offline` is set.
+    fn run(&self, flake_dir: &Path, offline: bool) -> io::Result<()>;
+}
+
+/// The default [`LockRunner`]: runs `nix flake lock` in the flake directory.
+#[derive(Debug, Default)]
+pub struct NixLockRunner;
+
+impl LockRunner for NixLockRunner {
+    fn run(&self, flake_dir: &Path, offline: bool) -> io::Result<()> {
+        let mut cmd = Command::new("nix");
+        if offline {
+            cmd.arg("--offline");
+        }
+        cmd.args(["flake", "lock"]);
+        let output = cmd.current_dir(flake_dir).output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!(
+                "nix flake lock failed: {}",
+                stderr
+            )));
+        }
+        Ok(())
+    }
 }
 
 /// Editor that drives changes to flake.nix files.
@@ -44,78 +117,132 @@ impl FlakeBuf {
 #[derive(Debug)]
 pub struct Editor {
     flake: FlakeBuf,
+    lock_runner: Box<dyn LockRunner>,
 }
 
 impl Editor {
     pub fn new(flake: FlakeBuf) -> Self {
-        Self { flake }
+        Self {
+            flake,
+            lock_runner: Box::new(NixLockRunner),
+        }
     }
 
     pub fn from_path(path: PathBuf) -> io::Result<Self> {
         let flake = FlakeBuf::from_path(path)?;
-        Ok(Self { flake })
+        Ok(Self::new(flake))
+    }
+
+    /// Replace the [`LockRunner`] used after writes.
+    pub fn with_lock_runner(mut self, lock_runner: Box<dyn LockRunner>) -> Self {
+        self.lock_runner = lock_runner;
+        self
     }
 
     pub fn text(&self) -> String {
         self.flake.text().to_string()
     }
 
+    /// The flake file as read, for mapping [`crate::input::Range`]s to
+    /// lines and columns.
+    pub fn rope(&self) -> &Rope {
+        self.flake.text()
+    }
+
     pub fn path(&self) -> &PathBuf {
         self.flake.path()
     }
 
+    /// The flake file name for summaries, falling back to the full path.
+    fn display_name(&self) -> String {
+        let path = self.path();
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string())
+    }
+
     pub fn create_flake_edit(&self) -> Result<FlakeEdit, Error> {
         FlakeEdit::from_text(&self.text())
     }
 
-    fn run_nix_flake_lock(&self, offline: bool) -> io::Result<()> {
+    fn run_nix_flake_lock(&self, state: &AppState) -> io::Result<()> {
         let flake_dir = match self.flake.path.parent() {
             Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
             _ => PathBuf::from("."),
         };
 
-        let mut cmd = Command::new("nix");
-        if offline {
-            cmd.arg("--offline");
-        }
-        cmd.args(["flake", "lock"]);
-        let output = cmd.current_dir(&flake_dir).output()?;
+        self.lock_runner.run(&flake_dir, state.lock_offline)?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(io::Error::other(format!(
-                "nix flake lock failed: {}",
-                stderr
-            )));
+        if state.prints_status() {
+            println!("Updated flake.lock");
         }
-
-        println!("Updated flake.lock");
         Ok(())
     }
 
     /// Apply changes to the flake file, or show diff if in diff mode.
     ///
-    /// Validates the new content for duplicate attributes before writing.
+    /// Validates the new content for duplicate attributes before writing,
+    /// and prints a `flake.nix | +N -M` summary after the write.
+    /// After a write the lockfile is refreshed: best effort by default,
+    /// as a hard requirement with [`AppState::relock`], and not at all
+    /// with [`AppState::no_lock`]. [`AppState::quiet_success`] silences
+    /// the diff and the summary. [`AppState::dry_run`] prints the whole
+    /// new document instead and leaves both files alone. With
+    /// [`AppState::sorts_params`] every url's parameters are put in
+    /// canonical order first. With [`AppState::backup`] the file is
+    /// copied aside right before the write.
     pub fn apply_or_diff(&self, new_content: &str, state: &AppState) -> Result<(), Error> {
+        let sorted;
+        let new_content = if state.sorts_params()
+            && let Some(text) = FlakeEdit::from_text(new_content)?.sort_url_params()?
+        {
+            sorted = text;
+            sorted.as_str()
+        } else {
+            new_content
+        };
         let validation = validate::validate(new_content);
         if validation.has_errors() {
             return Err(Error::Validation(validation.errors));
         }
 
-        if state.diff {
-            let old = self.text();
-            let diff = Diff::new(&old, new_content);
-            diff.compare();
+        if state.dry_run {
+            print!("{new_content}");
+        } else if state.diff {
+            if !state.quiet_success {
+                let old = self.text();
+                let diff = Diff::new(&old, new_content)
+                    .with_color(state.color())
+                    .with_context(state.diff_context);
+                diff.compare();
+            }
         } else {
+            let stats = Diff::new(&self.text(), new_content).stats();
+            if let Some(mode) = state.backup {
+                let backup = self.flake.backup(mode)?;
+                if state.prints_status() {
+                    let name = backup.file_name().unwrap_or_default().to_string_lossy();
+                    println!("Backed up {} to {name}", self.display_name());
+                }
+            }
             self.flake
                 .write(new_content)
                 .map_err(|source| Error::Write {
                     path: self.flake.path().clone(),
                     source,
                 })?;
+            if state.prints_status() {
+                println!("{} | {stats}", self.display_name());
+            }
 
-            if !state.no_lock
-                && let Err(e) = self.run_nix_flake_lock(state.lock_offline)
+            if state.relock {
+                self.run_nix_flake_lock(state)
+                    .map_err(|source| Error::Relock {
+                        path: self.flake.path().clone(),
+                        source,
+                    })?;
+            } else if !state.no_lock
+                && let Err(e) = self.run_nix_flake_lock(state)
             {
                 tracing::warn!("failed to update lockfile: {e}");
             }
@@ -123,3 +250,145 @@ impl Editor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const FLAKE: &str = "{\n  inputs = {};\n  outputs = { self }: { };\n}\n";
+
+    /// Counts invocations instead of running nix. Fails every call when
+    /// `fail` is set.
+    #[derive(Debug, Default)]
+    struct RecordingRunner {
+        calls: Arc<AtomicUsize>,
+        fail: bool,
+    }
+
+    impl LockRunner for RecordingRunner {
+        fn run(&self, _flake_dir: &Path, _offline: bool) -> io::Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.fail {
+                Err(io::Error::other("nix not available"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn editor_with_runner(dir: &Path, fail: bool) -> (Editor, Arc<AtomicUsize>) {
+        let path = dir.join("flake.nix");
+        std::fs::write(&path, FLAKE).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let runner = RecordingRunner {
+            calls: Arc::clone(&calls),
+            fail,
+        };
+        let editor = Editor::from_path(path)
+            .unwrap()
+            .with_lock_runner(Box::new(runner));
+        (editor, calls)
+    }
+
+    fn state(dir: &Path) -> AppState {
+        AppState::new(dir.join("flake.nix"), None).unwrap()
+    }
+
+    #[test]
+    fn relock_invokes_lock_runner() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (editor, calls) = editor_with_runner(tmp.path(), false);
+        let state = state(tmp.path()).with_relock(true);
+        editor.apply_or_diff(FLAKE, &state).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn no_lock_skips_lock_runner() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (editor, calls) = editor_with_runner(tmp.path(), false);
+        let state = state(tmp.path()).with_no_lock(true);
+        editor.apply_or_diff(FLAKE, &state).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn diff_skips_lock_runner() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (editor, calls) = editor_with_runner(tmp.path(), false);
+        let state = state(tmp.path()).with_relock(true).with_diff(true);
+        editor.apply_or_diff(FLAKE, &state).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn relock_failure_is_an_error() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (editor, _) = editor_with_runner(tmp.path(), true);
+        let state = state(tmp.path()).with_relock(true);
+        let err = editor
+            .apply_or_diff(FLAKE, &state)
+            .expect_err("a failed relock must surface");
+        assert!(matches!(err, Error::Relock { .. }), "got: {err:?}");
+    }
+
+    #[test]
+    fn backup_keeps_the_pre_edit_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (editor, _) = editor_with_runner(tmp.path(), false);
+        let edited = FLAKE.replace("inputs = {}", "inputs = { a.url = \"github:a/a\"; }");
+        let state = state(tmp.path())
+            .with_no_lock(true)
+            .with_backup(Some(BackupMode::Simple));
+        editor.apply_or_diff(&edited, &state).unwrap();
+        let backup = std::fs::read_to_string(tmp.path().join("flake.nix.bak")).unwrap();
+        assert_eq!(backup, FLAKE);
+        assert_eq!(std::fs::read_to_string(editor.path()).unwrap(), edited);
+    }
+
+    #[test]
+    fn timestamp_backup_is_named_by_time() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (editor, _) = editor_with_runner(tmp.path(), false);
+        let state = state(tmp.path())
+            .with_no_lock(true)
+            .with_backup(Some(BackupMode::Timestamp));
+        editor.apply_or_diff(FLAKE, &state).unwrap();
+        let names: Vec<String> = std::fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name != "flake.nix")
+            .collect();
+        assert_eq!(names.len(), 1, "{names:?}");
+        let secs = names[0]
+            .strip_prefix("flake.nix.")
+            .and_then(|rest| rest.strip_suffix(".bak"))
+            .unwrap_or_else(|| panic!("unexpected backup name {}", names[0]));
+        assert!(secs.parse::<u64>().is_ok(), "{secs}");
+    }
+
+    #[test]
+    fn diff_and_dry_run_skip_backup() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (editor, _) = editor_with_runner(tmp.path(), false);
+        for state in [
+            state(tmp.path()).with_diff(true),
+            state(tmp.path()).with_dry_run(true),
+        ] {
+            let state = state.with_backup(Some(BackupMode::Simple));
+            editor.apply_or_diff(FLAKE, &state).unwrap();
+        }
+        assert!(!tmp.path().join("flake.nix.bak").exists());
+    }
+
+    #[test]
+    fn default_lock_failure_is_only_a_warning() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (editor, calls) = editor_with_runner(tmp.path(), true);
+        editor.apply_or_diff(FLAKE, &state(tmp.path())).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -41,6 +41,15 @@ pub enum Error {
     #[error("`--flake` and `--lock` cannot be used with `follow [PATHS]`")]
     IncompatibleFollowOptions,
 
+    /// `--flake` was repeated outside `list`.
+    #[error("`--flake` can only be repeated with `list`")]
+    MultipleFlakes,
+
+    /// `--flake` was repeated together with `--lock-file` or
+    /// `list --changed-since`, which name a single lockfile.
+    #[error("`--flake` cannot be repeated together with `{flag}`")]
+    MultipleFlakesWith { flag: &'static str },
+
     /// A subcommand was invoked without a URI argument when one is required.
     #[error("no URI provided")]
     NoUri,
@@ -53,6 +62,10 @@ pub enum Error {
     #[error("no inputs found in the flake")]
     NoInputs,
 
+    /// A follows list was empty when at least one was required.
+    #[error("no follows declarations found in the flake")]
+    NoFollows,
+
     /// A flake reference could not be parsed by `nix_uri`.
     #[error("invalid URI '{uri}'")]
     InvalidUri {
@@ -61,6 +74,33 @@ pub enum Error {
         source: nix_uri::NixUriError,
     },
 
+    /// A `change --param` could not be applied to the url.
+    #[error("cannot apply --param '{param}' to '{uri}': {reason}")]
+    InvalidParam {
+        param: String,
+        uri: String,
+        reason: String,
+    },
+
+    /// `change --ref-or-rev` without a URI targeted an input whose
+    /// reference kind has no ref slot (e.g. `path:`).
+    #[error("cannot set a ref on input '{id}' ('{uri}')")]
+    RefNotSupported {
+        id: String,
+        uri: String,
+        #[source]
+        source: nix_uri::NixUriError,
+    },
+
+    /// A forge reference named an owner but no repository
+    /// (`github:nixos` or `github:nixos/`).
+    #[error("flake reference '{uri}' is missing a repository")]
+    MissingRepo {
+        uri: String,
+        scheme: String,
+        owner: String,
+    },
+
     /// An input id was malformed; carries the typed parse error.
     #[error("invalid input id '{id}'")]
     InvalidInputId {
@@ -77,6 +117,53 @@ pub enum Error {
         source: AttrPathParseError,
     },
 
+    /// `add --input-prefix` produced an id that is not a bare Nix
+    /// identifier.
+    #[error("input prefix '{prefix}' yields invalid input id '{id}'")]
+    InvalidInputPrefix { prefix: String, id: String },
+
+    /// `status` found inputs on which flake.nix and flake.lock disagree.
+    #[error("flake.lock is out of sync with flake.nix")]
+    LockDrift,
+
+    /// `doctor` left problems in flake.nix, `fixable` of which `--fix`
+    /// would resolve.
+    #[error("flake.nix has {count} unresolved {}", if *count == 1 { "problem" } else { "problems" })]
+    DoctorProblems { count: usize, fixable: usize },
+
+    /// `lint` found `count` inputs whose url is declared more than once.
+    #[error(
+        "flake.nix declares {count} {} more than once",
+        if *count == 1 { "input" } else { "inputs" }
+    )]
+    DuplicateInputs { count: usize },
+
+    /// `list --format template` without `--template` or a `[list]
+    /// template` in the configuration.
+    #[error("`list --format template` needs a template")]
+    MissingListTemplate,
+
+    /// A `list` template names a placeholder that is not a field of a
+    /// `list --json` entry, or leaves a `{` unclosed.
+    #[error("invalid list template: {reason}; valid placeholders are {valid}")]
+    ListTemplate { reason: String, valid: String },
+
+    /// `validate` found `count` errors in flake.nix.
+    #[error("flake.nix has {count} {}", if *count == 1 { "error" } else { "errors" })]
+    Invalid { count: usize },
+
+    /// `add --from-lock` without an id imports every input, which
+    /// can't be combined with the single-input add flags.
+    #[error(
+        "--from-lock without an id cannot be combined with --input-prefix, --replace-if-exists or --json-change"
+    )]
+    FromLockNeedsId,
+
+    /// `add` inferred an id that is not a bare Nix identifier, and
+    /// `--id-sanitize` was not given to rewrite it.
+    #[error("id '{id}' inferred from '{uri}' is not a bare identifier")]
+    InferredIdNotIdentifier { id: String, uri: String },
+
     /// `nix_uri` rendered a flake reference but could not infer an id from it.
     #[error("could not infer id from flake reference '{uri}'")]
     CouldNotInferId { uri: String },
@@ -86,6 +173,37 @@ pub enum Error {
     #[error("input '{id}' has no pinnable URL (it may use follows or a non-standard format)")]
     InputNotPinnable { id: String },
 
+    /// The `pin --before` date is not a `YYYY-MM-DD` day.
+    #[error(transparent)]
+    InvalidDate(#[from] crate::forge::commits::DateParseError),
+
+    /// `pin --before` could not find the commit to pin to.
+    #[error(transparent)]
+    PinBefore(#[from] crate::forge::commits::PinBeforeError),
+
+    /// `update --ref` could not move the input to the ref.
+    #[error(transparent)]
+    UpdateRef(#[from] crate::forge::update::RefUpdateError),
+
+    /// `update --select` named an input id that the flake does not declare.
+    #[error("no input named '{id}' in flake.nix")]
+    UpdateUnknownInput { id: String },
+
+    /// `copy` named an input the source flake does not declare.
+    #[error("no input named '{id}' in {path}", path = path.display())]
+    CopyUnknownInput { id: String, path: PathBuf },
+
+    /// `copy` named an input that only declares follows in its flake.
+    #[error(
+        "input '{id}' in {path} has no url to copy (it only declares follows)",
+        path = path.display()
+    )]
+    CopyNoUrl { id: String, path: PathBuf },
+
+    /// `copy` named an input this flake already declares.
+    #[error("input '{id}' already exists")]
+    CopyInputExists { id: String },
+
     /// Removing an input did not produce a syntax change.
     #[error("could not remove input '{id}'")]
     CouldNotRemove { id: ChangeId },
@@ -104,6 +222,11 @@ pub enum Error {
     #[error("could not create follows relationship for '{id}'")]
     FollowsCreateFailed { id: String },
 
+    /// `follow --remove` was given a path that names a whole input
+    /// rather than a follows declaration inside one.
+    #[error("'{path}' is not a follows path, expected `<input>.<nested>`")]
+    NotAFollowsPath { path: String },
+
     /// Validation of `flake.nix` failed after applying speculative edits.
     /// Distinct from `crate::Error::Validation` (which fires before edits)
     /// because the diagnostic flow needs to render the staged edits too.
@@ -117,6 +240,11 @@ pub enum Error {
         failures: Vec<(PathBuf, Box<Error>)>,
     },
 
+    /// `prune` found an outputs lambda taking `...` or binding `@inputs`,
+    /// through which any input may be used without being named.
+    #[error("cannot tell which inputs are unused: the outputs arguments are open")]
+    OpenOutputs,
+
     /// `toggle` was invoked without arguments and no input stores an
     /// alternate.
     #[error("no toggleable inputs in flake.nix")]
@@ -230,6 +358,67 @@ pub struct RefCandidate {
 }
 
 impl Error {
+    /// Name of the variant, for the `kind` of a `--output json` error
+    /// report. Wrapped errors report their own variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Flake(inner) => inner.kind(),
+            Self::Config(inner) => inner.kind(),
+            Self::Io { .. } => "Io",
+            Self::FlakeNotFound { .. } => "FlakeNotFound",
+            Self::FlakeDirEmpty { .. } => "FlakeDirEmpty",
+            Self::IncompatibleFollowOptions { .. } => "IncompatibleFollowOptions",
+            Self::MultipleFlakes { .. } => "MultipleFlakes",
+            Self::MultipleFlakesWith { .. } => "MultipleFlakesWith",
+            Self::NoUri { .. } => "NoUri",
+            Self::NoId { .. } => "NoId",
+            Self::NoInputs { .. } => "NoInputs",
+            Self::NoFollows { .. } => "NoFollows",
+            Self::InvalidUri { .. } => "InvalidUri",
+            Self::InvalidParam { .. } => "InvalidParam",
+            Self::RefNotSupported { .. } => "RefNotSupported",
+            Self::MissingRepo { .. } => "MissingRepo",
+            Self::InvalidInputId { .. } => "InvalidInputId",
+            Self::InvalidFollowsPath { .. } => "InvalidFollowsPath",
+            Self::InvalidInputPrefix { .. } => "InvalidInputPrefix",
+            Self::LockDrift { .. } => "LockDrift",
+            Self::DoctorProblems { .. } => "DoctorProblems",
+            Self::DuplicateInputs { .. } => "DuplicateInputs",
+            Self::MissingListTemplate { .. } => "MissingListTemplate",
+            Self::ListTemplate { .. } => "ListTemplate",
+            Self::Invalid { .. } => "Invalid",
+            Self::FromLockNeedsId { .. } => "FromLockNeedsId",
+            Self::InferredIdNotIdentifier { .. } => "InferredIdNotIdentifier",
+            Self::CouldNotInferId { .. } => "CouldNotInferId",
+            Self::InputNotPinnable { .. } => "InputNotPinnable",
+            Self::InvalidDate { .. } => "InvalidDate",
+            Self::PinBefore { .. } => "PinBefore",
+            Self::UpdateRef { .. } => "UpdateRef",
+            Self::UpdateUnknownInput { .. } => "UpdateUnknownInput",
+            Self::CopyUnknownInput { .. } => "CopyUnknownInput",
+            Self::CopyNoUrl { .. } => "CopyNoUrl",
+            Self::CopyInputExists { .. } => "CopyInputExists",
+            Self::CouldNotRemove { .. } => "CouldNotRemove",
+            Self::LockFile { .. } => "LockFile",
+            Self::FollowsCreateFailed { .. } => "FollowsCreateFailed",
+            Self::NotAFollowsPath { .. } => "NotAFollowsPath",
+            Self::ValidationAfterEdit { .. } => "ValidationAfterEdit",
+            Self::Batch { .. } => "Batch",
+            Self::OpenOutputs { .. } => "OpenOutputs",
+            Self::NoToggleableInputs { .. } => "NoToggleableInputs",
+            Self::MultipleToggleableInputs { .. } => "MultipleToggleableInputs",
+            Self::ToggleUnknownInput { .. } => "ToggleUnknownInput",
+            Self::ToggleNoAlternate { .. } => "ToggleNoAlternate",
+            Self::ToggleAmbiguousVariant { .. } => "ToggleAmbiguousVariant",
+            Self::ToggleRefUnmatched { .. } => "ToggleRefUnmatched",
+            Self::ToggleRefAmbiguous { .. } => "ToggleRefAmbiguous",
+            Self::TogglePathMissing { .. } => "TogglePathMissing",
+            Self::ToggleAlreadyActive { .. } => "ToggleAlreadyActive",
+            Self::ToggleRemoveUnstored { .. } => "ToggleRemoveUnstored",
+            Self::ToggleRemoveActive { .. } => "ToggleRemoveActive",
+        }
+    }
+
     /// Per-failure rendering of a `Batch` aggregate. Each item joins the
     /// path with the error and its full source chain so a reader sees the
     /// underlying cause without the renderer descending per-bullet.
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::cli::{CliArgs, Command};
+use crate::cli::{CliArgs, Command, ListFormat};
 use crate::edit::FlakeEdit;
 use crate::tui;
 
@@ -20,6 +21,7 @@ pub fn run(args: CliArgs) -> Result<()> {
         paths,
         transitive,
         depth,
+        ..
     } = args.subcommand()
         && !paths.is_empty()
     {
@@ -29,6 +31,30 @@ pub fn run(args: CliArgs) -> Result<()> {
         return follow::auto::run_batch(paths, *transitive, *depth, &args);
     }
 
+    if args.flakes().len() > 1 {
+        return run_list_many(&args);
+    }
+
+    // A duplicate input keeps `FlakeEdit` from loading the flake at all,
+    // so `doctor` and `lint` start from the text.
+    if let Command::Doctor { fix } = args.subcommand() {
+        let (editor, state) = setup_editor(&args, args.flake())?;
+        return commands::doctor(&editor, &state, *fix);
+    }
+    if let Command::Lint = args.subcommand() {
+        let (editor, state) = setup_editor(&args, args.flake())?;
+        return commands::lint(&editor, &state);
+    }
+    if let Command::Validate { path } = args.subcommand() {
+        let (editor, state) = setup_editor(&args, path.as_ref().or(args.flake()))?;
+        return commands::validate(&editor, &state);
+    }
+    // A watcher has to outlive a flake.nix that does not parse yet.
+    if let Command::Watch { command, interval } = args.subcommand() {
+        let (_, state) = setup_editor(&args, args.flake())?;
+        return commands::watch(&state, command, Duration::from_millis(*interval));
+    }
+
     let (editor, mut flake_edit, mut state) = setup(&args)?;
     let no_cache = args.no_cache();
 
@@ -36,38 +62,117 @@ pub fn run(args: CliArgs) -> Result<()> {
         Command::Add { .. } => dispatch_add(&args, &editor, &mut flake_edit, &state)?,
         Command::Remove { .. } => dispatch_remove(&args, &editor, &mut flake_edit, &state)?,
         Command::Change { .. } => dispatch_change(&args, &editor, &mut flake_edit, &state)?,
-        Command::List { .. } => dispatch_list(&args, &mut flake_edit)?,
+        Command::Rename { .. } => dispatch_rename(&args, &editor, &mut flake_edit, &state)?,
+        Command::Prune { .. } => dispatch_prune(&args, &editor, &mut flake_edit, &state)?,
+        Command::Status => commands::status(&mut flake_edit, editor.rope(), &state)?,
+        Command::Doctor { .. } => unreachable!("doctor is dispatched before setup"),
+        Command::Lint => unreachable!("lint is dispatched before setup"),
+        Command::Validate { .. } => unreachable!("validate is dispatched before setup"),
+        Command::Watch { .. } => unreachable!("watch is dispatched before setup"),
+        Command::List { .. } => dispatch_list(&args, &mut flake_edit, &state)?,
         Command::Update { .. } => dispatch_update(&args, &editor, &mut flake_edit, &state)?,
         Command::Pin { .. } => dispatch_pin(&args, &editor, &mut flake_edit, &state)?,
         Command::Unpin { .. } => dispatch_unpin(&args, &editor, &mut flake_edit, &state)?,
         Command::Toggle { .. } => dispatch_toggle(&args, &editor, &mut flake_edit, &state)?,
+        Command::Override { input, uri } => {
+            commands::store_override(&editor, &mut flake_edit, &state, input, uri)?
+        }
         Command::Follow { .. } => dispatch_follow(&args, &editor, &mut flake_edit, &mut state)?,
         Command::AddFollow { .. } => {
             dispatch_add_follow(&args, &editor, &mut flake_edit, &mut state)?
         }
+        Command::Copy { .. } => dispatch_copy(&args, &editor, &mut flake_edit, &state)?,
+        Command::Unfollow { .. } => dispatch_unfollow(&args, &editor, &mut flake_edit, &mut state)?,
         Command::Completion { .. } => {
-            return dispatch_completion(&args, &mut flake_edit, no_cache);
+            return dispatch_completion(&args, &mut flake_edit, &state, no_cache);
         }
         Command::Config { .. } => return dispatch_config(&args),
     }
 
-    crate::cache::populate_cache_from_input_map(flake_edit.curr_list(), no_cache);
+    crate::cache::populate_cache_from_input_map(
+        flake_edit.curr_list(),
+        state.cache_namespace(),
+        no_cache,
+    );
 
     Ok(())
 }
 
+/// The `flake.nix` named by `--flake`, which may also be its directory.
+fn resolve_flake_path(flake: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(flake);
+    if !path.is_dir() {
+        return Ok(path);
+    }
+    let flake_nix = path.join("flake.nix");
+    if !flake_nix.exists() {
+        return Err(Error::FlakeDirEmpty { path });
+    }
+    Ok(flake_nix)
+}
+
+/// `list` over every `--flake`. Each flake reads the `flake.lock` next
+/// to it.
+fn run_list_many(args: &CliArgs) -> Result<()> {
+    let Command::List {
+        format,
+        json,
+        locked,
+        changed_since,
+        template,
+    } = args.subcommand()
+    else {
+        return Err(Error::MultipleFlakes);
+    };
+    let format = match (json, template) {
+        (true, _) => &ListFormat::Entries,
+        (false, Some(_)) => &ListFormat::Template,
+        (false, None) => format,
+    };
+    if args.lock_file().is_some() {
+        return Err(Error::MultipleFlakesWith {
+            flag: "--lock-file",
+        });
+    }
+    if changed_since.is_some() {
+        return Err(Error::MultipleFlakesWith {
+            flag: "--changed-since",
+        });
+    }
+
+    let mut flakes = Vec::with_capacity(args.flakes().len());
+    for flake in args.flakes() {
+        let flake_path = resolve_flake_path(flake)?;
+        let editor =
+            Editor::from_path(flake_path.clone()).map_err(|source| Error::FlakeNotFound {
+                path: flake_path.clone(),
+                source,
+            })?;
+        let flake_edit = editor.create_flake_edit()?;
+        let lock_path = crate::lock::FlakeLock::path_beside(&flake_path);
+        let state = AppState::new(flake_path, args.config().map(PathBuf::from))?
+            .with_lock_file(Some(lock_path))
+            .with_no_color(args.no_color());
+        flakes.push((flake.clone(), flake_edit, state));
+    }
+    commands::list_many(&mut flakes, format, *locked, template.as_deref())
+}
+
 fn setup(args: &CliArgs) -> Result<(Editor, FlakeEdit, AppState)> {
-    let flake_path = if let Some(flake) = args.flake() {
-        let path = PathBuf::from(flake);
-        if path.is_dir() {
-            let flake_nix = path.join("flake.nix");
-            if !flake_nix.exists() {
-                return Err(Error::FlakeDirEmpty { path });
-            }
-            flake_nix
-        } else {
-            path
-        }
+    let (editor, state) = setup_editor(args, args.flake())?;
+    let force = matches!(args.subcommand(), Command::AddFollow { force: true, .. });
+    let flake_edit = editor
+        .create_flake_edit()?
+        .with_toggle_marker(state.config.toggle.marker.as_str())
+        .with_dangling_follows(force);
+    Ok((editor, flake_edit, state))
+}
+
+/// The editor and state of [`setup`], without parsing the flake. `flake`
+/// overrides `--flake`, for commands that take the path themselves.
+fn setup_editor(args: &CliArgs, flake: Option<&String>) -> Result<(Editor, AppState)> {
+    let flake_path = if let Some(flake) = flake {
+        resolve_flake_path(flake)?
     } else {
         let path = PathBuf::from("flake.nix");
         let binding = root::Root::from_path(&path).map_err(|source| Error::FlakeNotFound {
@@ -81,18 +186,24 @@ fn setup(args: &CliArgs) -> Result<(Editor, FlakeEdit, AppState)> {
         path: flake_path.clone(),
         source,
     })?;
-    let flake_edit = editor.create_flake_edit()?;
     let interactive = tui::is_interactive(args.non_interactive());
 
     let state = AppState::new(flake_path, args.config().map(PathBuf::from))?
         .with_diff(args.diff())
+        .with_diff_context(args.context())
+        .with_dry_run(args.dry_run())
         .with_no_lock(args.no_lock())
+        .with_relock(args.relock())
         .with_interactive(interactive)
+        .with_quiet_success(args.quiet_success())
         .with_lock_file(args.lock_file().map(PathBuf::from))
         .with_no_cache(args.no_cache())
-        .with_cache_path(args.cache().map(PathBuf::from));
-
-    Ok((editor, flake_edit, state))
+        .with_cache_path(args.cache().map(PathBuf::from))
+        .with_output(args.output())
+        .with_sort_params(args.sort_params())
+        .with_backup(args.backup())
+        .with_no_color(args.no_color());
+    Ok((editor, state))
 }
 
 fn dispatch_add(
@@ -104,9 +215,16 @@ fn dispatch_add(
     let Command::Add {
         uri,
         ref_or_rev,
+        git_ref,
         id,
         no_flake,
+        as_flake,
         shallow,
+        replace_if_exists,
+        input_prefix,
+        id_sanitize,
+        json_change,
+        from_lock,
     } = args.subcommand()
     else {
         unreachable!("wrong Command variant");
@@ -117,10 +235,24 @@ fn dispatch_add(
         state,
         id.clone(),
         uri.clone(),
-        *no_flake,
+        commands::AddOptions {
+            flake: match (*no_flake, *as_flake) {
+                (true, _) => Some(false),
+                (_, true) => Some(true),
+                _ => None,
+            },
+            replace_if_exists: *replace_if_exists,
+            input_prefix: input_prefix.clone(),
+            id_sanitize: *id_sanitize,
+            json_change: *json_change,
+            from_lock: from_lock.clone(),
+        },
         commands::UriOptions {
             ref_or_rev: ref_or_rev.as_deref(),
             shallow: *shallow,
+            shortcuts: Some(&state.config.shortcuts),
+            git_ref: git_ref.as_deref(),
+            params: &[],
         },
     )
 }
@@ -131,10 +263,10 @@ fn dispatch_remove(
     flake_edit: &mut FlakeEdit,
     state: &AppState,
 ) -> Result<()> {
-    let Command::Remove { id } = args.subcommand() else {
+    let Command::Remove { id, selection } = args.subcommand() else {
         unreachable!("wrong Command variant");
     };
-    commands::remove(editor, flake_edit, state, id.clone())
+    commands::remove(editor, flake_edit, state, id.clone(), selection.into())
 }
 
 fn dispatch_change(
@@ -148,6 +280,8 @@ fn dispatch_change(
         ref_or_rev,
         id,
         shallow,
+        params,
+        rename_output,
     } = args.subcommand()
     else {
         unreachable!("wrong Command variant");
@@ -161,15 +295,58 @@ fn dispatch_change(
         commands::UriOptions {
             ref_or_rev: ref_or_rev.as_deref(),
             shallow: *shallow,
+            shortcuts: Some(&state.config.shortcuts),
+            params,
+            ..Default::default()
         },
+        *rename_output,
     )
 }
 
-fn dispatch_list(args: &CliArgs, flake_edit: &mut FlakeEdit) -> Result<()> {
-    let Command::List { format } = args.subcommand() else {
+fn dispatch_rename(
+    args: &CliArgs,
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+) -> Result<()> {
+    let Command::Rename { from, to } = args.subcommand() else {
+        unreachable!("wrong Command variant");
+    };
+    commands::rename(editor, flake_edit, state, from, to)
+}
+
+fn dispatch_prune(
+    args: &CliArgs,
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+) -> Result<()> {
+    let Command::Prune { apply } = args.subcommand() else {
         unreachable!("wrong Command variant");
     };
-    commands::list(flake_edit, format)
+    commands::prune(editor, flake_edit, state, *apply)
+}
+
+fn dispatch_list(args: &CliArgs, flake_edit: &mut FlakeEdit, state: &AppState) -> Result<()> {
+    let Command::List {
+        format,
+        json,
+        locked,
+        changed_since,
+        template,
+    } = args.subcommand()
+    else {
+        unreachable!("wrong Command variant");
+    };
+    if let Some(reference) = changed_since {
+        return commands::list_changed_since(state, reference, *json);
+    }
+    let format = match (json, template) {
+        (true, _) => &ListFormat::Entries,
+        (false, Some(_)) => &ListFormat::Template,
+        (false, None) => format,
+    };
+    commands::list(flake_edit, state, format, *locked, template.as_deref())
 }
 
 fn dispatch_update(
@@ -178,10 +355,29 @@ fn dispatch_update(
     flake_edit: &mut FlakeEdit,
     state: &AppState,
 ) -> Result<()> {
-    let Command::Update { id, init } = args.subcommand() else {
+    let Command::Update {
+        id,
+        init,
+        select,
+        ref_name,
+        force,
+        selection,
+    } = args.subcommand()
+    else {
         unreachable!("wrong Command variant");
     };
-    commands::update(editor, flake_edit, state, id.clone(), *init)
+    if let (Some(id), Some(ref_name)) = (id, ref_name) {
+        return commands::update_to_ref(editor, flake_edit, state, id, ref_name, *force);
+    }
+    commands::update(
+        editor,
+        flake_edit,
+        state,
+        id.clone(),
+        select,
+        *init,
+        selection.into(),
+    )
 }
 
 fn dispatch_pin(
@@ -190,10 +386,11 @@ fn dispatch_pin(
     flake_edit: &mut FlakeEdit,
     state: &AppState,
 ) -> Result<()> {
-    let Command::Pin { id, rev } = args.subcommand() else {
+    let Command::Pin { id, rev, before } = args.subcommand() else {
         unreachable!("wrong Command variant");
     };
-    commands::pin(editor, flake_edit, state, id.clone(), rev.clone())
+    let before = before.as_deref().map(str::parse).transpose()?;
+    commands::pin(editor, flake_edit, state, id.clone(), rev.clone(), before)
 }
 
 fn dispatch_unpin(
@@ -242,18 +439,23 @@ fn dispatch_follow(
         paths: _,
         transitive,
         depth,
+        remove,
+        summary,
     } = args.subcommand()
     else {
         unreachable!("wrong Command variant");
     };
+    state.lock_offline = true;
+    if let Some(path) = remove {
+        return follow::remove_follow(editor, flake_edit, state, path);
+    }
     if let Some(min) = transitive {
         state.config.follow.transitive_min = *min;
     }
     if let Some(max) = depth {
         state.config.follow.max_depth = Some(*max);
     }
-    state.lock_offline = true;
-    follow::auto::run(editor, flake_edit, state)
+    follow::auto::run(editor, flake_edit, state, *summary)
 }
 
 fn dispatch_add_follow(
@@ -262,14 +464,59 @@ fn dispatch_add_follow(
     flake_edit: &mut FlakeEdit,
     state: &mut AppState,
 ) -> Result<()> {
-    let Command::AddFollow { input, target } = args.subcommand() else {
+    let Command::AddFollow { input, target, .. } = args.subcommand() else {
         unreachable!("wrong Command variant");
     };
     state.lock_offline = true;
     follow::add_follow(editor, flake_edit, state, input.clone(), target.clone())
 }
 
-fn dispatch_completion(args: &CliArgs, flake_edit: &mut FlakeEdit, no_cache: bool) -> Result<()> {
+fn dispatch_copy(
+    args: &CliArgs,
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+) -> Result<()> {
+    let Command::Copy { source, id, force } = args.subcommand() else {
+        unreachable!("wrong Command variant");
+    };
+    let source_path = resolve_flake_path(source)?;
+    let mut source_edit = Editor::from_path(source_path.clone())
+        .map_err(|source| Error::FlakeNotFound {
+            path: source_path.clone(),
+            source,
+        })?
+        .create_flake_edit()?;
+    commands::copy(
+        editor,
+        flake_edit,
+        state,
+        &mut source_edit,
+        &source_path,
+        id,
+        *force,
+    )
+}
+
+fn dispatch_unfollow(
+    args: &CliArgs,
+    editor: &Editor,
+    flake_edit: &mut FlakeEdit,
+    state: &mut AppState,
+) -> Result<()> {
+    let Command::Unfollow { path, selection } = args.subcommand() else {
+        unreachable!("wrong Command variant");
+    };
+    state.lock_offline = true;
+    follow::unfollow(editor, flake_edit, state, path.clone(), selection.into())
+}
+
+fn dispatch_completion(
+    args: &CliArgs,
+    flake_edit: &mut FlakeEdit,
+    state: &AppState,
+    no_cache: bool,
+) -> Result<()> {
     use crate::cache::{Cache, DEFAULT_URI_TYPES};
     use crate::cli::CompletionMode;
 
@@ -281,20 +528,20 @@ fn dispatch_completion(args: &CliArgs, flake_edit: &mut FlakeEdit, no_cache: boo
             for uri_type in DEFAULT_URI_TYPES {
                 println!("{}", uri_type);
             }
-            let cache = Cache::load();
+            let cache = Cache::load().scoped(state.cache_namespace());
             for uri in cache.list_uris() {
                 println!("{}", uri);
             }
         }
         CompletionMode::Change => {
             let inputs = flake_edit.list();
-            crate::cache::populate_cache_from_input_map(inputs, no_cache);
+            crate::cache::populate_cache_from_input_map(inputs, state.cache_namespace(), no_cache);
             for id in inputs.keys() {
                 println!("{}", id);
             }
         }
         CompletionMode::Follow => {
-            if let Ok(lock) = crate::lock::FlakeLock::from_default_path() {
+            if let Ok(lock) = crate::lock::FlakeLock::from_file(state.lock_path()) {
                 for nested in lock.nested_inputs() {
                     println!("{}", nested.path);
                 }
@@ -316,11 +563,17 @@ fn dispatch_config(args: &CliArgs) -> Result<()> {
     let Command::Config {
         print_default,
         path,
+        show,
     } = args.subcommand()
     else {
         unreachable!("wrong Command variant");
     };
-    commands::config(*print_default, *path)
+    commands::config(
+        *print_default,
+        *path,
+        *show,
+        args.config().map(std::path::Path::new),
+    )
 }
 
 #[cfg(test)]
@@ -1,7 +1,9 @@
 use std::path::PathBuf;
 
-use crate::cache::CacheConfig;
-use crate::config::{Config, ConfigError};
+use crate::cache::{CacheConfig, project_namespace};
+use crate::cli::{BackupMode, OutputFormat};
+use crate::config::{CacheScope, Config, ConfigError};
+use crate::lock::FlakeLock;
 
 /// Application state for a flake-edit session.
 ///
@@ -14,17 +16,33 @@ pub struct AppState {
     pub lock_file: Option<PathBuf>,
     /// Only show diff, don't write changes
     pub diff: bool,
+    /// Unchanged lines shown around each diff hunk
+    pub diff_context: usize,
+    /// Only print the resulting flake.nix, don't write changes
+    pub dry_run: bool,
     /// Skip running nix flake lock after changes
     pub no_lock: bool,
+    /// Require nix flake lock to succeed after changes
+    pub relock: bool,
     /// Pass `--offline` to `nix flake lock`. Set for follows-only edits so
     /// the lockfile refresh works without network access.
     pub lock_offline: bool,
     /// Allow interactive TUI prompts
     pub interactive: bool,
+    /// Suppress success messages and diffs, leaving only errors
+    pub quiet_success: bool,
     /// Disable reading from and writing to the completion cache
     pub no_cache: bool,
     /// Custom cache file path (for testing or portable configs)
     pub cache_path: Option<PathBuf>,
+    /// Report applied changes as prose or as JSON
+    pub output: OutputFormat,
+    /// Sort the query parameters of every input url on write
+    pub sort_params: bool,
+    /// Copy flake.nix aside before writing it
+    pub backup: Option<BackupMode>,
+    /// Never color diffs and tables, even on a terminal
+    pub no_color: bool,
     /// Loaded configuration
     pub config: Config,
 }
@@ -35,11 +53,19 @@ impl AppState {
             flake_path,
             lock_file: None,
             diff: false,
+            diff_context: crate::diff::DEFAULT_CONTEXT,
+            dry_run: false,
             no_lock: false,
+            relock: false,
             lock_offline: false,
             interactive: true,
+            quiet_success: false,
             no_cache: false,
             cache_path: None,
+            output: OutputFormat::default(),
+            sort_params: false,
+            backup: None,
+            no_color: false,
             config: Config::load_from(config_path.as_deref())?,
         })
     }
@@ -49,11 +75,26 @@ impl AppState {
         self
     }
 
+    pub fn with_diff_context(mut self, diff_context: usize) -> Self {
+        self.diff_context = diff_context;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
     pub fn with_no_lock(mut self, no_lock: bool) -> Self {
         self.no_lock = no_lock;
         self
     }
 
+    pub fn with_relock(mut self, relock: bool) -> Self {
+        self.relock = relock;
+        self
+    }
+
     pub fn with_lock_offline(mut self, lock_offline: bool) -> Self {
         self.lock_offline = lock_offline;
         self
@@ -64,6 +105,11 @@ impl AppState {
         self
     }
 
+    pub fn with_quiet_success(mut self, quiet_success: bool) -> Self {
+        self.quiet_success = quiet_success;
+        self
+    }
+
     pub fn with_lock_file(mut self, lock_file: Option<PathBuf>) -> Self {
         self.lock_file = lock_file;
         self
@@ -79,14 +125,75 @@ impl AppState {
         self
     }
 
-    /// Get the cache configuration based on CLI flags.
+    pub fn with_output(mut self, output: OutputFormat) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn with_sort_params(mut self, sort_params: bool) -> Self {
+        self.sort_params = sort_params;
+        self
+    }
+
+    pub fn with_backup(mut self, backup: Option<BackupMode>) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    pub fn with_no_color(mut self, no_color: bool) -> Self {
+        self.no_color = no_color;
+        self
+    }
+
+    /// The lock file to read: `--lock-file` if given, else the
+    /// `flake.lock` next to the flake.
+    pub fn lock_path(&self) -> PathBuf {
+        self.lock_file
+            .clone()
+            .unwrap_or_else(|| FlakeLock::path_beside(&self.flake_path))
+    }
+
+    /// Whether writes sort url parameters, from `--sort-params` or
+    /// `[format] sort_params`.
+    pub fn sorts_params(&self) -> bool {
+        self.sort_params || self.config.format.sort_params
+    }
+
+    /// Whether stdout output carries ANSI colors: never with
+    /// `--no-color`, otherwise only on a terminal without `NO_COLOR`.
+    pub fn color(&self) -> bool {
+        !self.no_color && crate::diff::use_color()
+    }
+
+    /// Whether changes are written to disk rather than shown.
+    pub fn writes(&self) -> bool {
+        !self.diff && !self.dry_run
+    }
+
+    /// Whether prose status lines go to stdout. `--output json` keeps
+    /// stdout to the JSON report alone, `--dry-run` to the document.
+    pub fn prints_status(&self) -> bool {
+        !self.quiet_success && !self.dry_run && self.output == OutputFormat::Text
+    }
+
+    /// Get the cache configuration based on CLI flags and `[cache] scope`.
     pub fn cache_config(&self) -> CacheConfig {
-        if self.no_cache {
+        let config = if self.no_cache {
             CacheConfig::None
         } else if let Some(ref path) = self.cache_path {
             CacheConfig::Custom(path.clone())
         } else {
             CacheConfig::Default
+        };
+        config.scoped(self.cache_namespace())
+    }
+
+    /// Namespace of the completion cache this flake reads and writes, or
+    /// `None` for the global entries.
+    pub fn cache_namespace(&self) -> Option<String> {
+        match self.config.cache.scope {
+            CacheScope::Global => None,
+            CacheScope::Project => Some(project_namespace(&self.flake_path)),
         }
     }
 }
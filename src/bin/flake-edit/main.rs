@@ -1,7 +1,7 @@
 use std::process::ExitCode;
 
 use clap::Parser;
-use flake_edit::cli::CliArgs;
+use flake_edit::cli::{CliArgs, OutputFormat};
 
 mod log;
 mod render;
@@ -12,10 +12,15 @@ fn main() -> ExitCode {
     log::init().ok();
     tracing::debug!("Cli args: {args:?}");
 
+    let output = args.output();
+    let no_color = args.no_color();
     match flake_edit::app::run(args) {
         Ok(()) => ExitCode::SUCCESS,
         Err(err) => {
-            render::report(&err);
+            match output {
+                OutputFormat::Text => render::report(&err, no_color),
+                OutputFormat::Json => render::report_json(&err),
+            }
             ExitCode::FAILURE
         }
     }
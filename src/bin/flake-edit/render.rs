@@ -5,17 +5,21 @@
 //! variants (validation errors and batch failures).
 //!
 //! `NO_COLOR` is honored: prefix keywords are styled red / yellow / cyan
-//! when color is on, plain when it's off. Bodies are never colored.
+//! when color is on, plain when it's off or stderr is not a terminal.
+//! Bodies are never colored.
 
-use std::io::{self, Write as _};
+use std::io::{self, IsTerminal as _, Write as _};
 
 use flake_edit::app;
 use flake_edit::app::error::chain_layers;
+use flake_edit::forge::commits::PinBeforeError;
+use flake_edit::forge::update::RefUpdateError;
 
 /// Print an error to stderr in the documented user-facing shape.
-pub(crate) fn report(err: &app::Error) {
+/// `no_color` is `--no-color`.
+pub(crate) fn report(err: &app::Error, no_color: bool) {
     let mut stderr = io::stderr().lock();
-    let style = Style::detect();
+    let style = Style::detect(no_color);
 
     let _ = write_error_line(&mut stderr, &style, &err.to_string());
 
@@ -56,16 +60,56 @@ pub(crate) fn report(err: &app::Error) {
     }
 }
 
-/// Style policy. The prefix keywords are colored when `NO_COLOR` is unset.
+/// Print an error to stderr as one JSON object, for `--output json`.
+///
+/// `kind` is [`app::Error::kind`], which looks through the library
+/// wrapper so a missing input reads `InputNotFound` rather than `Flake`. `context`
+/// carries whatever the prose report would show beyond the headline.
+pub(crate) fn report_json(err: &app::Error) {
+    let mut context = serde_json::Map::new();
+    let causes: Vec<String> = chain_layers(err).into_iter().skip(1).collect();
+    if !causes.is_empty() {
+        context.insert("causes".into(), causes.into());
+    }
+    let details: Vec<String> = [
+        err.validation_bullets(),
+        match err {
+            app::Error::Flake(inner) => inner.bullets(),
+            _ => None,
+        },
+        err.batch_bullets(),
+        err.candidate_bullets(),
+    ]
+    .into_iter()
+    .flatten()
+    .flatten()
+    .collect();
+    if !details.is_empty() {
+        context.insert("details".into(), details.into());
+    }
+    if let Some(hint) = hint_for(err) {
+        context.insert("hint".into(), hint.into());
+    }
+
+    let report = serde_json::json!({
+        "error": err.to_string(),
+        "kind": err.kind(),
+        "context": context,
+    });
+    let _ = writeln!(io::stderr().lock(), "{report}");
+}
+
+/// Style policy. The prefix keywords are colored unless `NO_COLOR` is set,
+/// `--no-color` is given, or stderr is not a terminal.
 #[derive(Copy, Clone)]
 struct Style {
     color: bool,
 }
 
 impl Style {
-    fn detect() -> Self {
+    fn detect(no_color: bool) -> Self {
         Self {
-            color: std::env::var("NO_COLOR").is_err(),
+            color: !no_color && std::env::var("NO_COLOR").is_err() && io::stderr().is_terminal(),
         }
     }
 
@@ -125,12 +169,81 @@ fn hint_for(err: &app::Error) -> Option<String> {
             "check that '{id}' is declared in flake.nix; run `flake-edit list` to verify input names; \
              use dot notation `flake-edit follow <input>.<nested-input> <target>` for deeper paths"
         )),
+        Error::LockDrift => Some("run `nix flake lock` to update flake.lock".into()),
+        Error::DoctorProblems { fixable, .. } if *fixable > 0 => {
+            Some("run `flake-edit doctor --fix` to apply the safe fixes".into())
+        }
+        Error::DuplicateInputs { .. } => Some(
+            "keep a single url declaration for each input; \
+             `flake-edit doctor --fix` drops a declaration repeated under the same attribute path"
+                .into(),
+        ),
+        Error::DoctorProblems { .. } => Some(
+            "the remaining problems need a manual edit; \
+             `flake-edit prune --apply` removes unused inputs"
+                .into(),
+        ),
+        Error::FromLockNeedsId => Some(
+            "name the input to add from the lock: `flake-edit add <id> --from-lock`".into(),
+        ),
+        Error::InvalidInputPrefix { .. } => Some(
+            "a prefixed id must start with a letter or `_` and contain only letters, digits, \
+             `_`, `'` and `-`"
+                .into(),
+        ),
+        Error::InferredIdNotIdentifier { uri, .. } => Some(format!(
+            "pass `--id-sanitize` to rewrite it, or name the input: `flake-edit add <id> {uri}`"
+        )),
+        Error::NotAFollowsPath { path } => Some(format!(
+            "name the nested input, e.g. `flake-edit unfollow {path}.nixpkgs`; \
+             drop the whole input with `flake-edit remove {path}`"
+        )),
         Error::FlakeNotFound { .. } | Error::FlakeDirEmpty { .. } => Some(
             "run `nix flake init` here, or pass `--flake <path>` pointing at a directory \
              containing flake.nix"
                 .into(),
         ),
+        Error::MissingRepo { scheme, owner, .. } => {
+            Some(format!("write the full form `{scheme}:{owner}/<repo>`"))
+        }
+        Error::InvalidParam { .. } => Some(
+            "parameters are ref, rev, dir, host, shallow, submodules, narHash, lastModified \
+             and revCount; an empty value clears one"
+                .into(),
+        ),
+        Error::RefNotSupported { id, .. } => Some(format!(
+            "pass the full new reference instead: `flake-edit change {id} <flakeref>`"
+        )),
+        Error::OpenOutputs => Some(
+            "list every input the outputs use, e.g. `outputs = { self, nixpkgs }: ...`, \
+             dropping `...` and any `@inputs` binding"
+                .into(),
+        ),
         Error::LockFile { .. } => Some("run `nix flake lock` to (re)generate flake.lock".into()),
+        Error::UpdateRef(RefUpdateError::PinnedToRev { id, .. }) => Some(format!(
+            "pass `--force` to replace the rev, or `flake-edit unpin {id}` first"
+        )),
+        Error::UpdateRef(RefUpdateError::Unsupported { id, .. }) => Some(format!(
+            "pass the full new reference instead: `flake-edit change {id} <flakeref>`"
+        )),
+        Error::PinBefore(PinBeforeError::NotForge { id, .. }) => Some(format!(
+            "pin it to a known rev instead: `flake-edit pin {id} <rev>`"
+        )),
+        Error::PinBefore(PinBeforeError::Lookup { .. }) => Some(
+            "check the network connection; a forge token in nix.conf `access-tokens` or \
+             GITHUB_TOKEN lifts the anonymous rate limit"
+                .into(),
+        ),
+        Error::MissingListTemplate => Some(
+            "pass `--template '{id}: {url}'`, or set `template` in the `[list]` section \
+             of the configuration"
+                .into(),
+        ),
+        Error::MultipleFlakes => Some("run the command once per flake".into()),
+        Error::MultipleFlakesWith { flag } => Some(format!(
+            "run `list {flag}` once per flake; without `{flag}`, repeated `--flake` reads \
+             each flake.lock from next to its flake.nix"
+        )),
         Error::Batch { .. } => {
             Some("run `flake-edit list` against each failing file to verify input names".into())
         }
@@ -144,9 +257,16 @@ fn hint_for(err: &app::Error) -> Option<String> {
         Error::MultipleToggleableInputs { .. } => {
             Some("pick one with `flake-edit toggle <id>`".into())
         }
-        Error::ToggleUnknownInput { .. } => {
+        Error::ToggleUnknownInput { .. } | Error::UpdateUnknownInput { .. } => {
             Some("run `flake-edit list` to see the current inputs".into())
         }
+        Error::CopyUnknownInput { path, .. } => Some(format!(
+            "run `flake-edit --flake {} list` to see its inputs",
+            path.display()
+        )),
+        Error::CopyInputExists { .. } => {
+            Some("pass `--force` to replace its url with the copied one".into())
+        }
         Error::ToggleNoAlternate { id } => Some(format!(
             "store one and switch to it with `flake-edit toggle {id} <ref>`"
         )),
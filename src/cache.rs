@@ -1,5 +1,5 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 use directories::ProjectDirs;
@@ -33,15 +33,63 @@ fn entry_key(id: &str, uri: &str) -> String {
     format!("{}.{}", id, uri)
 }
 
+/// Key of the per-project cache namespace for `flake_path`: the root of
+/// the git checkout holding it, or its directory outside of one.
+pub fn project_namespace(flake_path: &Path) -> String {
+    let dir = match flake_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let dir = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let root = dir
+        .ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .unwrap_or(&dir);
+    root.display().to_string()
+}
+
 /// Persistent store of previously seen flake URIs.
 ///
-/// Powers shell-completion suggestions, ranked by hit count.
+/// Powers shell-completion suggestions, ranked by hit count. Entries are
+/// global unless the cache is [scoped](Self::scoped) to a project, in
+/// which case reads and writes only see that project's entries.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Cache {
     entries: HashMap<String, CacheEntry>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    projects: BTreeMap<String, HashMap<String, CacheEntry>>,
+    #[serde(skip)]
+    namespace: Option<String>,
 }
 
 impl Cache {
+    /// Restrict reads and writes to the entries of `namespace`, or to the
+    /// global entries when it is `None`.
+    pub fn scoped(mut self, namespace: Option<String>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    fn scope(&self) -> Option<&HashMap<String, CacheEntry>> {
+        match &self.namespace {
+            Some(namespace) => self.projects.get(namespace),
+            None => Some(&self.entries),
+        }
+    }
+
+    fn scope_mut(&mut self) -> &mut HashMap<String, CacheEntry> {
+        match &self.namespace {
+            Some(namespace) => self.projects.entry(namespace.clone()).or_default(),
+            None => &mut self.entries,
+        }
+    }
+
+    fn scope_entries(&self) -> impl Iterator<Item = &CacheEntry> {
+        self.scope()
+            .into_iter()
+            .flat_map(|entries| entries.values())
+    }
+
     /// Write the cache to its on-disk location, creating the parent directory
     /// if needed.
     pub fn commit(&self) -> std::io::Result<()> {
@@ -84,18 +132,19 @@ impl Cache {
     /// Insert or bump the hit count of the `(id, uri)` entry.
     pub fn add_entry(&mut self, id: String, uri: String) {
         let key = entry_key(&id, &uri);
-        match self.entries.get_mut(&key) {
+        let entries = self.scope_mut();
+        match entries.get_mut(&key) {
             Some(entry) => entry.hit += 1,
             None => {
                 let entry = CacheEntry { id, uri, hit: 0 };
-                self.entries.insert(key, entry);
+                entries.insert(key, entry);
             }
         }
     }
 
     /// All cached URIs sorted by descending hit count.
     pub fn list_uris(&self) -> Vec<String> {
-        let mut entries: Vec<_> = self.entries.values().collect();
+        let mut entries: Vec<_> = self.scope_entries().collect();
         entries.sort_by_key(|b| std::cmp::Reverse(b.hit));
         entries.iter().map(|e| e.uri.clone()).collect()
     }
@@ -106,7 +155,7 @@ impl Cache {
     /// used for the same input id (e.g. both a remote `github:` and a local
     /// `path:` URI for testing).
     pub fn list_uris_for_id(&self, id: &str) -> Vec<String> {
-        let mut entries: Vec<_> = self.entries.values().filter(|e| e.id == id).collect();
+        let mut entries: Vec<_> = self.scope_entries().filter(|e| e.id == id).collect();
         entries.sort_by_key(|b| std::cmp::Reverse(b.hit));
         entries.iter().map(|e| e.uri.clone()).collect()
     }
@@ -117,9 +166,10 @@ impl Cache {
     /// Use this when populating the cache as a side effect of any command
     /// that reads inputs (`list`, `change`, `update`, ...), not only `add`.
     pub fn populate_from_inputs<'a>(&mut self, inputs: impl Iterator<Item = (&'a str, &'a str)>) {
+        let entries = self.scope_mut();
         for (id, uri) in inputs {
             let key = entry_key(id, uri);
-            self.entries.entry(key).or_insert_with(|| CacheEntry {
+            entries.entry(key).or_insert_with(|| CacheEntry {
                 id: id.to_string(),
                 uri: uri.to_string(),
                 hit: 0,
@@ -128,23 +178,25 @@ impl Cache {
     }
 }
 
-/// Load the on-disk cache, add any new `(id, uri)` pairs, and commit.
+/// Load the on-disk cache, add any new `(id, uri)` pairs to `namespace`
+/// (see [`Cache::scoped`]), and commit.
 ///
 /// Best-effort: I/O failures are logged, not propagated. A `no_cache` of
 /// `true` makes the call a no-op.
 pub fn populate_cache_from_inputs<'a>(
     inputs: impl Iterator<Item = (&'a str, &'a str)>,
+    namespace: Option<String>,
     no_cache: bool,
 ) {
     if no_cache {
         return;
     }
 
-    let mut cache = Cache::load();
-    let initial_len = cache.entries.len();
+    let mut cache = Cache::load().scoped(namespace);
+    let initial_len = cache.scope_entries().count();
     cache.populate_from_inputs(inputs);
 
-    if cache.entries.len() > initial_len
+    if cache.scope_entries().count() > initial_len
         && let Err(e) = cache.commit()
     {
         tracing::debug!("Could not write to cache: {}", e);
@@ -154,9 +206,17 @@ pub fn populate_cache_from_inputs<'a>(
 /// Convenience wrapper over [`populate_cache_from_inputs`] for the result of
 /// [`crate::edit::FlakeEdit::list`]. A `no_cache` of `true` makes the call a
 /// no-op.
-pub fn populate_cache_from_input_map(inputs: &crate::edit::InputMap, no_cache: bool) {
+pub fn populate_cache_from_input_map(
+    inputs: &crate::edit::InputMap,
+    namespace: Option<String>,
+    no_cache: bool,
+) {
     populate_cache_from_inputs(
-        inputs.iter().map(|(id, input)| (id.as_str(), input.url())),
+        inputs
+            .iter()
+            .filter(|(_, input)| !input.is_unresolved())
+            .map(|(id, input)| (id.as_str(), input.url())),
+        namespace,
         no_cache,
     );
 }
@@ -189,6 +249,39 @@ pub enum CacheConfig {
     None,
     /// Read and write at a custom path (`--cache`, or tests).
     Custom(std::path::PathBuf),
+    /// `cache`, scoped to the entries of one project (`[cache] scope`).
+    Project {
+        cache: Box<CacheConfig>,
+        namespace: String,
+    },
+}
+
+impl CacheConfig {
+    /// Scope this configuration to `namespace`. `None`, or a disabled
+    /// cache, is left unchanged.
+    pub fn scoped(self, namespace: Option<String>) -> Self {
+        match (self, namespace) {
+            (Self::None, _) => Self::None,
+            (config, Some(namespace)) => Self::Project {
+                cache: Box::new(config),
+                namespace,
+            },
+            (config, None) => config,
+        }
+    }
+
+    /// Load the cache this configuration names, or `None` when caching is
+    /// disabled.
+    pub fn load(&self) -> Option<Cache> {
+        match self {
+            Self::Default => Some(Cache::load()),
+            Self::Custom(path) => Some(Cache::from_path(path)),
+            Self::None => None,
+            Self::Project { cache, namespace } => {
+                Some(cache.load()?.scoped(Some(namespace.clone())))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -270,6 +363,53 @@ mod tests {
         assert!(uris.contains(&"github:nix-community/home-manager".to_string()));
     }
 
+    #[test]
+    fn project_scopes_do_not_leak() {
+        let mut cache = Cache::default().scoped(Some("/src/a".into()));
+        cache.add_entry("crane".into(), "github:ipetkov/crane".into());
+        let mut cache = cache.scoped(Some("/src/b".into()));
+        cache.populate_from_inputs([("nixpkgs", "github:NixOS/nixpkgs")].into_iter());
+
+        let cache = cache.scoped(Some("/src/a".into()));
+        assert_eq!(cache.list_uris(), vec!["github:ipetkov/crane"]);
+        assert!(cache.list_uris_for_id("nixpkgs").is_empty());
+        let cache = cache.scoped(Some("/src/b".into()));
+        assert_eq!(cache.list_uris(), vec!["github:NixOS/nixpkgs"]);
+        assert!(cache.list_uris_for_id("crane").is_empty());
+        let cache = cache.scoped(Some("/src/c".into()));
+        assert!(cache.list_uris().is_empty());
+        let cache = cache.scoped(None);
+        assert!(
+            cache.list_uris().is_empty(),
+            "project entries stay out of the global scope"
+        );
+    }
+
+    #[test]
+    fn scoped_cache_round_trips_through_json() {
+        let mut cache = Cache::default();
+        cache.add_entry("nixpkgs".into(), "github:NixOS/nixpkgs".into());
+        let mut cache = cache.scoped(Some("/src/a".into()));
+        cache.add_entry("crane".into(), "path:/src/crane".into());
+
+        let json = serde_json::to_string(&cache).unwrap();
+        let loaded: Cache = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.list_uris(), vec!["github:NixOS/nixpkgs"]);
+        let loaded = loaded.scoped(Some("/src/a".into()));
+        assert_eq!(loaded.list_uris(), vec!["path:/src/crane"]);
+    }
+
+    #[test]
+    fn project_namespace_is_the_git_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = std::fs::canonicalize(tmp.path()).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("nix/sub")).unwrap();
+        let nested = project_namespace(&root.join("nix/sub/flake.nix"));
+        assert_eq!(nested, root.display().to_string());
+        assert_eq!(project_namespace(&root.join("flake.nix")), nested);
+    }
+
     #[test]
     fn test_populate_does_not_increment_hits() {
         let mut cache = Cache::default();
@@ -17,6 +17,10 @@ pub enum Change {
     Change {
         id: Option<ChangeId>,
         uri: Option<String>,
+        /// Also rename the input to this id, as [`Change::Rename`] would,
+        /// including its argument in the outputs lambda.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rename: Option<Segment>,
     },
     /// Redirect a nested input to follow another input.
     ///
@@ -29,6 +33,16 @@ pub enum Change {
         /// The input to follow.
         target: AttrPath,
     },
+    /// Redirect several nested inputs in one change.
+    ///
+    /// Equivalent to applying one [`Change::Follows`] per entry in order,
+    /// but the walker threads the rewritten tree from one entry to the
+    /// next, so parent lookup and validation run once for the batch. An
+    /// entry the walker cannot place fails the whole batch.
+    AddFollows {
+        /// `(input, target)` pairs, applied in order.
+        entries: Vec<(ChangeId, AttrPath)>,
+    },
     /// Make `uri` the active url of an input, keeping the previously
     /// active url as a commented alternate on the adjacent line.
     ///
@@ -55,6 +69,32 @@ pub enum Change {
         /// The alternate activated when `uri` is the active url.
         activate: Option<String>,
     },
+    /// Store `uri` as a commented alternate of an input without activating
+    /// it. The new comment is written directly below the active url, so a
+    /// later [`Change::Toggle`] can flip to it.
+    ToggleStore {
+        id: ChangeId,
+        /// The url stored behind the toggle marker.
+        uri: String,
+    },
+    /// Change the id of a top-level input.
+    ///
+    /// Rewrites the key of every binding that declares `from`, every
+    /// `follows` target that names it (`"from"` and `"from/child"`), and its
+    /// argument in the outputs lambda along with its uses in the body.
+    Rename {
+        from: Segment,
+        to: Segment,
+    },
+    /// Pin an input to the commit `rev`, dropping the ref it tracked.
+    Pin {
+        id: ChangeId,
+        rev: String,
+    },
+    /// Drop the ref and rev of an input, so it tracks the default branch.
+    Unpin {
+        id: ChangeId,
+    },
 }
 
 /// Identifier for an input or nested-input target of a [`Change`].
@@ -107,11 +147,17 @@ impl ChangeId {
     }
 
     /// Match against the surrounding walker [`Context`], which carries the
-    /// enclosing top-level input.
+    /// enclosing top-level input and any nested inputs below it. The id
+    /// matches when it is a prefix of that path extended by `follows`, so
+    /// `crane` and `crane.flake-utils` both match the `systems` follows
+    /// inside `crane`'s `flake-utils` block.
     pub fn matches_with_ctx(&self, follows: &Segment, ctx: Option<Context>) -> bool {
-        let ctx_input = ctx.and_then(|c| c.first().cloned());
-        match ctx_input {
-            Some(input) => self.matches(&input, Some(follows)),
+        match ctx.filter(|c| c.first().is_some()) {
+            Some(ctx) => {
+                let ids = self.path().segments();
+                let path = ctx.segments().iter().chain([follows]);
+                ids.len() <= ctx.segments().len() + 1 && ids.iter().zip(path).all(|(a, b)| a == b)
+            }
             None => self.input() == follows,
         }
     }
@@ -159,7 +205,12 @@ impl Change {
             Change::Remove { ids } => ids.first().cloned(),
             Change::Change { id, .. } => id.clone(),
             Change::Follows { input, .. } => Some(input.clone()),
-            Change::Toggle { id, .. } | Change::ToggleRemove { id, .. } => Some(id.clone()),
+            Change::AddFollows { entries } => entries.first().map(|(input, _)| input.clone()),
+            Change::Toggle { id, .. }
+            | Change::ToggleRemove { id, .. }
+            | Change::ToggleStore { id, .. } => Some(id.clone()),
+            Change::Rename { from, .. } => Some(from.clone().into()),
+            Change::Pin { id, .. } | Change::Unpin { id } => Some(id.clone()),
         }
     }
 
@@ -167,6 +218,9 @@ impl Change {
         match self {
             Change::Remove { ids } => ids.clone(),
             Change::Follows { input, .. } => vec![input.clone()],
+            Change::AddFollows { entries } => {
+                entries.iter().map(|(input, _)| input.clone()).collect()
+            }
             _ => self.id().into_iter().collect(),
         }
     }
@@ -179,7 +233,7 @@ impl Change {
     pub fn uri(&self) -> Option<&String> {
         match self {
             Change::Change { uri, .. } | Change::Add { uri, .. } => uri.as_ref(),
-            Change::Toggle { uri, .. } => Some(uri),
+            Change::Toggle { uri, .. } | Change::ToggleStore { uri, .. } => Some(uri),
             _ => None,
         }
     }
@@ -204,38 +258,20 @@ impl Change {
                 .iter()
                 .map(|id| format!("Removed input: {}", id))
                 .collect(),
-            Change::Change { id, uri, .. } => {
+            Change::Change { id, uri, rename } => {
                 let id = id.as_ref().map(ChangeId::to_string);
-                vec![format!(
-                    "Changed input: {} -> {}",
-                    id.as_deref().unwrap_or("?"),
-                    uri.as_deref().unwrap_or("?")
-                )]
-            }
-            Change::Follows { input, target } => {
-                // Interleave segments with `.inputs.`: `[a, b, c]` renders as
-                // `a.inputs.b.inputs.c`. Length-1 paths get an `inputs.` prefix.
-                let segments = input.path().segments();
-                let path = if segments.len() == 1 {
-                    format!("inputs.{}", segments[0].render())
-                } else {
-                    let mut out = String::new();
-                    for (i, seg) in segments.iter().enumerate() {
-                        if i == 0 {
-                            out.push_str(&seg.render());
-                        } else {
-                            out.push_str(".inputs.");
-                            out.push_str(&seg.render());
-                        }
-                    }
-                    out
-                };
-                vec![format!(
-                    "Added follows: {}.follows = \"{}\"",
-                    path,
-                    target.to_flake_follows_string()
-                )]
+                let id = id.as_deref().unwrap_or("?");
+                let changed = format!("Changed input: {} -> {}", id, uri.as_deref().unwrap_or("?"));
+                match rename {
+                    Some(to) => vec![changed, format!("Renamed input: {} -> {}", id, to)],
+                    None => vec![changed],
+                }
             }
+            Change::Follows { input, target } => vec![follows_message(input, target)],
+            Change::AddFollows { entries } => entries
+                .iter()
+                .map(|(input, target)| follows_message(input, target))
+                .collect(),
             Change::Toggle { id, uri, previous } => {
                 vec![format!("Toggled {}: {} -> {}", id, previous, uri)]
             }
@@ -248,11 +284,41 @@ impl Change {
                     None => vec![removed],
                 }
             }
+            Change::ToggleStore { id, uri } => vec![format!("Stored {} override: {}", id, uri)],
+            Change::Rename { from, to } => vec![format!("Renamed input: {} -> {}", from, to)],
+            Change::Pin { id, rev } => vec![format!("Pinned input: {} to {}", id, rev)],
+            Change::Unpin { id } => vec![format!("Unpinned input: {}", id)],
             Change::None => vec![],
         }
     }
 }
 
+/// Render the success message for one follows declaration.
+fn follows_message(input: &ChangeId, target: &AttrPath) -> String {
+    // Interleave segments with `.inputs.`: `[a, b, c]` renders as
+    // `a.inputs.b.inputs.c`. Length-1 paths get an `inputs.` prefix.
+    let segments = input.path().segments();
+    let path = if segments.len() == 1 {
+        format!("inputs.{}", segments[0].render())
+    } else {
+        let mut out = String::new();
+        for (i, seg) in segments.iter().enumerate() {
+            if i == 0 {
+                out.push_str(&seg.render());
+            } else {
+                out.push_str(".inputs.");
+                out.push_str(&seg.render());
+            }
+        }
+        out
+    };
+    format!(
+        "Added follows: {}.follows = \"{}\"",
+        path,
+        target.to_flake_follows_string()
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -311,4 +377,28 @@ mod tests {
             "depth-1 message should start with `inputs.<id>.follows =`, got: {msg}"
         );
     }
+
+    #[test]
+    fn add_follows_reports_one_message_per_entry() {
+        let change = Change::AddFollows {
+            entries: vec![
+                (
+                    ChangeId::parse("crane.nixpkgs").unwrap(),
+                    AttrPath::parse("nixpkgs").unwrap(),
+                ),
+                (
+                    ChangeId::parse("naersk.nixpkgs").unwrap(),
+                    AttrPath::parse("nixpkgs").unwrap(),
+                ),
+            ],
+        };
+        assert_eq!(
+            change.success_messages(),
+            vec![
+                "Added follows: crane.inputs.nixpkgs.follows = \"nixpkgs\"",
+                "Added follows: naersk.inputs.nixpkgs.follows = \"nixpkgs\"",
+            ],
+        );
+        assert_eq!(change.ids().len(), 2);
+    }
 }
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[command(author, version = CliArgs::unstable_version(), about, long_about)]
@@ -8,21 +8,36 @@ use clap::{Parser, Subcommand, ValueEnum};
 pub struct CliArgs {
     /// Path to `flake.nix`, or a directory containing `flake.nix`.
     /// Defaults to `flake.nix` in the current directory.
+    /// Repeat with `list` to list several flakes at once.
     #[arg(long)]
-    flake: Option<String>,
+    flake: Vec<String>,
     /// Location of the `flake.lock` file.
-    /// Defaults to `flake.lock` in the current directory.
+    /// Defaults to `flake.lock` next to `flake.nix`.
     #[arg(long)]
     lock_file: Option<String>,
     /// Print a diff of the changes instead of writing them to disk.
     #[arg(long, default_value_t = false)]
     diff: bool,
+    /// Unchanged lines shown around each hunk of `--diff`.
+    #[arg(long, value_name = "N", default_value_t = 3, requires = "diff")]
+    context: usize,
+    /// Print the resulting flake.nix instead of writing it to disk.
+    #[arg(long, default_value_t = false, conflicts_with = "diff")]
+    dry_run: bool,
     /// Skip updating the lockfile after editing flake.nix.
     #[arg(long, default_value_t = false)]
     no_lock: bool,
+    /// Run `nix flake lock` after editing flake.nix and treat a failure
+    /// as an error. Without this flag a failed relock is only logged.
+    #[arg(long, default_value_t = false, conflicts_with = "no_lock")]
+    relock: bool,
     /// Disable interactive prompts.
     #[arg(long, default_value_t = false)]
     non_interactive: bool,
+    /// Print nothing on success: no status messages and no diffs.
+    /// Errors are still reported and the exit code carries the result.
+    #[arg(long, default_value_t = false)]
+    quiet_success: bool,
     /// Disable reading from and writing to the completion cache.
     #[arg(long, default_value_t = false)]
     no_cache: bool,
@@ -32,6 +47,30 @@ pub struct CliArgs {
     /// Path to a custom configuration file.
     #[arg(long, global = true)]
     config: Option<String>,
+    /// How to report errors and the changes a command applied.
+    #[arg(long, value_enum, default_value_t = OutputFormat::default())]
+    output: OutputFormat,
+    /// Sort the query parameters of every input url on write.
+    /// Same as `[format] sort_params` in the configuration.
+    #[arg(long, default_value_t = false)]
+    sort_params: bool,
+    /// Copy flake.nix to flake.nix.bak before writing it. With
+    /// `--backup=timestamp` the copy is named flake.nix.<unix-seconds>.bak
+    /// instead, so earlier backups are kept. Ignored with `--diff` and
+    /// `--dry-run`.
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "simple"
+    )]
+    backup: Option<BackupMode>,
+    /// Print diffs, tables and error prefixes without ANSI colors. Colors
+    /// are also off when their stream is not a terminal or `NO_COLOR` is
+    /// set.
+    #[arg(long, global = true, default_value_t = false)]
+    no_color: bool,
 
     #[command(subcommand)]
     subcommand: Command,
@@ -52,7 +91,12 @@ impl CliArgs {
     }
 
     pub fn flake(&self) -> Option<&String> {
-        self.flake.as_ref()
+        self.flake.first()
+    }
+
+    /// Every `--flake` given, in order.
+    pub fn flakes(&self) -> &[String] {
+        &self.flake
     }
 
     pub fn lock_file(&self) -> Option<&String> {
@@ -63,14 +107,26 @@ impl CliArgs {
         self.diff
     }
 
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
     pub fn no_lock(&self) -> bool {
         self.no_lock
     }
 
+    pub fn relock(&self) -> bool {
+        self.relock
+    }
+
     pub fn non_interactive(&self) -> bool {
         self.non_interactive
     }
 
+    pub fn quiet_success(&self) -> bool {
+        self.quiet_success
+    }
+
     pub fn no_cache(&self) -> bool {
         self.no_cache
     }
@@ -82,6 +138,26 @@ impl CliArgs {
     pub fn config(&self) -> Option<&String> {
         self.config.as_ref()
     }
+
+    pub fn output(&self) -> OutputFormat {
+        self.output
+    }
+
+    pub fn sort_params(&self) -> bool {
+        self.sort_params
+    }
+
+    pub fn backup(&self) -> Option<BackupMode> {
+        self.backup
+    }
+
+    pub fn context(&self) -> usize {
+        self.context
+    }
+
+    pub fn no_color(&self) -> bool {
+        self.no_color
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -96,22 +172,64 @@ pub enum Command {
         #[arg(long)]
         /// Pin to a specific ref_or_rev
         ref_or_rev: Option<String>,
+        /// Track this branch. `HEAD` means the default branch: forge urls
+        /// drop their ref, other urls get `ref=HEAD`.
+        #[arg(long, value_name = "REF", conflicts_with = "ref_or_rev")]
+        git_ref: Option<String>,
         /// The input itself is not a flake.
         #[arg(long, short)]
         no_flake: bool,
+        /// The input is a flake, even when the `[add]` configuration says
+        /// otherwise.
+        #[arg(long, conflicts_with = "no_flake")]
+        as_flake: bool,
         /// Use shallow clone for the input.
         #[arg(long, short)]
         shallow: bool,
+        /// Change the url of an input that already exists instead of
+        /// failing.
+        #[arg(long)]
+        replace_if_exists: bool,
+        /// Prepend this prefix to the input id, whether given or inferred
+        /// (e.g. `lib-` turns `nixpkgs` into `lib-nixpkgs`).
+        #[arg(long, value_name = "PREFIX")]
+        input_prefix: Option<String>,
+        /// Rewrite an inferred id that is not a bare identifier: other
+        /// characters become `_` and leading non-letters are dropped
+        /// (e.g. `git-hooks.nix` becomes `git_hooks_nix`).
+        #[arg(long)]
+        id_sanitize: bool,
+        /// Print the change as JSON instead of applying it.
+        #[arg(long)]
+        json_change: bool,
+        /// Add inputs from a flake.lock, pinned to their locked revisions.
+        /// With an ID only that input is added and PATH defaults to the
+        /// flake's own lock file; without one every input the flake lacks
+        /// is imported.
+        #[arg(
+            long,
+            value_name = "PATH",
+            num_args = 0..=1,
+            require_equals = true,
+            conflicts_with_all = ["uri", "ref_or_rev", "git_ref", "no_flake", "as_flake", "shallow", "id_sanitize"],
+        )]
+        from_lock: Option<Option<std::path::PathBuf>>,
     },
     /// Remove a specific flake reference based on its id.
     #[clap(alias = "rm")]
-    Remove { id: Option<String> },
+    Remove {
+        id: Option<String>,
+        #[command(flatten)]
+        selection: SelectionArgs,
+    },
     /// Change an existing flake reference's URI.
     #[clap(alias = "c")]
     Change {
         /// The name of an existing input attribute.
         id: Option<String>,
         /// The new URI for the input.
+        /// Omit it with `--ref-or-rev` or `--param` to change only those parts of
+        /// the current URI.
         uri: Option<String>,
         #[arg(long)]
         /// Pin to a specific ref_or_rev
@@ -119,12 +237,104 @@ pub enum Command {
         /// Use shallow clone for the input.
         #[arg(long, short)]
         shallow: bool,
+        /// Set a url parameter, e.g. `--param dir=lib`. Repeatable, applied
+        /// in order; an empty value clears the parameter.
+        #[arg(long = "param", value_name = "KEY=VALUE")]
+        params: Vec<String>,
+        /// Rename the input to the id inferred from the new URI, updating
+        /// its argument in the outputs lambda.
+        #[arg(long)]
+        rename_output: bool,
+    },
+    /// Rename an input, updating the follows declarations and outputs
+    /// references that name it.
+    ///
+    /// Example: `flake-edit rename utils flake-utils`
+    Rename {
+        /// The id of an existing input.
+        from: String,
+        /// The new id.
+        to: String,
+    },
+    /// List inputs the outputs never name, and remove them with `--apply`.
+    ///
+    /// Only works when the outputs arguments name every input they take,
+    /// i.e. without `...` or an `@inputs` binding.
+    Prune {
+        /// Remove the unused inputs instead of listing them.
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Report inputs on which flake.nix and flake.lock disagree.
+    ///
+    /// Lists inputs missing from the lock, inputs only the lock has, and
+    /// inputs whose url changed since they were locked. Exits non-zero
+    /// when there is any drift.
+    Status,
+    /// Check flake.nix for problems, and fix the safe ones with `--fix`.
+    ///
+    /// Reports duplicate input bindings, stale and dangling follows,
+    /// unsorted url parameters, unused inputs and the other follows
+    /// lints. `--fix` removes the later copy of a duplicate input, the
+    /// stale and dangling follows, and sorts url parameters. Everything
+    /// else is only reported. Exits non-zero while any problem is left.
+    Doctor {
+        /// Apply the safe fixes.
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Report inputs whose url is declared more than once.
+    ///
+    /// Nix rejects such a flake, and flake-edit would only see the last
+    /// declaration. Lists each duplicated input with the lines of its
+    /// declarations. Read-only. Exits non-zero when there is any.
+    Lint,
+    /// Check flake.nix for errors without editing it.
+    ///
+    /// Reports parse errors, duplicate attributes and broken follows
+    /// declarations, each with its line and column. Read-only. Exits
+    /// non-zero when there is any error, so it fits a pre-commit hook.
+    Validate {
+        /// The flake.nix to check, or its directory. Defaults to `--flake`.
+        path: Option<String>,
+    },
+    /// Re-run `list`, `status`, `doctor` or `validate` whenever flake.nix
+    /// changes.
+    Watch {
+        /// The command to re-run.
+        #[arg(value_enum, default_value_t = WatchCommand::default())]
+        command: WatchCommand,
+        /// How often to check flake.nix for changes, in milliseconds.
+        #[arg(long, default_value_t = 500, value_name = "MS")]
+        interval: u64,
     },
     /// List flake inputs
     #[clap(alias = "l")]
     List {
         #[arg(long, value_enum, default_value_t = ListFormat::default())]
         format: ListFormat,
+        /// Print a JSON array with one object per input: its `id`, `url`,
+        /// `flake`, `follows` and `ref_or_rev`. Unlike `--format json`, the
+        /// shape is kept stable for scripts.
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+        /// Annotate each input with its locked rev from `flake.lock`.
+        /// The detailed format shows how long ago that rev was committed
+        /// whenever a `flake.lock` is present, with or without this flag.
+        /// Inputs without a lock entry are listed without annotation.
+        #[arg(long)]
+        locked: bool,
+        /// List only the inputs whose locked rev differs from the one in
+        /// LOCK, e.g. the flake.lock of the main branch. Combines with
+        /// `--json`.
+        #[arg(long, value_name = "LOCK", conflicts_with_all = ["format", "locked"])]
+        changed_since: Option<std::path::PathBuf>,
+        /// Print TEMPLATE per input, e.g. `{id}: {url} ({ref_or_rev})`.
+        /// Placeholders are the fields of a `--json` entry. Overrides
+        /// `[list] template` in the configuration and implies
+        /// `--format template`.
+        #[arg(long, conflicts_with_all = ["format", "json", "changed_since"])]
+        template: Option<String>,
     },
     /// Update inputs to their latest specified release.
     #[clap(alias = "u")]
@@ -136,6 +346,30 @@ pub enum Command {
         /// itself isn't yet pinned to a specific release.
         #[arg(long)]
         init: bool,
+        /// Update only these inputs, without the interactive picker
+        /// (e.g. `--select nixpkgs,crane`).
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "IDS",
+            conflicts_with_all = ["id", "select_all", "select_none"]
+        )]
+        select: Vec<String>,
+        /// Track this branch or tag instead of the latest release
+        /// (e.g. `nixos-24.05`). Only for github, gitlab, sourcehut and
+        /// indirect inputs.
+        #[arg(
+            long = "ref",
+            value_name = "REF",
+            requires = "id",
+            conflicts_with_all = ["init", "select"]
+        )]
+        ref_name: Option<String>,
+        /// With `--ref`, also replace a rev the input is pinned to.
+        #[arg(long, requires = "ref_name")]
+        force: bool,
+        #[command(flatten)]
+        selection: SelectionArgs,
     },
     /// Pin inputs to their current or a specified rev.
     #[clap(alias = "p")]
@@ -144,6 +378,10 @@ pub enum Command {
         id: Option<String>,
         /// Optionally specify a rev for the inputs attribute.
         rev: Option<String>,
+        /// Pin to the newest commit made before this day (YYYY-MM-DD,
+        /// UTC), asking the forge. Only for github and gitlab inputs.
+        #[arg(long, value_name = "DATE", requires = "id", conflicts_with = "rev")]
+        before: Option<String>,
     },
     /// Unpin an input so it tracks the upstream default again.
     #[clap(alias = "up")]
@@ -170,6 +408,19 @@ pub enum Command {
         #[arg(short, long)]
         remove: bool,
     },
+    /// Store an override url for an input without activating it.
+    ///
+    /// The url is written as a commented alternate below the active one,
+    /// like `nix --override-input` but kept in the file. Flip to it and
+    /// back with `toggle`.
+    Override {
+        /// The id of an input attribute.
+        #[arg(value_name = "INPUT")]
+        input: String,
+        /// The flake reference to store.
+        #[arg(value_name = "URI")]
+        uri: String,
+    },
     /// Automatically add and remove follows declarations.
     ///
     /// Analyzes the flake.lock to find nested inputs that match top-level inputs,
@@ -194,6 +445,19 @@ pub enum Command {
         /// config file's `follow.max_depth`.
         #[arg(long)]
         depth: Option<usize>,
+        /// Remove the follows declaration at `<input>.<nested>` instead of
+        /// deduplicating (e.g. `--remove crane.nixpkgs`).
+        #[arg(
+            long,
+            value_name = "INPUT.NESTED",
+            conflicts_with_all = ["transitive", "depth", "paths"]
+        )]
+        remove: Option<String>,
+        /// Print only how many follows were written, stale follows removed
+        /// and nested inputs promoted to top-level, instead of listing
+        /// them. A JSON object with `--output json`.
+        #[arg(long, conflicts_with_all = ["remove", "paths"])]
+        summary: bool,
         /// Flake.nix paths to process. If empty, runs on current directory.
         #[arg(trailing_var_arg = true, num_args = 0..)]
         paths: Vec<std::path::PathBuf>,
@@ -212,6 +476,42 @@ pub enum Command {
         input: Option<String>,
         /// The target input to follow (e.g., "nixpkgs").
         target: Option<String>,
+        /// Write the follows even when the target is not an input of the
+        /// flake.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Copy an input declaration from another flake.
+    ///
+    /// Example: `flake-edit copy ../other/flake.nix crane`
+    ///
+    /// The url, `flake = false` and follows declarations of the input are
+    /// copied. Follows whose target is not an input here are skipped with
+    /// a warning.
+    Copy {
+        /// The flake.nix to copy from, or its directory.
+        source: String,
+        /// The id of the input to copy.
+        id: String,
+        /// Replace the url of an input that already exists instead of
+        /// failing. The existing input keeps its own `flake` setting.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove a single follows declaration, keeping the input.
+    ///
+    /// Example: `flake-edit unfollow rust-overlay.nixpkgs`
+    ///
+    /// This removes `rust-overlay.inputs.nixpkgs.follows`, and the
+    /// `inputs` attribute set around it if nothing else is left in it.
+    ///
+    /// Without arguments, starts an interactive selection.
+    Unfollow {
+        /// The follows path in dot notation (e.g., "rust-overlay.nixpkgs").
+        #[arg(value_name = "INPUT.NESTED")]
+        path: Option<String>,
+        #[command(flatten)]
+        selection: SelectionArgs,
     },
     #[clap(hide = true)]
     #[command(name = "completion")]
@@ -231,6 +531,10 @@ pub enum Command {
         /// Show where configuration would be loaded from.
         #[arg(long)]
         path: bool,
+        /// Print the effective configuration, noting where each value
+        /// came from.
+        #[arg(long)]
+        show: bool,
     },
 }
 
@@ -243,6 +547,35 @@ pub enum CompletionMode {
     Toggle,
 }
 
+/// Command re-run by `watch`.
+#[derive(Debug, Clone, Default, ValueEnum)]
+pub enum WatchCommand {
+    #[default]
+    List,
+    Status,
+    Doctor,
+    Validate,
+}
+
+/// How errors and applied changes are reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Prose for a reader at a terminal.
+    #[default]
+    Text,
+    /// One JSON object per report, for scripts.
+    Json,
+}
+
+/// Name of the copy `--backup` writes next to flake.nix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BackupMode {
+    /// `flake.nix.bak`, replacing an earlier backup.
+    Simple,
+    /// `flake.nix.<unix-seconds>.bak`.
+    Timestamp,
+}
+
 /// Output format for the `list` subcommand.
 #[derive(Debug, Clone, Default, ValueEnum)]
 pub enum ListFormat {
@@ -250,5 +583,28 @@ pub enum ListFormat {
     Toplevel,
     #[default]
     Detailed,
+    Compact,
     Json,
+    /// Each input rendered with `--template` or `[list] template`.
+    Template,
+    /// The `list --json` array of [`crate::app::commands::list::ListEntry`].
+    #[value(skip)]
+    Entries,
+}
+
+/// Starting selection for the interactive multi-select picker.
+#[derive(Debug, Clone, Default, Args)]
+pub struct SelectionArgs {
+    /// Start the interactive picker with every input selected.
+    #[arg(long, conflicts_with = "select_none")]
+    select_all: bool,
+    /// Start the interactive picker with no input selected (the default).
+    #[arg(long)]
+    select_none: bool,
+}
+
+impl SelectionArgs {
+    pub fn select_all(&self) -> bool {
+        self.select_all
+    }
 }
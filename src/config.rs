@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
 /// Default configuration TOML embedded in the binary.
@@ -25,6 +25,55 @@ pub enum ConfigError {
     },
 }
 
+impl ConfigError {
+    /// Name of the variant, for the `kind` of a `--output json` error
+    /// report.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Io { .. } => "Io",
+            Self::Parse { .. } => "Parse",
+        }
+    }
+}
+
+/// Where the configuration used for a run was found.
+///
+/// Only one file is ever read. Values it omits fall back to the embedded
+/// defaults, so the effective configuration is that file layered over
+/// [`Config::default`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Passed explicitly with `--config`.
+    Explicit(PathBuf),
+    /// Found by walking upward from the current directory.
+    Project(PathBuf),
+    /// `~/.config/flake-edit/config.toml`.
+    User(PathBuf),
+    /// No file was found; the embedded defaults apply.
+    Default,
+}
+
+impl ConfigSource {
+    /// Path of the file backing this source, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Explicit(path) | Self::Project(path) | Self::User(path) => Some(path),
+            Self::Default => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Explicit(path) => write!(f, "--config {}", path.display()),
+            Self::Project(path) => write!(f, "project config {}", path.display()),
+            Self::User(path) => write!(f, "user config {}", path.display()),
+            Self::Default => write!(f, "default"),
+        }
+    }
+}
+
 /// Filenames searched for project-level configuration, in priority order.
 const CONFIG_FILENAMES: &[&str] = &["flake-edit.toml", ".flake-edit.toml"];
 
@@ -34,6 +83,18 @@ const CONFIG_FILENAMES: &[&str] = &["flake-edit.toml", ".flake-edit.toml"];
 pub struct Config {
     #[serde(default)]
     pub follow: FollowConfig,
+    #[serde(default)]
+    pub toggle: ToggleConfig,
+    #[serde(default)]
+    pub format: FormatConfig,
+    #[serde(default)]
+    pub shortcuts: Shortcuts,
+    #[serde(default)]
+    pub cache: CacheOptions,
+    #[serde(default)]
+    pub add: AddConfig,
+    #[serde(default)]
+    pub list: ListConfig,
 }
 
 /// `[follow]` section of [`Config`].
@@ -46,6 +107,13 @@ pub struct FollowConfig {
     #[serde(default)]
     pub ignore: Vec<String>,
 
+    /// Nested input names to consider, all others are skipped. Empty (the
+    /// default) considers every input.
+    ///
+    /// See [`Self::can_consider`] for the matching rules.
+    #[serde(default)]
+    pub only: Vec<String>,
+
     /// Minimum number of transitive references required before a shared
     /// nested input is promoted to top-level. `0` disables transitive
     /// deduplication.
@@ -71,6 +139,7 @@ impl Default for FollowConfig {
     fn default() -> Self {
         Self {
             ignore: Vec::new(),
+            only: Vec::new(),
             transitive_min: default_transitive_min(),
             aliases: HashMap::new(),
             max_depth: None,
@@ -94,6 +163,18 @@ impl FollowConfig {
         })
     }
 
+    /// True if a nested input named `name` passes [`Self::only`]: the list
+    /// is empty, or names `name` or the canonical name it is an alias of.
+    pub fn can_consider(&self, name: &str) -> bool {
+        if self.only.is_empty() {
+            return true;
+        }
+        let canonical = self.resolve_alias(name);
+        self.only
+            .iter()
+            .any(|only| only == name || Some(only.as_str()) == canonical)
+    }
+
     /// Canonical name `name` is an alias of, or `None` if no alias applies.
     pub fn resolve_alias(&self, name: &str) -> Option<&str> {
         for (canonical, alternatives) in &self.aliases {
@@ -118,6 +199,209 @@ impl FollowConfig {
     }
 }
 
+/// Comment prefix `toggle` writes before a stored alternate unless
+/// [`ToggleConfig::marker`] says otherwise.
+pub const DEFAULT_TOGGLE_MARKER: &str = "# ";
+
+/// `[toggle]` section of [`Config`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToggleConfig {
+    /// Comment prefix marking a stored alternate, e.g. `## DISABLED: `.
+    ///
+    /// Written verbatim in front of a deactivated url. Detection matches
+    /// it without its trailing whitespace, followed by at most one space.
+    /// Must start with `#` and fit on one line.
+    #[serde(
+        default = "default_toggle_marker",
+        deserialize_with = "deserialize_toggle_marker"
+    )]
+    pub marker: String,
+}
+
+/// `[format]` section of [`Config`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatConfig {
+    /// Put the query parameters of every input url in canonical order on
+    /// each write, not only those of the url being edited. Off by default
+    /// so untouched inputs are never rewritten.
+    #[serde(default)]
+    pub sort_params: bool,
+}
+
+/// `[cache]` section of [`Config`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheOptions {
+    /// Which completion cache entries are read and written.
+    #[serde(default)]
+    pub scope: CacheScope,
+}
+
+/// Value of [`CacheOptions::scope`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheScope {
+    /// One set of completion uris shared by every flake.
+    #[default]
+    Global,
+    /// A separate set per project, keyed by the git checkout holding
+    /// `flake.nix` (or its directory outside of one).
+    Project,
+}
+
+/// `[add]` section of [`Config`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AddConfig {
+    /// Whether a new input is a flake when `add` is given neither
+    /// `--no-flake` nor `--as-flake`. `false` writes `flake = false` for
+    /// every new input.
+    #[serde(default = "default_flake")]
+    pub default_flake: bool,
+
+    /// Urls that are never flakes, e.g. `github:numtide/*`. `*` matches
+    /// any run of characters; the pattern must cover the whole url.
+    #[serde(default)]
+    pub non_flake_patterns: Vec<String>,
+}
+
+impl Default for AddConfig {
+    fn default() -> Self {
+        Self {
+            default_flake: default_flake(),
+            non_flake_patterns: Vec::new(),
+        }
+    }
+}
+
+impl AddConfig {
+    /// Whether a new input with `uri` is added as a flake.
+    pub fn is_flake(&self, uri: &str) -> bool {
+        self.default_flake
+            && !self
+                .non_flake_patterns
+                .iter()
+                .any(|pattern| glob_matches(pattern, uri))
+    }
+}
+
+/// True if `text` matches `pattern` in full, where `*` matches any run of
+/// characters, including none.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    // Without a `*` the prefix has to be the whole text.
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// `[list]` section of [`Config`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListConfig {
+    /// Line `list --format template` prints per input, e.g.
+    /// `{id}: {url} ({ref_or_rev})`. Each `{field}` is replaced by that
+    /// field of the input's `list --json` entry; `{{` and `}}` are
+    /// literal braces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+/// Url schemes a [`Shortcuts`] key may not take over.
+const RESERVED_SCHEMES: &[&str] = &[
+    "github",
+    "gitlab",
+    "sourcehut",
+    "git",
+    "hg",
+    "path",
+    "file",
+    "tarball",
+    "http",
+    "https",
+    "ssh",
+    "flake",
+];
+
+/// `[shortcuts]` section of [`Config`]: url prefixes expanded before a
+/// url is parsed.
+///
+/// With `gh = "github:"`, `gh:owner/repo` is read as `github:owner/repo`.
+/// Keys are made of letters, digits, `-` and `_`, and may not be the name
+/// of a url scheme.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, String>",
+    into = "BTreeMap<String, String>"
+)]
+pub struct Shortcuts(BTreeMap<String, String>);
+
+impl Shortcuts {
+    /// `uri` with a leading `<key>:` replaced by the expansion of `key`,
+    /// or unchanged when no shortcut matches.
+    pub fn expand(&self, uri: String) -> String {
+        match uri
+            .split_once(':')
+            .and_then(|(key, rest)| Some((self.0.get(key)?, rest)))
+        {
+            Some((expansion, rest)) => format!("{expansion}{rest}"),
+            None => uri,
+        }
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for Shortcuts {
+    type Error = String;
+
+    fn try_from(map: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        for key in map.keys() {
+            if key.is_empty()
+                || !key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "shortcut `{key}` must be made of letters, digits, `-` and `_`"
+                ));
+            }
+            if RESERVED_SCHEMES.contains(&key.as_str()) {
+                return Err(format!(
+                    "shortcut `{key}` would shadow the `{key}:` url scheme"
+                ));
+            }
+        }
+        Ok(Self(map))
+    }
+}
+
+impl From<Shortcuts> for BTreeMap<String, String> {
+    fn from(shortcuts: Shortcuts) -> Self {
+        shortcuts.0
+    }
+}
+
+impl Default for ToggleConfig {
+    fn default() -> Self {
+        Self {
+            marker: default_toggle_marker(),
+        }
+    }
+}
+
 impl Config {
     /// Load the first available configuration:
     /// 1. Project-level ([`CONFIG_FILENAMES`], walking upward from the
@@ -130,13 +414,69 @@ impl Config {
     /// Returns [`ConfigError`] if a discovered file cannot be read or
     /// parsed.
     pub fn load() -> Result<Self, ConfigError> {
-        if let Some(path) = Self::project_config_path() {
-            return Self::try_load_from_file(&path);
+        match Self::source(None).path() {
+            Some(path) => Self::try_load_from_file(path),
+            None => Ok(Self::default()),
         }
-        if let Some(path) = Self::user_config_path() {
-            return Self::try_load_from_file(&path);
+    }
+
+    /// Where [`Self::load_from`] would read configuration from for `path`.
+    ///
+    /// An explicit `path` wins, then the project-level file, then the
+    /// user-level file, then the embedded defaults.
+    pub fn source(path: Option<&Path>) -> ConfigSource {
+        Self::resolve_source(path, Self::project_config_path(), Self::user_config_path())
+    }
+
+    fn resolve_source(
+        explicit: Option<&Path>,
+        project: Option<PathBuf>,
+        user: Option<PathBuf>,
+    ) -> ConfigSource {
+        if let Some(path) = explicit {
+            ConfigSource::Explicit(path.to_path_buf())
+        } else if let Some(path) = project {
+            ConfigSource::Project(path)
+        } else if let Some(path) = user {
+            ConfigSource::User(path)
+        } else {
+            ConfigSource::Default
         }
-        Ok(Self::default())
+    }
+
+    /// Render the effective configuration for `source` as TOML.
+    ///
+    /// Every value carries a trailing comment naming where it came from:
+    /// the backing file when it sets the key, `default` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError`] if the backing file cannot be read or
+    /// parsed.
+    pub fn render_effective(source: &ConfigSource) -> Result<String, ConfigError> {
+        let (config, raw) = match source.path() {
+            Some(path) => {
+                let content = std::fs::read_to_string(path).map_err(|e| ConfigError::Io {
+                    path: path.to_path_buf(),
+                    source: e,
+                })?;
+                let parse_err = |e| ConfigError::Parse {
+                    path: path.to_path_buf(),
+                    source: e,
+                };
+                let config: Self = toml::from_str(&content).map_err(parse_err)?;
+                let raw: toml::Table = toml::from_str(&content).map_err(parse_err)?;
+                (config, raw)
+            }
+            None => (Self::default(), toml::Table::new()),
+        };
+        Ok(annotate_sources(&config.to_toml(), &raw, source))
+    }
+
+    /// Serialize to TOML. The config only holds TOML-representable
+    /// values, so this cannot fail.
+    fn to_toml(&self) -> String {
+        toml::to_string(self).expect("Config always serializes to TOML")
     }
 
     /// Load configuration from `path`, or fall back to [`Self::load`] when
@@ -204,10 +544,77 @@ impl Config {
     }
 }
 
+fn default_toggle_marker() -> String {
+    DEFAULT_TOGGLE_MARKER.to_string()
+}
+
+/// Reject markers that would not read back as a line comment.
+fn deserialize_toggle_marker<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let marker = String::deserialize(deserializer)?;
+    if !marker.starts_with('#') || marker.contains('\n') {
+        return Err(serde::de::Error::custom(
+            "toggle marker must start with `#` and fit on one line",
+        ));
+    }
+    Ok(marker)
+}
+
+fn default_flake() -> bool {
+    true
+}
+
 fn default_transitive_min() -> usize {
     0
 }
 
+/// Append a `# <source>` comment to every key and table header in
+/// `rendered`, looking each up in `raw` to tell file-set values from
+/// defaults.
+fn annotate_sources(rendered: &str, raw: &toml::Table, source: &ConfigSource) -> String {
+    let mut out = format!("# Effective configuration, loaded from: {source}\n\n");
+    let mut section: Vec<&str> = Vec::new();
+    for line in rendered.lines() {
+        let trimmed = line.trim();
+        let key_path: Option<Vec<&str>> =
+            if let Some(header) = trimmed.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+                section = header.split('.').collect();
+                Some(section.clone())
+            } else if let Some((key, _)) = trimmed.split_once(" = ") {
+                let mut path = section.clone();
+                path.push(key);
+                Some(path)
+            } else {
+                None
+            };
+        out.push_str(line);
+        if let Some(path) = key_path {
+            let origin = if lookup(raw, &path) {
+                source.to_string()
+            } else {
+                "default".to_string()
+            };
+            out.push_str(&format!("  # {origin}"));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// True if `path` names a key present in `table`.
+fn lookup(table: &toml::Table, path: &[&str]) -> bool {
+    let Some((first, rest)) = path.split_first() else {
+        return false;
+    };
+    match (table.get(*first), rest.is_empty()) {
+        (Some(_), true) => true,
+        (Some(toml::Value::Table(inner)), false) => lookup(inner, rest),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -240,6 +647,129 @@ mod tests {
         assert_eq!(cfg.follow.max_depth, None);
     }
 
+    #[test]
+    fn toggle_marker_defaults_to_hash_space() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.toggle.marker, "# ");
+    }
+
+    #[test]
+    fn toggle_marker_parses_from_toml() {
+        let config: Config = toml::from_str("[toggle]\nmarker = \"## DISABLED: \"").unwrap();
+        assert_eq!(config.toggle.marker, "## DISABLED: ");
+    }
+
+    #[test]
+    fn toggle_marker_must_be_a_comment() {
+        assert!(toml::from_str::<Config>("[toggle]\nmarker = \"// \"").is_err());
+        assert!(toml::from_str::<Config>("[toggle]\nmarker = \"#\\n\"").is_err());
+    }
+
+    #[test]
+    fn cache_scope_defaults_to_global() {
+        assert_eq!(Config::default().cache.scope, CacheScope::Global);
+        let config: Config = toml::from_str("[cache]\nscope = \"project\"").unwrap();
+        assert_eq!(config.cache.scope, CacheScope::Project);
+        assert!(toml::from_str::<Config>("[cache]\nscope = \"repo\"").is_err());
+    }
+
+    #[test]
+    fn shortcut_expands_only_a_leading_key() {
+        let config: Config =
+            toml::from_str("[shortcuts]\ngh = \"github:\"\nnp = \"github:nixos/nixpkgs\"\n")
+                .unwrap();
+        let expand = |uri: &str| config.shortcuts.expand(uri.to_string());
+        assert_eq!(expand("gh:foo/bar"), "github:foo/bar");
+        assert_eq!(
+            expand("np:/nixos-24.05"),
+            "github:nixos/nixpkgs/nixos-24.05"
+        );
+        assert_eq!(expand("github:foo/bar"), "github:foo/bar");
+        assert_eq!(expand("ghx:foo/bar"), "ghx:foo/bar");
+        assert_eq!(expand("path:./gh:x"), "path:./gh:x");
+    }
+
+    #[test]
+    fn shortcut_stands_in_for_forges_without_a_scheme() {
+        let config: Config = toml::from_str(
+            "[shortcuts]\ncodeberg = \"git+https://codeberg.org/\"\n\
+             bitbucket = \"git+https://bitbucket.org/\"\n",
+        )
+        .unwrap();
+        for (uri, expanded) in [
+            (
+                "codeberg:forgejo/forgejo?ref=v9.0",
+                "git+https://codeberg.org/forgejo/forgejo?ref=v9.0",
+            ),
+            (
+                "bitbucket:owner/repo",
+                "git+https://bitbucket.org/owner/repo",
+            ),
+        ] {
+            let uri = config.shortcuts.expand(uri.to_string());
+            assert_eq!(uri, expanded);
+            let flake_ref: nix_uri::FlakeRef = uri.parse().unwrap();
+            assert_eq!(flake_ref.to_string(), expanded);
+        }
+    }
+
+    #[test]
+    fn shortcut_may_not_shadow_a_scheme() {
+        let err = toml::from_str::<Config>("[shortcuts]\ngithub = \"gitlab:\"\n").unwrap_err();
+        assert!(err.to_string().contains("would shadow"), "{err}");
+        assert!(toml::from_str::<Config>("[shortcuts]\n\"git+https\" = \"x\"\n").is_err());
+    }
+
+    #[test]
+    fn source_precedence_is_explicit_project_user_default() {
+        let explicit = PathBuf::from("/cli/flake-edit.toml");
+        let project = PathBuf::from("/project/flake-edit.toml");
+        let user = PathBuf::from("/home/config.toml");
+
+        assert_eq!(
+            Config::resolve_source(Some(&explicit), Some(project.clone()), Some(user.clone())),
+            ConfigSource::Explicit(explicit),
+        );
+        assert_eq!(
+            Config::resolve_source(None, Some(project.clone()), Some(user.clone())),
+            ConfigSource::Project(project),
+        );
+        assert_eq!(
+            Config::resolve_source(None, None, Some(user.clone())),
+            ConfigSource::User(user),
+        );
+        assert_eq!(
+            Config::resolve_source(None, None, None),
+            ConfigSource::Default
+        );
+    }
+
+    #[test]
+    fn render_effective_marks_file_values_over_defaults() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("flake-edit.toml");
+        std::fs::write(&path, "[follow]\ntransitive_min = 3\n").unwrap();
+        let source = ConfigSource::Project(path);
+
+        let out = Config::render_effective(&source).unwrap();
+        let origin = source.to_string();
+        assert!(
+            out.contains(&format!("transitive_min = 3  # {origin}")),
+            "file value must name its source:\n{out}"
+        );
+        assert!(
+            out.contains("ignore = []  # default"),
+            "omitted value must fall back to the default:\n{out}"
+        );
+    }
+
+    #[test]
+    fn render_effective_without_file_is_all_defaults() {
+        let out = Config::render_effective(&ConfigSource::Default).unwrap();
+        assert!(out.starts_with("# Effective configuration, loaded from: default\n"));
+        assert!(out.contains("transitive_min = 0  # default"), "{out}");
+    }
+
     #[test]
     fn test_is_ignored_by_name() {
         let config = FollowConfig {
@@ -281,6 +811,29 @@ mod tests {
         assert!(!config.is_ignored("poetry2nix.flake-utils", "flake-utils"));
     }
 
+    #[test]
+    fn test_can_consider_without_allowlist() {
+        let config = FollowConfig::default();
+        assert!(config.can_consider("nixpkgs"));
+        assert!(config.can_consider("anything"));
+    }
+
+    #[test]
+    fn test_can_consider_allowlist() {
+        let config = FollowConfig {
+            only: vec!["nixpkgs".to_string(), "flake-utils".to_string()],
+            aliases: HashMap::from([("nixpkgs".to_string(), vec!["nixpkgs-lib".to_string()])]),
+            ..Default::default()
+        };
+        assert!(config.can_consider("nixpkgs"));
+        assert!(config.can_consider("flake-utils"));
+        // An alias counts as its canonical name.
+        assert!(config.can_consider("nixpkgs-lib"));
+        // Would match a top-level input, but is not allowlisted.
+        assert!(config.can_follow("systems", "systems"));
+        assert!(!config.can_consider("systems"));
+    }
+
     #[test]
     fn test_resolve_alias() {
         let config = FollowConfig {
@@ -318,4 +871,25 @@ mod tests {
         // but not the reverse
         assert!(!config.can_follow("nixpkgs", "nixpkgs-lib"));
     }
+
+    #[test]
+    fn add_non_flake_patterns_match_whole_url() {
+        let config = AddConfig {
+            non_flake_patterns: vec!["github:numtide/*".to_string(), "*/assets".to_string()],
+            ..Default::default()
+        };
+        assert!(!config.is_flake("github:numtide/treefmt-nix"));
+        assert!(!config.is_flake("github:numtide/treefmt-nix?ref=main"));
+        assert!(!config.is_flake("github:a-kenji/assets"));
+        assert!(config.is_flake("github:nixos/nixpkgs"));
+        assert!(config.is_flake("github:numtide"));
+        assert!(config.is_flake("github:a-kenji/assets-extra"));
+    }
+
+    #[test]
+    fn add_default_flake_parses_from_toml() {
+        let config: Config = toml::from_str("[add]\ndefault_flake = false\n").unwrap();
+        assert!(!config.add.is_flake("github:nixos/nixpkgs"));
+        assert!(Config::default().add.is_flake("github:nixos/nixpkgs"));
+    }
 }
@@ -1,12 +1,36 @@
 //! Wrapper for diffing the changes
 
+use std::fmt;
 use std::io::IsTerminal;
+
 pub struct Diff<'a> {
     old: &'a str,
     new: &'a str,
+    color: bool,
+    context: usize,
 }
 
-fn use_color() -> bool {
+/// Unchanged lines shown around each hunk unless `--context` says
+/// otherwise, as with `diff -u`.
+pub const DEFAULT_CONTEXT: usize = 3;
+
+/// Added and removed line counts of a [`Diff`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl fmt::Display for DiffStats {
+    /// Renders as `+<added> -<removed>`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "+{} -{}", self.added, self.removed)
+    }
+}
+
+/// Whether stdout output should carry ANSI colors: it must be a terminal
+/// and `NO_COLOR` must be unset.
+pub(crate) fn use_color() -> bool {
     // Respect NO_COLOR (https://no-color.org/)
     if std::env::var("NO_COLOR").is_ok() {
         return false;
@@ -16,14 +40,31 @@ fn use_color() -> bool {
 
 impl<'a> Diff<'a> {
     pub fn new(old: &'a str, new: &'a str) -> Self {
-        Self { old, new }
+        Self {
+            old,
+            new,
+            color: use_color(),
+            context: DEFAULT_CONTEXT,
+        }
+    }
+    /// Override whether [`Diff::compare`] colors its output. Defaults to
+    /// [`use_color`], so `false` is what `--no-color` needs.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+    /// Unchanged lines kept around each hunk, [`DEFAULT_CONTEXT`] unless
+    /// set.
+    pub fn with_context(mut self, context: usize) -> Self {
+        self.context = context;
+        self
     }
     pub fn compare(&self) {
-        print!("{}", self.to_string_colored(use_color()));
+        print!("{}", self.to_string_colored(self.color));
     }
     /// Return the diff as a string, optionally with ANSI colors
     pub fn to_string_colored(&self, color: bool) -> String {
-        let patch = diffy::create_patch(self.old, self.new);
+        let patch = self.patch(self.context);
         let f = if color {
             diffy::PatchFormatter::new().with_color()
         } else {
@@ -35,4 +76,128 @@ impl<'a> Diff<'a> {
     pub fn to_string_plain(&self) -> String {
         self.to_string_colored(false)
     }
+    /// Return the diff as an uncolored unified diff, with `context`
+    /// unchanged lines around each `@@ -a,b +c,d @@` hunk
+    pub fn to_unified(&self, context: usize) -> String {
+        diffy::PatchFormatter::new()
+            .fmt_patch(&self.patch(context))
+            .to_string()
+    }
+
+    /// Count the added and removed lines without rendering the patch
+    pub fn stats(&self) -> DiffStats {
+        let patch = self.patch(0);
+        let mut stats = DiffStats::default();
+        for line in patch.hunks().iter().flat_map(|hunk| hunk.lines()) {
+            match line {
+                diffy::Line::Insert(_) => stats.added += 1,
+                diffy::Line::Delete(_) => stats.removed += 1,
+                diffy::Line::Context(_) => {}
+            }
+        }
+        stats
+    }
+    fn patch(&self, context: usize) -> diffy::Patch<'a, str> {
+        diffy::DiffOptions::new()
+            .set_context_len(context)
+            .create_patch(self.old, self.new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "{\n  inputs = {\n    nixpkgs.url = \"github:nixos/nixpkgs\";\n  };\n}\n";
+    const ADDED: &str = "{\n  inputs = {\n    nixpkgs.url = \"github:nixos/nixpkgs\";\n    vmsh.url = \"github:mic92/vmsh\";\n  };\n}\n";
+    const CHANGED: &str =
+        "{\n  inputs = {\n    nixpkgs.url = \"github:nixos/nixpkgs/nixos-24.05\";\n  };\n}\n";
+
+    #[test]
+    fn stats_counts_added_lines() {
+        let stats = Diff::new(OLD, ADDED).stats();
+        assert_eq!(
+            stats,
+            DiffStats {
+                added: 1,
+                removed: 0
+            }
+        );
+    }
+
+    #[test]
+    fn stats_counts_removed_lines() {
+        let stats = Diff::new(ADDED, OLD).stats();
+        assert_eq!(
+            stats,
+            DiffStats {
+                added: 0,
+                removed: 1
+            }
+        );
+    }
+
+    #[test]
+    fn stats_counts_a_changed_line_as_both() {
+        let stats = Diff::new(OLD, CHANGED).stats();
+        assert_eq!(stats.to_string(), "+1 -1");
+    }
+
+    #[test]
+    fn stats_of_identical_text_is_zero() {
+        assert_eq!(Diff::new(OLD, OLD).stats(), DiffStats::default());
+    }
+
+    #[test]
+    fn plain_output_has_no_ansi_codes() {
+        let diff = Diff::new(OLD, CHANGED).with_color(true);
+        assert!(!diff.to_string_plain().contains('\x1b'));
+    }
+
+    #[test]
+    fn colored_output_differs_from_plain() {
+        let diff = Diff::new(OLD, CHANGED);
+        let colored = diff.to_string_colored(true);
+        assert!(colored.contains('\x1b'));
+        assert_ne!(colored, diff.to_string_plain());
+    }
+
+    /// Ten numbered lines, with `added` after the fifth when given.
+    fn numbered(added: Option<&str>) -> String {
+        let mut text = String::new();
+        for n in 1..=10 {
+            text.push_str(&format!("line {n}\n"));
+            if n == 5
+                && let Some(added) = added
+            {
+                text.push_str(&format!("{added}\n"));
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn unified_hunk_keeps_three_context_lines_by_default() {
+        let (old, new) = (numbered(None), numbered(Some("added")));
+        let unified = Diff::new(&old, &new).to_unified(DEFAULT_CONTEXT);
+        assert_eq!(
+            unified,
+            "--- original\n+++ modified\n@@ -3,6 +3,7 @@\n line 3\n line 4\n line 5\n+added\n line 6\n line 7\n line 8\n"
+        );
+    }
+
+    #[test]
+    fn unified_hunk_honors_a_smaller_context() {
+        let (old, new) = (numbered(None), numbered(Some("added")));
+        let unified = Diff::new(&old, &new).to_unified(1);
+        assert!(unified.contains("@@ -5,2 +5,3 @@\n line 5\n+added\n line 6\n"));
+        assert!(!unified.contains("line 4"));
+    }
+
+    #[test]
+    fn with_context_applies_to_the_rendered_diff() {
+        let (old, new) = (numbered(None), numbered(Some("added")));
+        let diff = Diff::new(&old, &new).with_context(1);
+        assert_eq!(diff.to_string_plain(), diff.to_unified(1));
+    }
 }
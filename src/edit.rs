@@ -1,13 +1,24 @@
 use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 
-use crate::change::Change;
+use crate::change::{Change, ChangeId};
+use crate::config::DEFAULT_TOGGLE_MARKER;
 use crate::error::Error;
+use crate::follows::{AttrPath, Segment};
 use crate::input::{Follows, Input};
 use crate::validate;
-use crate::walk::{Walker, toggle};
+use crate::walk::{self, Walker, WalkerError, rename, toggle};
 
 pub struct FlakeEdit {
     walker: Walker,
+    /// Set once an applied change produced text that differs from the
+    /// source it was applied to.
+    dirty: bool,
+    /// Comment prefix marking a stored toggle alternate.
+    toggle_marker: String,
+    /// Write a follows whose target is not a declared input instead of
+    /// rejecting it.
+    dangling_follows: bool,
 }
 
 #[derive(Default, Debug)]
@@ -27,12 +38,83 @@ pub fn sorted_input_ids(inputs: &InputMap) -> Vec<&String> {
     keys
 }
 
+/// Attributes `text` binds twice, each paired with whether the later
+/// binding declares an input and so is removed by [`dedup_inputs`].
+/// Empty when `text` does not parse.
+pub fn duplicate_bindings(text: &str) -> Vec<(validate::DuplicateAttr, bool)> {
+    let parsed = validate::ParsedSource::new(text);
+    if !parsed.parse_errors.is_empty() {
+        return Vec::new();
+    }
+    validate::validate_parsed(&parsed)
+        .errors
+        .into_iter()
+        .filter_map(|err| match err {
+            validate::ValidationError::DuplicateAttribute(dup) => Some(dup),
+            _ => None,
+        })
+        .map(|dup| {
+            let offset = parsed.line_map.location_to_offset(&dup.duplicate);
+            let input = walk::dedup::input_binding_at(&parsed.syntax, offset).is_some();
+            (dup, input)
+        })
+        .collect()
+}
+
+/// Input ids whose url `text` binds more than once, sorted by id, each
+/// with where its bindings start in source order. Covers the flat
+/// `inputs.<id>.url` form, the `inputs = { ... }` block, and a mix of the
+/// two, which [`FlakeEdit::from_text`] refuses to load. Empty when `text`
+/// does not parse.
+pub fn duplicate_inputs(text: &str) -> Vec<(String, Vec<validate::Location>)> {
+    let parsed = validate::ParsedSource::new(text);
+    if !parsed.parse_errors.is_empty() {
+        return Vec::new();
+    }
+    walk::duplicate_url_bindings(&parsed.syntax)
+        .into_iter()
+        .map(|(id, offsets)| {
+            let locations = offsets
+                .into_iter()
+                .map(|offset| parsed.line_map.offset_to_location(offset))
+                .collect();
+            (id, locations)
+        })
+        .collect()
+}
+
+/// Remove the later copy of every input binding `text` declares twice,
+/// keeping the first. Returns `None` when there is no such duplicate.
+pub fn dedup_inputs(text: &str) -> Option<String> {
+    let mut text = text.to_string();
+    let mut changed = false;
+    // Each removal shifts the locations after it, so duplicates are
+    // looked up again on the new text one at a time.
+    loop {
+        let parsed = validate::ParsedSource::new(&text);
+        let Some(binding) = duplicate_bindings(&text)
+            .into_iter()
+            .filter(|(_, input)| *input)
+            .find_map(|(dup, _)| {
+                let offset = parsed.line_map.location_to_offset(&dup.duplicate);
+                walk::dedup::input_binding_at(&parsed.syntax, offset)
+            })
+        else {
+            break;
+        };
+        text = walk::dedup::remove_binding(&binding)?.to_string();
+        changed = true;
+    }
+    changed.then_some(text)
+}
+
 #[derive(Default, Debug)]
 pub enum OutputChange {
     #[default]
     None,
     Add(String),
     Remove(String),
+    Rename(String, String),
 }
 
 /// Toggle surface of one input: its active url and the stored alternates
@@ -47,23 +129,68 @@ pub struct ToggleState {
 
 /// Result of applying a [`Change`].
 ///
-/// `text` is the new flake source, or `None` for a no-op (e.g. an
-/// already-existing follows declaration).
+/// `text` is the new flake source, or `None` when the source is left as
+/// it was. `None` never carries a reformatted copy of the input: an edit
+/// that reproduces the source byte for byte is reported as no change.
 #[derive(Debug, Default)]
 pub struct ApplyOutcome {
     pub text: Option<String>,
+    /// The change found what it targets and it was already in place (e.g.
+    /// a follows that is already declared), as opposed to finding nothing
+    /// to edit. Only ever set alongside a `None` text.
+    pub unchanged: bool,
 }
 
 impl FlakeEdit {
+    /// Read and parse the flake at `path`, whatever the file is named.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Read`] if the file cannot be read, and the errors
+    /// of [`Self::from_text`] otherwise.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|source| Error::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_text(&text)
+    }
+
     pub fn from_text(stream: &str) -> Result<Self, Error> {
         let parsed = validate::ParsedSource::new(stream);
         let validation = validate::validate_parsed(&parsed);
         if validation.has_errors() {
             return Err(Error::Validation(validation.errors));
         }
+        // Checked eagerly: `list` swallows walker errors, so a conflict
+        // found only on the first walk would list one definition silently.
+        if let Some(id) = walk::mixed_style_duplicate(&parsed.syntax) {
+            return Err(WalkerError::DuplicateInput { id }.into());
+        }
 
         let walker = Walker::from_root(parsed.syntax);
-        Ok(Self { walker })
+        Ok(Self {
+            walker,
+            dirty: false,
+            toggle_marker: DEFAULT_TOGGLE_MARKER.to_string(),
+            dangling_follows: false,
+        })
+    }
+
+    /// Use `marker` instead of [`DEFAULT_TOGGLE_MARKER`] to find and write
+    /// the commented alternates [`Change::Toggle`] flips between.
+    pub fn with_toggle_marker(mut self, marker: impl Into<String>) -> Self {
+        self.toggle_marker = marker.into();
+        self
+    }
+
+    /// Accept a [`Change::Follows`] or [`Change::AddFollows`] whose target
+    /// is not a declared input, instead of failing with
+    /// [`Error::FollowsTargetNotFound`].
+    pub fn with_dangling_follows(mut self, allow: bool) -> Self {
+        self.dangling_follows = allow;
+        self
     }
 
     /// Wrap an already-parsed `flake.nix` syntax tree, skipping the parse and
@@ -74,6 +201,9 @@ impl FlakeEdit {
     pub(crate) fn from_syntax(syntax: rnix::SyntaxNode) -> Self {
         Self {
             walker: Walker::from_root(syntax),
+            dirty: false,
+            toggle_marker: DEFAULT_TOGGLE_MARKER.to_string(),
+            dangling_follows: false,
         }
     }
 
@@ -95,6 +225,10 @@ impl FlakeEdit {
     }
     /// Apply `change` and return the resulting [`ApplyOutcome`].
     ///
+    /// Applying a change that is already in place is a no-op: the outcome
+    /// has no text, so applying the same change twice never rewrites the
+    /// source the second time.
+    ///
     /// Some edits require multiple walker passes. This method drives them all.
     /// A fatal validation failure surfaces as
     /// [`Error::Validation`].
@@ -105,8 +239,25 @@ impl FlakeEdit {
     /// is rejected (e.g. [`Error::DuplicateInput`],
     /// [`Error::InputNotFound`]).
     pub fn apply_change(&mut self, change: Change) -> Result<ApplyOutcome, Error> {
+        let before = self.walker.root.clone();
         let text = self.apply_change_text(change)?;
-        Ok(ApplyOutcome { text })
+        let unchanged = text.as_deref().is_some_and(|t| before.text() == t);
+        if unchanged {
+            return Ok(ApplyOutcome {
+                text: None,
+                unchanged,
+            });
+        }
+        self.dirty |= text.is_some();
+        Ok(ApplyOutcome { text, unchanged })
+    }
+
+    /// Whether any change applied so far altered the text. A change that
+    /// returns no text, or returns the text it started from (e.g. a follows
+    /// that is already declared), leaves the editor clean, so callers can
+    /// skip writing the file.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
     }
 
     fn apply_change_text(&mut self, change: Change) -> Result<Option<String>, Error> {
@@ -115,9 +266,13 @@ impl FlakeEdit {
             Change::Add { .. } => self.apply_add(change),
             Change::Remove { .. } => self.apply_remove(change),
             Change::Follows { .. } => self.apply_follows(change),
+            Change::AddFollows { .. } => self.apply_add_follows(change),
             Change::Change { .. } => self.apply_change_uri(change),
             Change::Toggle { .. } => self.apply_toggle(change),
             Change::ToggleRemove { .. } => self.apply_toggle_remove(change),
+            Change::ToggleStore { .. } => self.apply_toggle_store(change),
+            Change::Rename { .. } => self.apply_rename(change),
+            Change::Pin { .. } | Change::Unpin { .. } => self.apply_pin(change),
         }
     }
 
@@ -164,15 +319,18 @@ impl FlakeEdit {
     /// input can be spelled across multiple flat declarations
     /// (`inputs.foo.url = ...; inputs.foo.flake = false;`); each walk strips
     /// one occurrence. The post-loop outputs-lambda strip and orphan-follows
-    /// scrub only run for a top-level remove, since a depth-N follows id
+    /// scrub only run for the top-level ids, since a depth-N follows id
     /// shares its first segment with a still-present input and running the
     /// cleanup there would strip that input from the outputs lambda.
     fn apply_remove(&mut self, change: Change) -> Result<Option<String>, Error> {
         self.ensure_inputs_populated()?;
 
-        let id = change.id().unwrap();
-        let is_toplevel_remove = id.follows().is_none();
-        let removed_id = id.input().as_str().to_string();
+        let removed_ids: Vec<String> = change
+            .ids()
+            .iter()
+            .filter(|id| id.follows().is_none())
+            .map(|id| id.input().as_str().to_string())
+            .collect();
 
         let mut res = None;
         while let Some(changed_node) = self.walker.walk(&change)? {
@@ -183,11 +341,11 @@ impl FlakeEdit {
             self.walker.root = changed_node.clone();
         }
 
-        if is_toplevel_remove {
+        for removed_id in &removed_ids {
             let outputs = self.walker.list_outputs()?;
             match outputs {
                 Outputs::Multiple(out) | Outputs::Any(out) => {
-                    if out.contains(&removed_id)
+                    if out.contains(removed_id)
                         && let Some(changed_node) = self
                             .walker
                             .change_outputs(OutputChange::Remove(removed_id.clone()))?
@@ -199,7 +357,15 @@ impl FlakeEdit {
                 Outputs::None => {}
             }
 
-            let orphaned_follows = self.collect_orphaned_follows(&removed_id);
+            // Follows owned by another removed input went with it.
+            let orphaned_follows = self
+                .collect_orphaned_follows(removed_id)
+                .into_iter()
+                .filter(|orphan| {
+                    orphan
+                        .id()
+                        .is_some_and(|id| !removed_ids.contains(&id.input().as_str().to_string()))
+                });
             for orphan_change in orphaned_follows {
                 while let Some(changed_node) = self.walker.walk(&orphan_change)? {
                     if res == Some(changed_node.clone()) {
@@ -217,9 +383,14 @@ impl FlakeEdit {
     /// A `Change::Follows` whose parent input is missing is a hard error
     /// rather than a no-op so the caller learns about the typo; the parent
     /// check runs before the walk because the walker would silently produce
-    /// no edit otherwise.
+    /// no edit otherwise. A target that is not a declared input is rejected
+    /// too, since Nix cannot evaluate the result.
     fn apply_follows(&mut self, change: Change) -> Result<Option<String>, Error> {
-        let Change::Follows { ref input, .. } = change else {
+        let Change::Follows {
+            ref input,
+            ref target,
+        } = change
+        else {
             unreachable!("apply_follows dispatched only for Change::Follows");
         };
 
@@ -229,6 +400,28 @@ impl FlakeEdit {
         if !self.walker.inputs.contains_key(parent_id) {
             return Err(Error::InputNotFound(parent_id.to_string()));
         }
+        self.check_follows_target(input, target)?;
+
+        Ok(self.walker.walk(&change)?.map(|n| n.to_string()))
+    }
+
+    /// Batch form of [`Self::apply_follows`]. Every parent is checked up
+    /// front, so a typo in any entry rejects the whole batch before the
+    /// walker touches the tree.
+    fn apply_add_follows(&mut self, change: Change) -> Result<Option<String>, Error> {
+        let Change::AddFollows { ref entries } = change else {
+            unreachable!("apply_add_follows dispatched only for Change::AddFollows");
+        };
+
+        self.ensure_inputs_populated()?;
+
+        for (input, target) in entries {
+            let parent_id = input.input().as_str();
+            if !self.walker.inputs.contains_key(parent_id) {
+                return Err(Error::InputNotFound(parent_id.to_string()));
+            }
+            self.check_follows_target(input, target)?;
+        }
 
         Ok(self.walker.walk(&change)?.map(|n| n.to_string()))
     }
@@ -239,17 +432,57 @@ impl FlakeEdit {
     /// `Option<ChangeId>` is honored rather than asserted: a `Change::Change`
     /// with `id == None` is a no-op the walker handles without consulting
     /// the input map.
+    ///
+    /// A `rename` runs after the url rewrite, on its result, through the
+    /// same path as [`Change::Rename`].
     fn apply_change_uri(&mut self, change: Change) -> Result<Option<String>, Error> {
         if let Some(input_id) = change.id() {
             self.ensure_inputs_populated()?;
 
             let input_id_string = input_id.input().as_str().to_string();
-            if !self.walker.inputs.contains_key(&input_id_string) {
+            let Some(input) = self.walker.inputs.get(&input_id_string) else {
                 return Err(Error::InputNotFound(input_id_string));
+            };
+            if input.is_unresolved() && matches!(change, Change::Change { uri: Some(_), .. }) {
+                return Err(Error::UnresolvedUrl {
+                    id: input_id_string,
+                    expr: input.url().to_string(),
+                });
             }
         }
+        if let Change::Change {
+            id: Some(id),
+            rename: Some(to),
+            ..
+        } = &change
+            && id.input() != to
+            && self.walker.inputs.contains_key(to.as_str())
+        {
+            return Err(Error::DuplicateInput(to.to_string()));
+        }
 
-        Ok(self.walker.walk(&change)?.map(|n| n.to_string()))
+        let changed = self.walker.walk(&change)?;
+        let Change::Change {
+            id: Some(id),
+            rename: Some(to),
+            ..
+        } = change
+        else {
+            return Ok(changed.map(|n| n.to_string()));
+        };
+        // A refused rename takes the url rewrite back with it.
+        let previous = self.walker.root.clone();
+        if let Some(changed) = &changed {
+            self.walker.root = changed.clone();
+        }
+        match self.rename_input(id.input().clone(), to) {
+            Ok(Some(renamed)) => Ok(Some(renamed)),
+            Ok(None) => Ok(changed.map(|n| n.to_string())),
+            Err(err) => {
+                self.walker.root = previous;
+                Err(err)
+            }
+        }
     }
 
     /// A `Change::Toggle` edits through [`crate::walk::toggle`] directly
@@ -267,6 +500,12 @@ impl FlakeEdit {
         let Some(input) = self.walker.inputs.get(&id_str) else {
             return Err(Error::InputNotFound(id_str));
         };
+        if input.is_unresolved() {
+            return Err(Error::UnresolvedUrl {
+                id: id_str,
+                expr: input.url().to_string(),
+            });
+        }
         let Some(binding) = toggle::url_binding(&self.walker.root, input) else {
             return Err(Error::NoUrlToToggle(id_str));
         };
@@ -274,9 +513,12 @@ impl FlakeEdit {
             .parent()
             .expect("a url binding always sits inside an enclosing node");
 
-        let alternates = toggle::alternates(&binding);
+        let marker = self.toggle_marker.as_str();
+        let alternates = toggle::alternates(&binding, marker);
         if let Some(alternate) = alternates.iter().find(|a| a.url == uri) {
-            return Ok(Some(toggle::flip(&parent, &binding, alternate).to_string()));
+            return Ok(Some(
+                toggle::flip(&parent, &binding, alternate, marker).to_string(),
+            ));
         }
         if input.url() == uri {
             // The url is already active and not stored as an alternate.
@@ -285,10 +527,92 @@ impl FlakeEdit {
             return Ok(None);
         }
         Ok(Some(
-            toggle::synthesize(&parent, &binding, &uri).to_string(),
+            toggle::synthesize(&parent, &binding, &uri, marker).to_string(),
         ))
     }
 
+    /// A `Change::Pin` or `Change::Unpin` rewrites the input's url and
+    /// applies it as the equivalent `Change::Change`. A url that does not
+    /// parse as a flake reference is left alone.
+    fn apply_pin(&mut self, change: Change) -> Result<Option<String>, Error> {
+        let id = change
+            .id()
+            .expect("bug: Change::Pin and Change::Unpin always carry an id");
+        self.ensure_inputs_populated()?;
+
+        let id_str = id.input().as_str().to_string();
+        let Some(input) = self.walker.inputs.get(&id_str) else {
+            return Err(Error::InputNotFound(id_str));
+        };
+        let Ok(parsed) = input.url().parse::<nix_uri::FlakeRef>() else {
+            return Ok(None);
+        };
+        let uri = match change {
+            Change::Pin { rev, .. } => parsed.pin_to_rev(rev).into_uri(),
+            _ if parsed.ref_kind() == nix_uri::RefKind::None => return Ok(None),
+            _ => {
+                let mut parsed = parsed;
+                parsed.set_ref(None);
+                parsed.set_rev(None);
+                parsed.into_uri()
+            }
+        };
+        self.apply_change_uri(Change::Change {
+            id: Some(id),
+            uri: Some(uri),
+            rename: None,
+        })
+    }
+
+    /// A `Change::Rename` edits through [`crate::walk::rename`] directly:
+    /// the keys and follows targets naming the input are spliced in one
+    /// pass, then the outputs lambda's argument and its uses in the body are
+    /// renamed on the result, or the rename is refused when the body cannot
+    /// follow.
+    fn apply_rename(&mut self, change: Change) -> Result<Option<String>, Error> {
+        let Change::Rename { from, to } = change else {
+            unreachable!("apply_rename dispatched only for Change::Rename");
+        };
+        self.rename_input(from, to)
+    }
+
+    /// Rename `from` to `to` in the current tree, shared by
+    /// [`Self::apply_rename`] and a [`Change::Change`] carrying a `rename`.
+    fn rename_input(&mut self, from: Segment, to: Segment) -> Result<Option<String>, Error> {
+        self.ensure_inputs_populated()?;
+
+        if !self.walker.inputs.contains_key(from.as_str()) {
+            return Err(Error::InputNotFound(from.to_string()));
+        }
+        if from == to {
+            return Ok(None);
+        }
+        if self.walker.inputs.contains_key(to.as_str()) {
+            return Err(Error::DuplicateInput(to.to_string()));
+        }
+
+        let Some(renamed) = rename::rename_input(&self.walker.root, &from, &to) else {
+            return Ok(None);
+        };
+        // The outputs lambda follows the rename, or the whole rename is
+        // refused and the tree stays as it was.
+        let previous = std::mem::replace(&mut self.walker.root, renamed.clone());
+        match self
+            .walker
+            .change_outputs(OutputChange::Rename(from.to_string(), to.to_string()))
+        {
+            Ok(changed) => {
+                let changed = changed.unwrap_or(renamed);
+                self.walker.root = changed.clone();
+                Ok(Some(changed.to_string()))
+            }
+            Err(err) => {
+                self.walker.root = previous;
+                Err(err.into())
+            }
+        }
+    }
+
     /// A `Change::ToggleRemove` deletes the resolved variant's line through
     /// [`crate::walk::toggle`]. Removing a stored alternate drops its
     /// comment and leaves the active url untouched. Removing the active url
@@ -305,6 +629,12 @@ impl FlakeEdit {
         let Some(input) = self.walker.inputs.get(&id_str) else {
             return Err(Error::InputNotFound(id_str));
         };
+        if input.is_unresolved() {
+            return Err(Error::UnresolvedUrl {
+                id: id_str,
+                expr: input.url().to_string(),
+            });
+        }
         let Some(binding) = toggle::url_binding(&self.walker.root, input) else {
             return Err(Error::NoUrlToToggle(id_str));
         };
@@ -312,7 +642,8 @@ impl FlakeEdit {
             .parent()
             .expect("a url binding always sits inside an enclosing node");
 
-        let alternates = toggle::alternates(&binding);
+        let marker = self.toggle_marker.as_str();
+        let alternates = toggle::alternates(&binding, marker);
         if let Some(alternate) = alternates.iter().find(|a| a.url == uri) {
             return Ok(Some(
                 toggle::remove_alternate(&parent, alternate).to_string(),
@@ -323,12 +654,52 @@ impl FlakeEdit {
                 .and_then(|activate| alternates.into_iter().find(|a| a.url == activate))
                 .ok_or(Error::RemoveActiveWithoutAlternate(id_str))?;
             return Ok(Some(
-                toggle::flip_remove(&parent, &binding, &replacement).to_string(),
+                toggle::flip_remove(&parent, &binding, &replacement, marker).to_string(),
             ));
         }
         Ok(None)
     }
 
+    /// A `Change::ToggleStore` writes the new alternate through
+    /// [`crate::walk::toggle::store`]. A url that is already active or
+    /// stored is left alone, so storing never duplicates a line.
+    fn apply_toggle_store(&mut self, change: Change) -> Result<Option<String>, Error> {
+        let Change::ToggleStore { id, uri } = change else {
+            unreachable!("apply_toggle_store dispatched only for Change::ToggleStore");
+        };
+
+        self.ensure_inputs_populated()?;
+
+        let id_str = id.input().as_str().to_string();
+        let Some(input) = self.walker.inputs.get(&id_str) else {
+            return Err(Error::InputNotFound(id_str));
+        };
+        if input.is_unresolved() {
+            return Err(Error::UnresolvedUrl {
+                id: id_str,
+                expr: input.url().to_string(),
+            });
+        }
+        let Some(binding) = toggle::url_binding(&self.walker.root, input) else {
+            return Err(Error::NoUrlToToggle(id_str));
+        };
+        let parent = binding
+            .parent()
+            .expect("a url binding always sits inside an enclosing node");
+
+        let marker = self.toggle_marker.as_str();
+        if input.url() == uri
+            || toggle::alternates(&binding, marker)
+                .iter()
+                .any(|a| a.url == uri)
+        {
+            return Ok(None);
+        }
+        Ok(Some(
+            toggle::store(&parent, &binding, &uri, marker).to_string(),
+        ))
+    }
+
     /// Toggle states for every input with a url binding, keyed by input
     /// id. Inputs without one (follows-only inputs) are absent. An input
     /// is toggleable when its state lists at least one alternate.
@@ -339,7 +710,7 @@ impl FlakeEdit {
             let Some(binding) = toggle::url_binding(&self.walker.root, input) else {
                 continue;
             };
-            let alternates = toggle::alternates(&binding)
+            let alternates = toggle::alternates(&binding, &self.toggle_marker)
                 .into_iter()
                 .map(|a| a.url)
                 .collect();
@@ -354,10 +725,102 @@ impl FlakeEdit {
         Ok(states)
     }
 
+    /// Input ids whose url is bound more than once, sorted by id, each
+    /// with its number of bindings. Only the last binding shows up in
+    /// [`Self::list`]; the earlier ones are shadowed.
+    pub fn duplicates(&self) -> Vec<(String, usize)> {
+        walk::duplicate_url_bindings(&self.walker.root)
+            .into_iter()
+            .map(|(id, offsets)| (id, offsets.len()))
+            .collect()
+    }
+
+    /// Top-level inputs the `outputs` lambda never names, sorted by id.
+    ///
+    /// An input some other input follows is kept, since removing it would
+    /// leave that follows dangling. `None` when the outputs argument
+    /// pattern is open (`...` or `@inputs`) or absent: any input may then
+    /// be used without being named.
+    pub fn unused_inputs(&mut self) -> Result<Option<Vec<String>>, Error> {
+        self.ensure_inputs_populated()?;
+        let Some(named) = self.walker.outputs_idents()? else {
+            return Ok(None);
+        };
+        let followed: Vec<&str> = self
+            .walker
+            .inputs
+            .values()
+            .flat_map(|input| input.follows())
+            .filter_map(|follows| match follows {
+                Follows::Indirect {
+                    target: Some(target),
+                    ..
+                } => Some(target.first().as_str()),
+                _ => None,
+            })
+            .collect();
+        let unused = sorted_input_ids(&self.walker.inputs)
+            .into_iter()
+            .filter(|id| !named.contains(id.as_str()) && !followed.contains(&id.as_str()))
+            .cloned()
+            .collect();
+        Ok(Some(unused))
+    }
+
+    /// Rewrite every top-level input url with its query parameters in
+    /// canonical order (see [`crate::uri::sort_params`]).
+    ///
+    /// Returns the new source, or `None` when every url already was.
+    pub fn sort_url_params(&mut self) -> Result<Option<String>, Error> {
+        self.ensure_inputs_populated()?;
+        let rewrites: Vec<(Segment, String)> = sorted_input_ids(&self.walker.inputs)
+            .into_iter()
+            .filter_map(|id| {
+                let input = &self.walker.inputs[id];
+                if input.is_unresolved() {
+                    return None;
+                }
+                let sorted = crate::uri::sort_params(input.url())?;
+                Some((input.id().clone(), sorted))
+            })
+            .collect();
+        if rewrites.is_empty() {
+            return Ok(None);
+        }
+
+        let mut text = self.source_text();
+        for (id, uri) in rewrites {
+            let change = Change::Change {
+                id: Some(id.into()),
+                uri: Some(uri),
+                rename: None,
+            };
+            if let Some(next) = Self::from_text(&text)?.apply_change(change)?.text {
+                text = next;
+            }
+        }
+        Ok(Some(text))
+    }
+
     pub fn walker(&self) -> &Walker {
         &self.walker
     }
 
+    /// Reject `input` following `target` when the top-level input `target`
+    /// starts with is not declared, unless [`Self::with_dangling_follows`]
+    /// allows it.
+    fn check_follows_target(&self, input: &ChangeId, target: &AttrPath) -> Result<(), Error> {
+        let target_id = target.first().as_str();
+        if self.dangling_follows || self.walker.inputs.contains_key(target_id) {
+            return Ok(());
+        }
+        Err(Error::FollowsTargetNotFound {
+            input: input.to_string(),
+            target: target.to_flake_follows_string(),
+            id: target_id.to_string(),
+        })
+    }
+
     /// Walk once if the inputs map is empty.
     fn ensure_inputs_populated(&mut self) -> Result<(), Error> {
         if self.walker.inputs.is_empty() {
@@ -400,6 +863,8 @@ impl FlakeEdit {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::change::ChangeId;
+    use crate::follows::AttrPath;
 
     fn flake_with_nixpkgs_and_crane() -> &'static str {
         r#"{
@@ -541,6 +1006,7 @@ mod tests {
         let change = Change::Change {
             id: Some(crate::change::ChangeId::parse("crane").unwrap()),
             uri: Some("github:ipetkov/crane/v0.20.0".into()),
+            rename: None,
         };
         let text = fe
             .apply_change(change)
@@ -559,6 +1025,7 @@ mod tests {
         let change = Change::Change {
             id: Some(crate::change::ChangeId::parse("does-not-exist").unwrap()),
             uri: Some("github:owner/repo".into()),
+            rename: None,
         };
         let err = fe
             .apply_change(change)
@@ -569,6 +1036,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn change_uri_rename_onto_existing_input_is_rejected() {
+        let mut fe = FlakeEdit::from_text(flake_with_nixpkgs_and_crane()).unwrap();
+        let change = Change::Change {
+            id: Some(crate::change::ChangeId::parse("crane").unwrap()),
+            uri: Some("github:nixos/nixpkgs".into()),
+            rename: Some(Segment::from_unquoted("nixpkgs").unwrap()),
+        };
+        let err = fe
+            .apply_change(change)
+            .expect_err("renaming onto an existing id must error");
+        assert!(
+            matches!(err, Error::DuplicateInput(ref id) if id == "nixpkgs"),
+            "expected DuplicateInput(\"nixpkgs\"), got: {err:?}",
+        );
+        assert!(!fe.is_dirty(), "a rejected change must not touch the text");
+    }
+
     #[test]
     fn follows_missing_parent_returns_input_not_found() {
         let mut fe = FlakeEdit::from_text(flake_with_nixpkgs_and_crane()).unwrap();
@@ -585,6 +1070,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn follows_missing_target_is_rejected_unless_allowed() {
+        let change = Change::Follows {
+            input: ChangeId::parse("crane.nixpkgs").unwrap(),
+            target: AttrPath::parse("nixpkgz").unwrap(),
+        };
+        let mut fe = FlakeEdit::from_text(flake_with_nixpkgs_and_crane()).unwrap();
+        let err = fe
+            .apply_change(change.clone())
+            .expect_err("missing target must error");
+        assert!(
+            matches!(&err, Error::FollowsTargetNotFound { input, id, .. }
+                if input == "crane.nixpkgs" && id == "nixpkgz"),
+            "expected FollowsTargetNotFound, got: {err:?}",
+        );
+        assert!(!fe.is_dirty());
+
+        let mut fe = FlakeEdit::from_text(flake_with_nixpkgs_and_crane())
+            .unwrap()
+            .with_dangling_follows(true);
+        let text = fe.apply_change(change).unwrap().text.unwrap();
+        assert!(
+            text.contains(r#"inputs.nixpkgs.follows = "nixpkgz";"#),
+            "{text}"
+        );
+    }
+
     #[test]
     fn already_follows_is_noop() {
         let flake = r#"{
@@ -672,4 +1184,240 @@ mod tests {
             "RHS must not double-quote the target, got:\n{text}",
         );
     }
+
+    fn add_follows_entries(entries: &[(&str, &str)]) -> Vec<(ChangeId, AttrPath)> {
+        entries
+            .iter()
+            .map(|(input, target)| {
+                (
+                    ChangeId::parse(input).unwrap(),
+                    AttrPath::parse(target).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    const ADD_FOLLOWS_FLAKE: &str = r#"{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs";
+    systems.url = "github:nix-systems/default";
+    crane = {
+      url = "github:ipetkov/crane";
+    };
+    naersk.url = "github:nix-community/naersk";
+  };
+  outputs = { ... }: { };
+}"#;
+
+    #[test]
+    fn add_follows_matches_sequential_follows() {
+        let entries = add_follows_entries(&[
+            ("crane.nixpkgs", "nixpkgs"),
+            ("naersk.nixpkgs", "nixpkgs"),
+            ("crane.systems", "systems"),
+        ]);
+
+        let mut sequential = ADD_FOLLOWS_FLAKE.to_string();
+        for (input, target) in &entries {
+            let mut fe = FlakeEdit::from_text(&sequential).unwrap();
+            let change = Change::Follows {
+                input: input.clone(),
+                target: target.clone(),
+            };
+            sequential = fe
+                .apply_change(change)
+                .expect("apply Change::Follows")
+                .text
+                .expect("each follows must produce text");
+        }
+
+        let mut fe = FlakeEdit::from_text(ADD_FOLLOWS_FLAKE).unwrap();
+        let batched = fe
+            .apply_change(Change::AddFollows { entries })
+            .expect("apply Change::AddFollows")
+            .text
+            .expect("batch must produce text");
+
+        assert_eq!(batched, sequential);
+        assert!(batched.contains("naersk.inputs.nixpkgs.follows = \"nixpkgs\";"));
+        assert!(batched.contains("inputs.systems.follows = \"systems\";"));
+    }
+
+    #[test]
+    fn add_follows_missing_parent_rejects_whole_batch() {
+        let mut fe = FlakeEdit::from_text(ADD_FOLLOWS_FLAKE).unwrap();
+        let change = Change::AddFollows {
+            entries: add_follows_entries(&[
+                ("crane.nixpkgs", "nixpkgs"),
+                ("ghost.nixpkgs", "nixpkgs"),
+            ]),
+        };
+        let err = fe
+            .apply_change(change)
+            .expect_err("missing parent must error");
+        assert!(
+            matches!(err, Error::InputNotFound(ref id) if id == "ghost"),
+            "expected InputNotFound(\"ghost\"), got: {err:?}",
+        );
+    }
+
+    #[test]
+    fn add_follows_missing_target_rejects_whole_batch() {
+        let mut fe = FlakeEdit::from_text(ADD_FOLLOWS_FLAKE).unwrap();
+        let change = Change::AddFollows {
+            entries: add_follows_entries(&[
+                ("crane.nixpkgs", "nixpkgs"),
+                ("crane.flake-utils", "ghost"),
+            ]),
+        };
+        let err = fe
+            .apply_change(change)
+            .expect_err("missing target must error");
+        assert!(
+            matches!(&err, Error::FollowsTargetNotFound { id, .. } if id == "ghost"),
+            "expected FollowsTargetNotFound, got: {err:?}",
+        );
+    }
+
+    #[test]
+    fn from_text_rejects_url_declared_flat_and_in_block() {
+        let flake = r#"{
+  inputs.nixpkgs.url = "github:nixos/nixpkgs";
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    crane.url = "github:ipetkov/crane";
+  };
+  outputs = { ... }: { };
+}"#;
+        let Err(err) = FlakeEdit::from_text(flake) else {
+            panic!("mixed-style duplicate must error");
+        };
+        assert!(
+            matches!(err, Error::Walker(WalkerError::DuplicateInput { ref id }) if id == "nixpkgs"),
+            "expected DuplicateInput {{ id: \"nixpkgs\" }}, got: {err:?}",
+        );
+    }
+
+    #[test]
+    fn from_text_accepts_flat_follows_next_to_block_url() {
+        let flake = r#"{
+  inputs.crane.inputs.nixpkgs.follows = "nixpkgs";
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs";
+    crane.url = "github:ipetkov/crane";
+  };
+  outputs = { ... }: { };
+}"#;
+        let mut fe = FlakeEdit::from_text(flake).expect("flat follows merges with block url");
+        assert!(fe.list().contains_key("crane"));
+    }
+
+    #[test]
+    fn noop_change_leaves_editor_clean() {
+        let flake = r#"{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs";
+    crane = {
+      url = "github:ipetkov/crane";
+      inputs.nixpkgs.follows = "nixpkgs";
+    };
+  };
+  outputs = { ... }: { };
+}"#;
+        let mut fe = FlakeEdit::from_text(flake).unwrap();
+        assert!(!fe.is_dirty());
+        let change = Change::Follows {
+            input: ChangeId::parse("crane.nixpkgs").unwrap(),
+            target: AttrPath::parse("nixpkgs").unwrap(),
+        };
+        let outcome = fe.apply_change(change).unwrap();
+        assert!(outcome.text.is_none(), "no text for a no-op");
+        assert!(outcome.unchanged);
+        assert!(
+            !fe.is_dirty(),
+            "an existing follows must not mark the editor dirty"
+        );
+    }
+
+    #[test]
+    fn real_change_marks_editor_dirty() {
+        let mut fe = FlakeEdit::from_text(flake_with_nixpkgs_and_crane()).unwrap();
+        let change = Change::Change {
+            id: Some(ChangeId::parse("crane").unwrap()),
+            uri: Some("github:ipetkov/crane/v0.20.0".into()),
+            rename: None,
+        };
+        fe.apply_change(change).unwrap();
+        assert!(fe.is_dirty());
+    }
+
+    #[test]
+    fn unused_inputs_skips_named_and_followed_inputs() {
+        let flake = r#"{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs";
+    systems.url = "github:nix-systems/default";
+    treefmt-nix.url = "github:numtide/treefmt-nix";
+    crane = {
+      url = "github:ipetkov/crane";
+      inputs.systems.follows = "systems";
+    };
+    flake-utils.url = "github:numtide/flake-utils";
+  };
+  outputs = { self, crane, flake-utils }: {
+    lib = crane.lib;
+  };
+}"#;
+        let mut fe = FlakeEdit::from_text(flake).unwrap();
+        assert_eq!(
+            fe.unused_inputs().unwrap(),
+            Some(vec!["nixpkgs".to_string(), "treefmt-nix".to_string()])
+        );
+    }
+
+    #[test]
+    fn unused_inputs_is_none_for_open_outputs() {
+        let mut fe = FlakeEdit::from_text(flake_with_nixpkgs_and_crane()).unwrap();
+        assert_eq!(fe.unused_inputs().unwrap(), None);
+
+        let flake = r#"{
+  inputs.nixpkgs.url = "github:nixos/nixpkgs";
+  outputs = { self }@inputs: { };
+}"#;
+        let mut fe = FlakeEdit::from_text(flake).unwrap();
+        assert_eq!(fe.unused_inputs().unwrap(), None);
+    }
+
+    #[test]
+    fn toggle_uses_configured_marker() {
+        let flake = r#"{
+  inputs = {
+    ## DISABLED: nixpkgs.url = "github:nixos/nixpkgs/nixos-24.05";
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    # crane.url = "github:a-kenji/crane";
+    crane.url = "github:ipetkov/crane";
+  };
+  outputs = { ... }: { };
+}"#;
+        let mut fe = FlakeEdit::from_text(flake)
+            .unwrap()
+            .with_toggle_marker("## DISABLED: ");
+        let states = fe.toggle_states().unwrap();
+        assert_eq!(
+            states["nixpkgs"].alternates,
+            vec!["github:nixos/nixpkgs/nixos-24.05".to_string()]
+        );
+        assert!(states["crane"].alternates.is_empty());
+
+        let change = Change::Toggle {
+            id: ChangeId::parse("nixpkgs").unwrap(),
+            uri: "github:nixos/nixpkgs/nixos-24.05".into(),
+            previous: "github:nixos/nixpkgs/nixos-unstable".into(),
+        };
+        let text = fe.apply_change(change).unwrap().text.unwrap();
+        assert!(text.contains(
+            r#"    nixpkgs.url = "github:nixos/nixpkgs/nixos-24.05";
+    ## DISABLED: nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";"#
+        ));
+    }
 }
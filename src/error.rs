@@ -23,6 +23,22 @@ pub enum Error {
         #[source]
         source: std::io::Error,
     },
+    /// Failed to copy a flake file to `backup` before writing it.
+    #[error("failed to back up {path} to {backup}", path = path.display(), backup = backup.display())]
+    Backup {
+        path: PathBuf,
+        backup: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// `--relock` was requested but refreshing the lockfile after writing
+    /// `path` failed.
+    #[error("failed to relock after writing {path}", path = path.display())]
+    Relock {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
     /// The CST walker rejected a change. See [`WalkerError`] for details.
     #[error(transparent)]
     Walker(#[from] WalkerError),
@@ -30,6 +46,18 @@ pub enum Error {
     /// per-variant breakdown.
     #[error(transparent)]
     Lock(#[from] LockError),
+    /// `flake.lock` exists but is not valid JSON, e.g. a partially written
+    /// file. Carries the position the JSON parser stopped at.
+    #[error(
+        "malformed lock file {path} at line {line}, column {column}: {message}",
+        path = path.display()
+    )]
+    MalformedLock {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
     /// Tried to add an input that already exists. The wrapped string is the
     /// existing input id.
     #[error("input '{0}' already exists in the flake")]
@@ -37,10 +65,23 @@ pub enum Error {
     /// Tried to operate on an input id that is not declared in the flake.
     #[error("input '{0}' not found in the flake")]
     InputNotFound(String),
+    /// Tried to make `input` follow `target`, whose top-level input `id`
+    /// is not declared in the flake. Nix cannot evaluate the result.
+    #[error("cannot make '{input}' follow '{target}': input '{id}' not found in the flake")]
+    FollowsTargetNotFound {
+        input: String,
+        target: String,
+        id: String,
+    },
     /// Tried to toggle an input that has no url binding (e.g. a
     /// follows-only input).
     #[error("input '{0}' has no url to toggle (follows-only input)")]
     NoUrlToToggle(String),
+    /// Tried to rewrite the url of an input whose url is an expression
+    /// (see [`crate::input::Input::is_unresolved`]). Replacing it would
+    /// drop whatever the expression evaluates to.
+    #[error("input '{id}' takes its url from `{expr}`, which flake-edit does not evaluate")]
+    UnresolvedUrl { id: String, expr: String },
     /// Tried to remove an input's active url without a stored alternate to
     /// take its place. Honoring it would leave the input url-less.
     #[error("cannot remove the active url of '{0}' without an alternate to activate")]
@@ -60,6 +101,28 @@ pub enum Error {
 }
 
 impl Error {
+    /// Name of the variant, for the `kind` of a `--output json` error
+    /// report. Wrapped errors report their own variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Read { .. } => "Read",
+            Self::Write { .. } => "Write",
+            Self::Backup { .. } => "Backup",
+            Self::Relock { .. } => "Relock",
+            Self::Walker(inner) => inner.kind(),
+            Self::Lock(inner) => inner.kind(),
+            Self::MalformedLock { .. } => "MalformedLock",
+            Self::DuplicateInput { .. } => "DuplicateInput",
+            Self::InputNotFound { .. } => "InputNotFound",
+            Self::FollowsTargetNotFound { .. } => "FollowsTargetNotFound",
+            Self::NoUrlToToggle { .. } => "NoUrlToToggle",
+            Self::UnresolvedUrl { .. } => "UnresolvedUrl",
+            Self::RemoveActiveWithoutAlternate { .. } => "RemoveActiveWithoutAlternate",
+            Self::AddFollowDepthLimit { .. } => "AddFollowDepthLimit",
+            Self::Validation { .. } => "Validation",
+        }
+    }
+
     /// Actionable hint to display alongside the error, when one exists.
     ///
     /// Hints live here rather than in `#[error(...)]` strings so the binary
@@ -68,13 +131,43 @@ impl Error {
     pub fn hint(&self) -> Option<String> {
         match self {
             Self::DuplicateInput(id) => Some(format!(
-                "to replace it, run `flake-edit remove {id}` then `flake-edit add {id} <flakeref>`; \
+                "to replace it, run `flake-edit add --replace-if-exists {id} <flakeref>`; \
                  or add it under a different id with `flake-edit add [ID] <flakeref>`"
             )),
+            Self::Walker(WalkerError::DuplicateInput { id }) => Some(format!(
+                "keep a single definition of `{id}`: either the flat `inputs.{id}.url` \
+                 or the entry in the `inputs = {{ ... }}` block"
+            )),
+            Self::Walker(WalkerError::OutputsRename { from, .. }) => Some(format!(
+                "rename the bindings of `{from}` inside `outputs` first, \
+                 or rename the input by hand"
+            )),
             Self::InputNotFound(id) => Some(format!(
                 "to add it, run `flake-edit add {id} <flakeref>`; \
                  see declared inputs with `flake-edit list`"
             )),
+            Self::FollowsTargetNotFound { id, .. } => Some(format!(
+                "to add it, run `flake-edit add {id} <flakeref>`; \
+                 or pass `--force` to write the follows anyway"
+            )),
+            Self::UnresolvedUrl { expr, .. } => Some(format!(
+                "edit `{expr}` where it is defined, or write the url as a string literal"
+            )),
+            Self::Relock { .. } => Some(
+                "flake.nix was written; run `nix flake lock` once nix is available, \
+                 or drop `--relock` to only warn when relocking fails"
+                    .into(),
+            ),
+            Self::MalformedLock { .. } => {
+                Some("run `nix flake lock` to regenerate flake.lock".into())
+            }
+            Self::Lock(LockError::InputFollows { .. }) => Some(
+                "add the input it follows instead: `flake-edit add <id> --from-lock`".into(),
+            ),
+            Self::Lock(LockError::TypeHasNoRev { .. }) => Some(
+                "only inputs fetched from a forge, git or hg repository can be pinned to a rev"
+                    .into(),
+            ),
             Self::AddFollowDepthLimit { .. } => Some(
                 "use `flake-edit follow` for deeper paths (depth bounded by `follow.max_depth` in your config, if set)"
                     .into(),
@@ -361,8 +361,22 @@ impl FollowsGraph {
     /// Bounded by [`Self::with_max_depth`] for malformed graphs. Standard
     /// visited / on-stack sets keep pre-existing cycles from wedging the walk.
     pub fn would_create_cycle(&self, proposed: &Edge) -> bool {
+        self.cycle_through(proposed).is_some()
+    }
+
+    /// The cycle adding `proposed` would close, or `None` if it closes
+    /// none. The cycle starts with `proposed`, followed by the edges
+    /// leading from its target back to its source. Ancestor cycles, where
+    /// the target names an ancestor of the source, are `proposed` alone.
+    /// Same detection as [`Self::would_create_cycle`].
+    pub fn cycle_through(&self, proposed: &Edge) -> Option<Cycle> {
+        let closed = |edges: Vec<Edge>| {
+            Some(Cycle {
+                edges: std::iter::once(proposed.clone()).chain(edges).collect(),
+            })
+        };
         if is_one_step_cycle(proposed) {
-            return true;
+            return closed(Vec::new());
         }
         // Structural ancestor case: if the target's leading segment matches
         // any ancestor segment of the source, the edge would point a nested
@@ -372,21 +386,29 @@ impl FollowsGraph {
         let mut ancestor: Option<AttrPath> = proposed.source.parent();
         while let Some(a) = ancestor {
             if a.last() == target_first {
-                return true;
+                return closed(Vec::new());
             }
             ancestor = a.parent();
         }
         let mut visited: HashSet<AttrPath> = HashSet::new();
         let mut on_stack: HashSet<AttrPath> = HashSet::new();
-        self.dfs_reaches(
+        let mut path: Vec<Edge> = Vec::new();
+        if self.dfs_reaches(
             &proposed.follows,
             &proposed.source,
             0,
             &mut visited,
             &mut on_stack,
-        )
+            &mut path,
+        ) {
+            closed(path)
+        } else {
+            None
+        }
     }
 
+    /// Whether `target` is reachable from `node`. On success `path` holds
+    /// the edges walked, in order.
     fn dfs_reaches(
         &self,
         node: &AttrPath,
@@ -394,6 +416,7 @@ impl FollowsGraph {
         depth: usize,
         visited: &mut HashSet<AttrPath>,
         on_stack: &mut HashSet<AttrPath>,
+        path: &mut Vec<Edge>,
     ) -> bool {
         if depth >= self.max_depth {
             return false;
@@ -421,11 +444,13 @@ impl FollowsGraph {
         // `node`. The second captures the implicit "parent depends on target"
         // relation a declared `parent.child -> target` follows expresses.
         for edge in self.expanded_outgoing(node) {
-            if self.dfs_reaches(&edge.follows, target, depth + 1, visited, on_stack) {
+            path.push(edge.clone());
+            if self.dfs_reaches(&edge.follows, target, depth + 1, visited, on_stack, path) {
                 on_stack.remove(node);
                 visited.insert(node.clone());
                 return true;
             }
+            path.pop();
         }
         on_stack.remove(node);
         visited.insert(node.clone());
@@ -446,6 +471,12 @@ impl FollowsGraph {
         out
     }
 
+    /// Add `edge`, e.g. a follows staged for writing, so later queries
+    /// account for it.
+    pub fn add_edge(&mut self, edge: Edge) {
+        self.insert_edge(edge);
+    }
+
     fn insert_edge(&mut self, edge: Edge) {
         self.edges
             .entry(edge.source.clone())
@@ -974,6 +1005,46 @@ mod tests {
         assert!(g.would_create_cycle(&proposed));
     }
 
+    fn cycle_text(cycle: &Cycle) -> Vec<String> {
+        cycle
+            .edges
+            .iter()
+            .map(|e| format!("{} -> {}", e.source, e.follows))
+            .collect()
+    }
+
+    /// `a.b -> b` and `b.a -> a`: each parent depends on the other.
+    #[test]
+    fn cycle_through_reports_two_node_path() {
+        let mut g = FollowsGraph::default();
+        g.add_edge(declared_edge("a.b", "b"));
+        let cycle = g.cycle_through(&declared_edge("b.a", "a")).unwrap();
+        assert_eq!(cycle_text(&cycle), ["b.a -> a", "a.b -> b"]);
+        assert!(
+            FollowsGraph::default()
+                .cycle_through(&declared_edge("b.a", "a"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn cycle_through_reports_three_node_path() {
+        let mut g = FollowsGraph::default();
+        g.add_edge(declared_edge("a", "b"));
+        g.add_edge(declared_edge("b", "c"));
+        let cycle = g.cycle_through(&declared_edge("c", "a")).unwrap();
+        assert_eq!(cycle_text(&cycle), ["c -> a", "a -> b", "b -> c"]);
+        assert!(g.cycle_through(&declared_edge("c", "d")).is_none());
+    }
+
+    #[test]
+    fn cycle_through_ancestor_is_the_proposed_edge() {
+        let cycle = FollowsGraph::default()
+            .cycle_through(&declared_edge("a.b.c", "b"))
+            .unwrap();
+        assert_eq!(cycle_text(&cycle), ["a.b.c -> b"]);
+    }
+
     /// Multi-hop with a `"hls-1.10"` participant: typed [`AttrPath`]
     /// equality must survive the embedded dot.
     #[test]
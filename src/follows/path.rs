@@ -216,6 +216,26 @@ impl AttrPath {
         Ok(AttrPath(segments))
     }
 
+    /// Parse a user-supplied follows target.
+    ///
+    /// Accepts the slash form written on the right-hand side of
+    /// `follows = "..."` (`crane/nixpkgs`), where `/` is the only
+    /// separator and a `.` stays inside its segment. Anything without a
+    /// `/` is a dotted path as in [`Self::parse`].
+    pub fn parse_target(s: &str) -> Result<Self, AttrPathParseError> {
+        if !s.contains('/') {
+            return Self::parse(s);
+        }
+        let mut segments: SmallVec<[Segment; 2]> = SmallVec::new();
+        for raw in s.split('/') {
+            if raw.is_empty() {
+                return Err(AttrPathParseError::EmptySegment);
+            }
+            segments.push(Segment::from_source(raw)?);
+        }
+        Ok(AttrPath(segments))
+    }
+
     pub fn first(&self) -> &Segment {
         &self.0[0]
     }
@@ -650,6 +670,33 @@ mod tests {
         assert_eq!(p.to_flake_follows_string(), "hls-1.10/nixpkgs");
     }
 
+    #[test]
+    fn parse_target_slash_form_matches_dotted_form() {
+        let slashed = AttrPath::parse_target("crane/nixpkgs").unwrap();
+        assert_eq!(slashed, AttrPath::parse("crane.nixpkgs").unwrap());
+        assert_eq!(slashed.to_flake_follows_string(), "crane/nixpkgs");
+    }
+
+    #[test]
+    fn parse_target_slash_form_keeps_dots_in_segments() {
+        let p = AttrPath::parse_target("hls-1.10/nixpkgs").unwrap();
+        assert_eq!(p.len(), 2);
+        assert_eq!(p.first().as_str(), "hls-1.10");
+        assert_eq!(p.to_flake_follows_string(), "hls-1.10/nixpkgs");
+    }
+
+    #[test]
+    fn parse_target_rejects_empty_slash_segment() {
+        assert_eq!(
+            AttrPath::parse_target("crane//nixpkgs"),
+            Err(AttrPathParseError::EmptySegment)
+        );
+        assert_eq!(
+            AttrPath::parse_target("crane/"),
+            Err(AttrPathParseError::EmptySegment)
+        );
+    }
+
     #[test]
     fn parse_follows_target_accepts_slash_form() {
         let fallback = Segment::from_unquoted("fallback").unwrap();
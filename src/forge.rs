@@ -6,5 +6,6 @@ pub mod api;
 pub(crate) mod archive;
 pub(crate) mod cache;
 pub mod channel;
+pub mod commits;
 pub mod update;
 pub mod version;
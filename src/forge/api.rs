@@ -66,6 +66,10 @@ pub enum ApiError {
     /// returning usable data.
     #[error("no branches found for repository")]
     NoBranchesFound,
+
+    /// The repository has no commit older than the requested date.
+    #[error("no commits found before {date}")]
+    NoCommitsBefore { date: String },
 }
 
 /// Classify a `ureq::Error` from establishing the request into the
@@ -494,6 +498,17 @@ impl ForgeClient {
         Ok(fresh)
     }
 
+    /// Newest commit of `source` made before `date`. Not cached: one
+    /// `pin --before` asks for it once.
+    pub(crate) fn commit_before(
+        &self,
+        source: &super::commits::CommitSource,
+        date: &super::commits::Date,
+    ) -> Result<String, ApiError> {
+        let headers = Headers::for_domain(source.domain());
+        source.newest_before(date, |url| self.http.get(url, &headers))
+    }
+
     /// Resolve many `github.com` lookups in one GraphQL POST and
     /// prime the per-run caches with the results.
     ///
@@ -1015,6 +1030,32 @@ fn resolve_forge_token(domain: &str) -> Option<String> {
     None
 }
 
+#[cfg(test)]
+impl ForgeClient {
+    /// Answer `list_tags` for github.com `owner/repo` with `names` instead
+    /// of asking the forge. The GraphQL warm is turned off as well, so a
+    /// seeded client never reaches the network for tags.
+    pub(crate) fn with_github_tags(mut self, owner: &str, repo: &str, names: &[&str]) -> Self {
+        let tags = IntermediaryTags(
+            names
+                .iter()
+                .map(|name| IntermediaryTag {
+                    name: name.to_string(),
+                })
+                .collect(),
+        );
+        self.tags_cache
+            .lock()
+            .expect("forge tags cache poisoned")
+            .insert(
+                ("github.com".into(), owner.into(), repo.into()),
+                tags.into(),
+            );
+        self.github_graphql_enabled = false;
+        self
+    }
+}
+
 impl From<IntermediaryTags> for Tags {
     fn from(value: IntermediaryTags) -> Self {
         let mut versions = vec![];
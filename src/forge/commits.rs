@@ -0,0 +1,388 @@
+//! Newest commit before a date, for `pin --before`.
+//!
+//! GitHub and GitLab both list a repository's commits newest first and
+//! take an `until` timestamp and the ref to list, so one request with a
+//! page size of one answers the question, on the branch the input
+//! tracks. The request itself goes through a caller-supplied `get`,
+//! which keeps the url construction and response parsing testable
+//! without a network.
+
+use std::fmt;
+use std::str::FromStr;
+
+use nix_uri::{FlakeRef, FlakeRefType, GitForgePlatform};
+use serde::Deserialize;
+
+use super::api::ApiError;
+
+/// A calendar day, written `YYYY-MM-DD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+/// Why a `--before` value is not a [`Date`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum DateParseError {
+    #[error("expected a date as YYYY-MM-DD, got '{0}'")]
+    Format(String),
+    #[error("'{0}' is not a day of the calendar")]
+    OutOfRange(String),
+}
+
+impl Date {
+    fn days_in_month(year: u16, month: u8) -> u8 {
+        match month {
+            2 if year.is_multiple_of(4)
+                && (!year.is_multiple_of(100) || year.is_multiple_of(400)) =>
+            {
+                29
+            }
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = DateParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let format = || DateParseError::Format(s.to_string());
+        let mut parts = s.split('-');
+        let (Some(year), Some(month), Some(day), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(format());
+        };
+        if year.len() != 4
+            || month.len() != 2
+            || day.len() != 2
+            || !s.chars().all(|c| c.is_ascii_digit() || c == '-')
+        {
+            return Err(format());
+        }
+        let date = Self {
+            year: year.parse().map_err(|_| format())?,
+            month: month.parse().map_err(|_| format())?,
+            day: day.parse().map_err(|_| format())?,
+        };
+        if !(1..=12).contains(&date.month)
+            || date.day == 0
+            || date.day > Self::days_in_month(date.year, date.month)
+        {
+            return Err(DateParseError::OutOfRange(s.to_string()));
+        }
+        Ok(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Why [`super::update::Updater::pin_input_before`] could not pin an input.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum PinBeforeError {
+    /// No input with this id has an editable url.
+    #[error("input '{0}' has no pinnable URL (it may use follows or a non-standard format)")]
+    UnknownInput(String),
+    /// The input's forge cannot be asked for its commits by date.
+    #[error("input '{id}' is not a github or gitlab input: {uri}")]
+    NotForge { id: String, uri: String },
+    /// The forge could not be reached or gave no usable answer.
+    #[error("could not look up the newest commit of '{id}' before {date}")]
+    Lookup {
+        id: String,
+        date: Date,
+        #[source]
+        source: ApiError,
+    },
+}
+
+/// The forge repository a flake reference points at, when its forge
+/// can list commits by date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CommitSource {
+    platform: Platform,
+    domain: String,
+    owner: String,
+    repo: String,
+    /// Branch, tag or rev the reference follows; the default branch when
+    /// `None`.
+    ref_or_rev: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Platform {
+    GitHub,
+    GitLab,
+}
+
+impl CommitSource {
+    /// The repository of a `github:` or `gitlab:` reference, honouring a
+    /// `?host=` override, and the ref it follows. `None` for every other
+    /// kind of reference.
+    pub(crate) fn from_flake_ref(flake_ref: &FlakeRef) -> Option<Self> {
+        let FlakeRefType::GitForge(forge) = flake_ref.kind() else {
+            return None;
+        };
+        let platform = match forge.platform {
+            GitForgePlatform::GitHub => Platform::GitHub,
+            GitForgePlatform::GitLab => Platform::GitLab,
+            _ => return None,
+        };
+        Some(Self {
+            platform,
+            domain: flake_ref.domain()?.to_string(),
+            owner: forge.owner.clone(),
+            repo: forge.repo.clone(),
+            ref_or_rev: flake_ref.ref_or_rev().map(str::to_owned),
+        })
+    }
+
+    /// Domain the request is sent to, which picks the access token.
+    pub(crate) fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// Url listing the newest commit made before `date` began (UTC), on
+    /// the ref the reference follows.
+    fn url(&self, date: &Date) -> String {
+        let Self {
+            domain,
+            owner,
+            repo,
+            ref_or_rev,
+            ..
+        } = self;
+        let until = format!("{date}T00:00:00Z");
+        // Without a ref both forges list the default branch.
+        let on_ref = |param: &str| {
+            ref_or_rev
+                .as_deref()
+                .map(|r| format!("&{param}={}", encode_query_value(r)))
+                .unwrap_or_default()
+        };
+        match self.platform {
+            Platform::GitHub if domain == "github.com" => format!(
+                "https://api.github.com/repos/{owner}/{repo}/commits?until={until}{}&per_page=1",
+                on_ref("sha")
+            ),
+            // GitHub Enterprise serves the same API under `/api/v3`.
+            Platform::GitHub => format!(
+                "https://{domain}/api/v3/repos/{owner}/{repo}/commits?until={until}{}&per_page=1",
+                on_ref("sha")
+            ),
+            Platform::GitLab => {
+                // Subgroups are part of the owner; the project is
+                // addressed by its whole path, url-encoded.
+                let project = format!("{owner}/{repo}").replace('/', "%2F");
+                format!(
+                    "https://{domain}/api/v4/projects/{project}/repository/commits?until={until}{}&per_page=1",
+                    on_ref("ref_name")
+                )
+            }
+        }
+    }
+
+    /// The commit id at the head of a commit listing, `None` when the
+    /// listing is empty.
+    fn parse(&self, body: &str) -> Result<Option<String>, serde_json::Error> {
+        #[derive(Deserialize)]
+        struct GitHubCommit {
+            sha: String,
+        }
+        #[derive(Deserialize)]
+        struct GitLabCommit {
+            id: String,
+        }
+        Ok(match self.platform {
+            Platform::GitHub => serde_json::from_str::<Vec<GitHubCommit>>(body)?
+                .into_iter()
+                .next()
+                .map(|c| c.sha),
+            Platform::GitLab => serde_json::from_str::<Vec<GitLabCommit>>(body)?
+                .into_iter()
+                .next()
+                .map(|c| c.id),
+        })
+    }
+
+    /// Newest commit made before `date`, fetching the listing with `get`.
+    pub(crate) fn newest_before<F>(&self, date: &Date, get: F) -> Result<String, ApiError>
+    where
+        F: FnOnce(&str) -> Result<String, ApiError>,
+    {
+        let url = self.url(date);
+        tracing::debug!("Fetching newest commit before {}: {}", date, url);
+        let body = get(&url)?;
+        self.parse(&body)
+            .map_err(|source| ApiError::Json {
+                url: url.clone(),
+                source,
+            })?
+            .ok_or_else(|| ApiError::NoCommitsBefore {
+                date: date.to_string(),
+            })
+    }
+}
+
+/// `value` percent-encoded for a query string. Ref names may carry `/`,
+/// and in principle `&` or `+`.
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(uri: &str) -> Option<CommitSource> {
+        CommitSource::from_flake_ref(&uri.parse().unwrap())
+    }
+
+    fn date(s: &str) -> Date {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn date_parses_iso_days() {
+        assert_eq!(date("2024-01-01").to_string(), "2024-01-01");
+        assert_eq!(date("2024-02-29").to_string(), "2024-02-29");
+        assert_eq!(date("2000-02-29").to_string(), "2000-02-29");
+        assert!(date("2023-12-31") < date("2024-01-01"));
+    }
+
+    #[test]
+    fn date_rejects_other_shapes() {
+        for bad in [
+            "2024-1-01",
+            "24-01-01",
+            "2024/01/01",
+            "2024-01-01T00:00",
+            "2024-01",
+            "2024-+1-01",
+            "",
+        ] {
+            assert_eq!(
+                bad.parse::<Date>(),
+                Err(DateParseError::Format(bad.to_string())),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn date_rejects_days_off_the_calendar() {
+        for bad in [
+            "2024-00-10",
+            "2024-13-01",
+            "2024-01-00",
+            "2024-04-31",
+            "2023-02-29",
+            "1900-02-29",
+        ] {
+            assert_eq!(
+                bad.parse::<Date>(),
+                Err(DateParseError::OutOfRange(bad.to_string())),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn urls_for_each_forge() {
+        let before = date("2024-01-01");
+        assert_eq!(
+            source("github:nixos/nixpkgs").unwrap().url(&before),
+            "https://api.github.com/repos/nixos/nixpkgs/commits?until=2024-01-01T00:00:00Z&per_page=1"
+        );
+        assert_eq!(
+            source("github:nixos/nixpkgs/nixos-23.11")
+                .unwrap()
+                .url(&before),
+            "https://api.github.com/repos/nixos/nixpkgs/commits?until=2024-01-01T00:00:00Z&sha=nixos-23.11&per_page=1"
+        );
+        assert_eq!(
+            source("github:acme/tool?host=git.acme.corp")
+                .unwrap()
+                .url(&before),
+            "https://git.acme.corp/api/v3/repos/acme/tool/commits?until=2024-01-01T00:00:00Z&per_page=1"
+        );
+        assert_eq!(
+            source("gitlab:veloren/veloren").unwrap().url(&before),
+            "https://gitlab.com/api/v4/projects/veloren%2Fveloren/repository/commits?until=2024-01-01T00:00:00Z&per_page=1"
+        );
+        assert_eq!(
+            source("gitlab:veloren/veloren?ref=release/1.0")
+                .unwrap()
+                .url(&before),
+            "https://gitlab.com/api/v4/projects/veloren%2Fveloren/repository/commits?until=2024-01-01T00:00:00Z&ref_name=release%2F1.0&per_page=1"
+        );
+    }
+
+    #[test]
+    fn non_forge_references_have_no_source() {
+        assert_eq!(source("sourcehut:~misterio/nix-colors"), None);
+        assert_eq!(source("git+https://github.com/mic92/vmsh"), None);
+        assert_eq!(source("path:../vmsh"), None);
+        assert_eq!(source("nixpkgs"), None);
+    }
+
+    #[test]
+    fn newest_before_takes_the_head_of_the_listing() {
+        let github = source("github:nixos/nixpkgs").unwrap();
+        let rev = github
+            .newest_before(&date("2024-01-01"), |url| {
+                assert!(url.contains("until=2024-01-01T00:00:00Z"), "{url}");
+                Ok(r#"[{"sha":"b134951a4c9f3c995fd7be05f3243f8ecd65d798","commit":{}}]"#.into())
+            })
+            .unwrap();
+        assert_eq!(rev, "b134951a4c9f3c995fd7be05f3243f8ecd65d798");
+
+        let gitlab = source("gitlab:veloren/veloren").unwrap();
+        let rev = gitlab
+            .newest_before(&date("2024-01-01"), |_| {
+                Ok(r#"[{"id":"0123456789abcdef0123456789abcdef01234567"}]"#.into())
+            })
+            .unwrap();
+        assert_eq!(rev, "0123456789abcdef0123456789abcdef01234567");
+    }
+
+    #[test]
+    fn newest_before_reports_empty_and_failed_lookups() {
+        let github = source("github:nixos/nixpkgs").unwrap();
+        let before = date("1990-01-01");
+        assert!(matches!(
+            github.newest_before(&before, |_| Ok("[]".into())),
+            Err(ApiError::NoCommitsBefore { date }) if date == "1990-01-01"
+        ));
+        assert!(matches!(
+            github.newest_before(&before, |_| Ok("{\"message\":\"rate limited\"}".into())),
+            Err(ApiError::Json { .. })
+        ));
+        assert!(matches!(
+            github.newest_before(&before, |url| Err(ApiError::HttpStatus {
+                url: url.to_string(),
+                status: 503,
+            })),
+            Err(ApiError::HttpStatus { status: 503, .. })
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use nix_uri::{FlakeRef, RefKind};
+use nix_uri::{FlakeRef, FlakeRefType, RefKind};
 use ropey::Rope;
 use std::cmp::Ordering;
 use std::collections::HashSet;
@@ -10,9 +10,10 @@ use super::channel::{
     ChannelType, UpdateStrategy, channel_probe_candidates, detect_strategy, find_latest_channel,
     parse_channel_ref,
 };
+use super::commits::{CommitSource, Date, PinBeforeError};
 use super::version::{is_downgrade, parse_ref};
 use crate::edit::InputMap;
-use crate::input::Input;
+use crate::input::{Input, escape_string, unescape_string};
 use crate::uri::is_git_url;
 
 /// Cap on concurrent forge requests during the fetch phase. Overlaps
@@ -32,6 +33,8 @@ pub struct Updater {
     /// Measured in *characters*, since ropey indexes by char.
     offset: i32,
     client: ForgeClient,
+    /// Suppress the per-input status lines printed while updating.
+    quiet: bool,
 }
 
 /// Per-input outcome from the fetch phase.
@@ -54,10 +57,14 @@ struct UpdatePlan {
 }
 
 impl Updater {
-    fn print_update_status(id: &str, previous_version: &str, final_change: &str) -> bool {
+    fn print_update_status(&self, id: &str, previous_version: &str, final_change: &str) -> bool {
         let is_up_to_date = previous_version == final_change;
         let initialized = previous_version.is_empty();
 
+        if self.quiet {
+            return !is_up_to_date;
+        }
+
         if is_up_to_date {
             println!(
                 "{} is already on the latest version: {previous_version}.",
@@ -100,9 +107,23 @@ impl Updater {
             text,
             offset: 0,
             client,
+            quiet: false,
         }
     }
 
+    /// Ask `client` instead of a fresh [`ForgeClient`].
+    #[cfg(test)]
+    fn with_client(mut self, client: ForgeClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Suppress the `Updated ...` / `Initialized ...` status lines.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
     /// Char-index range of the URL string *contents* (without the surrounding `"`),
     /// adjusted for earlier in-place edits.
     fn url_char_range(&self, input: &UpdateInput) -> (usize, usize) {
@@ -133,6 +154,92 @@ impl Updater {
         Ok(())
     }
 
+    /// Pin the input named `id` to the newest commit its forge has from
+    /// before `date` on the input's ref, returning that rev.
+    ///
+    /// # Errors
+    ///
+    /// Fails for an unknown `id`, an input that is not on GitHub or
+    /// GitLab, and a forge lookup that fails or finds no commit.
+    pub fn pin_input_before(&mut self, id: &str, date: &Date) -> Result<String, PinBeforeError> {
+        self.sort();
+        let idx = self
+            .get_index(id)
+            .ok_or_else(|| PinBeforeError::UnknownInput(id.to_string()))?;
+        let input = self.inputs[idx].clone();
+        let uri = self.get_input_text(&input);
+        let source = uri
+            .parse::<FlakeRef>()
+            .ok()
+            .as_ref()
+            .and_then(CommitSource::from_flake_ref)
+            .ok_or_else(|| PinBeforeError::NotForge {
+                id: id.to_string(),
+                uri: uri.clone(),
+            })?;
+        let rev =
+            self.client
+                .commit_before(&source, date)
+                .map_err(|source| PinBeforeError::Lookup {
+                    id: id.to_string(),
+                    date: *date,
+                    source,
+                })?;
+        self.change_input_to_rev(&input, &rev);
+        Ok(rev)
+    }
+
+    /// Point the input named `id` at the branch or tag `ref_str`,
+    /// dropping any rev it carried. Returns whether the url changed.
+    ///
+    /// Only `github:`, `gitlab:`, `sourcehut:` and indirect inputs take
+    /// a ref this way. An input pinned to a rev is left alone unless
+    /// `force` is set, so a reproducibility pin is never dropped by
+    /// accident.
+    ///
+    /// # Errors
+    ///
+    /// See [`RefUpdateError`].
+    pub fn update_input_to_ref(
+        &mut self,
+        id: &str,
+        ref_str: &str,
+        force: bool,
+    ) -> Result<bool, RefUpdateError> {
+        self.sort();
+        let idx = self
+            .get_index(id)
+            .ok_or_else(|| RefUpdateError::UnknownInput(id.to_string()))?;
+        let input = self.inputs[idx].clone();
+        let uri = self.get_input_text(&input);
+        let unsupported = || RefUpdateError::Unsupported {
+            id: id.to_string(),
+            uri: uri.clone(),
+        };
+        let mut parsed = uri.parse::<FlakeRef>().map_err(|_| unsupported())?;
+        if !matches!(
+            parsed.kind(),
+            FlakeRefType::GitForge(_) | FlakeRefType::Indirect { .. }
+        ) {
+            return Err(unsupported());
+        }
+        if let Some(rev) = parsed.rev()
+            && !force
+        {
+            return Err(RefUpdateError::PinnedToRev {
+                id: id.to_string(),
+                rev: rev.to_string(),
+            });
+        }
+        if parsed.rev().is_none() && parsed.ref_() == Some(ref_str) {
+            return Ok(false);
+        }
+        parsed.set_rev(None);
+        parsed.set_ref(Some(ref_str.to_string()));
+        self.update_input(input, &parsed.into_uri());
+        Ok(true)
+    }
+
     /// Remove any `?ref=` or `?rev=` pin from `id`.
     ///
     /// # Errors
@@ -221,7 +328,7 @@ impl Updater {
 
         for (input, plan) in results {
             let Some(plan) = plan else { continue };
-            if Self::print_update_status(
+            if self.print_update_status(
                 input.input.id.as_str(),
                 &plan.previous_ref,
                 &plan.final_change,
@@ -238,7 +345,7 @@ impl Updater {
 
     fn get_input_text(&self, input: &UpdateInput) -> String {
         let (start, end) = self.url_char_range(input);
-        self.text.slice(start..end).to_string()
+        unescape_string(&self.text.slice(start..end).to_string())
     }
 
     /// Rewrite `input`'s URL to pin it to `rev`.
@@ -275,6 +382,7 @@ impl Updater {
         self.inputs.sort();
     }
     fn update_input(&mut self, input: UpdateInput, change: &str) {
+        let change = &escape_string(change);
         let (start, end) = self.url_char_range(&input);
         let previous_len = (end - start) as i32;
         self.text.remove(start..end);
@@ -283,6 +391,21 @@ impl Updater {
     }
 }
 
+/// Why [`Updater::update_input_to_ref`] left an input alone.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum RefUpdateError {
+    /// No input with this id has an editable url.
+    #[error("input '{0}' has no pinnable URL (it may use follows or a non-standard format)")]
+    UnknownInput(String),
+    /// The url has no ref slot that tracks a branch or tag.
+    #[error("input '{id}' cannot track a branch or tag: {uri}")]
+    Unsupported { id: String, uri: String },
+    /// The input is pinned to a commit and `force` was not set.
+    #[error("input '{id}' is pinned to rev {rev}")]
+    PinnedToRev { id: String, rev: String },
+}
+
 /// Wrapper that lets [`Updater`] sort inputs by source position.
 #[derive(Debug, Clone)]
 pub(crate) struct UpdateInput {
@@ -723,3 +846,40 @@ fn compute_semver_change(
         updated_uri,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::FlakeEdit;
+
+    #[test]
+    fn update_inputs_to_latest_semver_rewrites_only_the_named_inputs() {
+        let flake = r#"{
+  inputs = {
+    foo.url = "github:foo/foo/v1.0.0";
+    bar.url = "github:bar/bar/v1.0.0";
+    baz.url = "github:baz/baz/v1.0.0";
+  };
+
+  outputs = { self, foo, bar, baz }: { };
+}
+"#;
+        let inputs = FlakeEdit::from_text(flake).unwrap().list().clone();
+        let client = ForgeClient::new()
+            .with_github_tags("foo", "foo", &["v1.0.0", "v1.2.0"])
+            .with_github_tags("bar", "bar", &["v1.0.0", "v1.2.0"])
+            .with_github_tags("baz", "baz", &["v1.0.0", "v1.2.0"]);
+        let mut updater = Updater::new(Rope::from_str(flake), inputs)
+            .with_client(client)
+            .with_quiet(true);
+
+        updater.update_inputs_to_latest_semver(&["foo", "baz"], false);
+
+        assert_eq!(
+            updater.get_changes(),
+            flake
+                .replace("github:foo/foo/v1.0.0", "github:foo/foo/v1.2.0")
+                .replace("github:baz/baz/v1.0.0", "github:baz/baz/v1.2.0")
+        );
+    }
+}
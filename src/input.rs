@@ -1,4 +1,6 @@
+use nix_uri::{FlakeRef, NixUriError};
 use rnix::TextRange;
+use ropey::Rope;
 
 use crate::follows::{AttrPath, Segment, strip_outer_quotes};
 
@@ -11,6 +13,10 @@ pub struct Input {
     pub(crate) url: String,
     pub(crate) follows: Vec<Follows>,
     pub range: Range,
+    /// The url is an expression (a `let`-bound name, an `inherit`, a
+    /// select) rather than a string literal. `url` then holds its source
+    /// text, which is not a flake reference.
+    pub(crate) unresolved: bool,
 }
 
 /// Source byte range, half-open: `[start, end)`.
@@ -33,6 +39,16 @@ impl Range {
     pub fn is_empty(&self) -> bool {
         self.start == 0 && self.end == 0
     }
+
+    /// 1-indexed line and column of the range's start in `rope`, the text
+    /// it was recorded from. The column counts characters, so a url after
+    /// a non-ASCII key still reports the column an editor shows. A start
+    /// past the end of `rope` reports the end.
+    pub fn line_col(&self, rope: &Rope) -> (usize, usize) {
+        let char_idx = rope.byte_to_char(self.start.min(rope.len_bytes()));
+        let line = rope.char_to_line(char_idx);
+        (line + 1, char_idx - rope.line_to_char(line) + 1)
+    }
 }
 
 /// A `follows` declaration on an [`Input`].
@@ -69,18 +85,27 @@ impl Input {
             url: String::new(),
             follows: Vec::new(),
             range: Range::default(),
+            unresolved: false,
         }
     }
 
     /// Build an [`Input`] with `id`, `url`, and the range derived from
-    /// `text_range`. Surrounding double-quotes on `url` are stripped.
+    /// `text_range`. Surrounding double-quotes on `url` are stripped and
+    /// its escape sequences decoded. Anything but a string literal is kept
+    /// verbatim and marks the input [unresolved](Self::is_unresolved).
     pub(crate) fn with_url(id: Segment, url: String, text_range: TextRange) -> Self {
+        let unresolved = !url.starts_with('"') || is_interpolated(&url);
         Self {
             id,
             flake: true,
-            url: strip_outer_quotes(&url).to_string(),
+            url: if unresolved {
+                url
+            } else {
+                unescape_string(strip_outer_quotes(&url))
+            },
             follows: Vec::new(),
             range: Range::from_text_range(text_range),
+            unresolved,
         }
     }
 
@@ -91,14 +116,29 @@ impl Input {
     pub fn url(&self) -> &str {
         self.url.as_ref()
     }
+
+    /// Parse [`Input::url`] as a flake reference.
+    pub fn url_parsed(&self) -> Result<FlakeRef, NixUriError> {
+        self.url.parse()
+    }
+
     pub fn follows(&self) -> &Vec<Follows> {
         self.follows.as_ref()
     }
 
+    /// True if the url is not a string literal but an expression whose
+    /// value flake-edit does not evaluate, e.g. `url = nixpkgsUrl;` under a
+    /// `let` or `inherit url;`. [`Self::url`] is then the expression's
+    /// source text.
+    pub fn is_unresolved(&self) -> bool {
+        self.unresolved
+    }
+
     /// True if the URL can be rewritten in place. False for synthetic inputs
-    /// without a known source range.
+    /// without a known source range, and for [unresolved](Self::is_unresolved)
+    /// urls.
     pub fn has_editable_url(&self) -> bool {
-        !self.url.is_empty() && !self.range.is_empty()
+        !self.url.is_empty() && !self.range.is_empty() && !self.unresolved
     }
 
     /// Append an `Indirect` follows entry and re-normalize the follows vec
@@ -111,3 +151,135 @@ impl Input {
         self.follows.dedup();
     }
 }
+
+/// True if the source text of a `"..."` string splices in a `${...}`.
+fn is_interpolated(raw: &str) -> bool {
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '$' if chars.peek() == Some(&'{') => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Decode the body of a `"..."` string: `\n`, `\r` and `\t` become their
+/// control characters and `\<c>` becomes `c` for any other `c`.
+pub(crate) fn unescape_string(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Inverse of [`unescape_string`]: the body of a `"..."` string whose
+/// value is `s`, with `${` escaped so it is not read as interpolation.
+pub(crate) fn escape_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push_str(r"\\"),
+            '"' => out.push_str(r#"\""#),
+            '\n' => out.push_str(r"\n"),
+            '\r' => out.push_str(r"\r"),
+            '\t' => out.push_str(r"\t"),
+            '$' if chars.peek() == Some(&'{') => out.push_str(r"\$"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input_with_url(url: &str) -> Input {
+        let range = TextRange::new(0.into(), 0.into());
+        Input::with_url(Segment::from_unquoted("a").unwrap(), url.to_string(), range)
+    }
+
+    #[test]
+    fn url_parsed_strips_quotes() {
+        let input = input_with_url("\"github:nixos/nixpkgs/nixos-24.05\"");
+        let flake_ref = input.url_parsed().unwrap();
+        assert_eq!(flake_ref.id(), Some("nixpkgs"));
+        assert_eq!(flake_ref.ref_or_rev(), Some("nixos-24.05"));
+    }
+
+    #[test]
+    fn url_decodes_escape_sequences() {
+        let input = input_with_url(r#""github:nixos/nix\pkgs/nixos-24.05\t""#);
+        assert_eq!(input.url(), "github:nixos/nixpkgs/nixos-24.05\t");
+        let flake_ref = input_with_url(r#""github:nixos/nix\pkgs""#)
+            .url_parsed()
+            .unwrap();
+        assert_eq!(flake_ref.id(), Some("nixpkgs"));
+    }
+
+    #[test]
+    fn escape_string_round_trips() {
+        for s in [
+            "plain",
+            "a\"b",
+            "back\\slash",
+            "tab\there",
+            "new\nline",
+            "${x}",
+            "$x",
+        ] {
+            assert_eq!(unescape_string(&escape_string(s)), s, "{s:?}");
+        }
+        assert_eq!(escape_string("a\"b${c}"), r#"a\"b\${c}"#);
+    }
+
+    #[test]
+    fn non_literal_url_is_unresolved() {
+        let input = input_with_url("nixpkgsUrl");
+        assert!(input.is_unresolved());
+        assert_eq!(input.url(), "nixpkgsUrl");
+        assert!(!input.has_editable_url());
+        assert!(input_with_url("''github:nixos/nixpkgs''").is_unresolved());
+        assert!(input_with_url(r#""github:nixos/${branch}""#).is_unresolved());
+        assert!(!input_with_url(r#""github:nixos/nixpkgs""#).is_unresolved());
+        assert!(!input_with_url(r#""github:nixos/\${branch}""#).is_unresolved());
+    }
+
+    #[test]
+    fn line_col_maps_byte_offsets() {
+        let rope = Rope::from_str("{\n  inputs.é.url = \"x\";\n\n  b = 1;\n}\n");
+        let at = |start| Range { start, end: start }.line_col(&rope);
+        assert_eq!(at(0), (1, 1));
+        assert_eq!(at(1), (1, 2));
+        assert_eq!(at(2), (2, 1));
+        assert_eq!(at(4), (2, 3));
+        // `é` is two bytes but one column.
+        assert_eq!(at(14), (2, 12));
+        assert_eq!(at(24), (2, 22));
+        assert_eq!(at(25), (3, 1));
+        assert_eq!(at(28), (4, 3));
+        assert_eq!(at(1000), (6, 1));
+    }
+
+    #[test]
+    fn url_parsed_rejects_malformed_url() {
+        assert!(input_with_url("\"github:\"").url_parsed().is_err());
+    }
+}
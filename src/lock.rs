@@ -1,6 +1,6 @@
 use crate::error::Error;
 use crate::follows::{AttrPath, Segment};
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
@@ -49,6 +49,56 @@ pub enum LockError {
     /// A locked block has no `rev`.
     #[error("locked node has no rev")]
     LockedHasNoRev,
+    /// A locked block of a fetcher type that never records a `rev`
+    /// (`path`, `tarball`, `file`).
+    #[error("{node_type} inputs are locked without a rev")]
+    TypeHasNoRev { node_type: String },
+    /// A locked block has no `lastModified`.
+    #[error("locked node has no lastModified timestamp")]
+    LockedHasNoLastModified,
+    /// A top-level input is a follows of another input rather than a
+    /// node of its own.
+    #[error("input '{path}' follows another input in flake.lock")]
+    InputFollows { path: String },
+    /// A node's `original` reference is missing or of an unknown type,
+    /// so no flake URL can be rebuilt from it.
+    #[error("lockfile node '{node}' has no original reference to rebuild a url from")]
+    NoOriginal { node: String },
+}
+
+impl LockError {
+    /// Name of the variant, for the `kind` of a `--output json` error
+    /// report.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Parse { .. } => "Parse",
+            Self::MissingRoot { .. } => "MissingRoot",
+            Self::RootHasNoInputs { .. } => "RootHasNoInputs",
+            Self::InputHasNoSubInputs { .. } => "InputHasNoSubInputs",
+            Self::InputNotFound { .. } => "InputNotFound",
+            Self::FollowsTargetMissing { .. } => "FollowsTargetMissing",
+            Self::NodeMissingForPath { .. } => "NodeMissingForPath",
+            Self::NodeMissing { .. } => "NodeMissing",
+            Self::FollowsCycle { .. } => "FollowsCycle",
+            Self::NodeNotLocked { .. } => "NodeNotLocked",
+            Self::LockedHasNoRev { .. } => "LockedHasNoRev",
+            Self::TypeHasNoRev { .. } => "TypeHasNoRev",
+            Self::LockedHasNoLastModified { .. } => "LockedHasNoLastModified",
+            Self::InputFollows { .. } => "InputFollows",
+            Self::NoOriginal { .. } => "NoOriginal",
+        }
+    }
+}
+
+/// A top-level input whose locked rev differs between two lockfiles, see
+/// [`FlakeLock::changed_revs`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RevChange {
+    pub id: String,
+    /// Rev in the reference lock, `None` when it has no such input.
+    pub from: Option<String>,
+    /// Rev in this lock.
+    pub to: Option<String>,
 }
 
 /// A nested input discovered in `flake.lock` with its existing follows
@@ -77,8 +127,8 @@ impl NestedInput {
     }
 }
 
-/// Parsed `flake.lock`. Loaded with [`Self::from_default_path`],
-/// [`Self::from_file`], or [`Self::read_from_str`].
+/// Parsed `flake.lock`. Loaded with [`Self::beside`], [`Self::from_file`],
+/// [`Self::from_default_path`], or [`Self::read_from_str`].
 #[derive(Debug, Deserialize)]
 pub struct FlakeLock {
     nodes: HashMap<String, Node>,
@@ -91,12 +141,21 @@ pub(crate) struct Node {
     inputs: Option<HashMap<String, Input>>,
     locked: Option<Locked>,
     original: Option<Original>,
+    /// `false` for inputs declared with `flake = false`.
+    flake: Option<bool>,
 }
 
 impl Node {
     fn rev(&self) -> Result<String, LockError> {
         self.locked.as_ref().ok_or(LockError::NodeNotLocked)?.rev()
     }
+
+    fn last_modified(&self) -> Result<u64, LockError> {
+        self.locked
+            .as_ref()
+            .ok_or(LockError::NodeNotLocked)?
+            .last_modified()
+    }
 }
 
 /// Reference from a node's `inputs` map.
@@ -169,17 +228,32 @@ impl<'de> Deserialize<'de> for Input {
     }
 }
 
-/// Locked metadata for a node. Only [`Self::rev`] is consumed by the
-/// crate; the other JSON coordinates (`owner`, `repo`, `type`, `narHash`,
-/// ...) are ignored on parse.
+/// Locked metadata for a node. Only [`Self::rev`] and the fetcher
+/// `type` are consumed by the crate; the other JSON coordinates
+/// (`owner`, `repo`, `narHash`, ...) are ignored on parse.
 #[derive(Debug, Deserialize, Clone)]
 pub(crate) struct Locked {
     rev: Option<String>,
+    #[serde(rename = "type")]
+    node_type: Option<String>,
+    #[serde(rename = "lastModified")]
+    last_modified: Option<u64>,
 }
 
 impl Locked {
     fn rev(&self) -> Result<String, LockError> {
-        self.rev.clone().ok_or(LockError::LockedHasNoRev)
+        self.rev
+            .clone()
+            .ok_or_else(|| match self.node_type.as_deref() {
+                Some(node_type @ ("path" | "tarball" | "file")) => LockError::TypeHasNoRev {
+                    node_type: node_type.to_string(),
+                },
+                _ => LockError::LockedHasNoRev,
+            })
+    }
+
+    fn last_modified(&self) -> Result<u64, LockError> {
+        self.last_modified.ok_or(LockError::LockedHasNoLastModified)
     }
 }
 
@@ -191,16 +265,19 @@ pub(crate) enum Original {
         owner: String,
         repo: String,
         ref_field: Option<String>,
+        dir: Option<String>,
     },
     Gitlab {
         owner: String,
         repo: String,
         ref_field: Option<String>,
+        dir: Option<String>,
     },
     Sourcehut {
         owner: String,
         repo: String,
         ref_field: Option<String>,
+        dir: Option<String>,
     },
     Git {
         url: String,
@@ -246,6 +323,7 @@ impl<'de> Deserialize<'de> for Original {
             repo: String,
             #[serde(rename = "ref")]
             ref_field: Option<String>,
+            dir: Option<String>,
         }
         #[derive(Deserialize)]
         struct VcsPayload {
@@ -285,11 +363,13 @@ impl<'de> Deserialize<'de> for Original {
                     owner,
                     repo,
                     ref_field,
+                    dir,
                 } = payload(value)?;
                 Original::Github {
                     owner,
                     repo,
                     ref_field,
+                    dir,
                 }
             }
             "gitlab" => {
@@ -297,11 +377,13 @@ impl<'de> Deserialize<'de> for Original {
                     owner,
                     repo,
                     ref_field,
+                    dir,
                 } = payload(value)?;
                 Original::Gitlab {
                     owner,
                     repo,
                     ref_field,
+                    dir,
                 }
             }
             "sourcehut" => {
@@ -309,11 +391,13 @@ impl<'de> Deserialize<'de> for Original {
                     owner,
                     repo,
                     ref_field,
+                    dir,
                 } = payload(value)?;
                 Original::Sourcehut {
                     owner,
                     repo,
                     ref_field,
+                    dir,
                 }
             }
             "git" => {
@@ -346,6 +430,56 @@ impl<'de> Deserialize<'de> for Original {
 }
 
 impl Original {
+    /// Reconstruct a flake URL from the original reference, with `rev`
+    /// in place of any ref. Schemes without a rev slot (`tarball`,
+    /// `file`, `path`) ignore `rev`.
+    fn to_pinned_flake_url(&self, rev: &str) -> Option<String> {
+        match self {
+            Original::Github {
+                owner, repo, dir, ..
+            } => Some(forge_flake_url(
+                "github",
+                owner,
+                repo,
+                Some(rev),
+                dir.as_deref(),
+            )),
+            Original::Gitlab {
+                owner, repo, dir, ..
+            } => Some(forge_flake_url(
+                "gitlab",
+                owner,
+                repo,
+                Some(rev),
+                dir.as_deref(),
+            )),
+            Original::Sourcehut {
+                owner, repo, dir, ..
+            } => Some(forge_flake_url(
+                "sourcehut",
+                owner,
+                repo,
+                Some(rev),
+                dir.as_deref(),
+            )),
+            Original::Git { url, ref_field } | Original::Hg { url, ref_field } => {
+                let prefix = if matches!(self, Original::Git { .. }) {
+                    "git+"
+                } else {
+                    "hg+"
+                };
+                let url = prefixed_vcs_url(prefix, url, ref_field.as_deref());
+                let separator = if url.contains('?') { '&' } else { '?' };
+                Some(format!("{url}{separator}rev={rev}"))
+            }
+            Original::Indirect { id, .. } => Some(indirect_flake_url(id, Some(rev))),
+            Original::Tarball { .. }
+            | Original::File { .. }
+            | Original::Path { .. }
+            | Original::Unknown { .. } => self.to_flake_url(),
+        }
+    }
+
     /// Reconstruct a flake URL from the original reference. Returns
     /// `None` for [`Original::Unknown`], which also logs a
     /// `tracing::warn!` naming the unrecognized type.
@@ -355,21 +489,37 @@ impl Original {
                 owner,
                 repo,
                 ref_field,
-            } => Some(forge_flake_url("github", owner, repo, ref_field.as_deref())),
+                dir,
+            } => Some(forge_flake_url(
+                "github",
+                owner,
+                repo,
+                ref_field.as_deref(),
+                dir.as_deref(),
+            )),
             Original::Gitlab {
                 owner,
                 repo,
                 ref_field,
-            } => Some(forge_flake_url("gitlab", owner, repo, ref_field.as_deref())),
+                dir,
+            } => Some(forge_flake_url(
+                "gitlab",
+                owner,
+                repo,
+                ref_field.as_deref(),
+                dir.as_deref(),
+            )),
             Original::Sourcehut {
                 owner,
                 repo,
                 ref_field,
+                dir,
             } => Some(forge_flake_url(
                 "sourcehut",
                 owner,
                 repo,
                 ref_field.as_deref(),
+                dir.as_deref(),
             )),
             Original::Git { url, ref_field } => {
                 Some(prefixed_vcs_url("git+", url, ref_field.as_deref()))
@@ -392,14 +542,28 @@ impl Original {
     }
 }
 
-/// Build a `<scheme>:<owner>/<repo>[/<ref>]` URL, the shape shared by
-/// `github`, `gitlab`, and `sourcehut` flakerefs.
-fn forge_flake_url(scheme: &str, owner: &str, repo: &str, ref_field: Option<&str>) -> String {
+/// Build a `<scheme>:<owner>/<repo>[/<ref>][?dir=<dir>]` URL, the shape
+/// shared by `github`, `gitlab`, and `sourcehut` flakerefs.
+///
+/// Nix lifts `?dir=` out of forge URLs into a separate `dir` field of the
+/// lock's `original`, so it has to be put back here. Dropping it would
+/// point a subflake input at the repository root flake instead.
+fn forge_flake_url(
+    scheme: &str,
+    owner: &str,
+    repo: &str,
+    ref_field: Option<&str>,
+    dir: Option<&str>,
+) -> String {
     let mut url = format!("{scheme}:{owner}/{repo}");
     if let Some(r) = ref_field {
         url.push('/');
         url.push_str(r);
     }
+    if let Some(d) = dir {
+        url.push_str("?dir=");
+        url.push_str(d);
+    }
     url
 }
 
@@ -440,13 +604,39 @@ thread_local! {
 impl FlakeLock {
     const LOCK: &'static str = "flake.lock";
 
-    /// Load `flake.lock` from the current directory.
+    /// Load `flake.lock` from the current directory. Prefer
+    /// [`Self::beside`] when the flake is not necessarily in the current
+    /// directory.
+    ///
+    /// The binary no longer calls this, since it reads the lock next to
+    /// `--flake`. It stays as the library's shorthand for the common case
+    /// of a flake in the working directory.
     pub fn from_default_path() -> Result<Self, Error> {
-        let path = PathBuf::from(Self::LOCK);
-        Self::from_file(path)
+        Self::from_file(Self::LOCK)
+    }
+
+    /// Path of the `flake.lock` that belongs to the flake at `flake`: the
+    /// one in the same directory.
+    pub fn path_beside(flake: impl AsRef<Path>) -> PathBuf {
+        match flake.as_ref().parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.join(Self::LOCK),
+            _ => PathBuf::from(Self::LOCK),
+        }
+    }
+
+    /// Load the `flake.lock` next to the flake at `flake`, see
+    /// [`Self::path_beside`].
+    pub fn beside(flake: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_file(Self::path_beside(flake))
     }
 
     /// Load and parse a lockfile from `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Read`] if the file cannot be read and
+    /// [`Error::MalformedLock`], positioned at the failure, if it is not a
+    /// valid lockfile.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         let path = path.as_ref();
         let mut file = File::open(path).map_err(|source| Error::Read {
@@ -459,7 +649,7 @@ impl FlakeLock {
                 path: path.to_path_buf(),
                 source,
             })?;
-        Self::read_from_str(&contents)
+        serde_json::from_str(&contents).map_err(|e| malformed_lock(path, &e))
     }
 
     /// Parse lockfile JSON from `str`.
@@ -568,6 +758,165 @@ impl FlakeLock {
         Ok(node.rev()?)
     }
 
+    /// Resolve `path` to the `lastModified` timestamp (seconds since the
+    /// Unix epoch) of its locked revision.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::rev_for`], with a missing `lastModified` in place
+    /// of a missing `rev`.
+    pub fn last_modified_for(&self, path: &AttrPath) -> Result<u64, Error> {
+        let node_name = self.resolve_input_path(path)?;
+        let node = self
+            .nodes
+            .get(&node_name)
+            .ok_or_else(|| LockError::NodeMissing {
+                node: node_name.clone(),
+            })?;
+        Ok(node.last_modified()?)
+    }
+
+    /// Ids of the root's inputs, sorted.
+    pub fn input_ids(&self) -> Vec<Segment> {
+        let mut ids: Vec<Segment> = self
+            .nodes
+            .get(&self.root)
+            .and_then(|root| root.inputs.as_ref())
+            .into_iter()
+            .flat_map(|inputs| inputs.keys())
+            .filter_map(|id| Segment::from_unquoted(id.clone()).ok())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Top-level inputs of this lock whose locked rev differs from the
+    /// one in `reference`, sorted by id.
+    ///
+    /// Inputs only `reference` has are not reported. Inputs locked
+    /// without a rev (`path:`, tarballs) compare equal to a missing one.
+    pub fn changed_revs(&self, reference: &FlakeLock) -> Vec<RevChange> {
+        self.input_ids()
+            .into_iter()
+            .filter_map(|id| {
+                let path = AttrPath::new(id.clone());
+                let from = reference.rev_for(&path).ok();
+                let to = self.rev_for(&path).ok();
+                (from != to).then(|| RevChange {
+                    id: id.as_str().to_string(),
+                    from,
+                    to,
+                })
+            })
+            .collect()
+    }
+
+    /// Flake URL pinning the top-level input `id` to its locked revision.
+    ///
+    /// Rebuilt from the node's `original` reference with the locked `rev`
+    /// in place of any ref: `github:nixos/nixpkgs/nixos-unstable` locked at
+    /// `abc` becomes `github:nixos/nixpkgs/abc`, a git url gains `?rev=abc`.
+    /// References without a rev (`path:`, tarballs) come back unpinned.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`Error::Lock`] when `id` is not a root input, follows
+    /// another input, or its node has no usable `original`.
+    pub fn uri_for(&self, id: &Segment) -> Result<String, Error> {
+        let node_name = self.root_input_node(id)?;
+        let node = self
+            .nodes
+            .get(&node_name)
+            .ok_or_else(|| LockError::NodeMissing {
+                node: node_name.clone(),
+            })?;
+        let original = node
+            .original
+            .as_ref()
+            .ok_or_else(|| LockError::NoOriginal {
+                node: node_name.clone(),
+            })?;
+        let url = match node
+            .locked
+            .as_ref()
+            .and_then(|locked| locked.rev.as_deref())
+        {
+            Some(rev) => original.to_pinned_flake_url(rev),
+            None => original.to_flake_url(),
+        };
+        Ok(url.ok_or(LockError::NoOriginal { node: node_name })?)
+    }
+
+    /// Flake URL the top-level input `id` was locked from, rebuilt from the
+    /// node's `original` reference without pinning it.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::uri_for`].
+    pub fn original_uri_for(&self, id: &Segment) -> Result<String, Error> {
+        let node_name = self.root_input_node(id)?;
+        let original = self
+            .nodes
+            .get(&node_name)
+            .and_then(|node| node.original.as_ref())
+            .and_then(Original::to_flake_url)
+            .ok_or(LockError::NoOriginal { node: node_name })?;
+        Ok(original)
+    }
+
+    /// Whether the top-level input `id` is a flake. Inputs missing from the
+    /// lock count as flakes, the Nix default.
+    pub fn input_is_flake(&self, id: &Segment) -> bool {
+        self.root_input_node(id)
+            .ok()
+            .and_then(|node_name| self.nodes.get(&node_name)?.flake)
+            .unwrap_or(true)
+    }
+
+    /// Node key of the top-level input `id`, refusing follows.
+    fn root_input_node(&self, id: &Segment) -> Result<String, LockError> {
+        let root = self.nodes.get(&self.root).ok_or(LockError::MissingRoot)?;
+        let inputs = root.inputs.as_ref().ok_or(LockError::RootHasNoInputs)?;
+        match inputs.get(id.as_str()) {
+            Some(Input::Direct(node_name)) => Ok(node_name.clone()),
+            Some(Input::Indirect(_)) => Err(LockError::InputFollows {
+                path: id.to_string(),
+            }),
+            None => Err(LockError::InputNotFound {
+                path: id.to_string(),
+            }),
+        }
+    }
+
+    /// Fetcher type (`github`, `git`, `tarball`, `path`, ...) of every
+    /// locked node, keyed by node name.
+    ///
+    /// Read from each node's `locked.type`. Nodes without a `locked`
+    /// entry or `type` (the root) are skipped.
+    pub fn input_types(&self) -> HashMap<String, String> {
+        self.nodes
+            .iter()
+            .filter_map(|(name, node)| {
+                let node_type = node.locked.as_ref()?.node_type.clone()?;
+                Some((name.clone(), node_type))
+            })
+            .collect()
+    }
+
+    /// Fetcher type of the node the top-level input `id` resolves to.
+    ///
+    /// `None` when the input is missing from the lock or its node has no
+    /// `locked.type`.
+    pub fn input_type(&self, id: &Segment) -> Option<&str> {
+        let node_name = self.resolve_input_path(&AttrPath::new(id.clone())).ok()?;
+        self.nodes
+            .get(&node_name)?
+            .locked
+            .as_ref()?
+            .node_type
+            .as_deref()
+    }
+
     /// All nested inputs reachable from the root, with their existing
     /// follows targets.
     ///
@@ -675,6 +1024,25 @@ impl FlakeLock {
     }
 }
 
+/// Build an [`Error::MalformedLock`] from a JSON failure on `path`.
+///
+/// `serde_json` appends ` at line L column C` to its message; the position
+/// moves into dedicated fields, so the suffix is dropped from `message`.
+fn malformed_lock(path: &Path, err: &serde_json::Error) -> Error {
+    let message = err.to_string();
+    let suffix = format!(" at line {} column {}", err.line(), err.column());
+    let message = message
+        .strip_suffix(&suffix)
+        .unwrap_or(&message)
+        .to_string();
+    Error::MalformedLock {
+        path: path.to_path_buf(),
+        line: err.line(),
+        column: err.column(),
+        message,
+    }
+}
+
 /// Maximum recursion depth for [`FlakeLock::nested_inputs`]. Backstops
 /// pathological cycles in malformed lockfiles.
 pub const NESTED_INPUTS_MAX_DEPTH: usize = 64;
@@ -838,6 +1206,42 @@ mod tests {
         let minimal_lock = minimal_lock();
         FlakeLock::read_from_str(minimal_lock).expect("Should be parsed correctly.");
     }
+    #[test]
+    fn beside_reads_the_lock_next_to_the_flake() {
+        let tmp = tempfile::tempdir().unwrap();
+        let flake = tmp.path().join("flake.nix");
+        std::fs::write(tmp.path().join("flake.lock"), minimal_lock()).unwrap();
+        assert_eq!(
+            FlakeLock::path_beside(&flake),
+            tmp.path().join("flake.lock")
+        );
+        FlakeLock::beside(&flake).expect("lock next to the flake");
+        assert_eq!(
+            FlakeLock::path_beside("flake.nix"),
+            PathBuf::from("flake.lock")
+        );
+    }
+
+    #[test]
+    fn from_file_truncated_json_reports_position() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("flake.lock");
+        std::fs::write(&path, "{\n  \"nodes\": {\n    \"root\": {\n").unwrap();
+
+        let err = FlakeLock::from_file(&path).expect_err("truncated lock must not parse");
+        let Error::MalformedLock {
+            path: err_path,
+            line,
+            column,
+            message,
+        } = err
+        else {
+            panic!("expected MalformedLock, got: {err:?}");
+        };
+        assert_eq!(err_path, path);
+        assert_eq!((line, column), (4, 0));
+        assert_eq!(message, "EOF while parsing an object");
+    }
     /// The lockfile's top-level `"version"` field is not validated. A
     /// wildly unsupported version (e.g. `99`) must still parse cleanly
     /// so this crate can read whatever shape Nix produces.
@@ -1281,6 +1685,124 @@ mod tests {
         }
     }
 
+    /// `subflake_parent.flake.lock` locks two subflakes of one repository
+    /// (`?dir=tools`, `?dir=docs`). Each carries its own `nixpkgs`; the
+    /// nested paths must stay attributed to the parent that declares them,
+    /// not collapse onto the shared repository.
+    #[test]
+    fn fixture_subflake_parent_attributes_nested_inputs() {
+        let lock_text = std::fs::read_to_string("tests/fixtures/subflake_parent.flake.lock")
+            .expect("fixture present");
+        let lock = FlakeLock::read_from_str(&lock_text).expect("fixture parses");
+        let nested: Vec<(String, Option<String>)> = lock
+            .nested_inputs()
+            .into_iter()
+            .map(|n| (n.path.to_string(), n.url))
+            .collect();
+        assert_eq!(
+            nested,
+            vec![
+                (
+                    "docs.nixpkgs".to_string(),
+                    Some("github:NixOS/nixpkgs/nixos-24.05".to_string())
+                ),
+                (
+                    "tools.flake-utils".to_string(),
+                    Some("github:numtide/flake-utils".to_string())
+                ),
+                (
+                    "tools.nixpkgs".to_string(),
+                    Some("github:NixOS/nixpkgs/nixpkgs-unstable".to_string())
+                ),
+            ],
+        );
+        let tools = lock.nodes.get("tools").expect("tools node");
+        assert_eq!(
+            tools.original.as_ref().and_then(Original::to_flake_url),
+            Some("github:acme/mono?dir=tools".to_string()),
+        );
+    }
+
+    #[test]
+    fn changed_revs_against_reference_lock() {
+        let read = |name: &str| {
+            let text = std::fs::read_to_string(format!("tests/fixtures/{name}.flake.lock"))
+                .expect("fixture present");
+            FlakeLock::read_from_str(&text).expect("fixture parses")
+        };
+        let current = read("root");
+        let reference = read("changed_since");
+
+        let changes = current.changed_revs(&reference);
+        let ids: Vec<&str> = changes.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["crane", "nixpkgs", "rust-overlay"]);
+        assert_eq!(
+            changes[1],
+            RevChange {
+                id: "nixpkgs".into(),
+                from: Some("0d2a8e5b3c1f4e6a7b8c9d0e1f2a3b4c5d6e7f80".into()),
+                to: Some("81e8f48ebdecf07aab321182011b067aafc78896".into()),
+            }
+        );
+        assert_eq!(changes[2].from, None);
+
+        // Only inputs of the current lock are reported.
+        let ids: Vec<String> = reference
+            .changed_revs(&current)
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids, vec!["crane", "nixpkgs"]);
+        assert!(current.changed_revs(&current).is_empty());
+    }
+
+    /// `non_flake_and_path.flake.lock` mixes a `flake = false` github node
+    /// (no `inputs`), an absolute `path:` node (no `rev`) and a relative
+    /// `path:` subflake (no `rev`, no `lastModified`, a `parent` field).
+    #[test]
+    fn fixture_non_flake_and_path_nodes() {
+        let lock_text = std::fs::read_to_string("tests/fixtures/non_flake_and_path.flake.lock")
+            .expect("fixture present");
+        let lock = FlakeLock::read_from_str(&lock_text).expect("fixture parses");
+        let seg = |id: &str| Segment::from_unquoted(id).unwrap();
+
+        assert!(!lock.input_is_flake(&seg("vmsh")));
+        assert!(!lock.input_is_flake(&seg("store")));
+        assert!(lock.input_is_flake(&seg("local")));
+        assert_eq!(
+            lock.rev_for(&"vmsh".parse().unwrap()).unwrap(),
+            "0aa1b1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8"
+        );
+        assert_eq!(
+            lock.uri_for(&seg("vmsh")).unwrap(),
+            "github:mic92/vmsh/0aa1b1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8"
+        );
+
+        for id in ["store", "local"] {
+            assert!(
+                matches!(
+                    lock.rev_for(&id.parse().unwrap()),
+                    Err(Error::Lock(LockError::TypeHasNoRev { ref node_type })) if node_type == "path"
+                ),
+                "{id}"
+            );
+        }
+        assert!(lock.last_modified_for(&"local".parse().unwrap()).is_err());
+        assert_eq!(lock.uri_for(&seg("store")).unwrap(), "path:/srv/data");
+        assert_eq!(lock.uri_for(&seg("local")).unwrap(), "path:./sub");
+        assert_eq!(lock.input_type(&seg("local")), Some("path"));
+
+        let nested: Vec<(String, Option<String>)> = lock
+            .nested_inputs()
+            .into_iter()
+            .map(|n| (n.path.to_string(), n.follows.map(|t| t.to_string())))
+            .collect();
+        assert_eq!(
+            nested,
+            vec![("local.nixpkgs".to_string(), Some("nixpkgs".to_string()))]
+        );
+    }
+
     /// `dot_ancestor_cycle.flake.lock` exercises the dotted-segment case:
     /// the lockfile node `hls-1.10` is reachable through the typed
     /// `AttrPath`, even though a literal dot in the segment forces source-
@@ -1447,6 +1969,22 @@ mod tests {
         );
     }
 
+    /// Nix moves `?dir=` of forge refs into a separate `dir` field;
+    /// without it the URL names the repository root flake.
+    #[test]
+    fn to_flake_url_forge_with_dir_appends_query() {
+        let o = Original::Gitlab {
+            owner: "acme".to_string(),
+            repo: "mono".to_string(),
+            ref_field: Some("main".to_string()),
+            dir: Some("tools".to_string()),
+        };
+        assert_eq!(
+            o.to_flake_url().as_deref(),
+            Some("gitlab:acme/mono/main?dir=tools"),
+        );
+    }
+
     #[test]
     fn to_flake_url_tarball_returns_url_unchanged() {
         let o = Original::Tarball {
@@ -1522,4 +2060,153 @@ mod tests {
             "error must name the missing field, got: {err}",
         );
     }
+
+    #[test]
+    fn input_types_classifies_each_locked_node() {
+        let lock = FlakeLock::read_from_str(
+            r#"{
+  "nodes": {
+    "nixpkgs": {
+      "locked": { "owner": "nixos", "repo": "nixpkgs", "rev": "abc", "type": "github" },
+      "original": { "owner": "nixos", "repo": "nixpkgs", "type": "github" }
+    },
+    "mprisd": {
+      "locked": { "rev": "def", "type": "git", "url": "https://forge.example/mprisd" },
+      "original": { "type": "git", "url": "https://forge.example/mprisd" }
+    },
+    "blobs": {
+      "locked": { "type": "tarball", "url": "https://example.org/blobs.tar.gz" },
+      "original": { "type": "tarball", "url": "https://example.org/blobs.tar.gz" }
+    },
+    "local": {
+      "locked": { "path": "/srv/local", "type": "path" },
+      "original": { "path": "/srv/local", "type": "path" }
+    },
+    "root": {
+      "inputs": { "nixpkgs": "nixpkgs", "mprisd": "mprisd", "blobs": "blobs", "local": "local" }
+    }
+  },
+  "root": "root",
+  "version": 7
+}"#,
+        )
+        .unwrap();
+
+        let types = lock.input_types();
+        let expected: HashMap<String, String> = [
+            ("nixpkgs", "github"),
+            ("mprisd", "git"),
+            ("blobs", "tarball"),
+            ("local", "path"),
+        ]
+        .into_iter()
+        .map(|(name, ty)| (name.to_string(), ty.to_string()))
+        .collect();
+        assert_eq!(types, expected);
+    }
+
+    #[test]
+    fn input_types_of_root_fixture_are_all_github() {
+        let lock = FlakeLock::read_from_str(minimal_lock()).unwrap();
+        let types = lock.input_types();
+        assert!(!types.is_empty());
+        assert!(!types.contains_key(lock.root()));
+        assert!(types.values().all(|ty| ty == "github"));
+    }
+
+    #[test]
+    fn uri_for_pins_locked_rev() {
+        let lock = FlakeLock::read_from_str(minimal_lock()).unwrap();
+        let id = Segment::from_unquoted("nixpkgs").unwrap();
+        assert_eq!(
+            lock.uri_for(&id).unwrap(),
+            "github:nixos/nixpkgs/c00d587b1a1afbf200b1d8f0b0e4ba9deb1c7f0e"
+        );
+        assert!(lock.input_is_flake(&id));
+        assert_eq!(lock.input_ids(), vec![id]);
+    }
+
+    #[test]
+    fn uri_for_follows_input_returns_error() {
+        let lock = r#"{
+  "nodes": {
+    "root": {
+      "inputs": { "nixpkgs": "nixpkgs", "unstable": ["nixpkgs"] }
+    },
+    "nixpkgs": {
+      "locked": { "lastModified": 1, "narHash": "", "owner": "o", "repo": "r", "rev": "abc", "type": "github" },
+      "original": { "owner": "o", "repo": "r", "type": "github" }
+    }
+  },
+  "root": "root",
+  "version": 7
+}"#;
+        let parsed = FlakeLock::read_from_str(lock).unwrap();
+        let err = parsed
+            .uri_for(&Segment::from_unquoted("unstable").unwrap())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Lock(LockError::InputFollows { ref path }) if path == "unstable"
+        ));
+    }
+
+    #[test]
+    fn uri_for_git_node_appends_rev() {
+        let lock = r#"{
+  "nodes": {
+    "root": {
+      "inputs": { "mprisd": "mprisd" }
+    },
+    "mprisd": {
+      "flake": false,
+      "locked": { "lastModified": 1, "narHash": "", "rev": "abc", "type": "git", "url": "https://git.example.org/mprisd" },
+      "original": { "ref": "main", "type": "git", "url": "https://git.example.org/mprisd" }
+    }
+  },
+  "root": "root",
+  "version": 7
+}"#;
+        let parsed = FlakeLock::read_from_str(lock).unwrap();
+        let id = Segment::from_unquoted("mprisd").unwrap();
+        assert_eq!(
+            parsed.uri_for(&id).unwrap(),
+            "git+https://git.example.org/mprisd?ref=main&rev=abc"
+        );
+        assert!(!parsed.input_is_flake(&id));
+    }
+
+    #[test]
+    fn last_modified_for_reads_locked_timestamp() {
+        let lock = FlakeLock::read_from_str(minimal_lock()).unwrap();
+        assert_eq!(
+            lock.last_modified_for(&"nixpkgs".parse().unwrap()).unwrap(),
+            1718714799
+        );
+    }
+
+    #[test]
+    fn last_modified_for_node_without_timestamp_returns_error() {
+        let lock = r#"{
+  "nodes": {
+    "root": {
+      "inputs": { "bare": "bare" }
+    },
+    "bare": {
+      "locked": { "owner": "o", "repo": "r", "rev": "abc", "type": "github" },
+      "original": { "owner": "o", "repo": "r", "type": "github" }
+    }
+  },
+  "root": "root",
+  "version": 7
+}"#;
+        let parsed = FlakeLock::read_from_str(lock).unwrap();
+        let err = parsed
+            .last_modified_for(&"bare".parse().unwrap())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Lock(LockError::LockedHasNoLastModified)
+        ));
+    }
 }
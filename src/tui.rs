@@ -12,6 +12,7 @@ pub mod workflow;
 
 pub use crate::cache::CacheConfig;
 pub use app::App;
+pub use components::list::InitialSelection;
 pub use run::run;
 pub use workflow::{AppResult, ConfirmResultAction, MultiSelectResultData, SingleSelectResult};
 
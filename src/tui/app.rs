@@ -6,7 +6,7 @@
 //! - Maintains global state (show_diff) across screens
 //! - Is fully testable with pure update() and render() functions
 
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::layout::Rect;
 
 use crate::cache::CacheConfig;
@@ -15,9 +15,10 @@ use crate::cli::Command;
 use crate::lock::NestedInput;
 
 use super::completions::uri_completion_items;
-use super::components::confirm::ConfirmAction;
-use super::components::input::{Input, InputAction, InputResult, InputState};
-use super::components::list::{ListAction, ListResult, ListState};
+use super::components::confirm::{CONFIRM_BINDINGS, ConfirmAction};
+use super::components::help::Help;
+use super::components::input::{INPUT_BINDINGS, Input, InputAction, InputResult, InputState};
+use super::components::list::{InitialSelection, ListAction, ListResult, ListState, list_bindings};
 use super::workflow::{AddStep, ConfirmResultAction, FollowStep, WorkflowData};
 
 // Re-export workflow types that are part of the public API
@@ -33,6 +34,8 @@ pub struct App {
     cache_config: CacheConfig,
     screen: Screen,
     data: WorkflowData,
+    /// Whether the key binding help covers the screen
+    help: bool,
 }
 
 /// The current screen being displayed
@@ -107,6 +110,7 @@ impl App {
                 uri: None,
                 id: None,
             },
+            help: false,
         }
     }
 
@@ -138,6 +142,7 @@ impl App {
                 input_uris,
                 all_inputs: input_ids,
             },
+            help: false,
         }
     }
 
@@ -161,6 +166,7 @@ impl App {
                 selected_inputs: Vec::new(),
                 all_inputs: inputs,
             },
+            help: false,
         }
     }
 
@@ -193,6 +199,7 @@ impl App {
                 input_uris: std::collections::HashMap::new(),
                 all_inputs: Vec::new(),
             },
+            help: false,
         }
     }
 
@@ -212,6 +219,7 @@ impl App {
             data: WorkflowData::SelectOne {
                 selected_input: None,
             },
+            help: false,
         }
     }
 
@@ -231,6 +239,7 @@ impl App {
             data: WorkflowData::SelectMany {
                 selected_inputs: Vec::new(),
             },
+            help: false,
         }
     }
 
@@ -243,6 +252,7 @@ impl App {
             cache_config: CacheConfig::default(),
             screen: Screen::Confirm(ConfirmScreen { diff: diff.into() }),
             data: WorkflowData::ConfirmOnly { action: None },
+            help: false,
         }
     }
 
@@ -279,6 +289,7 @@ impl App {
                 nested_inputs,
                 top_level_inputs,
             },
+            help: false,
         }
     }
 
@@ -309,6 +320,7 @@ impl App {
                 nested_inputs: Vec::<NestedInput>::new(),
                 top_level_inputs,
             },
+            help: false,
         }
     }
 
@@ -347,11 +359,15 @@ impl App {
             }
 
             // Remove: interactive if no id provided
-            Command::Remove { id } => {
+            Command::Remove { id, selection } => {
                 if id.is_some() {
                     None
                 } else {
-                    Some(Self::remove("Remove", flake_text, input_ids).with_diff(diff))
+                    Some(
+                        Self::remove("Remove", flake_text, input_ids)
+                            .with_diff(diff)
+                            .with_initial_selection(selection.into()),
+                    )
                 }
             }
 
@@ -407,17 +423,25 @@ impl App {
                 }
             }
 
-            // Update: interactive if no id provided
-            Command::Update { id, .. } => {
-                if id.is_some() {
+            // Update: interactive if neither an id nor `--select` is given
+            Command::Update {
+                id,
+                select,
+                selection,
+                ..
+            } => {
+                if id.is_some() || !select.is_empty() {
                     None
                 } else {
-                    Some(Self::select_many(
-                        "Update",
-                        "Space select, U all, ^D diff",
-                        input_ids,
-                        diff,
-                    ))
+                    Some(
+                        Self::select_many(
+                            "Update",
+                            "Space select, U all, ^D diff",
+                            input_ids,
+                            diff,
+                        )
+                        .with_initial_selection(selection.into()),
+                    )
                 }
             }
 
@@ -426,7 +450,17 @@ impl App {
             | Command::Completion { .. }
             | Command::Follow { .. }
             | Command::AddFollow { .. }
+            | Command::Unfollow { .. }
+            | Command::Copy { .. }
             | Command::Toggle { .. }
+            | Command::Override { .. }
+            | Command::Rename { .. }
+            | Command::Prune { .. }
+            | Command::Status
+            | Command::Doctor { .. }
+            | Command::Lint
+            | Command::Validate { .. }
+            | Command::Watch { .. }
             | Command::Config { .. } => None,
         }
     }
@@ -443,16 +477,75 @@ impl App {
         &self.context
     }
 
+    /// Whether the key binding help overlay is shown
+    pub fn help_visible(&self) -> bool {
+        self.help
+    }
+
+    /// `(keys, effect)` rows of the current screen's key bindings
+    pub fn help_bindings(&self) -> Vec<(&'static str, &'static str)> {
+        match &self.screen {
+            Screen::Input(_) => INPUT_BINDINGS.to_vec(),
+            Screen::List(s) => list_bindings(s.state.multi_select(), s.state.search_active()),
+            Screen::Confirm(_) => CONFIRM_BINDINGS.to_vec(),
+        }
+    }
+
+    /// `F1` anywhere, and `?` where it is not text: on a list outside of
+    /// search and on the confirm screen.
+    fn is_help_key(&self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::F(1) => true,
+            KeyCode::Char('?') => match &self.screen {
+                Screen::List(s) => !s.state.search_active(),
+                Screen::Confirm(_) => true,
+                Screen::Input(_) => false,
+            },
+            _ => false,
+        }
+    }
+
     /// Compute the diff string for the current change against the flake text.
     /// Returns the unified diff showing what would change.
     ///
     /// This looks at the current screen state (including list selections)
-    /// to compute a live preview of what would happen.
+    /// to compute a live preview of what would happen. While a URI is
+    /// being typed and does not parse yet, the preview lists what is
+    /// missing from it instead.
     pub fn pending_diff(&self) -> String {
+        if let Some(uri) = self.pending_uri() {
+            let (_, diagnostics) = crate::uri::parse_partial(uri);
+            if !diagnostics.is_empty() {
+                return diagnostics
+                    .iter()
+                    .map(|d| format!("{uri}: {d}"))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+            }
+        }
         let change = self.build_preview_change();
         self.compute_diff(&change)
     }
 
+    /// The URI currently being typed, on the input screens that ask for one.
+    fn pending_uri(&self) -> Option<&str> {
+        let Screen::Input(screen) = &self.screen else {
+            return None;
+        };
+        let text = screen.state.text();
+        match &self.data {
+            WorkflowData::Add {
+                step: AddStep::Uri, ..
+            }
+            | WorkflowData::Change { .. }
+                if !text.is_empty() =>
+            {
+                Some(text)
+            }
+            _ => None,
+        }
+    }
+
     /// Build a Change based on current screen state for live preview.
     /// Unlike build_change(), this looks at current screen input/selections.
     fn build_preview_change(&self) -> Change {
@@ -465,11 +558,16 @@ impl App {
                 }
                 match &self.data {
                     WorkflowData::Add { step, uri, .. } => match step {
-                        AddStep::Uri => Change::Add {
-                            id: None,
-                            uri: Some(current_text.to_string()),
-                            flake: true,
-                        },
+                        // Preview under the id the next step will offer.
+                        AddStep::Uri => {
+                            let (inferred_id, uri) = Self::parse_uri_and_infer_id(current_text);
+                            Change::Add {
+                                id: inferred_id
+                                    .and_then(|id| crate::change::ChangeId::parse(&id).ok()),
+                                uri: Some(uri),
+                                flake: true,
+                            }
+                        }
                         AddStep::Id => Change::Add {
                             id: crate::change::ChangeId::parse(current_text).ok(),
                             uri: uri.clone(),
@@ -481,6 +579,7 @@ impl App {
                             .as_deref()
                             .and_then(|s| crate::change::ChangeId::parse(s).ok()),
                         uri: Some(current_text.to_string()),
+                        rename: None,
                     },
                     _ => self.build_change(),
                 }
@@ -557,7 +656,25 @@ impl App {
         self
     }
 
+    /// Preseed the selection of a multi-select list screen.
+    pub fn with_initial_selection(mut self, selection: InitialSelection) -> Self {
+        if let Screen::List(ref mut screen) = self.screen {
+            screen.state.set_selection(selection);
+        }
+        self
+    }
+
     pub fn update(&mut self, key: KeyEvent) -> UpdateResult {
+        // The help overlay swallows the key that closes it, so the
+        // screen below is left exactly as it was.
+        if self.help {
+            self.help = false;
+            return UpdateResult::Continue;
+        }
+        if self.is_help_key(key) {
+            self.help = true;
+            return UpdateResult::Continue;
+        }
         let screen = self.screen.clone();
         match screen {
             Screen::Input(s) => self.update_input(s, key),
@@ -883,6 +1000,9 @@ impl App {
     }
 
     pub fn cursor_position(&self, area: Rect) -> Option<(u16, u16)> {
+        if self.help {
+            return None;
+        }
         match &self.screen {
             Screen::Input(screen) => {
                 let input = Input::new(
@@ -898,7 +1018,25 @@ impl App {
         }
     }
 
+    /// Rows the inline viewport needs for the current screen, including
+    /// the live diff preview below input and list screens.
     pub fn terminal_height(&self) -> u16 {
+        let mut height = self.screen_height();
+        if self.show_diff && !matches!(self.screen, Screen::Confirm(_)) {
+            let diff = self.pending_diff();
+            if !diff.is_empty() {
+                height += super::helpers::layouts::diff_preview_height(diff.lines().count());
+            }
+        }
+        if self.help {
+            let bindings = self.help_bindings();
+            height.max(Help::new(&bindings, &self.context).required_height())
+        } else {
+            height
+        }
+    }
+
+    fn screen_height(&self) -> u16 {
         match &self.screen {
             Screen::Input(screen) => {
                 let input = Input::new(
@@ -8,7 +8,7 @@
 
 use std::collections::HashSet;
 
-use crate::cache::{Cache, CacheConfig, DEFAULT_URI_TYPES};
+use crate::cache::{CacheConfig, DEFAULT_URI_TYPES};
 
 /// Extract the owner/org prefix from a flake URI.
 ///
@@ -88,13 +88,7 @@ pub fn uri_completion_items(id: Option<&str>, cache_config: &CacheConfig) -> Vec
     // Track seen items for O(1) deduplication instead of O(n) contains() checks
     let mut seen: HashSet<String> = HashSet::new();
 
-    let cache = match cache_config {
-        CacheConfig::Default => Some(Cache::load()),
-        CacheConfig::Custom(path) => Some(Cache::from_path(path)),
-        CacheConfig::None => None,
-    };
-
-    if let Some(cache) = cache {
+    if let Some(cache) = cache_config.load() {
         let cached_uris = cache.list_uris();
 
         // Prepend ID-specific URIs (for change workflow)
@@ -146,6 +140,31 @@ pub fn uri_completion_items(id: Option<&str>, cache_config: &CacheConfig) -> Vec
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::Cache;
+
+    #[test]
+    fn completions_read_only_the_scoped_project() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("cache.json");
+        let mut cache = Cache::default().scoped(Some("a".into()));
+        cache.add_entry("crane".into(), "github:ipetkov/crane".into());
+        let mut cache = cache.scoped(Some("b".into()));
+        cache.add_entry("vmsh".into(), "github:mic92/vmsh".into());
+        std::fs::write(&path, serde_json::to_string(&cache).unwrap()).unwrap();
+
+        let scoped = |namespace: &str| {
+            uri_completion_items(
+                None,
+                &CacheConfig::Custom(path.clone()).scoped(Some(namespace.into())),
+            )
+        };
+        let a = scoped("a");
+        assert!(a.contains(&"github:ipetkov/crane".to_string()));
+        assert!(!a.iter().any(|item| item.contains("mic92")));
+        let b = scoped("b");
+        assert!(b.contains(&"github:mic92/vmsh".to_string()));
+        assert!(!b.iter().any(|item| item.contains("crane")));
+    }
 
     #[test]
     fn test_extract_owner_prefix_github() {
@@ -1,4 +1,5 @@
 pub mod confirm;
 pub mod footer;
+pub mod help;
 pub mod input;
 pub mod list;
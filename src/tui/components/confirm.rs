@@ -1,5 +1,5 @@
 mod model;
 mod view;
 
-pub use model::ConfirmAction;
+pub use model::{CONFIRM_BINDINGS, ConfirmAction};
 pub use view::Confirm;
@@ -20,6 +20,15 @@ impl ConfirmAction {
     }
 }
 
+/// Key bindings of the confirm screen as `(keys, effect)` rows for the
+/// help overlay. Mirrors [`ConfirmAction::from_key`].
+pub const CONFIRM_BINDINGS: &[(&str, &str)] = &[
+    ("y Enter", "apply"),
+    ("b Esc", "go back"),
+    ("n q", "exit"),
+    ("? F1", "show this help"),
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
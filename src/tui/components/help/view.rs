@@ -0,0 +1,65 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+
+use crate::tui::components::footer::Footer;
+use crate::tui::helpers::{context_span, layouts};
+use crate::tui::style::{BORDER_STYLE, HIGHLIGHT_STYLE};
+
+/// Read-only overlay listing the key bindings of the current screen
+pub struct Help<'a> {
+    bindings: &'a [(&'a str, &'a str)],
+    context: &'a str,
+}
+
+impl<'a> Help<'a> {
+    pub fn new(bindings: &'a [(&'a str, &'a str)], context: &'a str) -> Self {
+        Self { bindings, context }
+    }
+
+    /// Height needed to show every binding: one row each, plus the
+    /// borders and the footer
+    pub fn required_height(&self) -> u16 {
+        self.bindings.len() as u16 + 3
+    }
+}
+
+impl Widget for Help<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let (content_area, footer_area) = layouts::content_with_footer(area);
+
+        let key_width = self
+            .bindings
+            .iter()
+            .map(|(keys, _)| keys.chars().count())
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<Line> = self
+            .bindings
+            .iter()
+            .map(|(keys, effect)| {
+                Line::from(vec![
+                    Span::styled(format!(" {keys:<key_width$}  "), HIGHLIGHT_STYLE),
+                    Span::raw(*effect),
+                ])
+            })
+            .collect();
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::TOP | Borders::BOTTOM)
+                    .border_style(BORDER_STYLE),
+            )
+            .render(content_area, buf);
+
+        Footer::new(
+            vec![context_span(self.context), Span::raw(" Keys")],
+            vec![Span::raw("any key to close")],
+        )
+        .render(footer_area, buf);
+    }
+}
@@ -1,5 +1,5 @@
 mod model;
 mod view;
 
-pub use model::{InputAction, InputResult, InputState, MAX_VISIBLE_COMPLETIONS};
+pub use model::{INPUT_BINDINGS, InputAction, InputResult, InputState, MAX_VISIBLE_COMPLETIONS};
 pub use view::Input;
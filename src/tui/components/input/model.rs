@@ -52,6 +52,20 @@ impl InputAction {
     }
 }
 
+/// Key bindings of the input screen as `(keys, effect)` rows for the help
+/// overlay. Mirrors [`InputAction::from_key`]; `?` is text here.
+pub const INPUT_BINDINGS: &[(&str, &str)] = &[
+    ("Enter", "submit"),
+    ("Tab", "accept the completion"),
+    ("↑ ↓", "pick a completion"),
+    ("^A ^E", "jump to start or end"),
+    ("^B", "jump back a word"),
+    ("^U", "clear"),
+    ("Esc", "go back or cancel"),
+    ("^D", "toggle the diff preview"),
+    ("F1", "show this help"),
+];
+
 /// Result from input state machine
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum InputResult {
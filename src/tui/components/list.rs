@@ -1,5 +1,5 @@
 mod model;
 mod view;
 
-pub use model::{ListAction, ListResult, ListState};
+pub use model::{InitialSelection, ListAction, ListResult, ListState, list_bindings};
 pub use view::List;
@@ -1,14 +1,27 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
 use nucleo_matcher::{Config, Matcher, Utf32Str};
 
+use crate::cli::SelectionArgs;
+
+/// Items a half-page motion moves: half the tallest list the TUI draws.
+const HALF_PAGE: usize = 6;
+
 /// Actions that can be taken in a list selection UI
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ListAction {
     Up,
     Down,
+    /// Jump to the first item
+    Top,
+    /// Jump to the last item
+    Bottom,
+    /// Move up half a page, stopping at the first item
+    HalfPageUp,
+    /// Move down half a page, stopping at the last item
+    HalfPageDown,
     Select,
     ToggleDiff,
     Cancel,
@@ -29,15 +42,20 @@ impl ListAction {
     /// Map a key event to an action.
     ///
     /// The mapping depends on the mode. While searching, printable
-    /// characters (including the normal-mode bindings j/k/q/u) extend
+    /// characters (including the normal-mode bindings j/k/g/G/q/u) extend
     /// the query, Escape leaves search mode instead of cancelling, and
-    /// navigation moves to the arrow keys and Ctrl+J/Ctrl+K.
+    /// navigation moves to the arrow keys, Home/End, PageUp/PageDown and
+    /// Ctrl+J/Ctrl+K.
     pub fn from_key(key: KeyEvent, search_active: bool) -> Self {
         let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
         if search_active {
             return match key.code {
                 KeyCode::Up => ListAction::Up,
                 KeyCode::Down => ListAction::Down,
+                KeyCode::Home => ListAction::Top,
+                KeyCode::End => ListAction::Bottom,
+                KeyCode::PageUp => ListAction::HalfPageUp,
+                KeyCode::PageDown => ListAction::HalfPageDown,
                 KeyCode::Enter => ListAction::Select,
                 KeyCode::Esc => ListAction::SearchCancel,
                 KeyCode::Backspace => ListAction::SearchBackspace,
@@ -57,6 +75,13 @@ impl ListAction {
         match key.code {
             KeyCode::Up | KeyCode::Char('k') => ListAction::Up,
             KeyCode::Down | KeyCode::Char('j') => ListAction::Down,
+            KeyCode::Home | KeyCode::Char('g') => ListAction::Top,
+            KeyCode::End | KeyCode::Char('G') => ListAction::Bottom,
+            // Ctrl+D already toggles the diff on every screen, so the
+            // half page down of vim lives on PageDown only.
+            KeyCode::PageUp => ListAction::HalfPageUp,
+            KeyCode::Char('u') if ctrl => ListAction::HalfPageUp,
+            KeyCode::PageDown => ListAction::HalfPageDown,
             KeyCode::Enter => ListAction::Select,
             KeyCode::Esc | KeyCode::Char('q') => ListAction::Cancel,
             KeyCode::Char('d') if ctrl => ListAction::ToggleDiff,
@@ -68,6 +93,62 @@ impl ListAction {
     }
 }
 
+/// Key bindings of the list screen as `(keys, effect)` rows for the help
+/// overlay. Mirrors [`ListAction::from_key`].
+pub fn list_bindings(multi_select: bool, search_active: bool) -> Vec<(&'static str, &'static str)> {
+    let mut rows = if search_active {
+        vec![
+            ("↑ ^K", "move up"),
+            ("↓ ^J", "move down"),
+            ("Home End", "jump to the first / last item"),
+            ("PgUp PgDn", "move half a page"),
+            ("Enter", "select"),
+            ("Backspace", "delete from the search"),
+            ("Esc", "leave the search"),
+        ]
+    } else {
+        vec![
+            ("↑ k", "move up"),
+            ("↓ j", "move down"),
+            ("g G", "jump to the first / last item"),
+            ("^U PgUp", "move half a page up"),
+            ("PgDn", "move half a page down"),
+            ("Enter", "select"),
+            ("/", "search"),
+            ("Esc q", "cancel"),
+        ]
+    };
+    if multi_select {
+        rows.push(("Space", "toggle the item"));
+        if !search_active {
+            rows.push(("u", "toggle all items"));
+        }
+    }
+    rows.push(("^D", "toggle the diff preview"));
+    rows.push((if search_active { "F1" } else { "? F1" }, "show this help"));
+    rows
+}
+
+/// Which items a multi-select list starts out with selected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InitialSelection {
+    /// Start with nothing selected
+    #[default]
+    None,
+    /// Start with every item selected
+    All,
+}
+
+impl From<&SelectionArgs> for InitialSelection {
+    fn from(args: &SelectionArgs) -> Self {
+        if args.select_all() {
+            InitialSelection::All
+        } else {
+            InitialSelection::None
+        }
+    }
+}
+
 /// Result from list state machine
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ListResult {
@@ -94,6 +175,9 @@ pub struct ListState {
     query: Option<String>,
     /// Absolute indices of the items currently shown, best match first
     visible: Vec<usize>,
+    /// Char positions the query matched, by absolute index. Empty
+    /// without a query.
+    matches: HashMap<usize, Vec<u32>>,
 }
 
 impl ListState {
@@ -107,6 +191,7 @@ impl ListState {
             multi_select,
             query: None,
             visible,
+            matches: HashMap::new(),
         }
     }
 
@@ -142,6 +227,12 @@ impl ListState {
         self.visible.iter().map(|&i| (i, self.items[i].as_str()))
     }
 
+    /// Char positions in the item at absolute `index` that the search
+    /// query matched, in no particular order
+    pub fn match_indices(&self, index: usize) -> &[u32] {
+        self.matches.get(&index).map_or(&[], Vec::as_slice)
+    }
+
     /// Absolute index of the item under the cursor, `None` when the
     /// filtered list is empty
     pub fn highlighted(&self) -> Option<usize> {
@@ -156,6 +247,14 @@ impl ListState {
         self.show_diff = show_diff;
     }
 
+    /// Reset the selection to `selection`. Ignored for single-select lists.
+    pub fn set_selection(&mut self, selection: InitialSelection) {
+        self.selected = match selection {
+            InitialSelection::All if self.multi_select => (0..self.items.len()).collect(),
+            _ => HashSet::new(),
+        };
+    }
+
     pub fn is_selected(&self, index: usize) -> bool {
         self.selected.contains(&index)
     }
@@ -192,6 +291,12 @@ impl ListState {
                     self.cursor + 1
                 };
             }
+            ListAction::Top => self.cursor = 0,
+            ListAction::Bottom => self.cursor = self.visible.len().saturating_sub(1),
+            ListAction::HalfPageUp => self.cursor = self.cursor.saturating_sub(HALF_PAGE),
+            ListAction::HalfPageDown => {
+                self.cursor = (self.cursor + HALF_PAGE).min(self.visible.len().saturating_sub(1));
+            }
             ListAction::Toggle if self.multi_select => {
                 if let Some(&index) = self.visible.get(self.cursor) {
                     if !self.selected.remove(&index) {
@@ -261,30 +366,30 @@ impl ListState {
         None
     }
 
-    /// Recompute `visible` from the current query and reset the cursor.
+    /// Recompute `visible` and `matches` from the current query and reset
+    /// the cursor.
     ///
     /// Matching is fuzzy with smart case, ordered best score first.
     /// The sort is stable so equal scores keep the original list order.
     fn refilter(&mut self) {
         let query = self.query.as_deref().unwrap_or("");
+        self.matches.clear();
         if query.is_empty() {
             self.visible = (0..self.items.len()).collect();
         } else {
             let mut matcher = Matcher::new(Config::DEFAULT);
             let pattern = Pattern::parse(query, CaseMatching::Smart, Normalization::Smart);
             let mut char_buf: Vec<char> = Vec::new();
-            let mut scored: Vec<(u32, usize)> = self
-                .items
-                .iter()
-                .enumerate()
-                .filter_map(|(i, item)| {
-                    char_buf.clear();
-                    let haystack = Utf32Str::new(item, &mut char_buf);
-                    pattern
-                        .score(haystack, &mut matcher)
-                        .map(|score| (score, i))
-                })
-                .collect();
+            let mut scored: Vec<(u32, usize)> = Vec::new();
+            for (i, item) in self.items.iter().enumerate() {
+                char_buf.clear();
+                let haystack = Utf32Str::new(item, &mut char_buf);
+                let mut indices = Vec::new();
+                if let Some(score) = pattern.indices(haystack, &mut matcher, &mut indices) {
+                    scored.push((score, i));
+                    self.matches.insert(i, indices);
+                }
+            }
             scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
             self.visible = scored.into_iter().map(|(_, i)| i).collect();
         }
@@ -318,6 +423,93 @@ mod tests {
         assert_eq!(state.cursor(), 2); // Wrap around up
     }
 
+    #[test]
+    fn test_jump_motions() {
+        let mut state = ListState::new(items(20), false, false);
+        state.handle(ListAction::Bottom);
+        assert_eq!(state.cursor(), 19);
+        state.handle(ListAction::Bottom);
+        assert_eq!(state.cursor(), 19);
+        state.handle(ListAction::Top);
+        assert_eq!(state.cursor(), 0);
+        state.handle(ListAction::Top);
+        assert_eq!(state.cursor(), 0);
+    }
+
+    #[test]
+    fn test_half_page_motions_clamp() {
+        let mut state = ListState::new(items(20), false, false);
+        state.handle(ListAction::HalfPageDown);
+        assert_eq!(state.cursor(), HALF_PAGE);
+        for _ in 0..3 {
+            state.handle(ListAction::HalfPageDown);
+        }
+        assert_eq!(state.cursor(), 19, "half page down stops at the last item");
+        state.handle(ListAction::HalfPageUp);
+        assert_eq!(state.cursor(), 19 - HALF_PAGE);
+        for _ in 0..3 {
+            state.handle(ListAction::HalfPageUp);
+        }
+        assert_eq!(state.cursor(), 0, "half page up stops at the first item");
+    }
+
+    #[test]
+    fn test_motions_on_empty_filter() {
+        let mut state = ListState::new(items(20), false, false);
+        state.handle(ListAction::SearchStart);
+        state.handle(ListAction::SearchInput('z'));
+        for action in [
+            ListAction::Bottom,
+            ListAction::HalfPageDown,
+            ListAction::HalfPageUp,
+            ListAction::Top,
+        ] {
+            state.handle(action);
+            assert_eq!(state.cursor(), 0);
+        }
+    }
+
+    #[test]
+    fn test_vim_keys_only_outside_search() {
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        let plain = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        let shift = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::SHIFT);
+
+        assert_eq!(ListAction::from_key(plain('j'), false), ListAction::Down);
+        assert_eq!(ListAction::from_key(plain('k'), false), ListAction::Up);
+        assert_eq!(ListAction::from_key(plain('g'), false), ListAction::Top);
+        assert_eq!(ListAction::from_key(shift('G'), false), ListAction::Bottom);
+        assert_eq!(
+            ListAction::from_key(ctrl('u'), false),
+            ListAction::HalfPageUp
+        );
+        assert_eq!(
+            ListAction::from_key(ctrl('d'), false),
+            ListAction::ToggleDiff
+        );
+        assert_eq!(
+            ListAction::from_key(plain('u'), false),
+            ListAction::ToggleAll
+        );
+
+        for c in ['j', 'k', 'g', 'G'] {
+            assert_eq!(
+                ListAction::from_key(plain(c), true),
+                ListAction::SearchInput(c)
+            );
+        }
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        for (code, action) in [
+            (KeyCode::Home, ListAction::Top),
+            (KeyCode::End, ListAction::Bottom),
+            (KeyCode::PageUp, ListAction::HalfPageUp),
+            (KeyCode::PageDown, ListAction::HalfPageDown),
+        ] {
+            assert_eq!(ListAction::from_key(key(code), false), action);
+            assert_eq!(ListAction::from_key(key(code), true), action);
+        }
+    }
+
     #[test]
     fn test_single_select_toggle_diff() {
         let mut state = ListState::new(items(3), false, false);
@@ -364,6 +556,36 @@ mod tests {
         assert_eq!(state.selected_count(), 0);
     }
 
+    #[test]
+    fn test_initial_selection_all() {
+        let mut state = ListState::new(items(3), true, false);
+        state.set_selection(InitialSelection::All);
+        assert_eq!(state.selected_indices(), vec![0, 1, 2]);
+        assert_eq!(state.cursor(), 0);
+    }
+
+    #[test]
+    fn test_initial_selection_none() {
+        let mut state = ListState::new(items(3), true, false);
+        state.set_selection(InitialSelection::None);
+        assert!(state.selected_indices().is_empty());
+    }
+
+    #[test]
+    fn test_initial_selection_all_ignored_for_single_select() {
+        let mut state = ListState::new(items(3), false, false);
+        state.set_selection(InitialSelection::All);
+        assert!(state.selected_indices().is_empty());
+    }
+
+    #[test]
+    fn test_initial_selection_all_then_toggle_all_clears() {
+        let mut state = ListState::new(items(3), true, false);
+        state.set_selection(InitialSelection::All);
+        state.handle(ListAction::ToggleAll);
+        assert_eq!(state.selected_count(), 0);
+    }
+
     fn input_ids() -> Vec<String> {
         [
             "bookah",
@@ -538,6 +760,25 @@ mod tests {
         assert_eq!(visible_texts(&state), vec!["flake-fmt"]);
     }
 
+    #[test]
+    fn test_search_records_matched_chars() {
+        let mut state = ListState::new(input_ids(), false, false);
+        assert!(state.match_indices(8).is_empty());
+
+        state.handle(ListAction::SearchStart);
+        state.handle(ListAction::SearchInput('f'));
+        state.handle(ListAction::SearchInput('m'));
+        let mut matched = state.match_indices(8).to_vec();
+        matched.sort_unstable();
+        // "fm" in "flake-fmt": the contiguous "fm" outscores f..m
+        assert_eq!(matched, vec![6, 7]);
+        // Items the query filtered out carry no matches
+        assert!(state.match_indices(0).is_empty());
+
+        state.handle(ListAction::SearchCancel);
+        assert!(state.match_indices(8).is_empty());
+    }
+
     #[test]
     fn test_search_smart_case_matches_capitals() {
         let items = vec!["Flake-Fmt".to_string(), "bookah".to_string()];
@@ -8,7 +8,7 @@ use ratatui::{
 
 use super::model::ListState as SelectionState;
 use crate::tui::components::footer::Footer;
-use crate::tui::helpers::{checkbox_line, context_span, diff_toggle_style, layouts};
+use crate::tui::helpers::{checkbox_line, context_span, diff_toggle_style, layouts, match_spans};
 use crate::tui::style::{BORDER_STYLE, HIGHLIGHT_STYLE, HIGHLIGHT_SYMBOL};
 
 /// Parse an item string that may contain a follows indicator.
@@ -22,18 +22,23 @@ fn parse_follows_item(item: &str) -> (&str, Option<&str>) {
     }
 }
 
-/// Create a styled line for an item that may have a follows indicator.
-fn styled_item_line(item: &str) -> Line<'_> {
+/// Create a styled line for an item that may have a follows indicator,
+/// marking the chars at `matches`.
+fn styled_item_line<'a>(item: &'a str, matches: &[u32]) -> Line<'a> {
     let (path, follows) = parse_follows_item(item);
+    let mut spans = match_spans(path, matches, Style::default());
     if let Some(target) = follows {
-        Line::from(vec![
-            Span::raw(path),
-            Span::styled(" · ", Style::default().fg(Color::DarkGray)),
-            Span::styled(target, Style::default().fg(Color::DarkGray)),
-        ])
-    } else {
-        Line::raw(path)
+        // Match positions count the tab separating path and target.
+        let offset = path.chars().count() as u32 + 1;
+        let target_matches: Vec<u32> = matches
+            .iter()
+            .filter_map(|&m| m.checked_sub(offset))
+            .collect();
+        let dimmed = Style::default().fg(Color::DarkGray);
+        spans.push(Span::styled(" · ", dimmed));
+        spans.extend(match_spans(target, &target_matches, dimmed));
     }
+    Line::from(spans)
 }
 
 /// Unified list widget for single and multi-select
@@ -63,12 +68,18 @@ impl Widget for List<'_> {
         let list_items: Vec<ListItem> = if self.state.multi_select() {
             self.state
                 .visible_items()
-                .map(|(i, item)| ListItem::new(checkbox_line(item, self.state.is_selected(i))))
+                .map(|(i, item)| {
+                    ListItem::new(checkbox_line(
+                        item,
+                        self.state.is_selected(i),
+                        self.state.match_indices(i),
+                    ))
+                })
                 .collect()
         } else {
             self.state
                 .visible_items()
-                .map(|(_, item)| ListItem::new(styled_item_line(item)))
+                .map(|(i, item)| ListItem::new(styled_item_line(item, self.state.match_indices(i))))
                 .collect()
         };
 
@@ -112,6 +123,7 @@ impl Widget for List<'_> {
 mod tests {
     use super::*;
     use crate::tui::components::list::ListAction;
+    use ratatui::style::Modifier;
     use ratatui::{Terminal, backend::TestBackend};
 
     fn create_test_terminal(width: u16, height: u16) -> Terminal<TestBackend> {
@@ -274,6 +286,24 @@ mod tests {
         insta::assert_snapshot!(output);
     }
 
+    #[test]
+    fn test_styled_item_line_marks_matches_in_path_and_target() {
+        let line = styled_item_line("crane.nixpkgs\tnixpkgs", &[0, 1, 14]);
+        let matched: Vec<&str> = line
+            .spans
+            .iter()
+            .filter(|span| span.style.add_modifier.contains(Modifier::UNDERLINED))
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(matched, vec!["cr", "n"]);
+        let text: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(text, "crane.nixpkgs · nixpkgs");
+    }
+
     #[test]
     fn test_parse_follows_item_with_target() {
         let (path, follows) = parse_follows_item("crane.nixpkgs\tnixpkgs");
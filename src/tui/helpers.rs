@@ -5,7 +5,7 @@ use ratatui::{
 
 use super::style::{
     DIFF_ADD_STYLE, DIFF_HUNK_STYLE, DIFF_REMOVE_STYLE, HIGHLIGHT_COLOR, HIGHLIGHT_STYLE,
-    LABEL_STYLE,
+    LABEL_STYLE, LIST_MATCH_STYLE,
 };
 
 /// Color diff lines for display
@@ -51,16 +51,42 @@ pub(crate) fn context_span(context: &str) -> Span<'_> {
     Span::styled(format!(" {} ", context), LABEL_STYLE)
 }
 
+/// Split `text` into spans, patching [`LIST_MATCH_STYLE`] onto the chars
+/// at `matches`. Positions count chars from the start of `text`.
+pub(crate) fn match_spans<'a>(text: &'a str, matches: &[u32], style: Style) -> Vec<Span<'a>> {
+    let matched_style = style.patch(LIST_MATCH_STYLE);
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut run_matched = false;
+    for (pos, (byte, _)) in text.char_indices().enumerate() {
+        let matched = matches.contains(&(pos as u32));
+        if matched != run_matched && byte > start {
+            let run_style = if run_matched { matched_style } else { style };
+            spans.push(Span::styled(&text[start..byte], run_style));
+            start = byte;
+        }
+        run_matched = matched;
+    }
+    if start < text.len() {
+        let run_style = if run_matched { matched_style } else { style };
+        spans.push(Span::styled(&text[start..], run_style));
+    }
+    spans
+}
+
 /// Create a checkbox line for multi-select lists
-pub(crate) fn checkbox_line<'a>(text: &'a str, selected: bool) -> Line<'a> {
-    if selected {
-        Line::from(vec![
+pub(crate) fn checkbox_line<'a>(text: &'a str, selected: bool, matches: &[u32]) -> Line<'a> {
+    let (checkbox, text_style) = if selected {
+        (
             Span::styled("[x] ", HIGHLIGHT_STYLE),
-            Span::styled(text, Style::new().fg(HIGHLIGHT_COLOR)),
-        ])
+            Style::new().fg(HIGHLIGHT_COLOR),
+        )
     } else {
-        Line::from(vec![Span::raw("[ ] "), Span::raw(text)])
-    }
+        (Span::raw("[ ] "), Style::new())
+    };
+    let mut spans = vec![checkbox];
+    spans.extend(match_spans(text, matches, text_style));
+    Line::from(spans)
 }
 
 /// Standard layout helpers for consistent widget structure
@@ -89,11 +115,18 @@ pub(crate) mod layouts {
     /// Split area into main content area and diff preview area
     /// Returns (main_area, diff_area)
     pub(crate) fn content_with_diff_preview(area: Rect, diff_lines: usize) -> (Rect, Rect) {
-        // Reserve space for diff preview (borders + content lines)
         // Ensure main content gets at least 4 lines
-        let diff_height = (diff_lines as u16 + 2).max(4);
-        let chunks =
-            Layout::vertical([Constraint::Min(4), Constraint::Length(diff_height)]).split(area);
+        let chunks = Layout::vertical([
+            Constraint::Min(4),
+            Constraint::Length(diff_preview_height(diff_lines)),
+        ])
+        .split(area);
         (chunks[0], chunks[1])
     }
+
+    /// Height of the diff preview pane below a screen: borders plus
+    /// content lines, at least 4 and at most 20
+    pub(crate) fn diff_preview_height(diff_lines: usize) -> u16 {
+        (diff_lines as u16 + 2).clamp(4, 20)
+    }
 }
@@ -41,6 +41,11 @@ pub(crate) const COMPLETION_SELECTED_MATCH_STYLE: Style = Style::new()
     .fg(HIGHLIGHT_COLOR)
     .add_modifier(Modifier::BOLD);
 
+/// Style for characters a list search query matched
+pub(crate) const LIST_MATCH_STYLE: Style = Style::new()
+    .fg(HIGHLIGHT_COLOR)
+    .add_modifier(Modifier::UNDERLINED);
+
 /// Dimmed style for secondary text like descriptions
 pub(crate) const DIMMED_STYLE: Style = Style::new().fg(Color::DarkGray);
 
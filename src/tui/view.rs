@@ -8,6 +8,7 @@ use ratatui::{
 
 use super::app::{App, Screen};
 use super::components::confirm::Confirm;
+use super::components::help::Help;
 use super::components::input::Input;
 use super::components::list::List;
 use super::helpers::{color_diff_lines, layouts};
@@ -64,6 +65,9 @@ impl Widget for &App {
                 Confirm::new(&screen.diff, self.context()).render(area, buf);
             }
         }
+        if self.help_visible() {
+            Help::new(&self.help_bindings(), self.context()).render(area, buf);
+        }
     }
 }
 
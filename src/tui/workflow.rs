@@ -13,6 +13,7 @@ use crate::change::Change;
 use crate::diff::Diff;
 use crate::edit::FlakeEdit;
 use crate::lock::NestedInput;
+use crate::uri::infer_id;
 
 /// Result from single-select including selected item and whether diff preview is enabled
 #[derive(Debug, Clone)]
@@ -136,6 +137,7 @@ impl WorkflowData {
                     .as_deref()
                     .and_then(|s| crate::change::ChangeId::parse(s).ok()),
                 uri: uri.clone(),
+                rename: None,
             },
             WorkflowData::Remove {
                 selected_inputs, ..
@@ -187,7 +189,7 @@ impl WorkflowData {
 pub fn parse_uri_and_infer_id(uri: &str) -> (Option<String>, String) {
     match uri.parse::<FlakeRef>() {
         Ok(flake_ref) => {
-            let id = flake_ref.id().map(str::to_owned);
+            let id = infer_id(&flake_ref);
             (id, flake_ref.into_uri())
         }
         Err(_) => (None, uri.to_string()),
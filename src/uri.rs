@@ -1,3 +1,544 @@
+use nix_uri::{FlakeRef, FlakeRefType, NixUriError, ResourceType, UnsupportedReason};
+
 pub fn is_git_url(uri: &str) -> bool {
     uri.starts_with("git+https://") || uri.starts_with("git+http://")
 }
+
+/// Forge schemes whose references take an `<owner>/<repo>` path.
+const FORGE_SCHEMES: &[&str] = &["github", "gitlab", "sourcehut"];
+
+/// Scheme and owner of a forge reference that names only an owner, as in
+/// `github:nixos` or `github:nixos/`. nix-uri rejects both, but with a
+/// parser position rather than a word about the missing repository.
+pub fn forge_owner_without_repo(uri: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = uri.split_once(':')?;
+    if !FORGE_SCHEMES.contains(&scheme) {
+        return None;
+    }
+    let path = rest.split(['?', '#']).next().unwrap_or(rest);
+    let owner = path.strip_suffix('/').unwrap_or(path);
+    (!owner.is_empty() && !owner.contains('/')).then_some((scheme, owner))
+}
+
+/// A problem found by [`parse_partial`] in a flake reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseDiagnostic {
+    /// A forge reference names no owner (`github:`).
+    MissingOwner,
+    /// A forge reference names no repository (`github:nixos`).
+    MissingRepo,
+    /// The reference does not parse. Carries nix-uri's message.
+    Invalid(String),
+}
+
+impl std::fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingOwner => write!(f, "missing owner, expected `<scheme>:<owner>/<repo>`"),
+            Self::MissingRepo => {
+                write!(f, "missing repository, expected `<scheme>:<owner>/<repo>`")
+            }
+            Self::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+/// Parse a flake reference that may still be incomplete, as while it is
+/// being typed.
+///
+/// A complete reference comes back as-is with no diagnostics. A forge
+/// reference missing its owner or repository comes back with those fields
+/// left empty, one diagnostic per missing part. Anything else that fails
+/// to parse has no partial form and yields `None` with
+/// [`ParseDiagnostic::Invalid`].
+pub fn parse_partial(uri: &str) -> (Option<FlakeRef>, Vec<ParseDiagnostic>) {
+    let err = match uri.parse::<FlakeRef>() {
+        Ok(flake_ref) => return (Some(flake_ref), Vec::new()),
+        Err(err) => err,
+    };
+    let invalid = || (None, vec![ParseDiagnostic::Invalid(err.to_string())]);
+    let Some((scheme, rest)) = uri.split_once(':') else {
+        return invalid();
+    };
+    if !FORGE_SCHEMES.contains(&scheme) {
+        return invalid();
+    }
+    let split = rest.find(['?', '#']).unwrap_or(rest.len());
+    let (path, tail) = rest.split_at(split);
+    let (owner, repo) = path.split_once('/').unwrap_or((path, ""));
+    if !owner.is_empty() && !repo.is_empty() {
+        return invalid();
+    }
+
+    // Parse with stand-ins for the missing parts, then blank them again.
+    let mut diagnostics = Vec::new();
+    let owner = if owner.is_empty() {
+        diagnostics.push(ParseDiagnostic::MissingOwner);
+        "_"
+    } else {
+        owner
+    };
+    let repo = if repo.is_empty() {
+        diagnostics.push(ParseDiagnostic::MissingRepo);
+        "_"
+    } else {
+        repo
+    };
+    let Ok(mut flake_ref) = format!("{scheme}:{owner}/{repo}{tail}").parse::<FlakeRef>() else {
+        return invalid();
+    };
+    if let FlakeRefType::GitForge(forge) = flake_ref.kind_mut() {
+        if diagnostics.contains(&ParseDiagnostic::MissingOwner) {
+            forge.owner.clear();
+        }
+        if diagnostics.contains(&ParseDiagnostic::MissingRepo) {
+            forge.repo.clear();
+        }
+    }
+    (Some(flake_ref), diagnostics)
+}
+
+/// Validated construction of a forge [`FlakeRef`] from its parts, for
+/// callers assembling references from untrusted data instead of parsing
+/// a whole url.
+pub trait FlakeRefExt: Sized {
+    /// Build `<type_name>:<owner>/<repo>[/<ref_or_rev>][?<params>]`.
+    ///
+    /// `type_name` must be a forge scheme (`github`, `gitlab`,
+    /// `sourcehut`). `owner` and `repo` may only hold ASCII letters,
+    /// digits, `-`, `_` and `.` (plus a leading `~` on a sourcehut owner).
+    /// `ref_or_rev` must be a legal git ref name or a commit hash; a ref
+    /// containing `/` goes into `?ref=` so it is not read as path
+    /// segments. Parameter keys must be alphanumeric, and neither keys
+    /// nor values may contain `&`, `=`, `?`, `#` or whitespace.
+    ///
+    /// # Errors
+    ///
+    /// [`NixUriError::Unsupported`] for a non-forge `type_name`,
+    /// [`NixUriError::InvalidValue`] naming the first illegal part, or
+    /// whatever the parser rejects in the assembled reference.
+    fn try_from_parts(
+        type_name: &str,
+        owner: &str,
+        repo: &str,
+        ref_or_rev: Option<&str>,
+        params: &[(&str, &str)],
+    ) -> Result<Self, NixUriError>;
+}
+
+impl FlakeRefExt for FlakeRef {
+    fn try_from_parts(
+        type_name: &str,
+        owner: &str,
+        repo: &str,
+        ref_or_rev: Option<&str>,
+        params: &[(&str, &str)],
+    ) -> Result<Self, NixUriError> {
+        if !FORGE_SCHEMES.contains(&type_name) {
+            return Err(NixUriError::Unsupported(UnsupportedReason::UriType {
+                ty: type_name.to_string(),
+            }));
+        }
+        let bare_owner = match type_name {
+            "sourcehut" => owner.strip_prefix('~').unwrap_or(owner),
+            _ => owner,
+        };
+        check_name("owner", bare_owner)?;
+        check_name("repo", repo)?;
+
+        let mut query: Vec<String> = Vec::new();
+        let mut uri = format!("{type_name}:{owner}/{repo}");
+        if let Some(reference) = ref_or_rev {
+            check_ref(reference)?;
+            if reference.contains('/') {
+                query.push(format!("ref={reference}"));
+            } else {
+                uri.push('/');
+                uri.push_str(reference);
+            }
+        }
+        for (key, value) in params {
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(invalid_value("params", format!("illegal key `{key}`")));
+            }
+            if value.chars().any(is_query_delimiter) {
+                return Err(invalid_value(
+                    "params",
+                    format!("illegal value `{value}` for `{key}`"),
+                ));
+            }
+            query.push(format!("{key}={value}"));
+        }
+        if !query.is_empty() {
+            uri.push('?');
+            uri.push_str(&query.join("&"));
+        }
+        uri.parse()
+    }
+}
+
+fn invalid_value(field: &'static str, reason: impl Into<String>) -> NixUriError {
+    NixUriError::InvalidValue {
+        field,
+        reason: reason.into(),
+    }
+}
+
+fn is_query_delimiter(c: char) -> bool {
+    matches!(c, '&' | '=' | '?' | '#') || c.is_whitespace() || c.is_control()
+}
+
+/// An owner or repository name: non-empty, not `.` or `..`, and only
+/// ASCII letters, digits, `-`, `_` and `.`.
+fn check_name(field: &'static str, name: &str) -> Result<(), NixUriError> {
+    let legal = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+    if name.is_empty() {
+        return Err(invalid_value(field, "must not be empty"));
+    }
+    if name == "." || name == ".." || !name.chars().all(legal) {
+        return Err(invalid_value(
+            field,
+            format!("`{name}` may only contain ASCII letters, digits, `-`, `_` and `.`"),
+        ));
+    }
+    Ok(())
+}
+
+/// The subset of `git check-ref-format` a reference must pass to be
+/// written into a flake url.
+fn check_ref(reference: &str) -> Result<(), NixUriError> {
+    let illegal =
+        |c: char| is_query_delimiter(c) || matches!(c, '~' | '^' | ':' | '*' | '[' | '\\');
+    let malformed = reference.is_empty()
+        || reference.chars().any(illegal)
+        || reference.contains("..")
+        || reference.contains("//")
+        || reference.contains("@{")
+        || reference.starts_with(['/', '-', '.'])
+        || reference.ends_with(['/', '.'])
+        || reference.ends_with(".lock");
+    if malformed {
+        return Err(invalid_value(
+            "ref",
+            format!("`{reference}` is not a valid git ref name"),
+        ));
+    }
+    Ok(())
+}
+
+/// Archive suffixes stripped from a tarball's file name to get its id.
+/// Compound suffixes come first so `.tar.gz` wins over `.gz`.
+const ARCHIVE_SUFFIXES: &[&str] = &[
+    ".tar.gz", ".tar.xz", ".tar.bz2", ".tar.zst", ".tgz", ".txz", ".tbz2", ".tar", ".zip",
+];
+
+/// Input id to use when the user gave only a flake reference.
+///
+/// Forge and git refs name their repository. Refs without a repository
+/// fall back to what they point at: the registry id for indirect refs,
+/// the last path segment for hg and tarball urls (without `.git` or the
+/// archive suffix), and the final directory for path refs.
+pub fn infer_id(flake_ref: &FlakeRef) -> Option<String> {
+    if let Some(id) = flake_ref.id() {
+        return Some(id.to_string());
+    }
+    let name = match flake_ref.kind() {
+        FlakeRefType::Indirect { id, .. } => id.as_str(),
+        FlakeRefType::Resource(res) => {
+            let last = last_segment(&res.location)?;
+            match res.res_type {
+                ResourceType::Tarball | ResourceType::File => ARCHIVE_SUFFIXES
+                    .iter()
+                    .find_map(|suffix| last.strip_suffix(suffix))
+                    .unwrap_or(last),
+                _ => last.strip_suffix(".git").unwrap_or(last),
+            }
+        }
+        FlakeRefType::Path { path, .. } => last_segment(path)?,
+        _ => return None,
+    };
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// `uri` with its query parameters in the canonical, key-sorted order
+/// [`FlakeRef`] renders them in.
+///
+/// `None` when `uri` has no parameters, does not parse, or is already
+/// canonical, so references without a query are never rewritten.
+pub fn sort_params(uri: &str) -> Option<String> {
+    if !uri.contains('?') {
+        return None;
+    }
+    let sorted = uri.parse::<FlakeRef>().ok()?.to_string();
+    (sorted != uri).then_some(sorted)
+}
+
+/// Split a `flake=<bool>` query parameter off `uri`.
+///
+/// Nix reads `?flake=false` like the `flake = false;` attribute. The
+/// parameter is returned as that boolean along with `uri` minus it, the
+/// other parameters left as written. `None` when `uri` has no `flake`
+/// parameter or its value is not `true`, `false`, `1` or `0`.
+pub fn take_flake_param(uri: &str) -> Option<(String, bool)> {
+    let (location, rest) = uri.split_once('?')?;
+    let (query, fragment) = match rest.split_once('#') {
+        Some((query, fragment)) => (query, Some(fragment)),
+        None => (rest, None),
+    };
+    let mut flake = None;
+    let mut kept = Vec::new();
+    for pair in query.split('&') {
+        match pair.split_once('=') {
+            Some(("flake", value)) => {
+                flake = Some(match value {
+                    "true" | "1" => true,
+                    "false" | "0" => false,
+                    _ => return None,
+                });
+            }
+            _ => kept.push(pair),
+        }
+    }
+    let flake = flake?;
+    let mut uri = location.to_string();
+    if !kept.is_empty() {
+        uri.push('?');
+        uri.push_str(&kept.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        uri.push('#');
+        uri.push_str(fragment);
+    }
+    Some((uri, flake))
+}
+
+/// Last non-empty `/`-separated segment of `location`, ignoring any query
+/// or fragment.
+fn last_segment(location: &str) -> Option<&str> {
+    let location = location.split(['?', '#']).next().unwrap_or(location);
+    location.rsplit('/').find(|segment| !segment.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forge_owner_without_repo_matches_both_spellings() {
+        for uri in ["github:nixos", "github:nixos/", "github:nixos?ref=main"] {
+            assert_eq!(
+                forge_owner_without_repo(uri),
+                Some(("github", "nixos")),
+                "{uri}"
+            );
+        }
+        assert_eq!(
+            forge_owner_without_repo("sourcehut:~sircmpwn"),
+            Some(("sourcehut", "~sircmpwn"))
+        );
+        for uri in [
+            "github:nixos/nixpkgs",
+            "github:",
+            "flake:nixpkgs",
+            "nixpkgs",
+        ] {
+            assert_eq!(forge_owner_without_repo(uri), None, "{uri}");
+        }
+    }
+
+    #[test]
+    fn parse_partial_keeps_what_an_incomplete_forge_ref_names() {
+        use nix_uri::GitForgePlatform;
+
+        for (uri, owner, expected) in [
+            (
+                "github:",
+                "",
+                &[ParseDiagnostic::MissingOwner, ParseDiagnostic::MissingRepo][..],
+            ),
+            ("github:o", "o", &[ParseDiagnostic::MissingRepo][..]),
+            ("github:o/", "o", &[ParseDiagnostic::MissingRepo][..]),
+        ] {
+            let (flake_ref, diagnostics) = parse_partial(uri);
+            assert_eq!(diagnostics, expected, "{uri}");
+            let flake_ref = flake_ref.unwrap_or_else(|| panic!("{uri}: expected a partial ref"));
+            let FlakeRefType::GitForge(forge) = flake_ref.kind() else {
+                panic!("{uri}: expected a forge ref, got {flake_ref:?}");
+            };
+            assert_eq!(forge.platform, GitForgePlatform::GitHub, "{uri}");
+            assert_eq!(forge.owner, owner, "{uri}");
+            assert_eq!(forge.repo, "", "{uri}");
+        }
+
+        let (flake_ref, diagnostics) = parse_partial("gitlab:o?ref=main");
+        assert_eq!(diagnostics, [ParseDiagnostic::MissingRepo]);
+        assert_eq!(flake_ref.unwrap().ref_(), Some("main"));
+    }
+
+    #[test]
+    fn parse_partial_passes_complete_and_invalid_refs_through() {
+        let (flake_ref, diagnostics) = parse_partial("github:nixos/nixpkgs");
+        assert!(diagnostics.is_empty());
+        assert_eq!(flake_ref.unwrap().repo(), Some("nixpkgs"));
+
+        for uri in ["github:o/r/bad ref", "::"] {
+            let (flake_ref, diagnostics) = parse_partial(uri);
+            assert!(flake_ref.is_none(), "{uri}");
+            assert!(
+                matches!(diagnostics.as_slice(), [ParseDiagnostic::Invalid(_)]),
+                "{uri}: {diagnostics:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn infer_id_covers_refs_without_a_repo() {
+        for (uri, expected) in [
+            ("github:nix-community/crane", Some("crane")),
+            ("git+https://github.com/nix-community/crane", Some("crane")),
+            (
+                "git+https://github.com/nix-community/crane.git",
+                Some("crane"),
+            ),
+            ("flake:nixpkgs", Some("nixpkgs")),
+            ("nixpkgs/nixos-24.05", Some("nixpkgs")),
+            ("hg+https://hg.example.com/user/project", Some("project")),
+            ("https://example.com/releases/foo.tar.gz", Some("foo")),
+            ("tarball+https://example.com/src.tgz", Some("src")),
+            ("path:/home/me/myflake", Some("myflake")),
+            ("path:/home/me/myflake/", Some("myflake")),
+            ("path:/", None),
+        ] {
+            let flake_ref: FlakeRef = uri.parse().unwrap();
+            assert_eq!(infer_id(&flake_ref).as_deref(), expected, "{uri}");
+        }
+    }
+
+    #[test]
+    fn sort_params_orders_query_keys() {
+        assert_eq!(
+            sort_params("git+https://git.example.org/mprisd?shallow=1&ref=main").as_deref(),
+            Some("git+https://git.example.org/mprisd?ref=main&shallow=1")
+        );
+        assert_eq!(sort_params("github:nixos/nixpkgs?dir=lib&ref=main"), None);
+        assert_eq!(sort_params("github:nixos/nixpkgs/nixos-unstable"), None);
+        assert_eq!(sort_params("not a flake ref?b=1&a=2"), None);
+    }
+
+    #[test]
+    fn take_flake_param_splits_the_boolean_off() {
+        assert_eq!(
+            take_flake_param("github:o/r?flake=false"),
+            Some(("github:o/r".into(), false))
+        );
+        assert_eq!(
+            take_flake_param("github:o/r?dir=sub&flake=true&ref=main#frag"),
+            Some(("github:o/r?dir=sub&ref=main#frag".into(), true))
+        );
+        assert_eq!(
+            take_flake_param("git+https://example.org/r?flake=0&shallow=1"),
+            Some(("git+https://example.org/r?shallow=1".into(), false))
+        );
+        assert_eq!(take_flake_param("github:o/r?flake=maybe"), None);
+        assert_eq!(take_flake_param("github:o/r?dir=flake"), None);
+        assert_eq!(take_flake_param("github:o/r"), None);
+    }
+
+    #[test]
+    fn rendering_keeps_unknown_params() {
+        // Keys nix-uri has no slot for are kept and rendered with the
+        // known ones, joined by a single `&`, in canonical order.
+        let mut flake_ref: FlakeRef = "github:o/r?foo=bar&dir=sub&baz=qux".parse().unwrap();
+        assert_eq!(flake_ref.to_string(), "github:o/r?baz=qux&dir=sub&foo=bar");
+        flake_ref.set_ref(Some("v1".into()));
+        assert_eq!(
+            flake_ref.to_string(),
+            "github:o/r/v1?baz=qux&dir=sub&foo=bar"
+        );
+        assert_eq!(
+            sort_params("github:o/r?foo=bar&dir=sub&baz=qux").as_deref(),
+            Some("github:o/r?baz=qux&dir=sub&foo=bar")
+        );
+    }
+
+    /// `type_name`, `owner`, `repo`, `ref_or_rev`, `params`.
+    type Parts = (
+        &'static str,
+        &'static str,
+        &'static str,
+        Option<&'static str>,
+        &'static [(&'static str, &'static str)],
+    );
+
+    #[test]
+    fn try_from_parts_builds_valid_refs() {
+        let cases: [(Parts, &str); 5] = [
+            (
+                ("github", "nixos", "nixpkgs", None, &[]),
+                "github:nixos/nixpkgs",
+            ),
+            (
+                ("github", "nixos", "nixpkgs", Some("nixos-24.05"), &[]),
+                "github:nixos/nixpkgs/nixos-24.05",
+            ),
+            (
+                (
+                    "gitlab",
+                    "inkscape",
+                    "inkscape",
+                    Some("release/1.3"),
+                    &[("dir", "share")],
+                ),
+                "gitlab:inkscape/inkscape?dir=share&ref=release/1.3",
+            ),
+            (
+                (
+                    "sourcehut",
+                    "~sircmpwn",
+                    "hare",
+                    Some("0123456789abcdef0123456789abcdef01234567"),
+                    &[],
+                ),
+                "sourcehut:~sircmpwn/hare/0123456789abcdef0123456789abcdef01234567",
+            ),
+            (
+                ("github", "a-kenji", "flake-edit", None, &[("dir", "lib")]),
+                "github:a-kenji/flake-edit?dir=lib",
+            ),
+        ];
+        for ((ty, owner, repo, reference, params), expected) in cases {
+            let flake_ref = FlakeRef::try_from_parts(ty, owner, repo, reference, params)
+                .unwrap_or_else(|e| panic!("{expected}: {e}"));
+            assert_eq!(flake_ref.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn try_from_parts_rejects_illegal_parts() {
+        let cases: [Parts; 9] = [
+            ("git", "nixos", "nixpkgs", None, &[]),
+            ("github", "", "nixpkgs", None, &[]),
+            ("github", "nix os", "nixpkgs", None, &[]),
+            ("github", "nixos", "nix/pkgs", None, &[]),
+            ("github", "nixos", "..", None, &[]),
+            ("github", "nixos", "nixpkgs", Some("a..b"), &[]),
+            ("github", "nixos", "nixpkgs", Some("main?rev=x"), &[]),
+            ("github", "nixos", "nixpkgs", None, &[("dir", "a&ref=b")]),
+            ("github", "nixos", "nixpkgs", None, &[("", "x")]),
+        ];
+        for (ty, owner, repo, reference, params) in cases {
+            assert!(
+                FlakeRef::try_from_parts(ty, owner, repo, reference, params).is_err(),
+                "{ty}:{owner}/{repo} {reference:?} {params:?} should be rejected"
+            );
+        }
+        assert!(matches!(
+            FlakeRef::try_from_parts("git", "nixos", "nixpkgs", None, &[]),
+            Err(NixUriError::Unsupported(_))
+        ));
+        assert!(matches!(
+            FlakeRef::try_from_parts("github", "nixos", "nix/pkgs", None, &[]),
+            Err(NixUriError::InvalidValue { field: "repo", .. })
+        ));
+    }
+}
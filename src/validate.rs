@@ -4,13 +4,15 @@
 //! - `syntax` (private): rnix parse errors and duplicate-attribute detection.
 //! - `follows` (crate-private): cycle, stale, target, contradiction, and depth
 //!   lints.
+//! - `lock` (crate-private): declared-vs-locked fetcher type lint.
 //!
 //! [`validate`] runs the syntax-level lints. [`validate_full`] adds the
 //! follows-graph lints that need a parsed [`InputMap`] and an optional
-//! [`FlakeLock`].
+//! [`FlakeLock`], plus the lock type lint when a lock is given.
 
 pub mod error;
 pub(crate) mod follows;
+mod lock;
 mod syntax;
 
 pub use error::{DuplicateAttr, Location, Severity, ValidationError, ValidationResult};
@@ -47,7 +49,8 @@ pub(crate) fn validate_parsed(parsed: &ParsedSource) -> ValidationResult {
     }
 }
 
-/// Run syntax checks plus every follows-graph lint.
+/// Run syntax checks plus every follows-graph lint, and with a lock the
+/// declared-vs-locked fetcher type lint.
 ///
 /// Walks `flake.lock` once via [`FlakeLock::nested_inputs`], builds the lock
 /// graph from that single walk, and hands both to
@@ -62,12 +65,21 @@ pub fn validate_full(
     let lock_graph = nested_inputs
         .as_deref()
         .map(FollowsGraph::from_nested_inputs);
-    validate_full_with_lock_graph(
+    let mut result = validate_full_with_lock_graph(
         &parsed,
         inputs,
         lock_graph.as_ref(),
         nested_inputs.as_deref().unwrap_or(&[]),
-    )
+    );
+    if let Some(lock) = lock {
+        let offset_to_location = |offset: usize| parsed.line_map.offset_to_location(offset);
+        result.warnings.extend(lock::lint_lock_type_mismatch(
+            inputs,
+            lock,
+            &offset_to_location,
+        ));
+    }
+    result
 }
 
 /// Like [`validate_full`] but skips the lock-drift lints (`lint_follows_stale`
@@ -518,4 +530,73 @@ mod tests {
             result.warnings,
         );
     }
+
+    const MISMATCH_FLAKE: &str = r#"{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs";
+    mprisd.url = "github:kenji/mprisd";
+  };
+  outputs = _: { };
+}
+"#;
+
+    fn mismatch_lock() -> FlakeLock {
+        FlakeLock::read_from_str(
+            r#"{
+  "nodes": {
+    "nixpkgs": {
+      "locked": { "owner": "nixos", "repo": "nixpkgs", "rev": "abc", "type": "github" },
+      "original": { "owner": "nixos", "repo": "nixpkgs", "type": "github" }
+    },
+    "mprisd": {
+      "locked": { "rev": "def", "type": "git", "url": "https://forge.example/kenji/mprisd" },
+      "original": { "type": "git", "url": "https://forge.example/kenji/mprisd" }
+    },
+    "root": { "inputs": { "nixpkgs": "nixpkgs", "mprisd": "mprisd" } }
+  },
+  "root": "root",
+  "version": 7
+}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn validate_full_warns_on_declared_vs_locked_type_mismatch() {
+        let mut edit = crate::edit::FlakeEdit::from_text(MISMATCH_FLAKE).unwrap();
+        let inputs = edit.list().clone();
+        let result = validate_full(MISMATCH_FLAKE, &inputs, Some(&mismatch_lock()));
+
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        let mismatches: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|e| matches!(e, ValidationError::LockTypeMismatch { .. }))
+            .collect();
+        assert_eq!(
+            mismatches,
+            vec![&ValidationError::LockTypeMismatch {
+                id: "mprisd".into(),
+                declared: "github".into(),
+                locked: "git".into(),
+                location: Location {
+                    line: 4,
+                    column: 18
+                },
+            }],
+        );
+    }
+
+    #[test]
+    fn validate_full_skips_type_lint_without_lock() {
+        let mut edit = crate::edit::FlakeEdit::from_text(MISMATCH_FLAKE).unwrap();
+        let inputs = edit.list().clone();
+        let result = validate_full(MISMATCH_FLAKE, &inputs, None);
+        assert!(
+            !result
+                .warnings
+                .iter()
+                .any(|e| matches!(e, ValidationError::LockTypeMismatch { .. }))
+        );
+    }
 }
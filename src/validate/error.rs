@@ -102,6 +102,20 @@ pub enum ValidationError {
         lock_target: Option<crate::follows::AttrPath>,
         location: Location,
     },
+    /// A top-level input's declared scheme differs from the fetcher type
+    /// `flake.lock` recorded for it. Warning: the lock is stale relative
+    /// to `flake.nix`.
+    #[error(
+        "lock type mismatch at {location}: '{id}' is declared as {declared} but flake.lock fetched {locked} (run `nix flake lock`)"
+    )]
+    LockTypeMismatch {
+        id: String,
+        /// Fetcher type implied by the url in `flake.nix`.
+        declared: String,
+        /// `locked.type` of the input's node in `flake.lock`.
+        locked: String,
+        location: Location,
+    },
     /// A follows path is deeper than the configured graph traversal bound.
     #[error("follows depth exceeded at {location}: {} -> {} reached depth {depth} (max {max_depth})", edge.source, edge.follows)]
     FollowsDepthExceeded {
@@ -112,6 +126,23 @@ pub enum ValidationError {
     },
 }
 
+impl ValidationError {
+    /// Where the problem sits, for the variants that carry one location.
+    pub fn location_mut(&mut self) -> Option<&mut Location> {
+        match self {
+            Self::ParseError { location, .. }
+            | Self::FollowsCycle { location, .. }
+            | Self::FollowsStale { location, .. }
+            | Self::FollowsTargetNotToplevel { location, .. }
+            | Self::FollowsContradiction { location, .. }
+            | Self::FollowsStaleLock { location, .. }
+            | Self::LockTypeMismatch { location, .. }
+            | Self::FollowsDepthExceeded { location, .. } => Some(location),
+            Self::DuplicateAttribute(_) => None,
+        }
+    }
+}
+
 fn format_edges(edges: &[crate::follows::Edge]) -> String {
     edges
         .iter()
@@ -131,9 +162,9 @@ impl ValidationError {
     /// Severity for this variant.
     pub fn severity(&self) -> Severity {
         match self {
-            ValidationError::FollowsStale { .. } | ValidationError::FollowsStaleLock { .. } => {
-                Severity::Warning
-            }
+            ValidationError::FollowsStale { .. }
+            | ValidationError::FollowsStaleLock { .. }
+            | ValidationError::LockTypeMismatch { .. } => Severity::Warning,
             _ => Severity::Error,
         }
     }
@@ -237,6 +268,15 @@ mod tests {
                 },
                 Severity::Warning,
             ),
+            (
+                ValidationError::LockTypeMismatch {
+                    id: "a".into(),
+                    declared: "github".into(),
+                    locked: "git".into(),
+                    location: loc(),
+                },
+                Severity::Warning,
+            ),
             (
                 ValidationError::FollowsDepthExceeded {
                     edge: declared_edge("a.b", "x"),
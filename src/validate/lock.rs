@@ -0,0 +1,82 @@
+//! Lints comparing `flake.nix` declarations against `flake.lock`.
+//!
+//! Unlike the follows lints these need the parsed [`FlakeLock`] itself,
+//! so only [`super::validate_full`] runs them.
+
+use nix_uri::{FlakeRef, FlakeRefType, ResourceType};
+
+use super::error::{Location, ValidationError};
+use crate::edit::InputMap;
+use crate::lock::FlakeLock;
+
+/// The `locked.type` Nix records for a declared url, or `None` when the
+/// url does not parse or does not pin a fetcher (indirect registry refs
+/// resolve to whatever the registry points at).
+pub(crate) fn declared_fetcher_type(url: &str) -> Option<String> {
+    let flake_ref: FlakeRef = url.parse().ok()?;
+    let fetcher = match flake_ref.kind() {
+        FlakeRefType::GitForge(forge) => return Some(forge.platform.to_string()),
+        FlakeRefType::Resource(res) => match res.res_type {
+            ResourceType::Git => "git",
+            ResourceType::Mercurial => "hg",
+            ResourceType::File => "file",
+            ResourceType::Tarball => "tarball",
+            _ => return None,
+        },
+        FlakeRefType::Path { .. } => "path",
+        _ => return None,
+    };
+    Some(fetcher.to_string())
+}
+
+/// Lint top-level inputs whose declared scheme differs from the fetcher
+/// type `flake.lock` recorded for them. Always
+/// [`super::Severity::Warning`]: the lock is usually just stale.
+///
+/// Output is sorted by input id.
+pub(crate) fn lint_lock_type_mismatch<F: Fn(usize) -> Location>(
+    inputs: &InputMap,
+    lock: &FlakeLock,
+    offset_to_location: &F,
+) -> Vec<ValidationError> {
+    let mut ids: Vec<&String> = inputs.keys().collect();
+    ids.sort();
+    ids.into_iter()
+        .filter_map(|id| {
+            let input = &inputs[id];
+            if input.is_unresolved() {
+                return None;
+            }
+            let declared = declared_fetcher_type(input.url())?;
+            let locked = lock.input_type(input.id())?;
+            (declared != locked).then(|| ValidationError::LockTypeMismatch {
+                id: id.clone(),
+                declared,
+                locked: locked.to_string(),
+                location: offset_to_location(input.range.start),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declared_fetcher_type_follows_lock_naming() {
+        for (url, expected) in [
+            ("github:nixos/nixpkgs", Some("github")),
+            ("gitlab:o/r", Some("gitlab")),
+            ("sourcehut:~o/r", Some("sourcehut")),
+            ("git+https://example.org/r", Some("git")),
+            ("hg+https://example.org/r", Some("hg")),
+            ("https://example.org/src.tar.gz", Some("tarball")),
+            ("path:/srv/flake", Some("path")),
+            ("nixpkgs", None),
+            ("", None),
+        ] {
+            assert_eq!(declared_fetcher_type(url).as_deref(), expected, "{url}");
+        }
+    }
+}
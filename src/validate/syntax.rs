@@ -60,7 +60,7 @@ impl LineMap {
         }
     }
 
-    pub(super) fn offset_to_location(&self, offset: usize) -> Location {
+    pub(crate) fn offset_to_location(&self, offset: usize) -> Location {
         let line = self
             .line_starts
             .iter()
@@ -73,6 +73,11 @@ impl LineMap {
         }
     }
 
+    /// Byte offset of `location`, the inverse of [`Self::offset_to_location`].
+    pub(crate) fn location_to_offset(&self, location: &Location) -> usize {
+        self.line_starts[location.line - 1] + location.column - 1
+    }
+
     pub(super) fn range_to_location(&self, range: TextRange) -> Location {
         self.offset_to_location(range.start().into())
     }
@@ -1,17 +1,19 @@
 //! CST walking and mutation for `flake.nix` files.
 
 mod context;
+pub(crate) mod dedup;
 mod error;
 mod inputs;
 mod node;
 mod outputs;
+pub(crate) mod rename;
 pub(crate) mod toggle;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use rnix::{Root, SyntaxKind, SyntaxNode};
 
-use crate::change::Change;
+use crate::change::{Change, ChangeId};
 use crate::edit::{OutputChange, Outputs};
 use crate::follows::path::follows_idents_prefixed;
 use crate::follows::{AttrPath, Segment, strip_outer_quotes};
@@ -22,9 +24,9 @@ pub use error::WalkerError;
 
 use inputs::walk_inputs;
 use node::{
-    FollowsKind, adjacent_whitespace_index, get_sibling_whitespace, insertion_index_after,
-    last_line_with_newline, make_quoted_string, make_toplevel_flake_false_attr,
-    make_toplevel_url_attr, parse_node, substitute_child,
+    FollowsKind, get_sibling_whitespace, insertion_index_after, last_line_with_newline,
+    make_quoted_string, make_toplevel_flake_false_attr, make_toplevel_url_attr, parse_node,
+    remove_child_with_whitespace, substitute_child, url_entry_nodes,
 };
 
 /// The flake's top-level attribute set.
@@ -123,14 +125,136 @@ fn insert_flat_follows_after(
         .insert_child(insert_index, follows_node.green().into());
 
     if let Some(whitespace) = get_sibling_whitespace(ref_child) {
-        let ws_str = whitespace.to_string();
-        let ws_node = parse_node(last_line_with_newline(&ws_str));
-        green = green.insert_child(insert_index, ws_node.green().into());
+        green = green.insert_child(insert_index, whitespace.green().into());
     }
 
     SyntaxNode::new_root(attr_set.replace_with(green))
 }
 
+/// Input id of a `url`-bearing binding: `<id>.url = ...` or
+/// `<id> = { url = ...; }`. `path` is the attrpath below `inputs`.
+fn url_binding_id(path: &[String], value: Option<&SyntaxNode>) -> Option<String> {
+    match path {
+        [id, url] if url == "url" => Some(id.clone()),
+        [id] => {
+            let value = value.filter(|v| v.kind() == SyntaxKind::NODE_ATTR_SET)?;
+            value
+                .children()
+                .filter(|c| c.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+                .filter_map(|c| c.children().find(|n| n.kind() == SyntaxKind::NODE_ATTRPATH))
+                .any(|p| p.to_string() == "url")
+                .then(|| id.clone())
+        }
+        _ => None,
+    }
+}
+
+fn unquoted_idents(attrpath: &SyntaxNode) -> Vec<String> {
+    attrpath
+        .children()
+        .map(|c| strip_outer_quotes(&c.to_string()).to_string())
+        .collect()
+}
+
+/// A `url`-bearing binding of a top-level input.
+struct UrlBinding {
+    id: String,
+    /// Byte offset of the binding.
+    offset: usize,
+    /// Written as a flat top-level `inputs.<id>` attribute rather than
+    /// inside the `inputs = { ... }` block.
+    flat: bool,
+}
+
+/// Every binding that gives a top-level input its `url`, in source order.
+fn url_bindings(root: &SyntaxNode) -> Vec<UrlBinding> {
+    let Some(attr_set) = flake_attr_set(root) else {
+        return Vec::new();
+    };
+    let mut bindings = Vec::new();
+
+    for toplevel in attr_set.children() {
+        if toplevel.kind() != SyntaxKind::NODE_ATTRPATH_VALUE {
+            continue;
+        }
+        let Some(attrpath) = toplevel
+            .children()
+            .find(|c| c.kind() == SyntaxKind::NODE_ATTRPATH)
+        else {
+            continue;
+        };
+        let idents = unquoted_idents(&attrpath);
+        if idents.first().map(String::as_str) != Some("inputs") {
+            continue;
+        }
+        let value = attrpath.next_sibling();
+        if idents.len() > 1 {
+            bindings.extend(
+                url_binding_id(&idents[1..], value.as_ref()).map(|id| UrlBinding {
+                    id,
+                    offset: toplevel.text_range().start().into(),
+                    flat: true,
+                }),
+            );
+            continue;
+        }
+        let Some(block_set) = value.filter(|v| v.kind() == SyntaxKind::NODE_ATTR_SET) else {
+            continue;
+        };
+        for entry in block_set.children() {
+            let Some(entry_path) = entry
+                .children()
+                .find(|c| c.kind() == SyntaxKind::NODE_ATTRPATH)
+            else {
+                continue;
+            };
+            let entry_value = entry_path.next_sibling();
+            bindings.extend(
+                url_binding_id(&unquoted_idents(&entry_path), entry_value.as_ref()).map(|id| {
+                    UrlBinding {
+                        id,
+                        offset: entry.text_range().start().into(),
+                        flat: false,
+                    }
+                }),
+            );
+        }
+    }
+    bindings
+}
+
+/// First input id (in lexical order) whose `url` is bound both by a flat
+/// top-level `inputs.<id>` attribute and inside an `inputs = { ... }`
+/// block. Other mixes, such as a block `url` plus a flat
+/// `inputs.<id>.inputs.X.follows`, are legitimate merges and not reported.
+pub(crate) fn mixed_style_duplicate(root: &SyntaxNode) -> Option<String> {
+    let mut flat = BTreeSet::new();
+    let mut block = BTreeSet::new();
+    for binding in url_bindings(root) {
+        if binding.flat {
+            flat.insert(binding.id);
+        } else {
+            block.insert(binding.id);
+        }
+    }
+    flat.intersection(&block).next().cloned()
+}
+
+/// Input ids whose `url` is bound more than once, sorted by id, each with
+/// the byte offsets of its bindings in source order. The walker keeps
+/// only the last binding it meets, so the earlier ones are silently
+/// shadowed.
+pub(crate) fn duplicate_url_bindings(root: &SyntaxNode) -> Vec<(String, Vec<usize>)> {
+    let mut by_id: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for binding in url_bindings(root) {
+        by_id.entry(binding.id).or_default().push(binding.offset);
+    }
+    by_id
+        .into_iter()
+        .filter(|(_, offsets)| offsets.len() > 1)
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct Walker {
     pub(crate) root: SyntaxNode,
@@ -164,14 +288,61 @@ impl<'a> Walker {
         if cst.kind() != SyntaxKind::NODE_ROOT {
             return Err(WalkerError::NotARoot);
         }
+        if let Change::AddFollows { entries } = change {
+            return self.walk_add_follows(cst, entries);
+        }
         self.walk_toplevel(cst, None, change)
     }
 
+    /// Apply each `(input, target)` entry as a [`Change::Follows`], feeding
+    /// the rebuilt root of one entry into the next. An entry the walker
+    /// cannot place fails the batch with [`WalkerError::FollowsNotPlaced`],
+    /// as a lone [`Change::Follows`] would fail to produce text. Returns
+    /// `None` only for an empty batch.
+    ///
+    /// This is one walk per entry, not a single traversal. Spliced-in
+    /// fragments keep the `NODE_ROOT` wrapper [`parse_node`] gave them,
+    /// which hides their whitespace from the sibling lookups the next entry
+    /// relies on for indentation, so placing a later entry needs the tree
+    /// the earlier ones produced, reparsed. Folding the entries into one
+    /// traversal would mean unwrapping the fragment at every splice site.
+    /// What the batch saves over separate changes is the validation and
+    /// input-map walk a fresh [`crate::edit::FlakeEdit`] would run.
+    fn walk_add_follows(
+        &mut self,
+        mut root: SyntaxNode,
+        entries: &[(ChangeId, AttrPath)],
+    ) -> Result<Option<SyntaxNode>, WalkerError> {
+        let mut changed = false;
+        for (input, target) in entries {
+            let change = Change::Follows {
+                input: input.clone(),
+                target: target.clone(),
+            };
+            let Some(node) = self.walk_toplevel(root.clone(), None, &change)? else {
+                return Err(WalkerError::FollowsNotPlaced {
+                    input: input.to_string(),
+                });
+            };
+            root = parse_node(&node.to_string());
+            changed = true;
+        }
+        Ok(changed.then_some(root))
+    }
+
     /// List the `outputs` arguments without touching `inputs`.
     pub(crate) fn list_outputs(&mut self) -> Result<Outputs, WalkerError> {
         outputs::list_outputs(&self.root)
     }
 
+    /// Identifiers the `outputs` lambda names, see
+    /// [`outputs::outputs_idents`].
+    pub(crate) fn outputs_idents(
+        &self,
+    ) -> Result<Option<std::collections::HashSet<String>>, WalkerError> {
+        outputs::outputs_idents(&self.root)
+    }
+
     /// Apply an [`OutputChange`] to the `outputs` attribute alone.
     pub(crate) fn change_outputs(
         &mut self,
@@ -190,6 +361,9 @@ impl<'a> Walker {
         let Some(attr_set) = flake_attr_set(&node) else {
             return Ok(None);
         };
+        if let Some(id) = mixed_style_duplicate(&node) {
+            return Err(WalkerError::DuplicateInput { id });
+        }
 
         for toplevel in attr_set.children() {
             if toplevel.kind() != SyntaxKind::NODE_ATTRPATH_VALUE {
@@ -439,12 +613,10 @@ impl<'a> Walker {
         // Empty replacement means we remove the entire toplevel node and
         // propagate through attr_set to NODE_ROOT.
         if replacement.to_string().is_empty() {
-            let element: rnix::SyntaxElement = toplevel.clone().into();
-            let mut green = attr_set.green().remove_child(toplevel.index());
-            if let Some(ws_index) = adjacent_whitespace_index(&element) {
-                green = green.remove_child(ws_index);
-            }
-            return Some(SyntaxNode::new_root(attr_set.replace_with(green)));
+            let green = remove_child_with_whitespace(attr_set, toplevel, toplevel.index());
+            return Some(SyntaxNode::new_root(
+                attr_set.replace_with(green.green().into_owned()),
+            ));
         }
 
         let sibling = child.next_sibling()?;
@@ -500,26 +672,16 @@ impl<'a> Walker {
             ws
         };
 
-        let addition = make_toplevel_url_attr(id, uri);
+        let entry = url_entry_nodes(
+            ws_node.as_ref(),
+            make_toplevel_url_attr(id, uri),
+            (!flake).then(|| make_toplevel_flake_false_attr(id)),
+        );
         let insert_pos = toplevel.index() - 1;
-
-        let mut green = attr_set
-            .green()
-            .insert_child(insert_pos, addition.green().into());
-
-        if let Some(ref ws) = ws_node {
-            green = green.insert_child(insert_pos, ws.green().into());
-        }
-
-        // Append `inputs.<id>.flake = false;` when the new input opts out of flake mode.
-        if !flake {
-            let no_flake = make_toplevel_flake_false_attr(id);
-            green = green.insert_child(toplevel.index() + 1, no_flake.green().into());
-
-            if let Some(ref ws) = ws_node {
-                green = green.insert_child(toplevel.index() + 1, ws.green().into());
-            }
-        }
+        let green = attr_set.green().splice_children(
+            insert_pos..insert_pos,
+            entry.iter().map(|n| n.green().into()),
+        );
 
         Some(SyntaxNode::new_root(attr_set.replace_with(green)))
     }
@@ -547,6 +709,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn add_follows_fails_on_an_entry_it_cannot_place() {
+        let flake = "{
+  inputs = {
+    nixpkgs.url = \"github:nixos/nixpkgs\";
+    crane.url = \"github:ipetkov/crane\";
+  };
+  outputs = { ... }: { };
+}";
+        let mut walker = Walker::new(flake);
+        walker.walk(&Change::None).unwrap();
+        // Known to the input map, but declared nowhere in the text.
+        walker.inputs.insert(
+            "ghost".to_string(),
+            Input::new(crate::follows::Segment::from_unquoted("ghost").unwrap()),
+        );
+        let change = Change::AddFollows {
+            entries: vec![
+                (
+                    ChangeId::parse("crane.nixpkgs").unwrap(),
+                    AttrPath::parse("nixpkgs").unwrap(),
+                ),
+                (
+                    ChangeId::parse("ghost.nixpkgs").unwrap(),
+                    AttrPath::parse("nixpkgs").unwrap(),
+                ),
+            ],
+        };
+        let err = walker.walk(&change).unwrap_err();
+        assert!(
+            matches!(&err, WalkerError::FollowsNotPlaced { input } if input == "ghost.nixpkgs"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn walk_reports_input_declared_flat_and_in_block() {
+        let flake = "{
+  inputs = {
+    crane = {
+      url = \"github:ipetkov/crane\";
+    };
+  };
+  inputs.crane.url = \"github:ipetkov/crane/v0.20.0\";
+
+  outputs = { self, ... }: { };
+}
+";
+        let mut walker = Walker::new(flake);
+        let err = walker
+            .walk(&Change::None)
+            .expect_err("mixed-style duplicate must be reported");
+        assert!(
+            matches!(err, WalkerError::DuplicateInput { ref id } if id == "crane"),
+            "expected DuplicateInput {{ id: \"crane\" }}, got: {err:?}",
+        );
+    }
+
     #[test]
     fn handle_follows_flat_toplevel_inserts_follows_after_last_parent_attr() {
         let flake = "{
@@ -1,6 +1,11 @@
 use crate::follows::Segment;
 
 /// Path of enclosing input identifiers tracked by [`super::Walker`] during CST traversal.
+///
+/// The first segment is the top-level input that owns everything below it.
+/// Each nested input the walk descends into (`inputs.b = { ... }` inside
+/// `a`) pushes one more segment, so a binding inside `a`'s `b` block sees
+/// `[a, b]`.
 #[derive(Debug, Clone)]
 pub struct Context {
     level: Vec<Segment>,
@@ -16,6 +21,23 @@ impl Context {
     pub fn first_matches(&self, s: &Segment) -> bool {
         self.first() == Some(s)
     }
+
+    /// Enclosing nested inputs below the top-level one, outermost first.
+    pub fn nested(&self) -> &[Segment] {
+        self.level.get(1..).unwrap_or_default()
+    }
+
+    /// Every enclosing segment, outermost first.
+    pub fn segments(&self) -> &[Segment] {
+        &self.level
+    }
+
+    /// The context one nested input further down.
+    pub fn descend(&self, segments: impl IntoIterator<Item = Segment>) -> Self {
+        let mut level = self.level.clone();
+        level.extend(segments);
+        Self { level }
+    }
 }
 
 impl From<Segment> for Context {
@@ -0,0 +1,48 @@
+//! Removal of duplicate input bindings.
+//!
+//! Nix rejects an attribute set that binds the same path twice. Inside
+//! the flake's `inputs` the first binding wins and a later copy can be
+//! dropped with the lines belonging to it. Duplicates elsewhere (in
+//! `outputs`, `nixConfig`, ...) are never touched here.
+
+use rnix::{SyntaxKind, SyntaxNode, TextSize};
+
+use crate::follows::strip_outer_quotes;
+
+use super::node::remove_child_with_whitespace;
+
+/// The binding whose attrpath starts at `offset`, when it declares an
+/// input: its top-level binding is `inputs` or starts with `inputs.`.
+pub(crate) fn input_binding_at(root: &SyntaxNode, offset: usize) -> Option<SyntaxNode> {
+    let offset = TextSize::try_from(offset).ok()?;
+    let token = root.token_at_offset(offset).right_biased()?;
+    let binding = token
+        .parent_ancestors()
+        .find(|node| node.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)?;
+    let attrpath = binding
+        .children()
+        .find(|c| c.kind() == SyntaxKind::NODE_ATTRPATH)?;
+    if attrpath.text_range().start() != offset {
+        return None;
+    }
+    let top_level = binding
+        .ancestors()
+        .filter(|node| node.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        .last()?;
+    let first_segment = top_level
+        .children()
+        .find(|c| c.kind() == SyntaxKind::NODE_ATTRPATH)?
+        .first_child()?
+        .to_string();
+    (strip_outer_quotes(&first_segment) == "inputs").then_some(binding)
+}
+
+/// Remove `binding` together with its whitespace, trailing same-line
+/// comment and the comment block above it. Returns the new root.
+pub(crate) fn remove_binding(binding: &SyntaxNode) -> Option<SyntaxNode> {
+    let parent = binding.parent()?;
+    let rebuilt = remove_child_with_whitespace(&parent, binding, binding.index());
+    Some(SyntaxNode::new_root(
+        parent.replace_with(rebuilt.green().into_owned()),
+    ))
+}
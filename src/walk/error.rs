@@ -13,9 +13,44 @@ pub enum WalkerError {
     /// node and `offset` is the byte offset where it starts.
     #[error("unexpected non-attribute at top level of flake.nix at byte {offset}: {snippet}")]
     UnexpectedTopLevel { snippet: String, offset: u32 },
+
+    /// The same input's `url` is bound both by a flat top-level
+    /// `inputs.<id>` attribute and inside the `inputs = { ... }` block.
+    /// Nix rejects the merged attribute, so the walker refuses to pick one.
+    #[error(
+        "input '{id}' is declared both as `inputs.{id}` and inside the `inputs = {{ ... }}` block"
+    )]
+    DuplicateInput { id: String },
+
+    /// Renaming input `from` to `to` would leave the `outputs` lambda
+    /// naming `from` somewhere the rename cannot follow. `reason` says
+    /// where.
+    #[error("cannot rename '{from}' to '{to}' in the outputs lambda: {reason}")]
+    OutputsRename {
+        from: String,
+        to: String,
+        reason: String,
+    },
+
+    /// An entry of a [`crate::change::Change::AddFollows`] batch found no
+    /// place in the tree for its declaration. The whole batch is refused.
+    #[error("could not place follows declaration for '{input}'")]
+    FollowsNotPlaced { input: String },
 }
 
 impl WalkerError {
+    /// Name of the variant, for the `kind` of a `--output json` error
+    /// report.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NotARoot { .. } => "NotARoot",
+            Self::UnexpectedTopLevel { .. } => "UnexpectedTopLevel",
+            Self::DuplicateInput { .. } => "DuplicateInput",
+            Self::OutputsRename { .. } => "OutputsRename",
+            Self::FollowsNotPlaced { .. } => "FollowsNotPlaced",
+        }
+    }
+
     pub(crate) fn unexpected_top_level(text: &str, offset: u32) -> Self {
         const MAX_SNIPPET: usize = 60;
 
@@ -9,19 +9,20 @@ use crate::input::Input;
 
 use super::context::Context;
 use super::node::{
-    FollowsKind, adjacent_whitespace_index, empty_node, extract_indent, get_sibling_whitespace,
-    insertion_index_after, is_attrset_content_empty, last_line_with_newline, make_attrset_url_attr,
-    make_attrset_url_flake_false_attr, make_flake_false_attr, make_quoted_string, make_url_attr,
-    parse_node, remove_child_with_whitespace, should_remove_input, should_remove_nested_input,
-    substitute_child, trailing_inline_comments, uses_attrset_style,
+    FollowsKind, empty_node, extract_indent, get_sibling_whitespace, insertion_index_after,
+    is_attrset_content_empty, make_attrset_url_attr, make_attrset_url_flake_false_attr,
+    make_flake_false_attr, make_quoted_string, make_url_attr, parse_node, removal_companions,
+    remove_child_with_whitespace, should_remove_input, should_remove_nested_input,
+    substitute_child, url_entry_nodes, uses_attrset_style,
 };
 
 /// Insert or update `inputs[id]` from a parsed `Input`.
 ///
 /// When `ctx` carries an enclosing input, the `input` is interpreted as a follows
-/// edge attached to that owner instead of a top-level entry. Only depth-1 follows
-/// shapes (`<owner>.<id>.follows = ...`) hit this path. Deeper shapes are parsed up
-/// front in [`handle_attrpath_follows`] and bypass this helper.
+/// edge attached to that owner instead of a top-level entry. The edge's path is
+/// the nested inputs `ctx` has descended into, followed by `id`. Flat
+/// attrpaths (`<owner>.inputs.<a>.inputs.<b>.follows = ...`) are parsed up front
+/// in [`handle_attrpath_follows`] and bypass this helper.
 pub(crate) fn insert_with_ctx(
     inputs: &mut HashMap<String, Input>,
     id: Segment,
@@ -33,7 +34,11 @@ pub(crate) fn insert_with_ctx(
             // The follows target arrives as the `input.url` token.
             let target = AttrPath::parse_follows_target(&input.url, &id);
             let key = follows.as_str().to_string();
-            let nested_path = AttrPath::new(id.clone());
+            let mut segments = ctx.nested().iter().cloned().chain([id.clone()]);
+            let mut nested_path = AttrPath::new(segments.next().expect("ends with `id`"));
+            for seg in segments {
+                nested_path.push(seg);
+            }
             if let Some(node) = inputs.get_mut(&key) {
                 node.push_indirect_follows(nested_path, target);
             } else {
@@ -51,6 +56,7 @@ pub(crate) fn insert_with_ctx(
             if !input.url.is_empty() {
                 node.url = input.url;
                 node.range = input.range;
+                node.unresolved = input.unresolved;
             }
             if !input.flake {
                 node.flake = input.flake;
@@ -82,14 +88,21 @@ pub(crate) fn walk_inputs(
         // effect of populating the `inputs` map via the per-attr handlers, and
         // `Remove`/`Change` rewrite a single matched child in place. All three
         // only need to traverse children, never rebuilding the block.
-        // The toggle changes never reach the walk because `FlakeEdit`
-        // edits through `walk::toggle` directly. Grouped here for
-        // exhaustiveness.
+        // The toggle and rename changes never reach the walk because
+        // `FlakeEdit` edits through `walk::toggle` and `walk::rename`
+        // directly, `Pin` and `Unpin` reach it as a `Change`, and
+        // `AddFollows` is split into per-entry `Follows` by `Walker::walk`.
+        // Grouped here for exhaustiveness.
         Change::None
         | Change::Remove { .. }
         | Change::Change { .. }
+        | Change::AddFollows { .. }
         | Change::Toggle { .. }
-        | Change::ToggleRemove { .. } => walk_children(inputs, &node, ctx, change),
+        | Change::ToggleRemove { .. }
+        | Change::ToggleStore { .. }
+        | Change::Rename { .. }
+        | Change::Pin { .. }
+        | Change::Unpin { .. } => walk_children(inputs, &node, ctx, change),
     }
 }
 
@@ -112,6 +125,23 @@ fn walk_children(
                     return Some(result);
                 }
             }
+            // Only the top-level block: inside an input's own `{ ... }`,
+            // `inherit url;` is that input's url and is read by its handler.
+            SyntaxKind::NODE_INHERIT if ctx.is_none() => {
+                let inherit = child.as_node()?;
+                record_inherited_inputs(inputs, inherit);
+                if let Some(replacement) = remove_inherited_input(inherit, change) {
+                    let mut green = node
+                        .green()
+                        .replace_child(child.index(), replacement.green().into());
+                    if replacement.text().is_empty() {
+                        for idx in removal_companions(&child).into_iter().rev() {
+                            green = green.remove_child(idx);
+                        }
+                    }
+                    return Some(SyntaxNode::new_root(green));
+                }
+            }
             _ => {}
         }
     }
@@ -187,25 +217,17 @@ fn insert_into_empty_inputs(node: &SyntaxNode, id: &str, uri: &str, flake: bool)
         .position(|c| c.as_token().map(|t| t.text() == "}").unwrap_or(false))
         .unwrap_or(green.children().count());
 
-    green = green.insert_child(brace_index, uri_node.green().into());
-    green = green.insert_child(brace_index, parse_node(&entry_indent).green().into());
-
-    let mut offset = 2;
-    if !flake {
-        let no_flake = make_flake_false_attr(id);
-        green = green.insert_child(
-            brace_index + offset,
-            parse_node(&entry_indent).green().into(),
-        );
-        offset += 1;
-        green = green.insert_child(brace_index + offset, no_flake.green().into());
-        offset += 1;
-    }
-
-    green = green.insert_child(
-        brace_index + offset,
-        parse_node(&closing_indent).green().into(),
+    let entry = url_entry_nodes(
+        Some(&parse_node(&entry_indent)),
+        uri_node,
+        (!flake).then(|| make_flake_false_attr(id)),
+    );
+    let closing_index = brace_index + entry.len();
+    green = green.splice_children(
+        brace_index..brace_index,
+        entry.iter().map(|n| n.green().into()),
     );
+    green = green.insert_child(closing_index, parse_node(&closing_indent).green().into());
 
     SyntaxNode::new_root(green)
 }
@@ -334,14 +356,22 @@ fn insert_after_flat_input(
         .insert_child(insert_index, follows_node.green().into());
 
     if let Some(whitespace) = get_sibling_whitespace(ref_child) {
-        let ws_str = whitespace.to_string();
-        let ws_node = parse_node(last_line_with_newline(&ws_str));
-        green = green.insert_child(insert_index, ws_node.green().into());
+        green = green.insert_child(insert_index, whitespace.green().into());
     }
 
     Some(SyntaxNode::new_root(green))
 }
 
+/// The value bound to a `url` key: the right-hand side of `url = ...;`,
+/// or the name itself for `inherit url;`, which brings it in from an
+/// enclosing scope.
+fn url_value(attr: &SyntaxNode, key: &SyntaxNode) -> Option<SyntaxNode> {
+    if attr.kind() == SyntaxKind::NODE_INHERIT {
+        return Some(key.clone());
+    }
+    key.next_sibling()
+}
+
 /// Handle a flat-style URL attribute (`inputs.foo.url = "..."`), returning the
 /// replacement node when `change` modifies it.
 fn handle_flat_url(
@@ -403,11 +433,15 @@ fn handle_nested_input(
 
     for attr in nested_attr.children() {
         for binding in attr.children() {
-            if binding.to_string() == "url" {
-                let url = binding.next_sibling().unwrap();
+            if binding.to_string() == "url"
+                && let Some(url) = url_value(&attr, &binding)
+            {
                 let input = Input::with_url(id_seg.clone(), url.to_string(), url.text_range());
                 insert_with_ctx(inputs, id_seg.clone(), input, ctx);
             }
+            if binding.to_string() == "flake" {
+                record_flake_leaf(inputs, &id_seg, &binding, ctx);
+            }
             if should_remove_input(change, ctx, &id_seg) {
                 return Some(empty_node());
             }
@@ -423,6 +457,76 @@ fn handle_nested_input(
     None
 }
 
+/// Record the `flake = <bool>;` binding of an attrset-style input. Inside
+/// another input's attrset it would be misread as a follows, so only
+/// top-level inputs are recorded.
+fn record_flake_leaf(
+    inputs: &mut HashMap<String, Input>,
+    id_seg: &Segment,
+    leaf: &SyntaxNode,
+    ctx: &Option<Context>,
+) {
+    if ctx.is_some() {
+        return;
+    }
+    let Some(is_flake) = leaf
+        .next_sibling()
+        .and_then(|value| value.to_string().parse().ok())
+    else {
+        return;
+    };
+    let mut input = Input::new(id_seg.clone());
+    input.flake = is_flake;
+    insert_with_ctx(inputs, id_seg.clone(), input, ctx);
+}
+
+/// Record the inputs an `inherit` brings into the `inputs` block. Their
+/// declarations live elsewhere, so each is listed with the inherited
+/// expression (`nixpkgs`, or `sources.nixpkgs` for `inherit (sources)
+/// nixpkgs;`) as an unresolved url and left alone by every edit.
+fn record_inherited_inputs(inputs: &mut HashMap<String, Input>, inherit: &SyntaxNode) {
+    let from = inherit
+        .children()
+        .find(|c| c.kind() == SyntaxKind::NODE_INHERIT_FROM)
+        .and_then(|from| from.children().next())
+        .map(|expr| expr.to_string());
+    for name in inherit
+        .children()
+        .filter(|c| c.kind() != SyntaxKind::NODE_INHERIT_FROM)
+    {
+        let id_seg = Segment::from_syntax_or_sentinel(&name);
+        let expr = match &from {
+            Some(from) => format!("{from}.{name}"),
+            None => name.to_string(),
+        };
+        let input = Input::with_url(id_seg.clone(), expr, name.text_range());
+        insert_with_ctx(inputs, id_seg, input, &None);
+    }
+}
+
+/// Drop the input `change` removes from an `inherit`: the whole statement
+/// when it is the only name, otherwise just the name.
+fn remove_inherited_input(inherit: &SyntaxNode, change: &Change) -> Option<SyntaxNode> {
+    let names: Vec<SyntaxNode> = inherit
+        .children()
+        .filter(|c| c.kind() != SyntaxKind::NODE_INHERIT_FROM)
+        .collect();
+    let name = names
+        .iter()
+        .find(|name| should_remove_input(change, &None, &Segment::from_syntax_or_sentinel(name)))?;
+    if names.len() == 1 {
+        return Some(empty_node());
+    }
+    let mut green = inherit.green().remove_child(name.index());
+    if let Some(ws) = name
+        .prev_sibling_or_token()
+        .filter(|t| t.kind() == SyntaxKind::TOKEN_WHITESPACE)
+    {
+        green = green.remove_child(ws.index());
+    }
+    Some(SyntaxNode::new_root(green))
+}
+
 /// Handle a `NODE_IDENT` child during input walking, covering flat-style
 /// declarations like `inputs.nixpkgs.url = "..."`.
 fn handle_child_ident(
@@ -515,15 +619,7 @@ fn handle_child_attrpath_value(
             .replace_child(child.index(), replacement.green().into());
 
         if replacement.text().is_empty() {
-            let mut to_remove: Vec<usize> = trailing_inline_comments(child)
-                .iter()
-                .map(|t| t.index())
-                .collect();
-            if let Some(ws_index) = adjacent_whitespace_index(child) {
-                to_remove.push(ws_index);
-            }
-            to_remove.sort_unstable();
-            for idx in to_remove.into_iter().rev() {
+            for idx in removal_companions(child).into_iter().rev() {
                 green = green.remove_child(idx);
             }
         }
@@ -580,51 +676,33 @@ fn insert_added_input_into_block(
 
     let use_attrset = uses_attrset_style(parent);
 
-    // Reuse the whitespace before the last input but normalize to a single
-    // newline + indent. Copying the raw inter-entry whitespace would
-    // duplicate blank lines when the closing brace already has one.
+    // Reuse the whitespace before the last input, already cut down to a
+    // single newline + indent.
     let ws_reference = last_attr.as_ref().unwrap_or(child_node);
-    if let Some(whitespace) = get_sibling_whitespace(ws_reference) {
-        let ws_str = whitespace.to_string();
-        let ws_node = parse_node(last_line_with_newline(&ws_str));
-        let mut green = parent
-            .green()
-            .insert_child(insert_index, ws_node.green().into());
-        let mut offset = 1;
+    let ws_node = get_sibling_whitespace(ws_reference);
 
-        if use_attrset {
-            let indent = extract_indent(&ws_str);
+    let entry = match &ws_node {
+        // The attrset form carries `flake = false;` inside its own braces.
+        Some(ws) if use_attrset => {
+            let ws = ws.to_string();
+            let indent = extract_indent(&ws);
             let uri_node = if flake {
                 make_attrset_url_attr(id, uri, indent)
             } else {
                 make_attrset_url_flake_false_attr(id, uri, indent)
             };
-            green = green.insert_child(insert_index + offset, uri_node.green().into());
-        } else {
-            let uri_node = make_url_attr(id, uri);
-            green = green.insert_child(insert_index + offset, uri_node.green().into());
-            offset += 1;
-
-            if !flake {
-                let no_flake = make_flake_false_attr(id);
-                let compact_ws_node = parse_node(last_line_with_newline(&ws_str));
-                green = green.insert_child(insert_index + offset, compact_ws_node.green().into());
-                offset += 1;
-                green = green.insert_child(insert_index + offset, no_flake.green().into());
-            }
+            url_entry_nodes(ws_node.as_ref(), uri_node, None)
         }
-        return SyntaxNode::new_root(green);
-    }
-
-    let uri_node = make_url_attr(id, uri);
-    let mut green = parent
-        .green()
-        .insert_child(insert_index, uri_node.green().into());
-
-    if !flake {
-        let no_flake = make_flake_false_attr(id);
-        green = green.insert_child(insert_index + 1, no_flake.green().into());
-    }
+        _ => url_entry_nodes(
+            ws_node.as_ref(),
+            make_url_attr(id, uri),
+            (!flake).then(|| make_flake_false_attr(id)),
+        ),
+    };
+    let green = parent.green().splice_children(
+        insert_index..insert_index,
+        entry.iter().map(|n| n.green().into()),
+    );
     SyntaxNode::new_root(green)
 }
 
@@ -1018,7 +1096,8 @@ fn handle_follows_attr(
 /// ctx-supplied owner). Otherwise chain[0] is treated as the owner ident
 /// and stripped from the nested path; a `ctx` that already names the owner
 /// still wins the owner slot, but chain handling matches by structural shape,
-/// not by ident equality.
+/// not by ident equality. Nested inputs the walk has descended into through
+/// `ctx` prefix the nested path.
 ///
 /// Precondition: `chain` is non-empty.
 fn resolve_follows_owner_and_nested(
@@ -1032,30 +1111,29 @@ fn resolve_follows_owner_and_nested(
         .map(|c| c.to_string() == "inputs")
         .unwrap_or(false);
     let chain_first = Segment::from_syntax_or_sentinel(&chain[0]);
-    match ctx.as_ref().and_then(|c| c.first().cloned()) {
-        Some(ctx_owner) if attrpath_starts_with_inputs => (
-            ctx_owner,
-            chain.iter().map(Segment::from_syntax_or_sentinel).collect(),
-        ),
-        Some(ctx_owner) if ctx_owner == chain_first => (
-            ctx_owner,
-            chain[1..]
-                .iter()
-                .map(Segment::from_syntax_or_sentinel)
-                .collect(),
-        ),
-        Some(ctx_owner) => (
-            ctx_owner,
-            chain.iter().map(Segment::from_syntax_or_sentinel).collect(),
-        ),
-        None => (
+    let Some((ctx_owner, ctx_nested)) = ctx
+        .as_ref()
+        .and_then(|c| c.first().cloned().map(|owner| (owner, c.nested())))
+    else {
+        return (
             chain_first,
             chain[1..]
                 .iter()
                 .map(Segment::from_syntax_or_sentinel)
                 .collect(),
-        ),
-    }
+        );
+    };
+    let chain = if !attrpath_starts_with_inputs && ctx_owner == chain_first {
+        &chain[1..]
+    } else {
+        chain
+    };
+    let nested = ctx_nested
+        .iter()
+        .cloned()
+        .chain(chain.iter().map(Segment::from_syntax_or_sentinel))
+        .collect();
+    (ctx_owner, nested)
 }
 
 /// Dispatch a `NODE_ATTRPATH` inside an input attrset to the `url`, `flake`,
@@ -1200,7 +1278,7 @@ fn handle_url_leaf(
     let id_node = child.prev_sibling().unwrap();
     let id_seg = Segment::from_syntax_or_sentinel(&id_node);
     let id_str = id_seg.as_str().to_string();
-    let uri = leaf.next_sibling().unwrap();
+    let uri = url_value(attr, leaf)?;
     let input = Input::with_url(id_seg.clone(), uri.to_string(), uri.text_range());
     insert_with_ctx(inputs, id_seg.clone(), input, ctx);
 
@@ -1219,9 +1297,10 @@ fn handle_url_leaf(
     } = change
         && change_id.input().as_str() == id_str
         && change_id.follows().is_none()
+        && let Some(value) = leaf.next_sibling()
     {
         let new_url = make_quoted_string(new_uri);
-        let new_attr = substitute_child(attr, leaf.next_sibling().unwrap().index(), &new_url);
+        let new_attr = substitute_child(attr, value.index(), &new_url);
         let new_child = substitute_child(child, attr.index(), &new_attr);
         return Some(substitute_child(node, child.index(), &new_child));
     }
@@ -1233,19 +1312,25 @@ fn handle_url_leaf(
 /// path: recursing through [`walk_inputs`] strips the matching entry
 /// inside the nested attrset, but cannot prune the now-empty `inputs`
 /// binding from the outer input block.
+///
+/// `ctx` is the context around `node`. The walk below `child` runs one
+/// nested input further down, see [`block_context`].
 fn handle_inputs_leaf(
     inputs: &mut HashMap<String, Input>,
     node: &SyntaxNode,
     child: &SyntaxNode,
     attr: &SyntaxNode,
     leaf: &SyntaxNode,
+    ctx: &Option<Context>,
     change: &Change,
 ) -> Option<SyntaxNode> {
     let id_node = child.prev_sibling().unwrap();
-    let id_seg = Segment::from_syntax_or_sentinel(&id_node);
-    let context: Context = id_seg.clone().into();
-    let ctx_some = Some(context);
+    let ctx_some = block_context(&id_node, ctx);
     if let Some(replacement) = walk_inputs(inputs, child.clone(), &ctx_some, change) {
+        // A nested input's block left without bindings goes with its key.
+        if ctx.is_some() && change.is_remove() && is_attrset_content_empty(&replacement) {
+            return Some(empty_node());
+        }
         return Some(substitute_child(node, child.index(), &replacement));
     }
 
@@ -1292,6 +1377,72 @@ fn handle_inputs_leaf(
     None
 }
 
+/// Context for the body of the input binding keyed by `key`.
+///
+/// Key segments before the first `inputs` keyword name the owning input,
+/// which an enclosing `ctx` already carries. The segments after it name
+/// nested inputs. A key without the keyword is the owner itself at the top
+/// level and a nested input inside an enclosing `inputs = { ... }` block.
+fn block_context(key: &SyntaxNode, ctx: &Option<Context>) -> Option<Context> {
+    let idents: Vec<SyntaxNode> = if key.kind() == SyntaxKind::NODE_ATTRPATH {
+        key.children().collect()
+    } else {
+        vec![key.clone()]
+    };
+    let keyword = idents.iter().position(|i| i.to_string() == "inputs");
+    let (owner, nested) = match keyword {
+        Some(idx) => idents.split_at(idx),
+        None => (idents.as_slice(), &[][..]),
+    };
+    let nested = nested
+        .iter()
+        .filter(|i| i.to_string() != "inputs")
+        .map(Segment::from_syntax_or_sentinel);
+    match ctx {
+        Some(ctx) if keyword.is_some() => Some(ctx.descend(nested)),
+        Some(ctx) => Some(ctx.descend(owner.iter().map(Segment::from_syntax_or_sentinel))),
+        None => {
+            let mut segments = owner
+                .iter()
+                .map(Segment::from_syntax_or_sentinel)
+                .chain(nested);
+            let first = segments.next()?;
+            Some(Context::from(first).descend(segments))
+        }
+    }
+}
+
+/// Walk each entry of an `inputs = { ... }` block inside an input, so that
+/// nested inputs declared there (`flake-utils = { inputs... }` or
+/// `flake-utils.inputs.systems.follows = ...`) reach the follows handlers.
+/// The block goes with its key once a removal leaves it without bindings.
+fn walk_nested_inputs_block(
+    inputs: &mut HashMap<String, Input>,
+    node: &SyntaxNode,
+    block: &SyntaxNode,
+    ctx: &Option<Context>,
+    change: &Change,
+) -> Option<SyntaxNode> {
+    for entry in block
+        .children()
+        .filter(|c| c.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+    {
+        let Some(replacement) = walk_input(inputs, &entry, ctx, change) else {
+            continue;
+        };
+        let new_block = if replacement.text().is_empty() {
+            remove_child_with_whitespace(block, &entry, entry.index())
+        } else {
+            substitute_child(block, entry.index(), &replacement)
+        };
+        if is_attrset_content_empty(&new_block) {
+            return Some(empty_node());
+        }
+        return Some(substitute_child(node, block.index(), &new_block));
+    }
+    None
+}
+
 fn find_inputs_block_attr(parent: &SyntaxNode) -> Option<SyntaxNode> {
     parent.children().find(|c| {
         if c.kind() != SyntaxKind::NODE_ATTRPATH_VALUE {
@@ -1343,9 +1494,7 @@ fn merge_follow_into_inputs_block(
             .green()
             .insert_child(insert_index, follows_node.green().into());
         if let Some(whitespace) = get_sibling_whitespace(&last_attr) {
-            let ws_str = whitespace.to_string();
-            let ws_node = parse_node(last_line_with_newline(&ws_str));
-            green = green.insert_child(insert_index, ws_node.green().into());
+            green = green.insert_child(insert_index, whitespace.green().into());
         }
         SyntaxNode::new_root(green)
     } else {
@@ -1428,6 +1577,18 @@ fn handle_input_attr_set(
     ctx: &Option<Context>,
     change: &Change,
 ) -> Option<SyntaxNode> {
+    // Only listing and removal descend into an input's own `inputs` block.
+    // The other changes address top-level inputs.
+    if ctx.is_some()
+        && matches!(change, Change::None | Change::Remove { .. })
+        && child
+            .prev_sibling()
+            .is_some_and(|key| key.to_string() == "inputs")
+        && let Some(result) = walk_nested_inputs_block(inputs, node, child, ctx, change)
+    {
+        return Some(result);
+    }
+
     for attr in child.children() {
         for leaf in attr.children() {
             let leaf_text = leaf.to_string();
@@ -1438,8 +1599,16 @@ fn handle_input_attr_set(
                 return Some(result);
             }
 
+            if leaf_text == "flake"
+                && let Some(id_node) = child.prev_sibling()
+            {
+                let id_seg = Segment::from_syntax_or_sentinel(&id_node);
+                record_flake_leaf(inputs, &id_seg, &leaf, ctx);
+            }
+
             if leaf_text.starts_with("inputs")
-                && let Some(result) = handle_inputs_leaf(inputs, node, child, &attr, &leaf, change)
+                && let Some(result) =
+                    handle_inputs_leaf(inputs, node, child, &attr, &leaf, ctx, change)
             {
                 return Some(result);
             }
@@ -1741,6 +1910,76 @@ mod tests {
         );
     }
 
+    #[test]
+    fn nested_block_follows_keep_the_full_path() {
+        let flake = r#"{
+  inputs = {
+    naersk = {
+      url = "github:nix-community/naersk";
+      inputs = {
+        flake-utils = {
+          inputs.systems.follows = "systems";
+          inputs.nixpkgs.follows = "nixpkgs";
+        };
+      };
+    };
+    fenix = {
+      url = "github:nix-community/fenix";
+      inputs.rust-analyzer = {
+        inputs.flake-utils.inputs.systems.follows = "systems";
+      };
+    };
+  };
+
+  outputs = { self, ... }: { };
+}
+"#;
+        let mut walker = Walker::new(flake);
+        let _ = walker.walk(&Change::None).expect("walker error");
+        let paths = |id: &str| -> Vec<String> {
+            walker.inputs[id]
+                .follows
+                .iter()
+                .map(|f| match f {
+                    crate::input::Follows::Indirect { path, .. } => path.to_string(),
+                    crate::input::Follows::Direct(name, _) => name.clone(),
+                })
+                .collect()
+        };
+        assert_eq!(
+            paths("naersk"),
+            ["flake-utils.nixpkgs", "flake-utils.systems"]
+        );
+        assert_eq!(paths("fenix"), ["rust-analyzer.flake-utils.systems"]);
+
+        let change = Change::Remove {
+            ids: vec![ChangeId::parse("naersk.flake-utils.systems").unwrap()],
+        };
+        let result = apply(flake, &change);
+        assert_eq!(
+            result.matches("inputs.systems.follows").count(),
+            1,
+            "only naersk's depth-2 follows should be removed, got:\n{result}"
+        );
+        assert!(
+            result.contains("inputs.nixpkgs.follows = \"nixpkgs\""),
+            "sibling depth-2 follows must remain intact, got:\n{result}"
+        );
+
+        let change = Change::Remove {
+            ids: vec![ChangeId::parse("fenix.rust-analyzer.flake-utils.systems").unwrap()],
+        };
+        let result = apply(flake, &change);
+        assert!(
+            !result.contains("rust-analyzer"),
+            "emptied nested block should be pruned, got:\n{result}"
+        );
+        assert!(
+            result.contains("url = \"github:nix-community/fenix\""),
+            "parent input's url binding must remain intact, got:\n{result}"
+        );
+    }
+
     #[test]
     fn remove_nested_follows_prunes_empty_intermediate_block() {
         // The depth-1 follows is the only entry in `disko.inputs = { ... }`.
@@ -1811,6 +2050,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn remove_nested_follows_preserves_detached_comment_inside_block() {
+        // Same as above with a blank line between the comment and the
+        // binding, so the comment is not attached to what is removed.
+        let flake = r#"{
+  inputs = {
+    disko = {
+      url = "github:nix-community/disko";
+      inputs = {
+        # keep this annotation
+
+        nixpkgs.follows = "nixpkgs";
+      };
+    };
+  };
+
+  outputs = _: { };
+}
+"#;
+        let change = Change::Remove {
+            ids: vec![ChangeId::parse("disko.nixpkgs").unwrap()],
+        };
+        let result = apply_until_fixed(flake, &change);
+
+        assert!(
+            result.contains("# keep this annotation") && result.contains("inputs = {"),
+            "detached comment must keep the inputs block, got:\n{result}"
+        );
+    }
+
     #[test]
     fn pre_existing_empty_inputs_block_is_not_touched() {
         // The user wrote an empty `inputs = { };` deliberately on `disko`,
@@ -1932,6 +2201,7 @@ mod tests {
         let change = Change::Change {
             id: Some(ChangeId::parse("nixpkgs").unwrap()),
             uri: Some("github:NixOS/nixpkgs/nixos-23.11".to_string()),
+            rename: None,
         };
         let result = walk_children(&mut map, &inputs_block, &None, &change)
             .expect("Change must rewrite the tree");
@@ -2196,6 +2466,7 @@ mod tests {
         let change = Change::Change {
             id: Some(ChangeId::parse("nixpkgs").unwrap()),
             uri: Some("github:NixOS/nixpkgs/nixos-23.11".to_string()),
+            rename: None,
         };
         let result = handle_url_leaf(&mut map, &node, &child, &attr, &leaf, &None, &change)
             .expect("matching Change::Change must rewrite");
@@ -2222,7 +2493,7 @@ mod tests {
         let change = Change::Remove {
             ids: vec![ChangeId::parse("flake-edit.nixpkgs").unwrap()],
         };
-        let result = handle_inputs_leaf(&mut map, &node, &child, &attr, &leaf, &change)
+        let result = handle_inputs_leaf(&mut map, &node, &child, &attr, &leaf, &None, &change)
             .expect("nested follows removal must rewrite");
         assert!(
             !result.to_string().contains("inputs.nixpkgs.follows"),
@@ -2252,7 +2523,7 @@ mod tests {
         let change = Change::Remove {
             ids: vec![ChangeId::parse("unrelated").unwrap()],
         };
-        let result = handle_inputs_leaf(&mut map, &node, &child, &attr, &leaf, &change);
+        let result = handle_inputs_leaf(&mut map, &node, &child, &attr, &leaf, &None, &change);
         assert!(result.is_none(), "unrelated removal must not rewrite");
     }
 
@@ -2497,6 +2768,7 @@ mod tests {
         let change = Change::Change {
             id: Some(ChangeId::parse("nixpkgs").unwrap()),
             uri: Some("github:NixOS/nixpkgs/nixos-23.11".to_string()),
+            rename: None,
         };
         let result = apply(flake, &change);
         assert_eq!(
@@ -2525,6 +2797,7 @@ mod tests {
         let change = Change::Change {
             id: Some(ChangeId::parse("flake-edit").unwrap()),
             uri: Some("github:a-kenji/flake-edit".to_string()),
+            rename: None,
         };
         assert!(apply_maybe(flake, &change).is_none());
     }
@@ -2,6 +2,7 @@ use rnix::{Root, SyntaxKind, SyntaxNode};
 
 use crate::change::Change;
 use crate::follows::{AttrPath, Segment};
+use crate::input::escape_string;
 
 use super::context::Context;
 
@@ -56,19 +57,19 @@ pub(crate) fn is_attrset_content_empty(node: &SyntaxNode) -> bool {
         .any(|t| t.kind() == SyntaxKind::TOKEN_COMMENT)
 }
 
-/// Whitespace node copied from `node`'s previous sibling (or next, as fallback).
+/// Whitespace node copied from `node`'s previous sibling (or next, as fallback),
+/// cut down to its last line so a blank line separating `node`'s group is not
+/// repeated in front of the inserted entry.
 pub(crate) fn get_sibling_whitespace(node: &SyntaxNode) -> Option<Node> {
-    if let Some(prev) = node.prev_sibling_or_token()
-        && prev.kind() == SyntaxKind::TOKEN_WHITESPACE
-    {
-        return Some(parse_node(prev.as_token().unwrap().green().text()));
-    }
-    if let Some(next) = node.next_sibling_or_token()
-        && next.kind() == SyntaxKind::TOKEN_WHITESPACE
-    {
-        return Some(parse_node(next.as_token().unwrap().green().text()));
-    }
-    None
+    let element: rnix::SyntaxElement = node.clone().into();
+    let ws = [
+        element.prev_sibling_or_token(),
+        element.next_sibling_or_token(),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|t| t.kind() == SyntaxKind::TOKEN_WHITESPACE)?;
+    Some(parse_node(last_line_with_newline(&ws.to_string())))
 }
 
 /// Insertion index after `node`, skipping trailing same-line whitespace and comments.
@@ -128,19 +129,91 @@ pub(crate) fn trailing_inline_comments(child: &rnix::SyntaxElement) -> Vec<rnix:
     Vec::new()
 }
 
-/// Remove `node` from `parent` along with any adjacent whitespace token and a
-/// trailing same-line comment (so the comment does not move onto a neighbour).
+/// Whether `el` starts its line: its previous sibling element is
+/// line-breaking whitespace (or it is the first child).
+pub(crate) fn on_own_line(el: &rnix::SyntaxElement) -> bool {
+    match el.prev_sibling_or_token() {
+        None => true,
+        Some(prev) => {
+            prev.kind() == SyntaxKind::TOKEN_WHITESPACE && prev.to_string().contains('\n')
+        }
+    }
+}
+
+/// The own-line comment block directly above `child`, nearest first, with
+/// the single-newline whitespace tokens between its lines.
+///
+/// A blank line ends the block, and so does a comment that trails an
+/// earlier statement on its line: both belong to what comes before, not
+/// to `child`.
+pub(crate) fn leading_attached_comments(child: &rnix::SyntaxElement) -> Vec<rnix::SyntaxElement> {
+    let mut attached = Vec::new();
+    let mut cursor = child.prev_sibling_or_token();
+    while let Some(ws) = cursor {
+        if ws.kind() != SyntaxKind::TOKEN_WHITESPACE || ws.to_string().matches('\n').count() != 1 {
+            break;
+        }
+        let Some(comment) = ws.prev_sibling_or_token() else {
+            break;
+        };
+        if comment.kind() != SyntaxKind::TOKEN_COMMENT || !on_own_line(&comment) {
+            break;
+        }
+        cursor = comment.prev_sibling_or_token();
+        attached.push(ws);
+        attached.push(comment);
+    }
+    attached
+}
+
+/// Sibling indices to strip along with a removed `child`, ascending: its
+/// trailing same-line comment, the comment block attached above it, and
+/// one whitespace token separating the removed lines from their
+/// neighbours. `child`'s own index is not included.
+///
+/// The comment block above the last entry of an attrset stays: it is all
+/// that is left of the block's content, and keeps it from being pruned.
+pub(crate) fn removal_companions(child: &rnix::SyntaxElement) -> Vec<usize> {
+    let leading = if has_sibling_entries(child) {
+        leading_attached_comments(child)
+    } else {
+        Vec::new()
+    };
+    let trailing = trailing_inline_comments(child);
+    let mut indices: Vec<usize> = trailing.iter().chain(&leading).map(|t| t.index()).collect();
+    let top = leading.last().unwrap_or(child);
+    let bottom = trailing.last().unwrap_or(child);
+    if let Some(ws_index) = separator_whitespace_index(top, bottom) {
+        indices.push(ws_index);
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+/// Whether `child`'s parent holds a binding or `inherit` besides `child`.
+fn has_sibling_entries(child: &rnix::SyntaxElement) -> bool {
+    child.parent().is_some_and(|parent| {
+        parent.children().any(|node| {
+            matches!(
+                node.kind(),
+                SyntaxKind::NODE_ATTRPATH_VALUE | SyntaxKind::NODE_INHERIT
+            ) && rnix::SyntaxElement::from(node) != *child
+        })
+    })
+}
+
+/// Remove `node` from `parent` along with the lines that belong to it: an
+/// adjacent whitespace token, a trailing same-line comment (so it does not
+/// move onto a neighbour), and the comment block directly above it.
 pub(crate) fn remove_child_with_whitespace(
     parent: &SyntaxNode,
     node: &SyntaxNode,
     index: usize,
 ) -> SyntaxNode {
     let element: rnix::SyntaxElement = node.clone().into();
-    let mut to_remove = vec![index];
-    to_remove.extend(trailing_inline_comments(&element).iter().map(|t| t.index()));
-    if let Some(ws_index) = adjacent_whitespace_index(&element) {
-        to_remove.push(ws_index);
-    }
+    let mut to_remove = removal_companions(&element);
+    to_remove.push(index);
     to_remove.sort_unstable();
 
     // Remove highest index first so earlier indices stay valid against the
@@ -211,6 +284,45 @@ pub(crate) fn last_line_with_newline(ws_str: &str) -> &str {
     }
 }
 
+/// Whitespace token to strip with the lines from `top` to `bottom`.
+///
+/// Usually the one above them. When that one holds a blank line separating
+/// the group the lines open, and another entry follows in the same group,
+/// the one below goes instead so the separator survives for that entry.
+/// The one below also goes when the lines open the block and a blank line
+/// follows them, which would otherwise end up right after the `{`.
+fn separator_whitespace_index(
+    top: &rnix::SyntaxElement,
+    bottom: &rnix::SyntaxElement,
+) -> Option<usize> {
+    let is_blank_line = |ws: &rnix::SyntaxElement| {
+        ws.kind() == SyntaxKind::TOKEN_WHITESPACE && ws.to_string().matches('\n').count() > 1
+    };
+    if top.prev_sibling_or_token().is_some_and(|ws| {
+        ws.kind() == SyntaxKind::TOKEN_WHITESPACE
+            && ws
+                .prev_sibling_or_token()
+                .is_some_and(|brace| brace.kind() == SyntaxKind::TOKEN_L_BRACE)
+    }) && let Some(next) = bottom.next_sibling_or_token()
+        && is_blank_line(&next)
+    {
+        return Some(next.index());
+    }
+    if top
+        .prev_sibling_or_token()
+        .is_some_and(|ws| is_blank_line(&ws))
+        && let Some(next) = bottom.next_sibling_or_token()
+        && next.kind() == SyntaxKind::TOKEN_WHITESPACE
+        && !is_blank_line(&next)
+        && next
+            .next_sibling_or_token()
+            .is_some_and(|after| after.kind() != SyntaxKind::TOKEN_R_BRACE)
+    {
+        return Some(next.index());
+    }
+    adjacent_whitespace_index(top)
+}
+
 /// Index of `child`'s adjacent whitespace token (preferring the previous sibling)
 /// for stripping after a removal or replacement.
 pub(crate) fn adjacent_whitespace_index(child: &rnix::SyntaxElement) -> Option<usize> {
@@ -266,14 +378,14 @@ pub(crate) fn should_remove_nested_input(
     false
 }
 
-/// Quoted string node, e.g. `"github:NixOS/nixpkgs"`.
+/// Quoted string node, e.g. `"github:NixOS/nixpkgs"`. `s` is escaped.
 pub(crate) fn make_quoted_string(s: &str) -> Node {
-    parse_node(&format!("\"{}\"", s))
+    parse_node(&format!("\"{}\"", escape_string(s)))
 }
 
 /// Top-level URL attribute, e.g. `inputs.nixpkgs.url = "github:NixOS/nixpkgs";`.
 pub(crate) fn make_toplevel_url_attr(id: &str, uri: &str) -> Node {
-    parse_node(&format!("inputs.{}.url = \"{}\";", id, uri))
+    parse_node(&format!("inputs.{}.url = \"{}\";", id, escape_string(uri)))
 }
 
 /// Top-level `flake = false` attribute, e.g. `inputs.not_a_flake.flake = false;`.
@@ -283,7 +395,7 @@ pub(crate) fn make_toplevel_flake_false_attr(id: &str) -> Node {
 
 /// Nested URL attribute, e.g. `nixpkgs.url = "github:NixOS/nixpkgs";`.
 pub(crate) fn make_url_attr(id: &str, uri: &str) -> Node {
-    parse_node(&format!("{}.url = \"{}\";", id, uri))
+    parse_node(&format!("{}.url = \"{}\";", id, escape_string(uri)))
 }
 
 /// Nested `flake = false` attribute, e.g. `not_a_flake.flake = false;`.
@@ -298,7 +410,10 @@ pub(crate) fn make_flake_false_attr(id: &str) -> Node {
 pub(crate) fn make_attrset_url_attr(id: &str, uri: &str, indent: &str) -> Node {
     parse_node(&format!(
         "{} = {{\n{}  url = \"{}\";\n{}}};",
-        id, indent, uri, indent
+        id,
+        indent,
+        escape_string(uri),
+        indent
     ))
 }
 
@@ -306,10 +421,33 @@ pub(crate) fn make_attrset_url_attr(id: &str, uri: &str, indent: &str) -> Node {
 pub(crate) fn make_attrset_url_flake_false_attr(id: &str, uri: &str, indent: &str) -> Node {
     parse_node(&format!(
         "{} = {{\n{}  url = \"{}\";\n{}  flake = false;\n{}}};",
-        id, indent, uri, indent, indent
+        id,
+        indent,
+        escape_string(uri),
+        indent,
+        indent
     ))
 }
 
+/// Children spliced in for a newly added input: `url_attr`, followed by
+/// `flake_false` when the input opts out of flake mode. With `ws`, each
+/// attribute is preceded by that whitespace so it starts its own line.
+///
+/// Every add site goes through this so `flake = false;` lands right after
+/// the url in the empty-block, in-block, and top-level flat styles alike.
+pub(crate) fn url_entry_nodes(
+    ws: Option<&Node>,
+    url_attr: Node,
+    flake_false: Option<Node>,
+) -> Vec<Node> {
+    let mut nodes = Vec::with_capacity(4);
+    for attr in std::iter::once(url_attr).chain(flake_false) {
+        nodes.extend(ws.cloned());
+        nodes.push(attr);
+    }
+    nodes
+}
+
 /// Shape of a `follows = ...` attribute to splice into the CST.
 ///
 /// Each variant captures both the attrpath layout and the surrounding insertion context.
@@ -545,4 +683,80 @@ mod tests {
         let result = remove_child_with_whitespace(&attr_set, &a, a.index());
         assert_eq!(result.to_string(), "{b = 2;}");
     }
+
+    fn remove_named(src: &str, name: &str) -> String {
+        let root = parse_node(src);
+        let attr_set = root.first_child().expect("attr set");
+        let node = attr_set
+            .children()
+            .find(|c| c.to_string().starts_with(name))
+            .expect("binding present");
+        remove_child_with_whitespace(&attr_set, &node, node.index()).to_string()
+    }
+
+    #[test]
+    fn remove_child_sweeps_attached_comment_block() {
+        let src = "{\n  a = 1;\n  # pin to stable\n  # see #123\n  b = 2;\n  c = 3;\n}";
+        assert_eq!(remove_named(src, "b ="), "{\n  a = 1;\n  c = 3;\n}");
+    }
+
+    #[test]
+    fn remove_child_sweeps_attached_comment_on_first_child() {
+        let src = "{\n  # pin to stable\n  a = 1;\n  b = 2;\n}";
+        assert_eq!(remove_named(src, "a ="), "{\n  b = 2;\n}");
+    }
+
+    #[test]
+    fn remove_child_keeps_comment_after_blank_line() {
+        let src = "{\n  a = 1;\n  # about b and c\n\n  b = 2;\n  c = 3;\n}";
+        assert_eq!(
+            remove_named(src, "b ="),
+            "{\n  a = 1;\n  # about b and c\n\n  c = 3;\n}"
+        );
+    }
+
+    #[test]
+    fn remove_child_keeps_blank_line_before_rest_of_group() {
+        let src = "{\n  a = 1;\n\n  b = 2;\n  c = 3;\n}";
+        assert_eq!(remove_named(src, "b ="), "{\n  a = 1;\n\n  c = 3;\n}");
+    }
+
+    #[test]
+    fn remove_child_keeps_one_blank_line_for_single_entry_group() {
+        let src = "{\n  a = 1;\n\n  b = 2;\n\n  c = 3;\n}";
+        assert_eq!(remove_named(src, "b ="), "{\n  a = 1;\n\n  c = 3;\n}");
+    }
+
+    #[test]
+    fn remove_child_drops_blank_line_of_emptied_last_group() {
+        let src = "{\n  a = 1;\n\n  b = 2;\n}";
+        assert_eq!(remove_named(src, "b ="), "{\n  a = 1;\n}");
+    }
+
+    #[test]
+    fn remove_child_drops_blank_line_of_emptied_first_group() {
+        let src = "{\n  a = 1;\n\n  b = 2;\n}";
+        assert_eq!(remove_named(src, "a ="), "{\n  b = 2;\n}");
+    }
+
+    #[test]
+    fn remove_child_keeps_blank_line_above_commented_group_head() {
+        let src = "{\n  a = 1;\n\n  # dev tools\n  b = 2;\n  c = 3;\n}";
+        assert_eq!(remove_named(src, "b ="), "{\n  a = 1;\n\n  c = 3;\n}");
+    }
+
+    #[test]
+    fn sibling_whitespace_drops_blank_line() {
+        let root = parse_node("{\n  a = 1;\n\n  b = 2;\n}");
+        let attr_set = root.first_child().expect("attr set");
+        let b = attr_set.children().last().expect("`b` binding present");
+        let ws = get_sibling_whitespace(&b).expect("whitespace before `b`");
+        assert_eq!(ws.to_string(), "\n  ");
+    }
+
+    #[test]
+    fn remove_child_keeps_previous_siblings_trailing_comment() {
+        let src = "{\n  a = 1; # about a\n  b = 2;\n}";
+        assert_eq!(remove_named(src, "b ="), "{\n  a = 1; # about a\n}");
+    }
 }
@@ -1,6 +1,9 @@
-use rnix::{SyntaxKind, SyntaxNode};
+use std::collections::HashSet;
+
+use rnix::{SyntaxKind, SyntaxNode, TextRange};
 
 use crate::edit::{OutputChange, Outputs};
+use crate::follows::Segment;
 
 use super::error::WalkerError;
 use super::node::parse_node;
@@ -72,6 +75,45 @@ pub(crate) fn list_outputs(root: &SyntaxNode) -> Result<Outputs, WalkerError> {
     }
 }
 
+/// Identifiers the `outputs` lambda names, in its argument pattern or its
+/// body.
+///
+/// Only answers for a lambda whose argument pattern names every input it
+/// takes: one with `...` or an `@` binding can reach any input without
+/// naming it, so those yield `None`, as does a flake without an outputs
+/// lambda. Attribute names count as references too, which errs towards
+/// calling an input used.
+pub(crate) fn outputs_idents(root: &SyntaxNode) -> Result<Option<HashSet<String>>, WalkerError> {
+    if root.kind() != SyntaxKind::NODE_ROOT {
+        return Err(WalkerError::NotARoot);
+    }
+    let Some(outputs_lambda) = outputs_lambda(root) else {
+        return Ok(None);
+    };
+    let Some(pattern) = outputs_lambda
+        .children()
+        .find(|n| n.kind() == SyntaxKind::NODE_PATTERN)
+    else {
+        return Ok(None);
+    };
+    let open = pattern.children_with_tokens().any(|c| {
+        matches!(
+            c.kind(),
+            SyntaxKind::TOKEN_ELLIPSIS | SyntaxKind::NODE_PAT_BIND
+        )
+    });
+    if open {
+        return Ok(None);
+    }
+
+    let idents = outputs_lambda
+        .descendants()
+        .filter(|d| d.kind() == SyntaxKind::NODE_IDENT)
+        .map(|d| d.to_string())
+        .collect();
+    Ok(Some(idents))
+}
+
 /// Whether a lambda parameter pattern ends its last `NODE_PAT_ENTRY` with
 /// a comma. Looks past any `NODE_PAT_BIND` so `{ a, b }@inputs` is judged
 /// by `b`, not by the binding.
@@ -309,10 +351,185 @@ fn remove_output_arg(pattern: &SyntaxNode, name: &str) -> Option<SyntaxNode> {
     Some(SyntaxNode::new_root(green))
 }
 
+/// The `outputs = <lambda>` value of a flake.nix root, parentheses
+/// unwrapped.
+fn outputs_lambda(root: &SyntaxNode) -> Option<SyntaxNode> {
+    super::flake_attr_set(root)?
+        .children()
+        .filter(|c| c.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        .filter_map(|toplevel| {
+            let outputs_node = toplevel
+                .children()
+                .find(|child| child.to_string() == "outputs")?;
+            Some(unwrap_parens(&outputs_node.next_sibling()?))
+        })
+        .find(|lambda| lambda.kind() == SyntaxKind::NODE_LAMBDA)
+}
+
+/// Whether the `NODE_ATTRPATH_VALUE` children of `node` bind names in
+/// scope: a `let` block or a `rec` attribute set.
+fn binds_names(node: &SyntaxNode) -> bool {
+    match node.kind() {
+        SyntaxKind::NODE_LET_IN | SyntaxKind::NODE_LEGACY_LET => true,
+        SyntaxKind::NODE_ATTR_SET => node
+            .children_with_tokens()
+            .any(|c| c.kind() == SyntaxKind::TOKEN_REC),
+        _ => false,
+    }
+}
+
+/// The expression `inherit (<source>) ...;` takes its names from, with
+/// parentheses unwrapped. `None` for a bare `inherit`.
+fn inherit_source(inherit: &SyntaxNode) -> Option<SyntaxNode> {
+    inherit
+        .children()
+        .find(|c| c.kind() == SyntaxKind::NODE_INHERIT_FROM)?
+        .first_child()
+        .map(|source| unwrap_parens(&source))
+}
+
+/// Rename the outputs lambda's argument `from` to `to` wherever the
+/// lambda names it: the pattern entry, every reference to it in the body,
+/// and `<args>.from` through an `@args` binding or a plain `args:`
+/// parameter.
+///
+/// Refuses rather than leave the body naming an unbound `from`: when the
+/// body binds `from` itself, inherits it bare or from the args (that also
+/// names an attribute), reaches it through `with <args>;`, or `to` cannot
+/// be spelled as a bare identifier. Returns `None` when the lambda never
+/// names `from`.
+fn rename_output_arg(
+    root: &SyntaxNode,
+    from: &str,
+    to: &str,
+) -> Result<Option<SyntaxNode>, WalkerError> {
+    let refuse = |reason: String| WalkerError::OutputsRename {
+        from: from.to_string(),
+        to: to.to_string(),
+        reason,
+    };
+    let Some(lambda) = outputs_lambda(root) else {
+        return Ok(None);
+    };
+    let pattern = lambda
+        .children()
+        .find(|n| n.kind() == SyntaxKind::NODE_PATTERN);
+    let entry = pattern
+        .as_ref()
+        .and_then(|p| find_pat_entry_by_name(p, from))
+        .and_then(|e| e.first_child());
+    let args = match &pattern {
+        Some(pattern) => pattern
+            .children()
+            .find(|c| c.kind() == SyntaxKind::NODE_PAT_BIND)
+            .and_then(|bind| bind.first_child()),
+        None => lambda
+            .children()
+            .find(|c| c.kind() == SyntaxKind::NODE_IDENT_PARAM)
+            .and_then(|param| param.first_child()),
+    }
+    .map(|ident| ident.to_string());
+    let Some(body) = lambda.last_child() else {
+        return Ok(None);
+    };
+
+    // `with <args>;` brings `from` into scope without naming the args.
+    if let Some(args) = &args
+        && body.descendants().any(|with| {
+            with.kind() == SyntaxKind::NODE_WITH
+                && with.first_child().is_some_and(|ns| ns.to_string() == *args)
+                && with.descendants().skip(1).any(|d| {
+                    d.kind() == SyntaxKind::NODE_IDENT
+                        && d.to_string() == from
+                        && d.parent()
+                            .is_some_and(|p| p.kind() != SyntaxKind::NODE_ATTRPATH)
+                })
+        })
+    {
+        return Err(refuse(format!(
+            "`with {args};` can name '{from}' without the args"
+        )));
+    }
+
+    let mut spans: Vec<TextRange> = entry.iter().map(|e| e.text_range()).collect();
+    let idents = body
+        .descendants()
+        .filter(|d| d.kind() == SyntaxKind::NODE_IDENT && d.to_string() == from);
+    for ident in idents {
+        let Some(parent) = ident.parent() else {
+            continue;
+        };
+        let first = parent.first_child().as_ref() == Some(&ident);
+        match parent.kind() {
+            // An attribute name: `args.from` follows the rename, a `let`
+            // or `rec` key shadows the argument, anything else is an
+            // unrelated attribute.
+            SyntaxKind::NODE_ATTRPATH => {
+                let Some(holder) = parent.parent() else {
+                    continue;
+                };
+                match holder.kind() {
+                    SyntaxKind::NODE_SELECT | SyntaxKind::NODE_HAS_ATTR
+                        if first
+                            && args.is_some()
+                            && holder.first_child().map(|e| e.to_string()) == args =>
+                    {
+                        spans.push(ident.text_range());
+                    }
+                    SyntaxKind::NODE_ATTRPATH_VALUE
+                        if first
+                            && entry.is_some()
+                            && holder.parent().is_some_and(|scope| binds_names(&scope)) =>
+                    {
+                        return Err(refuse(format!("the outputs body binds '{from}' itself")));
+                    }
+                    _ => {}
+                }
+            }
+            SyntaxKind::NODE_PAT_ENTRY
+            | SyntaxKind::NODE_IDENT_PARAM
+            | SyntaxKind::NODE_PAT_BIND
+                if entry.is_some() =>
+            {
+                return Err(refuse(format!("the outputs body binds '{from}' itself")));
+            }
+            SyntaxKind::NODE_INHERIT if entry.is_some() => {
+                return Err(refuse(format!(
+                    "`inherit {from};` would also rename the attribute it defines"
+                )));
+            }
+            SyntaxKind::NODE_INHERIT
+                if args.is_some() && inherit_source(&parent).map(|s| s.to_string()) == args =>
+            {
+                let args = args.as_deref().unwrap_or_default();
+                return Err(refuse(format!(
+                    "`inherit ({args}) {from};` would also rename the attribute it defines"
+                )));
+            }
+            _ if entry.is_some() => spans.push(ident.text_range()),
+            _ => {}
+        }
+    }
+    if spans.is_empty() {
+        return Ok(None);
+    }
+    if Segment::from_unquoted(to.to_string()).map_or(true, |s| s.needs_quoting()) {
+        return Err(refuse(format!("'{to}' is not a bare identifier")));
+    }
+
+    let mut text = root.to_string();
+    spans.sort_by_key(|span| std::cmp::Reverse(span.start()));
+    for span in spans {
+        text.replace_range(std::ops::Range::<usize>::from(span), to);
+    }
+    Ok(Some(parse_node(&text)))
+}
+
 /// Change the outputs attribute in a flake.nix root node.
 ///
-/// Locates the `outputs = <lambda>` attribute, detects the lambda
-/// pattern's style once, dispatches to [`add_output_arg`] or
+/// A rename goes to [`rename_output_arg`], which rewrites the body as
+/// well. Otherwise locates the `outputs = <lambda>` attribute, detects the
+/// lambda pattern's style once, dispatches to [`add_output_arg`] or
 /// [`remove_output_arg`], then rebuilds bottom-up
 /// (pattern -> lambda -> toplevel -> attr_set) and uses
 /// `attr_set.replace_with()` to propagate to NODE_ROOT, preserving any
@@ -324,6 +541,9 @@ pub(crate) fn change_outputs(
     if root.kind() != SyntaxKind::NODE_ROOT {
         return Err(WalkerError::NotARoot);
     }
+    if let OutputChange::Rename(from, to) = &change {
+        return rename_output_arg(root, from, to);
+    }
 
     let Some(attr_set) = super::flake_attr_set(root) else {
         return Ok(None);
@@ -359,7 +579,7 @@ pub(crate) fn change_outputs(
         let new_pattern = match &change {
             OutputChange::Add(name) => Some(add_output_arg(&pattern, name, &style)),
             OutputChange::Remove(name) => remove_output_arg(&pattern, name),
-            OutputChange::None => None,
+            OutputChange::Rename(..) | OutputChange::None => None,
         };
         let Some(new_pattern) = new_pattern else {
             continue;
@@ -628,4 +848,90 @@ mod tests {
         let new_p = remove_output_arg(&p, "self").expect("entry must be found");
         assert_eq!(new_p.to_string(), "{ nixpkgs\n, flake-utils\n}");
     }
+
+    /// The text of a flake whose outputs lambda is `outputs`.
+    fn renamed(outputs: &str, from: &str, to: &str) -> Result<Option<String>, WalkerError> {
+        let root = parse_node(&format!("{{ outputs = {outputs}; }}"));
+        Ok(rename_output_arg(&root, from, to)?.map(|root| {
+            let text = root.to_string();
+            text["{ outputs = ".len()..text.len() - "; }".len()].to_string()
+        }))
+    }
+
+    #[test]
+    fn rename_output_arg_keeps_separators() {
+        let new_p = renamed("{ self\n, nixpkgs\n, flake-utils\n}: {}", "nixpkgs", "pkgs")
+            .unwrap()
+            .expect("entry must be found");
+        assert_eq!(new_p, "{ self\n, pkgs\n, flake-utils\n}: {}");
+    }
+
+    #[test]
+    fn rename_output_arg_returns_none_for_missing_entry() {
+        let out = renamed("{ self, nixpkgs }: {}", "crane", "ipetkov-crane").unwrap();
+        assert!(out.is_none());
+    }
+
+    #[test]
+    fn rename_output_arg_rewrites_references_in_the_body() {
+        let out = renamed(
+            "{ self, nixpkgs }: { x = nixpkgs.legacyPackages; y = \"${nixpkgs}\"; inherit (nixpkgs) lib; z.nixpkgs = 1; }",
+            "nixpkgs",
+            "pkgs",
+        )
+        .unwrap();
+        assert_eq!(
+            out.as_deref(),
+            Some(
+                "{ self, pkgs }: { x = pkgs.legacyPackages; y = \"${pkgs}\"; inherit (pkgs) lib; z.nixpkgs = 1; }"
+            )
+        );
+    }
+
+    #[test]
+    fn rename_output_arg_follows_the_args_binding() {
+        let out = renamed(
+            "{ self, ... }@inputs: { x = inputs.nixpkgs.lib; y = inputs ? nixpkgs; }",
+            "nixpkgs",
+            "pkgs",
+        )
+        .unwrap();
+        assert_eq!(
+            out.as_deref(),
+            Some("{ self, ... }@inputs: { x = inputs.pkgs.lib; y = inputs ? pkgs; }")
+        );
+        let out = renamed("inputs: inputs.nixpkgs", "nixpkgs", "pkgs").unwrap();
+        assert_eq!(out.as_deref(), Some("inputs: inputs.pkgs"));
+    }
+
+    #[test]
+    fn rename_output_arg_leaves_unrelated_names_alone() {
+        let out = renamed(
+            "{ self, ... }@inputs: { x = nixpkgs; y = self.nixpkgs; }",
+            "nixpkgs",
+            "pkgs",
+        )
+        .unwrap();
+        assert!(out.is_none());
+    }
+
+    #[rstest::rstest]
+    #[case("{ nixpkgs }: let nixpkgs = 1; in nixpkgs")]
+    #[case("{ nixpkgs }: rec { nixpkgs = 1; }")]
+    #[case("{ nixpkgs }: map (nixpkgs: nixpkgs) [ ]")]
+    #[case("{ nixpkgs }: { inherit nixpkgs; }")]
+    #[case("{ self, ... }@inputs: let inherit (inputs) nixpkgs; in { x = nixpkgs; }")]
+    #[case("inputs: { inherit (inputs) self nixpkgs; }")]
+    #[case("{ self, ... }@inputs: with inputs; { x = nixpkgs; }")]
+    #[case("inputs: with inputs; nixpkgs.lib")]
+    fn rename_output_arg_refuses_what_it_cannot_follow(#[case] outputs: &str) {
+        let err = renamed(outputs, "nixpkgs", "pkgs").unwrap_err();
+        assert!(matches!(err, WalkerError::OutputsRename { .. }), "{err}");
+    }
+
+    #[test]
+    fn rename_output_arg_refuses_an_id_that_needs_quoting() {
+        let err = renamed("{ nixpkgs }: nixpkgs", "nixpkgs", "nix.pkgs").unwrap_err();
+        assert!(err.to_string().contains("not a bare identifier"), "{err}");
+    }
 }
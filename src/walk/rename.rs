@@ -0,0 +1,204 @@
+//! Renaming a top-level input.
+//!
+//! An input's id is spelled in three places: the key of every binding that
+//! declares it (`inputs.<id>.url`, or `<id>.url` and `<id> = { ... }`
+//! inside an `inputs` block), the `follows` targets that name it (`"<id>"`
+//! and `"<id>/child"`), and the outputs lambda's argument pattern. The
+//! first two are rewritten here. The pattern goes through
+//! [`super::outputs::change_outputs`], which owns its formatting rules.
+//!
+//! Nested inputs that happen to share the id (`inputs.crane.inputs.<id>`)
+//! name an input of the dependency, not this one, and are left alone.
+
+use rnix::{SyntaxKind, SyntaxNode, TextRange};
+
+use crate::follows::{Segment, strip_outer_quotes};
+
+use super::node::parse_node;
+
+/// Rewrite every declaration of and follows reference to `from` as `to`.
+/// Returns `None` when nothing in the tree names `from`.
+pub(crate) fn rename_input(root: &SyntaxNode, from: &Segment, to: &Segment) -> Option<SyntaxNode> {
+    let attr_set = super::flake_attr_set(root)?;
+    let mut edits: Vec<(TextRange, String)> = Vec::new();
+
+    for binding in attr_set
+        .children()
+        .filter(|c| c.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+    {
+        let segments = attrpath_segments(&binding);
+        if segments.first().map(|s| s.to_string()).as_deref() != Some("inputs") {
+            continue;
+        }
+        match segments.get(1) {
+            // `inputs.<id>... = ...;`
+            Some(key) => push_key_edit(&mut edits, key, from, to),
+            // `inputs = { <id>... = ...; };`
+            None => {
+                let Some(block) = binding
+                    .children()
+                    .find(|c| c.kind() == SyntaxKind::NODE_ATTR_SET)
+                else {
+                    continue;
+                };
+                for entry in block
+                    .children()
+                    .filter(|c| c.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+                {
+                    if let Some(key) = attrpath_segments(&entry).first() {
+                        push_key_edit(&mut edits, key, from, to);
+                    }
+                }
+            }
+        }
+        for follows in binding
+            .descendants()
+            .filter(|d| d.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        {
+            push_follows_edit(&mut edits, &follows, from, to);
+        }
+    }
+
+    if edits.is_empty() {
+        return None;
+    }
+    // Splice back to front so earlier ranges stay valid.
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start()));
+    let mut text = root.to_string();
+    for (range, replacement) in edits {
+        text.replace_range(
+            usize::from(range.start())..usize::from(range.end()),
+            &replacement,
+        );
+    }
+    Some(parse_node(&text))
+}
+
+/// Attrpath segment nodes of a binding, e.g. `inputs`, `crane`, `url`.
+fn attrpath_segments(binding: &SyntaxNode) -> Vec<SyntaxNode> {
+    binding
+        .children()
+        .find(|c| c.kind() == SyntaxKind::NODE_ATTRPATH)
+        .map(|attrpath| attrpath.children().collect())
+        .unwrap_or_default()
+}
+
+fn push_key_edit(
+    edits: &mut Vec<(TextRange, String)>,
+    key: &SyntaxNode,
+    from: &Segment,
+    to: &Segment,
+) {
+    if Segment::from_syntax(key).is_ok_and(|s| &s == from) {
+        edits.push((key.text_range(), to.render()));
+    }
+}
+
+/// Retarget `follows = "<from>"` and `follows = "<from>/child"`. Values
+/// other than a plain string literal are left alone.
+fn push_follows_edit(
+    edits: &mut Vec<(TextRange, String)>,
+    binding: &SyntaxNode,
+    from: &Segment,
+    to: &Segment,
+) {
+    let segments = attrpath_segments(binding);
+    if segments.last().map(|s| s.to_string()).as_deref() != Some("follows") {
+        return;
+    }
+    let Some(value) = binding.children().last() else {
+        return;
+    };
+    if value.kind() != SyntaxKind::NODE_STRING
+        || value
+            .children()
+            .any(|c| c.kind() == SyntaxKind::NODE_INTERPOL)
+    {
+        return;
+    }
+    let text = value.to_string();
+    let target = strip_outer_quotes(&text);
+    let (head, rest) = match target.find('/') {
+        Some(idx) => target.split_at(idx),
+        None => (target, ""),
+    };
+    if head == from.as_str() {
+        edits.push((value.text_range(), format!("\"{}{rest}\"", to.as_str())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename(src: &str, from: &str, to: &str) -> Option<String> {
+        let from = Segment::from_unquoted(from).unwrap();
+        let to = Segment::from_unquoted(to).unwrap();
+        rename_input(&parse_node(src), &from, &to).map(|n| n.to_string())
+    }
+
+    #[test]
+    fn renames_flat_declarations_and_follows() {
+        let src = r#"{
+  inputs.nixpkgs.url = "github:nixos/nixpkgs";
+  inputs.crane.url = "github:ipetkov/crane";
+  inputs.crane.inputs.nixpkgs.follows = "nixpkgs";
+  outputs = { self, ... }: { };
+}"#;
+        let expected = r#"{
+  inputs.pkgs.url = "github:nixos/nixpkgs";
+  inputs.crane.url = "github:ipetkov/crane";
+  inputs.crane.inputs.nixpkgs.follows = "pkgs";
+  outputs = { self, ... }: { };
+}"#;
+        assert_eq!(rename(src, "nixpkgs", "pkgs").as_deref(), Some(expected));
+    }
+
+    #[test]
+    fn renames_block_declarations_and_slash_follows() {
+        let src = r#"{
+  inputs = {
+    utils = {
+      url = "github:numtide/flake-utils";
+      inputs.systems.follows = "systems";
+    };
+    crane.url = "github:ipetkov/crane";
+    crane.inputs.flake-utils.follows = "utils";
+    other.inputs.systems.follows = "utils/systems";
+  };
+}"#;
+        let expected = r#"{
+  inputs = {
+    flake-utils = {
+      url = "github:numtide/flake-utils";
+      inputs.systems.follows = "systems";
+    };
+    crane.url = "github:ipetkov/crane";
+    crane.inputs.flake-utils.follows = "flake-utils";
+    other.inputs.systems.follows = "flake-utils/systems";
+  };
+}"#;
+        assert_eq!(
+            rename(src, "utils", "flake-utils").as_deref(),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn leaves_same_named_nested_inputs_alone() {
+        let src = r#"{
+  inputs.crane.url = "github:ipetkov/crane";
+  inputs.crane.inputs.nixpkgs.follows = "";
+}"#;
+        assert_eq!(rename(src, "nixpkgs", "pkgs"), None);
+    }
+
+    #[test]
+    fn quotes_ids_that_need_it() {
+        let src = r#"{ inputs.hls.url = "github:haskell/hls"; }"#;
+        assert_eq!(
+            rename(src, "hls", "hls-1.10").as_deref(),
+            Some(r#"{ inputs."hls-1.10".url = "github:haskell/hls"; }"#)
+        );
+    }
+}
@@ -3,24 +3,28 @@
 //! An input is *toggleable* when a commented copy of its url binding sits
 //! in the contiguous comment block directly above or below the active
 //! binding (no blank line in between). Detection is parsing, not prefix
-//! matching: stripped of its leading `#` (and at most one following
+//! matching: stripped of its comment marker (and at most one following
 //! space), the comment must parse as a single binding that binds the same
 //! url attribute at the position it occupies, with a string-literal
 //! value. Anything else is prose and is ignored.
 //!
+//! The marker is `# ` unless configured otherwise (`[toggle] marker`).
+//! Detection matches it without its trailing whitespace, so the default
+//! accepts both `#x` and `# x`.
+//!
 //! Flips never move lines, only the comment marker: deactivating prefixes
-//! the binding's source text with `# ` at its existing indentation, and
-//! activating strips `#` plus at most one space. A trailing same-line
-//! comment rides along verbatim in both directions, so toggling twice is
-//! byte-identical (the one permitted normalization is `#x` becoming
-//! `# x` after the first round trip).
+//! the binding's source text with the marker at its existing indentation,
+//! and activating strips the marker plus at most one space. A trailing
+//! same-line comment rides along verbatim in both directions, so toggling
+//! twice is byte-identical (the one permitted normalization is `#x`
+//! becoming `# x` after the first round trip).
 
 use rnix::{SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextSize};
 
 use crate::follows::strip_outer_quotes;
-use crate::input::Input;
+use crate::input::{Input, escape_string, unescape_string};
 
-use super::node::{extract_indent, parse_node, trailing_inline_comments};
+use super::node::{extract_indent, on_own_line, parse_node, trailing_inline_comments};
 
 /// A commented alternate adjacent to an input's url binding.
 #[derive(Debug, Clone)]
@@ -71,9 +75,10 @@ fn attrpath_segments(binding: &SyntaxNode) -> Option<Vec<String>> {
     )
 }
 
-/// Collect the alternates stored next to `binding`, in file order: the
-/// contiguous own-line comment block above, then the one below.
-pub(crate) fn alternates(binding: &SyntaxNode) -> Vec<Alternate> {
+/// Collect the alternates stored next to `binding` behind `marker`, in
+/// file order: the contiguous own-line comment block above, then the one
+/// below.
+pub(crate) fn alternates(binding: &SyntaxNode, marker: &str) -> Vec<Alternate> {
     let Some(expected) = attrpath_segments(binding) else {
         return Vec::new();
     };
@@ -94,7 +99,7 @@ pub(crate) fn alternates(binding: &SyntaxNode) -> Vec<Alternate> {
                     break;
                 }
                 if let Some(token) = el.as_token()
-                    && let Some(url) = parse_alternate(&token.to_string(), &expected)
+                    && let Some(url) = parse_alternate(&token.to_string(), &expected, marker)
                 {
                     above.push(Alternate {
                         url,
@@ -123,7 +128,7 @@ pub(crate) fn alternates(binding: &SyntaxNode) -> Vec<Alternate> {
                 // still start on the next line.
                 if on_own_line(&el)
                     && let Some(token) = el.as_token()
-                    && let Some(url) = parse_alternate(&token.to_string(), &expected)
+                    && let Some(url) = parse_alternate(&token.to_string(), &expected, marker)
                 {
                     found.push(Alternate {
                         url,
@@ -138,20 +143,10 @@ pub(crate) fn alternates(binding: &SyntaxNode) -> Vec<Alternate> {
     found
 }
 
-/// Whether `el` starts its line: its previous sibling element is
-/// line-breaking whitespace (or it is the first child).
-fn on_own_line(el: &rnix::SyntaxElement) -> bool {
-    match el.prev_sibling_or_token() {
-        None => true,
-        Some(prev) => {
-            prev.kind() == SyntaxKind::TOKEN_WHITESPACE && prev.to_string().contains('\n')
-        }
-    }
-}
-
-/// Strip the comment marker: a leading `#` and at most one following space.
-fn uncomment(comment: &str) -> &str {
-    let body = comment.strip_prefix('#').unwrap_or(comment);
+/// Strip the comment marker: `marker` without its trailing whitespace,
+/// and at most one following space.
+fn uncomment<'c>(comment: &'c str, marker: &str) -> &'c str {
+    let body = comment.strip_prefix(marker.trim_end()).unwrap_or(comment);
     body.strip_prefix(' ').unwrap_or(body)
 }
 
@@ -161,11 +156,11 @@ fn uncomment(comment: &str) -> &str {
 /// The body is parsed inside a synthetic `{ ... }` because a bare binding
 /// is not a valid root expression. The wrapper makes "parses as a single
 /// binding" checkable with an error-free parse.
-fn parse_alternate(comment: &str, expected: &[String]) -> Option<String> {
-    if !comment.starts_with('#') {
+fn parse_alternate(comment: &str, expected: &[String], marker: &str) -> Option<String> {
+    if !comment.starts_with(marker.trim_end()) {
         return None;
     }
-    let body = uncomment(comment);
+    let body = uncomment(comment, marker);
     let parse = rnix::Root::parse(&format!("{{\n{body}\n}}"));
     if !parse.errors().is_empty() {
         return None;
@@ -190,16 +185,16 @@ fn parse_alternate(comment: &str, expected: &[String]) -> Option<String> {
     if value.kind() != SyntaxKind::NODE_STRING {
         return None;
     }
-    Some(strip_outer_quotes(&value.to_string()).to_string())
+    Some(unescape_string(strip_outer_quotes(&value.to_string())))
 }
 
-/// The commented form of `binding`'s line: the `# `-prefixed source text
-/// (with any trailing same-line comment riding along verbatim) and the
-/// indices of the tail tokens that merge into it.
-fn deactivated_line(binding: &SyntaxNode) -> (String, Vec<usize>) {
+/// The commented form of `binding`'s line: the `marker`-prefixed source
+/// text (with any trailing same-line comment riding along verbatim) and
+/// the indices of the tail tokens that merge into it.
+fn deactivated_line(binding: &SyntaxNode, marker: &str) -> (String, Vec<usize>) {
     let element: rnix::SyntaxElement = binding.clone().into();
     let tail = trailing_inline_comments(&element);
-    let mut text = format!("# {binding}");
+    let mut text = format!("{marker}{binding}");
     for token in &tail {
         text.push_str(&token.to_string());
     }
@@ -208,9 +203,14 @@ fn deactivated_line(binding: &SyntaxNode) -> (String, Vec<usize>) {
 
 /// Activate the stored `alternate` and deactivate the active `binding`.
 /// Both lines keep their position. Only the comment marker moves.
-pub(crate) fn flip(parent: &SyntaxNode, binding: &SyntaxNode, alternate: &Alternate) -> SyntaxNode {
-    let activated = parse_node(uncomment(&alternate.token.to_string()));
-    let (comment_text, tail) = deactivated_line(binding);
+pub(crate) fn flip(
+    parent: &SyntaxNode,
+    binding: &SyntaxNode,
+    alternate: &Alternate,
+    marker: &str,
+) -> SyntaxNode {
+    let activated = parse_node(uncomment(&alternate.token.to_string(), marker));
+    let (comment_text, tail) = deactivated_line(binding, marker);
     // Replacements are one-to-one and leave sibling indices valid. The
     // tail removals shift only children after the binding, so they run
     // last and in descending order.
@@ -246,8 +246,9 @@ pub(crate) fn flip_remove(
     parent: &SyntaxNode,
     binding: &SyntaxNode,
     alternate: &Alternate,
+    marker: &str,
 ) -> SyntaxNode {
-    let activated = parse_node(uncomment(&alternate.token.to_string()));
+    let activated = parse_node(uncomment(&alternate.token.to_string(), marker));
     let element: rnix::SyntaxElement = binding.clone().into();
     let mut to_remove: Vec<usize> = trailing_inline_comments(&element)
         .iter()
@@ -272,9 +273,14 @@ pub(crate) fn flip_remove(
 }
 
 /// Store `uri` as the new active url: the active `binding` is commented in
-/// place and the new url binding is written directly below it, at the same
-/// indentation and with the same attrpath spelling.
-pub(crate) fn synthesize(parent: &SyntaxNode, binding: &SyntaxNode, uri: &str) -> SyntaxNode {
+/// place behind `marker` and the new url binding is written directly below
+/// it, at the same indentation and with the same attrpath spelling.
+pub(crate) fn synthesize(
+    parent: &SyntaxNode,
+    binding: &SyntaxNode,
+    uri: &str,
+    marker: &str,
+) -> SyntaxNode {
     let attrpath_text = binding
         .children()
         .find(|c| c.kind() == SyntaxKind::NODE_ATTRPATH)
@@ -286,7 +292,7 @@ pub(crate) fn synthesize(parent: &SyntaxNode, binding: &SyntaxNode, uri: &str) -
         .map(|t| extract_indent(&t.to_string()).to_string())
         .unwrap_or_default();
 
-    let (comment_text, tail) = deactivated_line(binding);
+    let (comment_text, tail) = deactivated_line(binding, marker);
     // Order matters: the replacement is one-to-one and the tail removals
     // only touch children after the binding, so `binding.index()` stays
     // valid throughout and the inserts below land directly after the
@@ -297,7 +303,7 @@ pub(crate) fn synthesize(parent: &SyntaxNode, binding: &SyntaxNode, uri: &str) -
     for index in tail.iter().rev() {
         green = green.remove_child(*index);
     }
-    let new_line = parse_node(&format!("{attrpath_text} = \"{uri}\";"));
+    let new_line = parse_node(&format!("{attrpath_text} = \"{}\";", escape_string(uri)));
     green = green.insert_child(
         binding.index() + 1,
         parse_node(&format!("\n{indent}")).green().into(),
@@ -305,3 +311,40 @@ pub(crate) fn synthesize(parent: &SyntaxNode, binding: &SyntaxNode, uri: &str) -
     green = green.insert_child(binding.index() + 2, new_line.green().into());
     SyntaxNode::new_root(parent.replace_with(green))
 }
+
+/// Store `uri` as a new alternate without activating it: a `marker`-prefixed
+/// url binding is written on its own line directly below the active
+/// `binding` (after any same-line comment), at the same indentation and
+/// with the same attrpath spelling.
+pub(crate) fn store(
+    parent: &SyntaxNode,
+    binding: &SyntaxNode,
+    uri: &str,
+    marker: &str,
+) -> SyntaxNode {
+    let attrpath_text = binding
+        .children()
+        .find(|c| c.kind() == SyntaxKind::NODE_ATTRPATH)
+        .map(|c| c.to_string())
+        .unwrap_or_default();
+    let indent = binding
+        .prev_sibling_or_token()
+        .filter(|t| t.kind() == SyntaxKind::TOKEN_WHITESPACE)
+        .map(|t| extract_indent(&t.to_string()).to_string())
+        .unwrap_or_default();
+
+    let element: rnix::SyntaxElement = binding.clone().into();
+    let after = trailing_inline_comments(&element)
+        .last()
+        .map_or(binding.index(), |token| token.index())
+        + 1;
+    let comment = parse_node(&format!(
+        "{marker}{attrpath_text} = \"{}\";",
+        escape_string(uri)
+    ));
+    let green = parent
+        .green()
+        .insert_child(after, parse_node(&format!("\n{indent}")).green().into())
+        .insert_child(after + 1, comment.green().into());
+    SyntaxNode::new_root(parent.replace_with(green))
+}
@@ -0,0 +1,127 @@
+//! `FlakeEdit::apply_change` must be idempotent: applying a change to the
+//! source it produced leaves that source alone, instead of handing back a
+//! reformatted copy of it.
+
+mod common;
+
+use common::load_flake;
+use flake_edit::Error;
+use flake_edit::change::{Change, ChangeId};
+use flake_edit::edit::FlakeEdit;
+use flake_edit::follows::AttrPath;
+use rstest::rstest;
+
+/// Apply `change` to the fixture, then to the result, and return the
+/// second application's result along with the text it started from.
+fn apply_twice(
+    fixture: &str,
+    change: &Change,
+) -> (String, FlakeEdit, Result<Option<String>, Error>) {
+    let original = load_flake(fixture);
+    let first = FlakeEdit::from_text(&original)
+        .unwrap()
+        .apply_change(change.clone())
+        .unwrap()
+        .text
+        .unwrap_or_else(|| panic!("fixture {fixture}: first application must change the source"));
+    assert_ne!(first, original, "fixture {fixture}: first application");
+
+    let mut again = FlakeEdit::from_text(&first).unwrap();
+    let outcome = again.apply_change(change.clone()).map(|o| o.text);
+    (first, again, outcome)
+}
+
+/// Two distinct top-level inputs of the fixture, in a stable order.
+fn two_inputs(fixture: &str) -> (String, String) {
+    let mut fe = FlakeEdit::from_text(&load_flake(fixture)).unwrap();
+    let mut ids: Vec<String> = fe.list().keys().cloned().collect();
+    ids.sort();
+    assert!(ids.len() >= 2, "fixture {fixture} needs two inputs");
+    (ids[0].clone(), ids[1].clone())
+}
+
+#[rstest]
+fn reapplied_add_is_rejected_without_edit(
+    #[values(
+        "root",
+        "root_alt",
+        "toplevel_nesting",
+        "completely_flat_toplevel",
+        "one_level_nesting_flat",
+        "flat_nested_flat",
+        "first_nested_node",
+        "split_inputs_block_and_flat"
+    )]
+    fixture: &str,
+    #[values(true, false)] flake: bool,
+) {
+    let change = Change::Add {
+        id: Some(ChangeId::parse("idem-probe").unwrap()),
+        uri: Some("github:a-kenji/idem-probe".into()),
+        flake,
+    };
+    let (_, again, outcome) = apply_twice(fixture, &change);
+    assert!(
+        matches!(outcome, Err(Error::DuplicateInput(ref id)) if id == "idem-probe"),
+        "fixture {fixture}: {outcome:?}"
+    );
+    assert!(!again.is_dirty());
+}
+
+#[rstest]
+fn reapplied_remove_is_a_noop(
+    #[values(
+        "root",
+        "root_alt",
+        "toplevel_nesting",
+        "completely_flat_toplevel",
+        "one_level_nesting_flat",
+        "flat_nested_flat",
+        "first_nested_node",
+        "split_inputs_block_and_flat"
+    )]
+    fixture: &str,
+) {
+    let (id, _) = two_inputs(fixture);
+    let change = Change::Remove {
+        ids: vec![ChangeId::parse(&id).unwrap()],
+    };
+    let (_, again, outcome) = apply_twice(fixture, &change);
+    assert!(
+        matches!(outcome, Ok(None)),
+        "fixture {fixture}: removing {id} twice: {outcome:?}"
+    );
+    assert!(!again.is_dirty());
+}
+
+#[rstest]
+fn reapplied_follows_is_a_noop(
+    #[values(
+        "root",
+        "root_alt",
+        "toplevel_nesting",
+        "completely_flat_toplevel",
+        "one_level_nesting_flat",
+        "flat_nested_flat",
+        "first_nested_node",
+        "split_inputs_block_and_flat"
+    )]
+    fixture: &str,
+) {
+    let (input, target) = two_inputs(fixture);
+    let change = Change::Follows {
+        input: ChangeId::parse(&format!("{input}.idem-probe")).unwrap(),
+        target: AttrPath::parse(&target).unwrap(),
+    };
+    let (first, mut again, outcome) = apply_twice(fixture, &change);
+    assert!(
+        matches!(outcome, Ok(None)),
+        "fixture {fixture}: following twice: {outcome:?}"
+    );
+    assert!(!again.is_dirty());
+    assert_eq!(again.source_text(), first);
+
+    // The repeat is recognised as already in place, not as a miss.
+    let outcome = again.apply_change(change).unwrap();
+    assert!(outcome.text.is_none() && outcome.unchanged);
+}
@@ -35,8 +35,15 @@ fn path_redactions(settings: &mut insta::Settings) {
         if let Some(s) = value.as_str()
             && let Some(idx) = s.find(FIXTURE_MARKER)
         {
+            // Keep the `--flag=` of a path passed with `require_equals`.
+            let flag = s.split_once('=').map_or("", |(flag, _)| flag);
+            let flag = if flag.starts_with("--") {
+                &s[..=flag.len()]
+            } else {
+                ""
+            };
             let rest = &s[idx + FIXTURE_MARKER.len()..];
-            return Content::from(format!("[FIXTURES]/{rest}"));
+            return Content::from(format!("{flag}[FIXTURES]/{rest}"));
         }
         value
     });
@@ -54,6 +61,11 @@ fn stderr_path_filters(settings: &mut insta::Settings) {
     settings.add_filter(r"[^\s']*(/tests/fixtures/)([^\s]+)", "[FIXTURES]/$2");
 }
 
+/// The detailed `list` shows each input's age relative to the clock.
+fn age_filters(settings: &mut insta::Settings) {
+    settings.add_filter(r"\(\d+ \w+ ago\)", "([AGE])");
+}
+
 #[rstest]
 #[case("root")]
 #[case("root_alt")]
@@ -66,6 +78,9 @@ fn stderr_path_filters(settings: &mut insta::Settings) {
 #[case("deeply_nested_inputs")]
 #[case("follows_cycle")]
 #[case("let_wrapped")]
+#[case("escaped_url")]
+#[case("let_bound_url")]
+#[case("inherited_inputs")]
 fn test_list(#[case] fixture: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
@@ -79,9 +94,11 @@ fn test_list(#[case] fixture: &str) {
 #[case("root", "simple")]
 #[case("root", "toplevel")]
 #[case("root", "json")]
+#[case("root", "compact")]
 #[case("let_wrapped", "simple")]
 #[case("let_wrapped", "toplevel")]
 #[case("let_wrapped", "json")]
+#[case("inherited_inputs", "compact")]
 fn test_list_format(#[case] fixture: &str, #[case] format: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
@@ -99,6 +116,118 @@ fn test_list_format(#[case] fixture: &str, #[case] format: &str) {
     });
 }
 
+/// `changed_since.flake.lock` moves nixpkgs and crane and lacks
+/// rust-overlay, as a lockfile from before an update would.
+#[rstest]
+#[case("changed_since", &[], "text")]
+#[case("changed_since", &["--json"], "json")]
+#[case("root", &[], "unchanged")]
+fn test_list_changed_since(#[case] reference: &str, #[case] args: &[&str], #[case] suffix: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("root"))
+                .arg("--lock-file")
+                .arg(fixture_lock_path("root"))
+                .arg("list")
+                .arg("--changed-since")
+                .arg(fixture_lock_path(reference))
+                .args(args)
+        );
+    });
+}
+
+/// Repeating `--flake` lists every named flake, each line prefixed with
+/// its path. Paths are relative to the fixture directory so they stay
+/// stable in the snapshot.
+#[rstest]
+#[case("simple")]
+#[case("toplevel")]
+#[case("json")]
+fn test_list_many(#[case] format: &str) {
+    let mut settings = insta::Settings::clone_current();
+    settings.set_snapshot_suffix(format);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+                .arg("--flake")
+                .arg("root.flake.nix")
+                .arg("--flake")
+                .arg("completely_flat_toplevel.flake.nix")
+                .arg("list")
+                .arg("--format")
+                .arg(format)
+        );
+    });
+}
+
+/// Repeated `--flake` is only meaningful for `list`.
+#[test]
+fn test_list_many_rejected_outside_list() {
+    assert_cmd_snapshot!(
+        cli()
+            .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+            .arg("--flake")
+            .arg("root.flake.nix")
+            .arg("--flake")
+            .arg("completely_flat_toplevel.flake.nix")
+            .arg("--diff")
+            .arg("add")
+            .arg("vmsh")
+            .arg("github:mic92/vmsh")
+    );
+}
+
+/// Repeated `--flake` reads each lockfile next to its flake, so a single
+/// `--lock-file` or `--changed-since` lockfile is rejected.
+#[rstest]
+#[case("lock_file", &["--lock-file", "root.flake.lock", "list"])]
+#[case("changed_since", &["list", "--changed-since", "root.flake.lock"])]
+fn test_list_many_rejected_with_lockfile(#[case] suffix: &str, #[case] args: &[&str]) {
+    let mut settings = insta::Settings::clone_current();
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+                .arg("--flake")
+                .arg("root.flake.nix")
+                .arg("--flake")
+                .arg("completely_flat_toplevel.flake.nix")
+                .args(args)
+        );
+    });
+}
+
+/// `list` prints the `[list] template` of the configuration per input,
+/// `--template` overrides it, and an unknown placeholder fails.
+#[rstest]
+#[case("config", &["--format", "template"])]
+#[case("override", &["--template", "{id} <- {url}"])]
+#[case("bogus", &["--template", "{id} {bogus}"])]
+fn test_list_template(#[case] suffix: &str, #[case] args: &[&str]) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("root"))
+                .arg("--config")
+                .arg(fixture_config_path("list_template"))
+                .arg("list")
+                .args(args)
+        );
+    });
+}
+
 /// Flat inputs declared with a quoted key segment (`inputs."master".url`,
 /// `inputs."nixos-24.11".url`) must be visible to `list`. The dotted quoted
 /// key is a single name (`nixos-24.11`), not a nested `nixos-24`/`11` path.
@@ -163,6 +292,7 @@ fn list_format_invalid_value_rejected_at_parse_time() {
 #[case("all_blanks", "vmsh", "github:mic92/vmsh")]
 #[case("deeply_nested_inputs", "vmsh", "github:mic92/vmsh")]
 #[case("let_wrapped", "vmsh", "github:mic92/vmsh")]
+#[case("root", "dev", "path:/home/me/dev")]
 fn test_add(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
@@ -181,60 +311,161 @@ fn test_add(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
     });
 }
 
+/// `--context` sets the unchanged lines around each `--diff` hunk.
 #[rstest]
-#[case("root", "not_a_flake", "github:a-kenji/not_a_flake")]
-#[case("all_blanks", "not_a_flake", "github:a-kenji/not_a_flake")]
-fn test_add_no_flake(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+#[case("0")]
+#[case("1")]
+#[case("10")]
+fn test_add_diff_context(#[case] context: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
-    let suffix = format!("{fixture}_{id}");
-    settings.set_snapshot_suffix(suffix);
+    settings.set_snapshot_suffix(format!("context_{context}"));
     settings.bind(|| {
         assert_cmd_snapshot!(
             cli()
                 .arg("--flake")
-                .arg(fixture_path(fixture))
+                .arg(fixture_path("centerpiece"))
                 .arg("--diff")
+                .arg("--context")
+                .arg(context)
                 .arg("add")
-                .arg("--no-flake")
-                .arg(id)
-                .arg(uri)
+                .arg("vmsh")
+                .arg("github:mic92/vmsh")
         );
     });
 }
 
-#[rstest]
-#[case("root", "shallow_input", "github:foo/bar")]
-fn test_add_shallow(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+/// Adding an id that is already declared fails by default.
+#[test]
+fn test_add_existing_id_errors() {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
-    let suffix = format!("{fixture}_{id}");
-    settings.set_snapshot_suffix(suffix);
     settings.bind(|| {
         assert_cmd_snapshot!(
             cli()
                 .arg("--flake")
-                .arg(fixture_path(fixture))
+                .arg(fixture_path("root"))
                 .arg("--diff")
                 .arg("add")
-                .arg("--shallow")
-                .arg(id)
-                .arg(uri)
+                .arg("nixpkgs")
+                .arg("github:nixos/nixpkgs/nixos-24.05")
+        );
+    });
+}
+
+/// A symlinked `flake.nix` is edited through the link: the target gets
+/// the change and the link stays a link.
+#[cfg(unix)]
+#[test]
+fn test_add_through_symlinked_flake() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let target = tmp.path().join("real.flake.nix");
+    let link = tmp.path().join("flake.nix");
+    fs::copy(fixture_path("root"), &target).expect("copy flake.nix");
+    std::os::unix::fs::symlink(&target, &link).expect("symlink flake.nix");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&link)
+        .arg("--no-lock")
+        .arg("add")
+        .arg("vmsh")
+        .arg("github:mic92/vmsh")
+        .output()
+        .expect("run flake-edit");
+
+    assert!(
+        output.status.success(),
+        "flake-edit failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let meta = fs::symlink_metadata(&link).expect("stat flake.nix");
+    assert!(
+        meta.file_type().is_symlink(),
+        "flake.nix must stay a symlink"
+    );
+    let written = fs::read_to_string(&target).expect("read link target");
+    assert!(written.contains("github:mic92/vmsh"));
+}
+
+/// `watch` starts on a flake.nix that does not parse, keeps running, and
+/// lists the inputs once a save makes it parse.
+#[test]
+fn test_watch_survives_broken_save() {
+    use std::io::Read;
+    use std::process::Stdio;
+    use std::thread;
+    use std::time::Duration;
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::write(&flake, "{ inputs = { nixpkgs.url = ").expect("write flake.nix");
+
+    let mut child = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("watch")
+        .arg("list")
+        .arg("--interval")
+        .arg("20")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("run flake-edit");
+
+    thread::sleep(Duration::from_millis(500));
+    let exited = child.try_wait().expect("poll flake-edit");
+    if exited.is_some() {
+        let mut stderr = String::new();
+        child
+            .stderr
+            .take()
+            .unwrap()
+            .read_to_string(&mut stderr)
+            .ok();
+        panic!("watch exited on a broken flake.nix: {stderr}");
+    }
+
+    fs::copy(fixture_path("root"), &flake).expect("copy flake.nix");
+    thread::sleep(Duration::from_millis(1000));
+    child.kill().expect("stop flake-edit");
+    let output = child.wait_with_output().expect("collect output");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("error:"), "{stderr}");
+    assert!(stdout.contains("nixpkgs"), "{stdout}");
+}
+
+/// `update --select` refuses an id the flake does not declare before
+/// contacting any forge.
+#[test]
+fn test_update_select_unknown_input() {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("root"))
+                .arg("--diff")
+                .arg("update")
+                .arg("--select")
+                .arg("nixpkgs,nonexistent")
         );
     });
 }
 
+/// `update --ref` moves one input to a named branch without asking the
+/// forge, and refuses inputs whose url has no ref to move.
 #[rstest]
-#[case("root", "shallow_ref_input", "github:foo/bar", "main")]
-fn test_add_shallow_with_ref(
-    #[case] fixture: &str,
-    #[case] id: &str,
-    #[case] uri: &str,
-    #[case] ref_or_rev: &str,
-) {
+#[case("root", &["nixpkgs", "--ref", "nixos-24.05"], "branch")]
+#[case("root", &["nixpkgs", "--ref", "nixos-unstable"], "unchanged")]
+#[case("non_flake_and_path", &["store", "--ref", "main"], "path_input")]
+fn test_update_ref(#[case] fixture: &str, #[case] args: &[&str], #[case] suffix: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
-    let suffix = format!("{fixture}_{id}");
+    error_filters(&mut settings);
     settings.set_snapshot_suffix(suffix);
     settings.bind(|| {
         assert_cmd_snapshot!(
@@ -242,40 +473,1003 @@ fn test_add_shallow_with_ref(
                 .arg("--flake")
                 .arg(fixture_path(fixture))
                 .arg("--diff")
-                .arg("add")
-                .arg("--shallow")
-                .arg("--ref-or-rev")
-                .arg(ref_or_rev)
-                .arg(id)
-                .arg(uri)
+                .arg("update")
+                .args(args)
         );
     });
 }
 
+/// `update --ref` keeps stdout to one JSON object with `--output json`,
+/// whether or not the input moved.
+#[test]
+fn test_update_ref_json() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::copy(fixture_path("root"), &flake).expect("copy flake.nix");
+    let run = |reference: &str| {
+        let output = cli()
+            .arg("--flake")
+            .arg(&flake)
+            .arg("--no-lock")
+            .arg("--output")
+            .arg("json")
+            .args(["update", "nixpkgs", "--ref", reference])
+            .output()
+            .expect("run flake-edit");
+        assert!(
+            output.status.success(),
+            "flake-edit failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice::<serde_json::Value>(&output.stdout)
+            .expect("stdout is one JSON object")
+    };
+
+    let report = run("nixos-unstable");
+    assert_eq!(report, serde_json::json!({ "status": "unchanged" }));
+
+    let report = run("nixos-24.05");
+    assert_eq!(report["status"], "applied");
+    assert_eq!(report["change"]["Change"]["id"], "nixpkgs");
+    assert_eq!(
+        report["change"]["Change"]["uri"],
+        "github:nixos/nixpkgs/nixos-24.05"
+    );
+}
+
+/// `--quiet-success` writes the change without printing anything.
+#[test]
+fn test_add_quiet_success_prints_nothing() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::copy(fixture_path("root"), &flake).expect("copy flake.nix");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("--no-lock")
+        .arg("--quiet-success")
+        .arg("add")
+        .arg("vmsh")
+        .arg("github:mic92/vmsh")
+        .output()
+        .expect("run flake-edit");
+
+    assert!(
+        output.status.success(),
+        "flake-edit failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        output.stdout.is_empty(),
+        "stdout must be empty, got:\n{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let written = fs::read_to_string(&flake).expect("read flake.nix");
+    assert!(written.contains("github:mic92/vmsh"));
+}
+
+/// `add --from-lock` imports the inputs the flake lacks, pinned to
+/// their locked revisions, and leaves existing ones alone.
+#[test]
+fn test_add_from_lock() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::copy(fixture_path("root"), &flake).expect("copy flake.nix");
+    let before = fs::read_to_string(&flake).expect("read flake.nix");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("--no-lock")
+        .arg("add")
+        .arg(format!("--from-lock={}", fixture_lock_path("centerpiece")))
+        .output()
+        .expect("run flake-edit");
+
+    assert!(
+        output.status.success(),
+        "flake-edit failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Skipped existing input: nixpkgs"));
+    assert!(stdout.contains("Skipped existing input: crane"));
+    let written = fs::read_to_string(&flake).expect("read flake.nix");
+    assert!(
+        written
+            .contains("github:nix-community/home-manager/1f679ed2a2ebe3894bad9f89fb0bd9f141c28a68")
+    );
+    assert!(
+        written.contains("github:numtide/treefmt-nix/29a3d7b768c70addce17af0869f6e2bd8f5be4b7")
+    );
+    assert_eq!(
+        written.matches("nixpkgs.url").count(),
+        before.matches("nixpkgs.url").count(),
+        "existing inputs must not be re-added"
+    );
+}
+
+/// `add <id> --from-lock` rebuilds the url of a single lock node,
+/// pinned to its rev; a pure follows has no node and is an error.
 #[rstest]
-#[case("root")]
-fn test_add_infer_id(#[case] fixture: &str) {
+#[case("vmsh")]
+#[case("mprisd")]
+#[case("unstable")]
+fn test_add_id_from_lock(#[case] id: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
-    settings.set_snapshot_suffix(fixture);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(id);
     settings.bind(|| {
         assert_cmd_snapshot!(
             cli()
                 .arg("--flake")
-                .arg(fixture_path(fixture))
+                .arg(fixture_path("root"))
                 .arg("--diff")
                 .arg("add")
-                .arg("github:mic92/vmsh")
+                .arg(id)
+                .arg(format!("--from-lock={}", fixture_lock_path("from_lock")))
         );
     });
 }
 
+/// A bare `--from-lock` is followed by the input id, not a lock path:
+/// the lock next to the flake is read.
+#[test]
+fn test_add_from_lock_before_id() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::copy(fixture_path("root"), &flake).expect("copy flake.nix");
+    fs::copy(
+        fixture_lock_path("from_lock"),
+        tmp.path().join("flake.lock"),
+    )
+    .expect("copy flake.lock");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("--diff")
+        .arg("add")
+        .arg("--from-lock")
+        .arg("vmsh")
+        .output()
+        .expect("run flake-edit");
+
+    assert!(
+        output.status.success(),
+        "flake-edit failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout
+            .contains("vmsh.url = \"github:mic92/vmsh/8f2c1e0b7a6d5c4b3a29180f7e6d5c4b3a2918f0\""),
+        "{stdout}"
+    );
+}
+
+/// `--quiet-success` still reports failures on stderr.
+#[test]
+fn test_add_quiet_success_reports_errors() {
+    let output = cli()
+        .arg("--flake")
+        .arg(fixture_path("root"))
+        .arg("--diff")
+        .arg("--quiet-success")
+        .arg("add")
+        .arg("nixpkgs")
+        .arg("github:nixos/nixpkgs/nixos-24.05")
+        .output()
+        .expect("run flake-edit");
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(
+        !output.stderr.is_empty(),
+        "a failed add must explain itself on stderr"
+    );
+}
+
+/// Piped output is never colored: neither a `--diff` on stdout nor the
+/// error prefix on stderr, with or without `--no-color`.
 #[rstest]
-#[case("root", "nixpkgs")]
-#[case("root_alt", "nixpkgs")]
-#[case("toplevel_nesting", "nixpkgs")]
-#[case("completely_flat_toplevel", "nixpkgs")]
-#[case("completely_flat_toplevel_alt", "nixpkgs")]
+#[case(&[])]
+#[case(&["--no-color"])]
+fn test_no_color(#[case] flags: &[&str]) {
+    let diff = cli()
+        .env_remove("NO_COLOR")
+        .args(flags)
+        .arg("--flake")
+        .arg(fixture_path("centerpiece"))
+        .arg("--diff")
+        .args(["remove", "crane"])
+        .output()
+        .expect("run flake-edit");
+    assert!(diff.status.success());
+    let stdout = String::from_utf8_lossy(&diff.stdout);
+    assert!(stdout.contains("-    crane"), "got:\n{stdout}");
+    assert!(!stdout.contains('\x1b'), "got:\n{stdout:?}");
+
+    let error = cli()
+        .env_remove("NO_COLOR")
+        .args(flags)
+        .arg("--flake")
+        .arg(fixture_path("root"))
+        .arg("--diff")
+        .args(["remove", "missing"])
+        .output()
+        .expect("run flake-edit");
+    assert!(!error.status.success());
+    let stderr = String::from_utf8_lossy(&error.stderr);
+    assert!(stderr.starts_with("error"), "got:\n{stderr:?}");
+    assert!(!stderr.contains('\x1b'), "got:\n{stderr:?}");
+}
+
+/// `--dry-run` prints the resulting flake.nix and leaves both files on
+/// disk untouched.
+#[rstest]
+#[case(&["add", "vmsh", "github:mic92/vmsh"], "vmsh.url = \"github:mic92/vmsh\";")]
+#[case(&["remove", "crane"], "treefmt-nix.url = \"github:numtide/treefmt-nix\";")]
+#[case(&["change", "crane", "github:ipetkov/crane/v0.20.0"], "crane.url = \"github:ipetkov/crane/v0.20.0\";")]
+#[case(&["pin", "nixpkgs", "abc123"], "nixpkgs.url = \"github:NixOS/nixpkgs/abc123\";")]
+#[case(&["follow"], "home-manager.inputs.nixpkgs.follows = \"nixpkgs\";")]
+fn test_dry_run_prints_without_writing(#[case] args: &[&str], #[case] expected: &str) {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    let lock = tmp.path().join("flake.lock");
+    fs::copy(fixture_path("centerpiece"), &flake).expect("copy flake.nix");
+    fs::copy(fixture_lock_path("centerpiece"), &lock).expect("copy flake.lock");
+    let original = fs::read_to_string(&flake).expect("read flake.nix");
+    let original_lock = fs::read_to_string(&lock).expect("read flake.lock");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("--lock-file")
+        .arg(&lock)
+        .arg("--dry-run")
+        .args(args)
+        .output()
+        .expect("run flake-edit");
+
+    assert!(
+        output.status.success(),
+        "flake-edit failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(expected),
+        "stdout must contain {expected:?}, got:\n{stdout}"
+    );
+    assert!(
+        stdout.trim_end().ends_with('}'),
+        "stdout must be the document alone"
+    );
+    assert_eq!(
+        fs::read_to_string(&flake).expect("read flake.nix"),
+        original
+    );
+    assert_eq!(
+        fs::read_to_string(&lock).expect("read flake.lock"),
+        original_lock
+    );
+}
+
+/// `?flake=false` in the url is written as the `flake = false;`
+/// attribute, the same as `--no-flake`.
+#[rstest]
+#[case(&["x", "github:o/r?flake=false"], &["--no-flake", "x", "github:o/r"])]
+#[case(&["github:o/r?dir=sub&flake=false"], &["--no-flake", "github:o/r?dir=sub"])]
+#[case(&["x", "github:o/r?flake=true"], &["x", "github:o/r"])]
+fn test_add_flake_param_matches_attribute(#[case] uri_form: &[&str], #[case] attr_form: &[&str]) {
+    let add = |args: &[&str]| {
+        let output = cli()
+            .arg("--flake")
+            .arg(fixture_path("root"))
+            .arg("--diff")
+            .arg("add")
+            .args(args)
+            .output()
+            .expect("run flake-edit add");
+        assert!(
+            output.status.success(),
+            "flake-edit failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).expect("utf-8 diff")
+    };
+    let from_uri = add(uri_form);
+    assert!(!from_uri.contains("flake="), "got:\n{from_uri}");
+    assert_eq!(from_uri, add(attr_form));
+}
+
+/// A github branch with slashes is written whole, whether it is part of
+/// the url or given with `--ref-or-rev`.
+#[rstest]
+#[case(&["github:o/r/release/2024"], "github:o/r/release/2024")]
+#[case(&["github:o/r/feature/foo/bar"], "github:o/r/feature/foo/bar")]
+#[case(&["github:o/r", "--ref-or-rev", "feature/foo/bar"], "github:o/r/feature/foo/bar")]
+fn test_change_slashed_ref(#[case] args: &[&str], #[case] expected: &str) {
+    let output = cli()
+        .arg("--flake")
+        .arg(fixture_path("root"))
+        .arg("--no-lock")
+        .arg("--diff")
+        .args(["change", "nixpkgs"])
+        .args(args)
+        .output()
+        .expect("run flake-edit change");
+    assert!(
+        output.status.success(),
+        "flake-edit failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let diff = String::from_utf8(output.stdout).expect("utf-8 diff");
+    assert!(
+        diff.contains(&format!("+    nixpkgs.url = \"{expected}\";")),
+        "got:\n{diff}"
+    );
+}
+
+/// `--backup` copies flake.nix aside right before the write, and not at
+/// all when nothing is written.
+#[rstest]
+#[case(&[], true)]
+#[case(&["--diff"], false)]
+#[case(&["--dry-run"], false)]
+fn test_backup_before_write(#[case] mode: &[&str], #[case] backed_up: bool) {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::copy(fixture_path("centerpiece"), &flake).expect("copy flake.nix");
+    let original = fs::read_to_string(&flake).expect("read flake.nix");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("--no-lock")
+        .arg("--backup")
+        .args(mode)
+        .args(["remove", "crane"])
+        .output()
+        .expect("run flake-edit");
+    assert!(
+        output.status.success(),
+        "flake-edit failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let backup = tmp.path().join("flake.nix.bak");
+    if backed_up {
+        assert_eq!(fs::read_to_string(&backup).expect("read backup"), original);
+        assert_ne!(
+            fs::read_to_string(&flake).expect("read flake.nix"),
+            original
+        );
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.contains("Backed up flake.nix to flake.nix.bak"),
+            "got:\n{stdout}"
+        );
+    } else {
+        assert!(!backup.exists(), "no backup without a write");
+        assert_eq!(
+            fs::read_to_string(&flake).expect("read flake.nix"),
+            original
+        );
+    }
+}
+
+/// `--output json` reports a failure as one JSON object on stderr.
+#[test]
+fn test_remove_json_error() {
+    let output = cli()
+        .arg("--flake")
+        .arg(fixture_path("root"))
+        .arg("--diff")
+        .arg("--output")
+        .arg("json")
+        .arg("remove")
+        .arg("nonexistent-input")
+        .output()
+        .expect("run flake-edit");
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stderr).expect("stderr is one JSON object");
+    assert_eq!(report["kind"], "CouldNotRemove");
+    assert_eq!(
+        report["error"],
+        "could not remove input 'nonexistent-input'"
+    );
+    assert!(report["context"].is_object());
+}
+
+/// The JSON `kind` of a library error is its own variant, not the
+/// wrapper the binary carries it in.
+#[test]
+fn test_rename_json_error_kind() {
+    let output = cli()
+        .arg("--flake")
+        .arg(fixture_path("root"))
+        .arg("--diff")
+        .arg("--output")
+        .arg("json")
+        .args(["rename", "nonexistent-input", "renamed"])
+        .output()
+        .expect("run flake-edit");
+
+    assert!(!output.status.success());
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stderr).expect("stderr is one JSON object");
+    assert_eq!(report["kind"], "InputNotFound");
+}
+
+/// `--output json` reports an applied change with its serde form.
+#[test]
+fn test_add_json_applied() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::copy(fixture_path("root"), &flake).expect("copy flake.nix");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("--no-lock")
+        .arg("--output")
+        .arg("json")
+        .arg("add")
+        .arg("vmsh")
+        .arg("github:mic92/vmsh")
+        .output()
+        .expect("run flake-edit");
+
+    assert!(
+        output.status.success(),
+        "flake-edit failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is one JSON object");
+    assert_eq!(report["status"], "applied");
+    assert_eq!(report["change"]["Add"]["id"], "vmsh");
+    assert_eq!(report["change"]["Add"]["uri"], "github:mic92/vmsh");
+    let written = fs::read_to_string(&flake).expect("read flake.nix");
+    assert!(written.contains("github:mic92/vmsh"));
+}
+
+/// `pin` and `unpin` report with `--output json` like any other change.
+#[test]
+fn test_pin_json_applied() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::copy(fixture_path("root"), &flake).expect("copy flake.nix");
+    let run = |args: &[&str]| {
+        let output = cli()
+            .arg("--flake")
+            .arg(&flake)
+            .arg("--no-lock")
+            .arg("--output")
+            .arg("json")
+            .args(args)
+            .output()
+            .expect("run flake-edit");
+        assert!(
+            output.status.success(),
+            "flake-edit failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice::<serde_json::Value>(&output.stdout)
+            .expect("stdout is one JSON object")
+    };
+
+    let report = run(&["pin", "nixpkgs", "abc123"]);
+    assert_eq!(report["status"], "applied");
+    assert_eq!(report["change"]["Pin"]["id"], "nixpkgs");
+    assert_eq!(report["change"]["Pin"]["rev"], "abc123");
+    let written = fs::read_to_string(&flake).expect("read flake.nix");
+    assert!(written.contains("github:nixos/nixpkgs/abc123"), "{written}");
+
+    let report = run(&["unpin", "nixpkgs"]);
+    assert_eq!(report["status"], "applied");
+    assert_eq!(report["change"]["Unpin"]["id"], "nixpkgs");
+    let written = fs::read_to_string(&flake).expect("read flake.nix");
+    assert!(written.contains("\"github:nixos/nixpkgs\""), "{written}");
+}
+
+/// `--output json` reports a change that leaves flake.nix as it was as
+/// one JSON object too, not as prose.
+#[rstest]
+#[case("root", &["change", "nixpkgs", "github:nixos/nixpkgs/nixos-unstable"], "Change")]
+#[case("root", &["add-follow", "crane.nixpkgs", "nixpkgs"], "Follows")]
+#[case("toggle_flat", &["override", "rust-overlay", "github:a-kenji/rust-overlay"], "ToggleStore")]
+#[case("root", &["unpin", "flake-utils"], "Unpin")]
+fn test_json_unchanged(#[case] fixture: &str, #[case] args: &[&str], #[case] variant: &str) {
+    let output = cli()
+        .arg("--flake")
+        .arg(fixture_path(fixture))
+        .arg("--diff")
+        .arg("--output")
+        .arg("json")
+        .args(args)
+        .output()
+        .expect("run flake-edit");
+
+    assert!(
+        output.status.success(),
+        "flake-edit failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is one JSON object");
+    assert_eq!(report["status"], "unchanged");
+    assert!(report["change"][variant].is_object(), "got {report}");
+}
+
+/// Read-only reports keep stdout to JSON lines with `--output json`.
+#[rstest]
+#[case("status", Some("status_url_mismatch"), "status", "drift")]
+#[case("status", Some("status_missing_node"), "status", "drift")]
+#[case("status", Some("status"), "status", "unchanged")]
+#[case("unused_inputs", None, "prune", "unused")]
+fn test_json_report_lines(
+    #[case] fixture: &str,
+    #[case] lock: Option<&str>,
+    #[case] command: &str,
+    #[case] status: &str,
+) {
+    let mut cmd = cli();
+    cmd.arg("--flake").arg(fixture_path(fixture));
+    if let Some(lock) = lock {
+        cmd.arg("--lock-file").arg(fixture_lock_path(lock));
+    }
+    let output = cmd
+        .arg("--output")
+        .arg("json")
+        .arg(command)
+        .output()
+        .expect("run flake-edit");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.trim().is_empty(), "nothing reported");
+    for line in stdout.lines() {
+        let report: serde_json::Value =
+            serde_json::from_str(line).unwrap_or_else(|_| panic!("not JSON: {line}"));
+        assert_eq!(report["status"], status, "got {report}");
+    }
+}
+
+/// `add --from-lock` reports the inputs it skipped as unchanged adds
+/// with `--output json`, next to the applied ones.
+#[test]
+fn test_add_from_lock_json() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::copy(fixture_path("root"), &flake).expect("copy flake.nix");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("--no-lock")
+        .arg("--output")
+        .arg("json")
+        .arg("add")
+        .arg(format!("--from-lock={}", fixture_lock_path("centerpiece")))
+        .output()
+        .expect("run flake-edit");
+
+    assert!(
+        output.status.success(),
+        "flake-edit failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let reports: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|_| panic!("not JSON: {line}")))
+        .collect();
+    let skipped: Vec<&serde_json::Value> = reports
+        .iter()
+        .filter(|report| report["status"] == "unchanged")
+        .map(|report| &report["change"]["Add"]["id"])
+        .collect();
+    assert!(
+        skipped.contains(&&serde_json::json!("nixpkgs")),
+        "{reports:?}"
+    );
+    assert!(
+        skipped.contains(&&serde_json::json!("crane")),
+        "{reports:?}"
+    );
+    assert!(
+        reports.iter().any(|report| report["status"] == "applied"),
+        "{reports:?}"
+    );
+}
+
+/// `--replace-if-exists` changes an existing input's url and adds an
+/// unknown id as usual.
+#[rstest]
+#[case("root", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
+#[case("root", "vmsh", "github:mic92/vmsh")]
+fn test_add_replace_if_exists(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{id}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add")
+                .arg("--replace-if-exists")
+                .arg(id)
+                .arg(uri)
+        );
+    });
+}
+
+/// `--input-prefix` is prepended to inferred and explicit ids.
+#[rstest]
+#[case("root", "inferred", &["github:nixos/nixpkgs/nixos-24.05"])]
+#[case("root", "explicit", &["utils", "github:numtide/flake-utils"])]
+#[case("completely_flat_toplevel", "inferred", &["github:mic92/vmsh"])]
+fn test_add_input_prefix(#[case] fixture: &str, #[case] name: &str, #[case] args: &[&str]) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{name}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add")
+                .arg("--input-prefix")
+                .arg("lib-")
+                .args(args)
+        );
+    });
+}
+
+/// `--json-change` prints the change an add would make and applies nothing.
+#[rstest]
+#[case("root", "explicit", &["vmsh", "github:mic92/vmsh"])]
+#[case("root", "inferred", &["github:mic92/vmsh"])]
+#[case("root", "no_flake_ref", &["--no-flake", "--ref-or-rev", "v1", "vmsh", "github:mic92/vmsh"])]
+#[case("root", "replace", &["--replace-if-exists", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05"])]
+fn test_add_json_change(#[case] fixture: &str, #[case] name: &str, #[case] args: &[&str]) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{name}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add")
+                .arg("--json-change")
+                .args(args)
+        );
+    });
+}
+
+/// A prefix that would need quoting is rejected before any edit.
+#[rstest]
+#[case("root", "dotted", "lib.")]
+#[case("root", "leading_digit", "2-")]
+fn test_add_input_prefix_rejected(#[case] fixture: &str, #[case] name: &str, #[case] prefix: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    let suffix = format!("{fixture}_{name}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add")
+                .arg("--input-prefix")
+                .arg(prefix)
+                .arg("github:nixos/nixpkgs")
+        );
+    });
+}
+
+/// Relative `path:` references are accepted as written, for local
+/// subflakes next to or above the flake.
+#[rstest]
+#[case("dot", &["local", "path:."])]
+#[case("sibling", &["sibling", "path:../sibling"])]
+#[case("subflake_inferred", &["path:./subflake"])]
+fn test_add_relative_path(#[case] name: &str, #[case] args: &[&str]) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    settings.set_snapshot_suffix(name);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("root"))
+                .arg("--diff")
+                .arg("add")
+                .args(args)
+        );
+    });
+}
+
+/// An inferred id that is not a bare identifier is rejected, unless
+/// `--id-sanitize` rewrites it.
+#[rstest]
+#[case("myst_parser", true, "github:executablebooks/MyST-Parser")]
+#[case("dotted", false, "github:cachix/git-hooks.nix")]
+#[case("dotted", true, "github:cachix/git-hooks.nix")]
+#[case("leading_digit", true, "git+https://example.com/2fa.git")]
+fn test_add_id_sanitize(#[case] name: &str, #[case] sanitize: bool, #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    let suffix = if sanitize {
+        format!("{name}_sanitized")
+    } else {
+        name.to_string()
+    };
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        let mut cmd = cli();
+        cmd.arg("--flake")
+            .arg(fixture_path("root"))
+            .arg("--diff")
+            .arg("add");
+        if sanitize {
+            cmd.arg("--id-sanitize");
+        }
+        assert_cmd_snapshot!(cmd.arg(uri));
+    });
+}
+
+/// `flake = false;` follows the new url in every layout: inside an
+/// `inputs` block, top-level flat, an empty block, and attrset style.
+#[rstest]
+#[case("root", "not_a_flake", "github:a-kenji/not_a_flake")]
+#[case("all_blanks", "not_a_flake", "github:a-kenji/not_a_flake")]
+#[case(
+    "completely_flat_toplevel",
+    "not_a_flake",
+    "github:a-kenji/not_a_flake"
+)]
+#[case("empty_inputs", "not_a_flake", "github:a-kenji/not_a_flake")]
+#[case(
+    "wrapper_with_empty_inputs_block",
+    "not_a_flake",
+    "github:a-kenji/not_a_flake"
+)]
+fn test_add_no_flake(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{id}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add")
+                .arg("--no-flake")
+                .arg(id)
+                .arg(uri)
+        );
+    });
+}
+
+/// The `[add]` configuration decides whether a new input is a flake
+/// unless `--no-flake` or `--as-flake` is given.
+#[rstest]
+#[case("non_flake_patterns", &["vmsh", "github:mic92/vmsh"], "pattern_match")]
+#[case("non_flake_patterns", &["github:mic92/vmsh"], "pattern_match_infer_id")]
+#[case("non_flake_patterns", &["nixos", "github:nixos/nixpkgs"], "pattern_miss")]
+#[case("non_flake_patterns", &["--as-flake", "vmsh", "github:mic92/vmsh"], "pattern_as_flake")]
+#[case("no_default_flake", &["vmsh", "github:mic92/vmsh"], "default")]
+#[case("no_default_flake", &["--as-flake", "vmsh", "github:mic92/vmsh"], "default_as_flake")]
+fn test_add_flake_config(#[case] config: &str, #[case] args: &[&str], #[case] suffix: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("root"))
+                .arg("--config")
+                .arg(fixture_config_path(config))
+                .arg("--diff")
+                .arg("add")
+                .args(args)
+        );
+    });
+}
+
+#[rstest]
+#[case("root", "shallow_input", "github:foo/bar")]
+fn test_add_shallow(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{id}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add")
+                .arg("--shallow")
+                .arg(id)
+                .arg(uri)
+        );
+    });
+}
+
+#[rstest]
+#[case("root", "shallow_ref_input", "github:foo/bar", "main")]
+fn test_add_shallow_with_ref(
+    #[case] fixture: &str,
+    #[case] id: &str,
+    #[case] uri: &str,
+    #[case] ref_or_rev: &str,
+) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{id}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add")
+                .arg("--shallow")
+                .arg("--ref-or-rev")
+                .arg(ref_or_rev)
+                .arg(id)
+                .arg(uri)
+        );
+    });
+}
+
+/// `--git-ref HEAD` tracks the default branch: a forge url drops its ref
+/// segment, a `git+` url gets `?ref=HEAD`.
+#[rstest]
+#[case("root", "github", "github:mic92/vmsh/main", "HEAD")]
+#[case("root", "git", "git+https://git.example.com/mic92/vmsh", "HEAD")]
+#[case("root", "github_branch", "github:mic92/vmsh", "main")]
+fn test_add_git_ref(
+    #[case] fixture: &str,
+    #[case] suffix: &str,
+    #[case] uri: &str,
+    #[case] git_ref: &str,
+) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    settings.set_snapshot_suffix(format!("{fixture}_{suffix}"));
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add")
+                .arg("--git-ref")
+                .arg(git_ref)
+                .arg("vmsh")
+                .arg(uri)
+        );
+    });
+}
+
+#[rstest]
+#[case("root")]
+fn test_add_infer_id(#[case] fixture: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    settings.set_snapshot_suffix(fixture);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add")
+                .arg("github:mic92/vmsh")
+        );
+    });
+}
+
+/// Refs without a forge repository still infer an id: the registry id,
+/// the git repo name, or the final path component.
+#[rstest]
+#[case("indirect", "flake:home-manager")]
+#[case("git", "git+https://github.com/nix-community/naersk")]
+#[case("path", "path:/home/me/myflake")]
+fn test_add_infer_id_kinds(#[case] name: &str, #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    settings.set_snapshot_suffix(name);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("root"))
+                .arg("--diff")
+                .arg("add")
+                .arg(uri)
+        );
+    });
+}
+
+/// A url whose last segment is empty once `.git` is stripped infers a
+/// blank id. `add` refuses it instead of writing `inputs."".url`.
+#[rstest]
+#[case("empty", "git+https://host/a/.git")]
+#[case("whitespace", "git+https://host/a/ .git")]
+fn test_add_infer_id_blank(#[case] name: &str, #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    settings.set_snapshot_suffix(name);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("root"))
+                .arg("--diff")
+                .arg("add")
+                .arg(uri)
+        );
+    });
+}
+
+/// `github:nixos` and `github:nixos/` both name an owner without a
+/// repository and get the same error, whether or not an id is given.
+#[rstest]
+#[case("no_slash", &[], "github:nixos")]
+#[case("trailing_slash", &[], "github:nixos/")]
+#[case("no_slash_with_id", &["nixpkgs-fork"], "github:nixos")]
+#[case("trailing_slash_with_id", &["nixpkgs-fork"], "github:nixos/")]
+fn test_add_missing_repo(#[case] name: &str, #[case] id: &[&str], #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(name);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("root"))
+                .arg("--diff")
+                .arg("add")
+                .args(id)
+                .arg(uri)
+        );
+    });
+}
+
+#[rstest]
+#[case("root", "nixpkgs")]
+#[case("root_alt", "nixpkgs")]
+#[case("toplevel_nesting", "nixpkgs")]
+#[case("completely_flat_toplevel", "nixpkgs")]
+#[case("completely_flat_toplevel_alt", "nixpkgs")]
 #[case("one_level_nesting_flat", "nixpkgs")]
 #[case("flat_nested_flat", "nixpkgs")]
 #[case("first_nested_node", "nixpkgs")]
@@ -285,6 +1479,13 @@ fn test_add_infer_id(#[case] fixture: &str) {
 #[case("outputs_leading_comma_remove_first", "nixpkgs-unstable")]
 #[case("remove_preserves_sibling_url", "home-manager")]
 #[case("let_wrapped", "nixpkgs")]
+#[case("remove_attached_comments", "crane")]
+#[case("remove_attached_comments", "flake-utils")]
+#[case("remove_attached_comments_flat", "crane")]
+#[case("remove_attached_comments_flat", "flake-utils")]
+#[case("let_bound_url", "nixpkgs")]
+#[case("inherited_inputs", "nixpkgs")]
+#[case("inherited_inputs", "home-manager")]
 fn test_remove(#[case] fixture: &str, #[case] id: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
@@ -296,39 +1497,624 @@ fn test_remove(#[case] fixture: &str, #[case] id: &str) {
                 .arg("--flake")
                 .arg(fixture_path(fixture))
                 .arg("--diff")
-                .arg("rm")
-                .arg(id)
+                .arg("rm")
+                .arg(id)
+        );
+    });
+}
+
+#[rstest]
+#[case("root", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
+#[case("root_alt", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
+#[case("toplevel_nesting", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
+#[case(
+    "completely_flat_toplevel",
+    "nixpkgs",
+    "github:nixos/nixpkgs/nixos-24.05"
+)]
+#[case(
+    "completely_flat_toplevel_alt",
+    "nixpkgs",
+    "github:nixos/nixpkgs/nixos-24.05"
+)]
+#[case(
+    "one_level_nesting_flat",
+    "nixpkgs",
+    "github:nixos/nixpkgs/nixos-24.05"
+)]
+#[case("flat_nested_flat", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
+#[case("first_nested_node", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
+#[case("deeply_nested_inputs", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
+#[case("let_wrapped", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
+fn test_change(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{id}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("change")
+                .arg(id)
+                .arg(uri)
+        );
+    });
+}
+
+/// Urls are unescaped when read and escaped again when written: `\${`
+/// and `\"` survive an edit, and `nix\pkgs` reads as `nixpkgs`.
+#[rstest]
+#[case(&["change", "templates", "git+https://example.com/templates?dir=${other}"], "change")]
+#[case(&["change", "nixpkgs", "--ref-or-rev", "nixos-24.05"], "ref_only")]
+#[case(&["add", "local", "path:/srv/a\"b"], "add")]
+fn test_escaped_url_round_trip(#[case] args: &[&str], #[case] suffix: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("escaped_url"))
+                .arg("--diff")
+                .args(args)
+        );
+    });
+}
+
+#[rstest]
+#[case("root", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
+fn test_change_shallow(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{id}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("change")
+                .arg("--shallow")
+                .arg(id)
+                .arg(uri)
+        );
+    });
+}
+
+#[rstest]
+#[case("quoted_input_with_dots", "crane", "github:nix-community/naersk")]
+#[case("merged_inputs", "plugin-a", "github:foo/plugin-c/v1.0")]
+#[case("merged_inputs", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
+fn test_change_rename_output(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{id}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("change")
+                .arg("--rename-output")
+                .arg(id)
+                .arg(uri)
+        );
+    });
+}
+
+#[rstest]
+#[case("path_input", "nixpkgs", "nixos-24.05")]
+#[case("path_input", "local", "main")]
+fn test_change_ref_only(#[case] fixture: &str, #[case] id: &str, #[case] ref_or_rev: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    let suffix = format!("{fixture}_{id}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("change")
+                .arg("--ref-or-rev")
+                .arg(ref_or_rev)
+                .arg(id)
+        );
+    });
+}
+
+#[rstest]
+#[case("root", &["nixpkgs", "--param", "ref=nixos-24.05", "--param", "dir=lib"], "ref_and_dir")]
+#[case("root", &["nixpkgs", "--param", "rev=0123456789abcdef0123456789abcdef01234567", "--param", "ref="], "rev_then_clear_ref")]
+#[case("root", &["nixpkgs", "--param", "rev=abc"], "short_rev")]
+#[case("root", &["nixpkgs", "github:nixos/nixpkgs", "--param", "dir=lib"], "with_uri")]
+#[case("root", &["nixpkgs", "--param", "shallow=yes"], "bad_flag")]
+#[case("root", &["nixpkgs", "--param", "owner=foo"], "unknown_key")]
+#[case("path_input", &["local", "--param", "ref=main"], "ref_on_path")]
+fn test_change_params(#[case] fixture: &str, #[case] args: &[&str], #[case] suffix: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("change")
+                .args(args)
+        );
+    });
+}
+
+#[rstest]
+#[case("unused_inputs")]
+#[case("centerpiece")]
+#[case("root")]
+fn test_prune_list(#[case] fixture: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(fixture);
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("--flake").arg(fixture_path(fixture)).arg("prune"));
+    });
+}
+
+#[rstest]
+#[case("unused_inputs")]
+#[case("dot_ancestor_cycle")]
+fn test_prune_apply(#[case] fixture: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(fixture);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("prune")
+                .arg("--apply")
+        );
+    });
+}
+
+/// `status` against locks that match `status.flake.nix`, lack one of its
+/// inputs, carry an extra one, or were locked from a different url.
+#[rstest]
+#[case("status")]
+#[case("status_missing_node")]
+#[case("status_extra_node")]
+#[case("status_url_mismatch")]
+fn test_status(#[case] lock: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(lock);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("status"))
+                .arg("--lock-file")
+                .arg(fixture_lock_path(lock))
+                .arg("status")
+        );
+    });
+}
+
+/// Without `--lock-file` the lock next to `--flake` is read, not the one
+/// in the working directory.
+#[test]
+fn test_lock_beside_flake() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let dir = tmp.path().join("nested");
+    fs::create_dir(&dir).expect("create dir");
+    fs::copy(fixture_path("status"), dir.join("flake.nix")).expect("copy flake.nix");
+    fs::copy(fixture_lock_path("status"), dir.join("flake.lock")).expect("copy flake.lock");
+    // A lock in the working directory that disagrees with the flake.
+    fs::copy(
+        fixture_lock_path("status_missing_node"),
+        tmp.path().join("flake.lock"),
+    )
+    .expect("copy stray flake.lock");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(dir.join("flake.nix"))
+        .arg("status")
+        .current_dir(tmp.path())
+        .output()
+        .expect("run status");
+    assert!(
+        output.status.success(),
+        "status must read nested/flake.lock: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "flake.lock is in sync with flake.nix.\n"
+    );
+}
+
+/// `pin` without a rev takes it from the lock next to `--flake`, not from
+/// the one in the working directory.
+#[test]
+fn test_pin_reads_lock_beside_flake() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let dir = tmp.path().join("nested");
+    fs::create_dir(&dir).expect("create dir");
+    fs::copy(fixture_path("root"), dir.join("flake.nix")).expect("copy flake.nix");
+    fs::copy(fixture_lock_path("root"), dir.join("flake.lock")).expect("copy flake.lock");
+    // A lock in the working directory that pins nixpkgs elsewhere.
+    let lock = fs::read_to_string(fixture_lock_path("root")).expect("read flake.lock");
+    let stray = lock.replace(
+        "81e8f48ebdecf07aab321182011b067aafc78896",
+        "0000000000000000000000000000000000000000",
+    );
+    fs::write(tmp.path().join("flake.lock"), stray).expect("write stray flake.lock");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(dir.join("flake.nix"))
+        .arg("pin")
+        .arg("nixpkgs")
+        .current_dir(tmp.path())
+        .output()
+        .expect("run pin");
+    assert!(
+        output.status.success(),
+        "pin must read nested/flake.lock: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let flake = fs::read_to_string(dir.join("flake.nix")).expect("read flake.nix");
+    assert!(
+        flake.contains("github:nixos/nixpkgs/81e8f48ebdecf07aab321182011b067aafc78896"),
+        "{flake}"
+    );
+}
+
+/// `doctor` reports every problem of a broken flake; `--fix` applies the
+/// safe fixes and still fails on the ones left for a manual edit.
+#[rstest]
+#[case("report", &[])]
+#[case("fix", &["--fix"])]
+fn test_doctor(#[case] suffix: &str, #[case] args: &[&str]) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("doctor"))
+                .arg("--lock-file")
+                .arg(fixture_lock_path("doctor"))
+                .arg("--diff")
+                .arg("doctor")
+                .args(args)
+        );
+    });
+}
+
+/// A duplicate that shares its line with the first copy cannot be mapped
+/// back line by line, so the later findings are reported without one.
+#[test]
+fn test_doctor_unmapped_lines() {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("doctor_same_line_duplicate"))
+                .arg("doctor")
+        );
+    });
+}
+
+/// `lint` names every input whose url is declared twice, with the lines
+/// of its declarations, passes on a flake without any, and fails on one
+/// that does not parse.
+#[rstest]
+#[case("duplicate_flat_and_block")]
+#[case("duplicate_in_block")]
+#[case("root")]
+#[case("unclosed_inputs")]
+fn test_lint(#[case] fixture: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(fixture);
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("--flake").arg(fixture_path(fixture)).arg("lint"));
+    });
+}
+
+/// `validate` takes the flake as its argument, reports each error with its
+/// location and fails on any, and passes on a valid flake.
+#[rstest]
+#[case("root")]
+#[case("validate_parse_error")]
+#[case("validate_dangling_follows")]
+#[case("duplicate_flat_and_block")]
+fn test_validate(#[case] fixture: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(fixture);
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("validate").arg(fixture_path(fixture)));
+    });
+}
+
+#[test]
+fn doctor_fix_applies_only_safe_fixes() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::copy(fixture_path("doctor"), &flake).expect("copy flake.nix");
+    fs::copy(fixture_lock_path("doctor"), tmp.path().join("flake.lock")).expect("copy flake.lock");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("--no-lock")
+        .arg("doctor")
+        .arg("--fix")
+        .current_dir(tmp.path())
+        .output()
+        .expect("run doctor --fix");
+    assert!(!output.status.success(), "the unused input must still fail");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("fixed: duplicate attribute 'nixpkgs.url'"),
+        "the applied fix must be logged, got:\n{stdout}",
+    );
+    assert!(
+        stdout.contains("manual: unused input at line 14, column 19: systems"),
+        "the unused input must only be reported, got:\n{stdout}",
+    );
+
+    let result = fs::read_to_string(&flake).expect("read flake.nix");
+    assert_eq!(result.matches("nixpkgs.url").count(), 1, "got:\n{result}");
+    assert!(result.contains(r#"nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";"#));
+    assert!(!result.contains("flake-utils"), "got:\n{result}");
+    assert!(!result.contains("nixpkgs-unstable"), "got:\n{result}");
+    assert!(result.contains(r#"crane.inputs.nixpkgs.follows = "nixpkgs";"#));
+    assert!(
+        result.contains("mprisd?ref=main&shallow=1"),
+        "got:\n{result}"
+    );
+    assert!(
+        result.contains(r#"systems.url = "github:nix-systems/default";"#),
+        "the unused input must be kept, got:\n{result}",
+    );
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("doctor")
+        .current_dir(tmp.path())
+        .output()
+        .expect("run doctor");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout,
+        "manual: unused input at line 10, column 19: systems\n"
+    );
+}
+
+/// A flake.nix that does not parse is a problem, not an all-clear.
+#[test]
+fn doctor_fails_on_unparseable_flake() {
+    let output = cli()
+        .arg("--flake")
+        .arg(fixture_path("unclosed_inputs"))
+        .arg("doctor")
+        .output()
+        .expect("run doctor");
+    assert!(!output.status.success(), "a broken flake must fail doctor");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("manual: parse error at line 7, column 1"),
+        "the parse error must be reported, got:\n{stdout}",
+    );
+    assert!(!stdout.contains("No problems found."), "got:\n{stdout}");
+}
+
+#[rstest]
+#[case("root", "nonexistent-input")]
+fn test_remove_nonexistent(#[case] fixture: &str, #[case] id: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    let suffix = format!("{fixture}_{id}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("rm")
+                .arg(id)
+        );
+    });
+}
+
+#[rstest]
+#[case("root", "nonexistent-input", "github:foo/bar")]
+fn test_change_nonexistent(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    let suffix = format!("{fixture}_{id}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("change")
+                .arg(id)
+                .arg(uri)
+        );
+    });
+}
+
+/// An input whose url is a `let`-bound name or comes from an `inherit`
+/// is listed but never rewritten: the edit is refused with an error.
+#[rstest]
+#[case("let_bound_url", &["change", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05"], "change_let_bound")]
+#[case("inherited_inputs", &["change", "home-manager", "github:nix-community/home-manager/release-24.05"], "change_inherit_url")]
+#[case("inherited_inputs", &["change", "crane", "github:ipetkov/crane"], "change_inherit_input")]
+#[case("let_bound_url", &["pin", "nixpkgs", "abc1234"], "pin_let_bound")]
+fn test_unresolved_url_edits_refused(
+    #[case] fixture: &str,
+    #[case] args: &[&str],
+    #[case] suffix: &str,
+) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--no-lock")
+                .arg("--diff")
+                .args(args)
+        );
+    });
+}
+
+#[rstest]
+#[case("completely_flat_toplevel", "nixpkgs", "pkgs")]
+#[case("existing_follows_flat", "nixpkgs", "pkgs")]
+#[case("inputs_block_with_follows", "flake-parts", "parts")]
+#[case("follow_slash_syntax", "parent", "upstream")]
+#[case("rename_used_in_body", "nixpkgs", "pkgs-unstable")]
+fn test_rename(#[case] fixture: &str, #[case] from: &str, #[case] to: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{from}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("rename")
+                .arg(from)
+                .arg(to)
+        );
+    });
+}
+
+#[rstest]
+#[case("root", "nonexistent-input", "renamed")]
+#[case("root", "nixpkgs", "crane")]
+#[case("rename_shadowed_in_body", "nixpkgs", "pkgs")]
+fn test_rename_rejected(#[case] fixture: &str, #[case] from: &str, #[case] to: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    let suffix = format!("{fixture}_{from}_{to}");
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("rename")
+                .arg(from)
+                .arg(to)
+        );
+    });
+}
+
+/// Test the follow command for nested-style inputs
+#[rstest]
+#[case("centerpiece", "home-manager.nixpkgs", "nixpkgs")]
+#[case("centerpiece", "treefmt-nix.nixpkgs", "nixpkgs")]
+#[case("mixed_style", "blueprint.nixpkgs", "nixpkgs")]
+#[case("mixed_style", "blueprint.systems", "systems")]
+#[case("mixed_style", "mprisd.nixpkgs", "nixpkgs")]
+#[case("mixed_style", "mprisd.flake-parts", "flake-parts")]
+#[case("quoted_dotted_nested", "\"lib-v1.5\".nixpkgs", "nixpkgs")]
+fn test_add_follow(#[case] fixture: &str, #[case] input: &str, #[case] target: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{}", input.replace('.', "_"));
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add-follow")
+                .arg(input)
+                .arg(target)
+        );
+    });
+}
+
+/// Test the add-follow command for flat-style inputs
+#[rstest]
+#[case("mixed_style", "harmonia.nixpkgs", "nixpkgs")]
+#[case("flat_toplevel_no_follows", "crane.nixpkgs", "nixpkgs")]
+#[case("flat_toplevel_no_follows", "fenix.nixpkgs", "nixpkgs")]
+#[case("flat_toplevel_comments", "crane.nixpkgs", "nixpkgs")]
+#[case("flat_toplevel_comments", "fenix.nixpkgs", "nixpkgs")]
+#[case("toplevel_block_nested_follows", "blocky.flake-parts", "flake-parts")]
+fn test_add_follow_flat(#[case] fixture: &str, #[case] input: &str, #[case] target: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!("{fixture}_{}", input.replace('.', "_"));
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .arg("add-follow")
+                .arg(input)
+                .arg(target)
         );
     });
 }
 
+/// `add-follow --force` writes a follows whose target is not an input,
+/// in the nested and the flat style.
 #[rstest]
-#[case("root", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
-#[case("root_alt", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
-#[case("toplevel_nesting", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
-#[case(
-    "completely_flat_toplevel",
-    "nixpkgs",
-    "github:nixos/nixpkgs/nixos-24.05"
-)]
-#[case(
-    "completely_flat_toplevel_alt",
-    "nixpkgs",
-    "github:nixos/nixpkgs/nixos-24.05"
-)]
-#[case(
-    "one_level_nesting_flat",
-    "nixpkgs",
-    "github:nixos/nixpkgs/nixos-24.05"
-)]
-#[case("flat_nested_flat", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
-#[case("first_nested_node", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
-#[case("deeply_nested_inputs", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
-#[case("let_wrapped", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
-fn test_change(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+#[case("first_nested_node", "naersk.flake-utils", "flake-utils")]
+#[case("root", "crane.flake-compat", "flake-compat")]
+#[case("one_level_nesting_flat", "rust-overlay.flake-compat", "flake-compat")]
+fn test_add_follow_forced(#[case] fixture: &str, #[case] input: &str, #[case] target: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
-    let suffix = format!("{fixture}_{id}");
+    let suffix = format!("{fixture}_{}", input.replace('.', "_"));
     settings.set_snapshot_suffix(suffix);
     settings.bind(|| {
         assert_cmd_snapshot!(
@@ -336,41 +2122,48 @@ fn test_change(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
                 .arg("--flake")
                 .arg(fixture_path(fixture))
                 .arg("--diff")
-                .arg("change")
-                .arg(id)
-                .arg(uri)
+                .arg("add-follow")
+                .arg("--force")
+                .arg(input)
+                .arg(target)
         );
     });
 }
 
+/// `add-follow` refuses a target that is not an input of the flake, and
+/// writes it anyway with `--force`.
 #[rstest]
-#[case("root", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05")]
-fn test_change_shallow(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+#[case("missing_target", &[])]
+#[case("missing_target_forced", &["--force"])]
+fn test_add_follow_missing_target(#[case] suffix: &str, #[case] args: &[&str]) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
-    let suffix = format!("{fixture}_{id}");
+    error_filters(&mut settings);
     settings.set_snapshot_suffix(suffix);
     settings.bind(|| {
         assert_cmd_snapshot!(
             cli()
                 .arg("--flake")
-                .arg(fixture_path(fixture))
+                .arg(fixture_path("root"))
                 .arg("--diff")
-                .arg("change")
-                .arg("--shallow")
-                .arg(id)
-                .arg(uri)
+                .arg("add-follow")
+                .args(args)
+                .arg("rust-overlay.nixpkgs")
+                .arg("nixpkgz")
         );
     });
 }
 
+/// `follow --remove` drops one nested follows and keeps its siblings
 #[rstest]
-#[case("root", "nonexistent-input")]
-fn test_remove_nonexistent(#[case] fixture: &str, #[case] id: &str) {
+#[case("root", "crane.rust-overlay")]
+#[case("root", "rust-overlay.nixpkgs")]
+#[case("one_level_nesting_flat", "crane.rust-overlay")]
+#[case("toplevel_block_nested_follows", "blocky.nixpkgs")]
+fn test_follow_remove(#[case] fixture: &str, #[case] path: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
-    error_filters(&mut settings);
-    let suffix = format!("{fixture}_{id}");
+    let suffix = format!("{fixture}_{}", path.replace('.', "_"));
     settings.set_snapshot_suffix(suffix);
     settings.bind(|| {
         assert_cmd_snapshot!(
@@ -378,19 +2171,22 @@ fn test_remove_nonexistent(#[case] fixture: &str, #[case] id: &str) {
                 .arg("--flake")
                 .arg(fixture_path(fixture))
                 .arg("--diff")
-                .arg("rm")
-                .arg(id)
+                .arg("follow")
+                .arg("--remove")
+                .arg(path)
         );
     });
 }
 
+/// `follow --remove` refuses bare input ids and missing follows
 #[rstest]
-#[case("root", "nonexistent-input", "github:foo/bar")]
-fn test_change_nonexistent(#[case] fixture: &str, #[case] id: &str, #[case] uri: &str) {
+#[case("root", "crane")]
+#[case("root", "crane.nonexistent")]
+fn test_follow_remove_rejected(#[case] fixture: &str, #[case] path: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
     error_filters(&mut settings);
-    let suffix = format!("{fixture}_{id}");
+    let suffix = format!("{fixture}_{}", path.replace('.', "_"));
     settings.set_snapshot_suffix(suffix);
     settings.bind(|| {
         assert_cmd_snapshot!(
@@ -398,28 +2194,24 @@ fn test_change_nonexistent(#[case] fixture: &str, #[case] id: &str, #[case] uri:
                 .arg("--flake")
                 .arg(fixture_path(fixture))
                 .arg("--diff")
-                .arg("change")
-                .arg(id)
-                .arg(uri)
+                .arg("follow")
+                .arg("--remove")
+                .arg(path)
         );
     });
 }
 
-/// Test the follow command for nested-style inputs
+/// `unfollow` drops one follows declaration and keeps the input's url.
+/// Dropping the last entry of an `inputs = { ... }` block drops the block.
 #[rstest]
-#[case("first_nested_node", "naersk.flake-utils", "flake-utils")]
-#[case("root", "crane.flake-compat", "flake-compat")]
-#[case("centerpiece", "home-manager.nixpkgs", "nixpkgs")]
-#[case("centerpiece", "treefmt-nix.nixpkgs", "nixpkgs")]
-#[case("mixed_style", "blueprint.nixpkgs", "nixpkgs")]
-#[case("mixed_style", "blueprint.systems", "systems")]
-#[case("mixed_style", "mprisd.nixpkgs", "nixpkgs")]
-#[case("mixed_style", "mprisd.flake-parts", "flake-parts")]
-#[case("quoted_dotted_nested", "\"lib-v1.5\".nixpkgs", "nixpkgs")]
-fn test_add_follow(#[case] fixture: &str, #[case] input: &str, #[case] target: &str) {
+#[case("unfollow", "rust-overlay.nixpkgs")]
+#[case("unfollow", "crane.nixpkgs")]
+#[case("unfollow", "crane")]
+fn test_unfollow(#[case] fixture: &str, #[case] path: &str) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
-    let suffix = format!("{fixture}_{}", input.replace('.', "_"));
+    error_filters(&mut settings);
+    let suffix = format!("{fixture}_{}", path.replace('.', "_"));
     settings.set_snapshot_suffix(suffix);
     settings.bind(|| {
         assert_cmd_snapshot!(
@@ -427,26 +2219,63 @@ fn test_add_follow(#[case] fixture: &str, #[case] input: &str, #[case] target: &
                 .arg("--flake")
                 .arg(fixture_path(fixture))
                 .arg("--diff")
-                .arg("add-follow")
-                .arg(input)
-                .arg(target)
+                .arg("unfollow")
+                .arg(path)
         );
     });
 }
 
-/// Test the add-follow command for flat-style inputs
+/// `copy` imports an input with its `flake = false` and the follows
+/// whose targets exist here. An existing input needs `--force`, which
+/// only replaces its url. An input without a url cannot be copied.
 #[rstest]
-#[case("one_level_nesting_flat", "rust-overlay.flake-compat", "flake-compat")]
-#[case("mixed_style", "harmonia.nixpkgs", "nixpkgs")]
-#[case("flat_toplevel_no_follows", "crane.nixpkgs", "nixpkgs")]
-#[case("flat_toplevel_no_follows", "fenix.nixpkgs", "nixpkgs")]
-#[case("flat_toplevel_comments", "crane.nixpkgs", "nixpkgs")]
-#[case("flat_toplevel_comments", "fenix.nixpkgs", "nixpkgs")]
-#[case("toplevel_block_nested_follows", "blocky.flake-parts", "flake-parts")]
-fn test_add_follow_flat(#[case] fixture: &str, #[case] input: &str, #[case] target: &str) {
+#[case("crane", false)]
+#[case("vmsh", false)]
+#[case("nixpkgs", false)]
+#[case("nixpkgs", true)]
+#[case("naersk", true)]
+#[case("home-manager", false)]
+#[case("missing", false)]
+fn test_copy(#[case] id: &str, #[case] force: bool) {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
-    let suffix = format!("{fixture}_{}", input.replace('.', "_"));
+    error_filters(&mut settings);
+    stderr_path_filters(&mut settings);
+    let suffix = if force {
+        format!("{id}_force")
+    } else {
+        id.to_string()
+    };
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        let mut cmd = cli();
+        cmd.arg("--flake")
+            .arg(fixture_path("copy_target"))
+            .arg("--diff")
+            .arg("copy")
+            .arg(fixture_path("copy_source"))
+            .arg(id);
+        if force {
+            cmd.arg("--force");
+        }
+        assert_cmd_snapshot!(cmd);
+    });
+}
+
+/// A `parent/child` target follows a nested input and is written in
+/// slash form. An equal existing target is a no-op.
+#[rstest]
+#[case("root", "rust-overlay.nixpkgs", "crane/nixpkgs")]
+#[case("root", "rust-overlay.nixpkgs", "crane.nixpkgs")]
+#[case("follow_slash_syntax", "consumer.child", "parent/child")]
+fn test_add_follow_nested_target(#[case] fixture: &str, #[case] input: &str, #[case] target: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    let suffix = format!(
+        "{fixture}_{}_{}",
+        input.replace('.', "_"),
+        target.replace('.', "_").replace('/', "__")
+    );
     settings.set_snapshot_suffix(suffix);
     settings.bind(|| {
         assert_cmd_snapshot!(
@@ -672,6 +2501,7 @@ fn add_follow_accepts_two_segment_dot_path_unchanged() {
 #[case("follow_slash_syntax")] // slash-form `follows = "parent/child"` is the alias of `parent.child`
 #[case("nested_depths_with_stale")]
 // pins the default-unlimited contract: adds depth-2 and depth-3 follows, removes a stale top-level follow
+#[case("subflake_parent")] // two `?dir=` subflakes of one repo, each with its own nested nixpkgs
 #[case("let_wrapped")] // single input inside a `let ... in` body: follow must descend into the wrapper and report a clean no-op
 fn test_follow(#[case] fixture: &str) {
     let mut settings = insta::Settings::clone_current();
@@ -693,6 +2523,7 @@ fn test_follow(#[case] fixture: &str) {
 /// Test the follow command with a custom config file
 #[rstest]
 #[case("centerpiece", "ignore_treefmt")] // Config ignores treefmt-nix.nixpkgs, only home-manager follows
+#[case("mixed_style", "follow_only")] // Allowlist: only nixpkgs and systems follow, crane/flake-parts/nix/treefmt-nix are left alone
 #[case("treefmt_transitive", "transitive")] // Transitive follows with transitive_min = 2
 #[case("transitive_grandchild", "shallow_follows_1")] // explicit max_depth=1 opts out of writing the depth-2 candidate
 #[case("depth_upstream_redundant", "depth_upstream_redundant")] // upstream propagation makes depth-2 follow redundant; nothing emitted
@@ -737,6 +2568,80 @@ fn test_follow_with_config(#[case] fixture: &str, #[case] config: &str) {
     });
 }
 
+/// `follow --summary` prints one tally in place of the diff and the
+/// per-change listing.
+#[rstest]
+#[case("nested_depths_with_stale", None, &[], "followed: 3, removed stale: 1, promoted: 0\n")]
+#[case(
+    "nested_depths_with_stale",
+    None,
+    &["--output", "json"],
+    "{\"followed\":3,\"removed_stale\":1,\"promoted\":0}\n"
+)]
+#[case(
+    "transitive_promote_unlocks_deeper",
+    Some("transitive_promote_unlocks_deeper"),
+    &[],
+    "followed: 3, removed stale: 0, promoted: 1\n"
+)]
+#[case("root", None, &["--output", "json"], "{\"followed\":0,\"removed_stale\":0,\"promoted\":0}\n")]
+fn test_follow_summary(
+    #[case] fixture: &str,
+    #[case] config: Option<&str>,
+    #[case] args: &[&str],
+    #[case] expected: &str,
+) {
+    let mut cmd = cli();
+    cmd.arg("--flake")
+        .arg(fixture_path(fixture))
+        .arg("--lock-file")
+        .arg(fixture_lock_path(fixture))
+        .arg("--diff")
+        .args(args);
+    if let Some(config) = config {
+        cmd.arg("--config").arg(fixture_config_path(config));
+    }
+    let output = cmd
+        .args(["follow", "--summary"])
+        .output()
+        .expect("run follow");
+    assert!(
+        output.status.success(),
+        "follow failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+/// Writing with `follow --summary` edits the file and still prints only
+/// the tally.
+#[test]
+fn test_follow_summary_writes() {
+    let fixture = "nested_depths_with_stale";
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    fs::copy(fixture_path(fixture), &flake).expect("copy flake.nix");
+    let original = fs::read_to_string(&flake).expect("read flake.nix");
+
+    let output = cli()
+        .arg("--flake")
+        .arg(&flake)
+        .arg("--lock-file")
+        .arg(fixture_lock_path(fixture))
+        .arg("--no-lock")
+        .args(["follow", "--summary"])
+        .output()
+        .expect("run follow");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "followed: 3, removed stale: 1, promoted: 0\n"
+    );
+    let result = fs::read_to_string(&flake).expect("read flake.nix");
+    assert_ne!(result, original);
+    assert!(!result.contains("inputs.gone.follows"), "got:\n{result}");
+}
+
 /// Test the follow command with --transitive flag (overrides config)
 #[rstest]
 #[case("treefmt_transitive", 2)] // Same as config-based test but via CLI flag
@@ -835,6 +2740,25 @@ fn test_follow_with_malformed_config(#[case] fixture: &str, #[case] config: &str
     });
 }
 
+/// A truncated flake.lock surfaces the JSON position it broke at.
+#[test]
+fn test_follow_with_malformed_lock() {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    stderr_path_filters(&mut settings);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("root"))
+                .arg("--lock-file")
+                .arg(fixture_lock_path("truncated_lock"))
+                .arg("--diff")
+                .arg("follow")
+        );
+    });
+}
+
 /// Test that --flake and --lock are incompatible with follow [paths]
 #[test]
 fn test_follow_paths_incompatible_with_flake_flag() {
@@ -898,6 +2822,7 @@ fn test_list_empty_target_follows() {
     let mut settings = insta::Settings::clone_current();
     path_redactions(&mut settings);
     stderr_path_filters(&mut settings);
+    age_filters(&mut settings);
     settings.bind(|| {
         assert_cmd_snapshot!(
             cli()
@@ -1264,6 +3189,55 @@ fn test_pin_with_lock_file(#[case] fixture: &str) {
     });
 }
 
+/// `pin` reads the rev of a `flake = false` input from the lock, and
+/// rejects a `path:` input, which is locked without one.
+#[rstest]
+#[case("vmsh")]
+#[case("store")]
+fn test_pin_non_flake_and_path(#[case] id: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(id);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("non_flake_and_path"))
+                .arg("--lock-file")
+                .arg(fixture_lock_path("non_flake_and_path"))
+                .arg("--diff")
+                .arg("pin")
+                .arg(id)
+        );
+    });
+}
+
+/// `pin --before` only asks github and gitlab for commits, and rejects a
+/// malformed date before reading anything.
+#[rstest]
+#[case("store", "2024-01-01", "path_input")]
+#[case("vmsh", "2024-13-01", "bad_month")]
+#[case("vmsh", "01.01.2024", "bad_format")]
+fn test_pin_before_rejects(#[case] id: &str, #[case] date: &str, #[case] suffix: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("non_flake_and_path"))
+                .arg("--diff")
+                .arg("pin")
+                .arg(id)
+                .arg("--before")
+                .arg(date)
+        );
+    });
+}
+
 /// `toggle` previews with `--diff` like every other subcommand. Covers the
 /// zero-argument inference, the id form, ref forms naming the inactive and
 /// the active variant (flip-back), the `t` alias, and the two-arg form
@@ -1293,6 +3267,149 @@ fn test_toggle_diff(#[case] fixture: &str, #[case] args: &[&str], #[case] suffix
     });
 }
 
+/// `override` stores a commented alternate below the active url and
+/// leaves the active url alone.
+#[rstest]
+#[case("toggle_block", &["override", "rust-overlay", "github:a-kenji/rust-overlay"], "store_block")]
+#[case("toggle_toplevel_flat", &["override", "crane", "git+https://example.org/forks/crane"], "store_trailing_comment")]
+#[case("toggle_flat", &["override", "rust-overlay", "github:a-kenji/rust-overlay"], "already_stored")]
+#[case("toggle_flat", &["override", "missing", "github:a-kenji/rust-overlay"], "unknown_input")]
+fn test_override_diff(#[case] fixture: &str, #[case] args: &[&str], #[case] suffix: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(format!("{fixture}_{suffix}"));
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path(fixture))
+                .arg("--diff")
+                .args(args)
+        );
+    });
+}
+
+/// An override is a plain alternate: `toggle` flips to it and back, and
+/// the second flip restores the file as `override` left it.
+#[test]
+fn override_then_toggle_round_trips() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let flake = tmp.path().join("flake.nix");
+    let content = fs::read_to_string(fixture_path("toggle_block")).expect("read fixture");
+    fs::write(&flake, &content).expect("write flake.nix");
+
+    let run = |args: &[&str]| {
+        let output = cli()
+            .arg("--flake")
+            .arg(&flake)
+            .arg("--no-lock")
+            .args(args)
+            .output()
+            .expect("run flake-edit");
+        assert!(
+            output.status.success(),
+            "{args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr),
+        );
+        fs::read_to_string(&flake).expect("read flake.nix")
+    };
+
+    let stored = run(&["override", "rust-overlay", "github:a-kenji/rust-overlay"]);
+    assert!(stored.contains(r#"      url = "github:oxalica/rust-overlay";"#));
+    assert!(stored.contains(r#"      # url = "github:a-kenji/rust-overlay";"#));
+
+    let toggled = run(&["toggle", "rust-overlay"]);
+    assert!(toggled.contains(r#"      # url = "github:oxalica/rust-overlay";"#));
+    assert!(toggled.contains(r#"      url = "github:a-kenji/rust-overlay";"#));
+
+    let restored = run(&["toggle", "rust-overlay"]);
+    assert_eq!(restored, stored);
+}
+
+/// `[toggle] marker` changes both which comments count as alternates and
+/// the prefix a deactivated url gets.
+#[rstest]
+#[case(&["toggle", "rust-overlay"], "flip")]
+#[case(&["toggle", "nixpkgs", "github:nixos/nixpkgs/nixos-24.05"], "store")]
+#[case(&["toggle", "crane"], "default_marker_ignored")]
+fn test_toggle_custom_marker(#[case] args: &[&str], #[case] suffix: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    error_filters(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("toggle_custom_marker"))
+                .arg("--config")
+                .arg(fixture_config_path("disabled_marker"))
+                .arg("--diff")
+                .args(args)
+        );
+    });
+}
+
+/// With `--sort-params` or `[format] sort_params` every url written gets
+/// its parameters in canonical order, not just the edited one; without
+/// either the other urls are left as they are.
+#[rstest]
+#[case(true, None, "flag")]
+#[case(false, Some("sort_params"), "config")]
+#[case(false, None, "disabled")]
+fn test_sort_params_on_write(
+    #[case] sort_flag: bool,
+    #[case] config: Option<&str>,
+    #[case] suffix: &str,
+) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    let mut cmd = cli();
+    cmd.arg("--flake")
+        .arg(fixture_path("unsorted_params"))
+        .arg("--diff");
+    if sort_flag {
+        cmd.arg("--sort-params");
+    }
+    if let Some(config) = config {
+        cmd.arg("--config").arg(fixture_config_path(config));
+    }
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cmd.arg("add")
+                .arg("vmsh")
+                .arg("github:mic92/vmsh?ref=main&dir=nix")
+        );
+    });
+}
+
+/// `[shortcuts]` expands a leading `<key>:` before the url is parsed, and
+/// a shortcut named after a url scheme is rejected when the config loads.
+#[rstest]
+#[case("shortcuts", &["add", "gh:mic92/vmsh"], "add_infer_id")]
+#[case("shortcuts", &["add", "pkgs", "np:/nixos-24.05"], "add")]
+#[case("shortcuts", &["change", "nixpkgs", "gh:nixos/nixpkgs/nixos-24.05"], "change")]
+#[case("shortcut_shadows_scheme", &["add", "gh:mic92/vmsh"], "shadows_scheme")]
+fn test_shortcuts(#[case] config: &str, #[case] args: &[&str], #[case] suffix: &str) {
+    let mut settings = insta::Settings::clone_current();
+    path_redactions(&mut settings);
+    stderr_path_filters(&mut settings);
+    settings.set_snapshot_suffix(suffix);
+    settings.bind(|| {
+        assert_cmd_snapshot!(
+            cli()
+                .arg("--flake")
+                .arg(fixture_path("root"))
+                .arg("--config")
+                .arg(fixture_config_path(config))
+                .arg("--diff")
+                .args(args)
+        );
+    });
+}
+
 /// The non-interactive error catalogue of `toggle`: every inference
 /// failure names the candidates and points at the explicit two-arg form.
 #[rstest]
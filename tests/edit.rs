@@ -3,7 +3,7 @@
 mod common;
 
 use common::{Info, load_flake};
-use flake_edit::app::commands::list::ListOutput;
+use flake_edit::app::commands::list::{ListOutput, list_entries};
 use flake_edit::change::Change;
 use flake_edit::edit::FlakeEdit;
 use flake_edit::walk::Walker;
@@ -26,6 +26,7 @@ use rstest::rstest;
 #[case("stale_lockfile_only")]
 #[case("follows_empty_target")]
 #[case("nested_url_override")]
+#[case("nested_follows_depth_two")]
 fn test_flake_edit_list(#[case] fixture: &str) {
     let content = load_flake(fixture);
     let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
@@ -205,6 +206,9 @@ fn test_remove_input_walker(#[case] fixture: &str, #[case] input_id: &str) {
 #[case("completely_flat_toplevel", "crane.rust-overlay")]
 #[case("one_level_nesting_flat", "rust-overlay.flake-utils")]
 #[case("deeply_nested_inputs", "disko.nixpkgs")]
+#[case("nested_follows_depth_two", "crane.flake-utils.systems")]
+#[case("nested_follows_depth_two", "naersk.flake-utils.systems")]
+#[case("nested_follows_depth_two", "fenix.rust-analyzer.flake-utils.systems")]
 fn test_remove_nested_input(#[case] fixture: &str, #[case] input_id: &str) {
     let content = load_flake(fixture);
     let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
@@ -297,6 +301,7 @@ fn test_change_url(#[case] fixture: &str, #[case] input_id: &str, #[case] new_ur
     let change = Change::Change {
         id: Some(flake_edit::change::ChangeId::parse(input_id).unwrap()),
         uri: Some(new_url.to_owned()),
+        rename: None,
     };
     let info = Info::with_change(change.clone());
     let result = flake_edit.apply_change(change).unwrap().text.unwrap();
@@ -332,13 +337,150 @@ fn remove_drops_trailing_comment_with_its_statement() {
     assert_eq!(result, expected);
 }
 
+/// `list --json` prints one object per input, sorted by id, with its
+/// follows and the ref or rev parsed from its url.
+#[test]
+fn list_entries_json_shape() {
+    let content = load_flake("list_entries");
+    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    let json = serde_json::to_value(list_entries(flake_edit.list())).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([
+            {
+                "id": "crane",
+                "url": "github:ipetkov/crane",
+                "flake": true,
+                "follows": [{ "name": "nixpkgs", "target": "nixpkgs" }],
+                "ref_or_rev": null,
+                "unresolved": false,
+            },
+            {
+                "id": "nixpkgs",
+                "url": "github:nixos/nixpkgs/nixos-24.05",
+                "flake": true,
+                "follows": [],
+                "ref_or_rev": "nixos-24.05",
+                "unresolved": false,
+            },
+        ])
+    );
+}
+
+/// Changing the url of a non-flake input leaves its `flake = false` in
+/// place, in the flat, attrset and top-level flat layouts.
+#[rstest]
+#[case("flake-utils", "github:numtide/flake-utils")]
+#[case("vmsh", "github:mic92/vmsh/main")]
+#[case("top", "github:a/top2")]
+fn change_keeps_flake_false(#[case] id: &str, #[case] uri: &str) {
+    let content = load_flake("change_flake_false");
+    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    let change = Change::Change {
+        id: Some(flake_edit::change::ChangeId::parse(id).unwrap()),
+        uri: Some(uri.to_owned()),
+        rename: None,
+    };
+    let result = flake_edit.apply_change(change).unwrap().text.unwrap();
+    assert_eq!(
+        result.matches("flake = false;").count(),
+        content.matches("flake = false;").count(),
+        "{result}"
+    );
+    let mut changed = FlakeEdit::from_text(&result).unwrap();
+    assert_eq!(changed.list()[id].url(), uri);
+}
+
+/// `Change::Pin` rewrites the url to the rev, `Change::Unpin` takes the
+/// ref and rev off again, and unpinning an unpinned input is a no-op.
+#[test]
+fn pin_and_unpin_rewrite_the_url() {
+    let content = load_flake("root");
+    let id = || flake_edit::change::ChangeId::parse("nixpkgs").unwrap();
+    let pin = Change::Pin {
+        id: id(),
+        rev: "abc123".to_owned(),
+    };
+    let pinned = FlakeEdit::from_text(&content)
+        .unwrap()
+        .apply_change(pin)
+        .unwrap()
+        .text
+        .unwrap();
+    assert!(
+        pinned.contains(r#"nixpkgs.url = "github:nixos/nixpkgs/abc123";"#),
+        "{pinned}"
+    );
+
+    let unpinned = FlakeEdit::from_text(&pinned)
+        .unwrap()
+        .apply_change(Change::Unpin { id: id() })
+        .unwrap()
+        .text
+        .unwrap();
+    assert!(
+        unpinned.contains(r#"nixpkgs.url = "github:nixos/nixpkgs";"#),
+        "{unpinned}"
+    );
+
+    let outcome = FlakeEdit::from_text(&unpinned)
+        .unwrap()
+        .apply_change(Change::Unpin { id: id() })
+        .unwrap();
+    assert!(outcome.text.is_none());
+}
+
+/// Blank lines split the inputs into groups. Removing the first input of a
+/// group keeps the separator in front of the rest of it, and removing the
+/// only input of the last group drops its separator along with it.
+#[rstest]
+#[case(
+    "treefmt-nix",
+    "flake-parts.url = \"github:hercules-ci/flake-parts\";\n\n    git-hooks.url"
+)]
+#[case(
+    "git-hooks",
+    "treefmt-nix.url = \"github:numtide/treefmt-nix\";\n\n    crane.url"
+)]
+#[case("crane", "git-hooks.url = \"github:cachix/git-hooks.nix\";\n  };")]
+fn remove_keeps_blank_line_between_groups(#[case] id: &str, #[case] expected: &str) {
+    let content = load_flake("grouped_inputs");
+    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    let change = Change::Remove {
+        ids: vec![flake_edit::change::ChangeId::parse(id).unwrap()],
+    };
+    let result = flake_edit.apply_change(change).unwrap().text.unwrap();
+    assert!(result.contains(expected), "{result}");
+}
+
+/// Adding after a single-input group must not open another group.
+#[test]
+fn add_does_not_repeat_blank_line_of_last_group() {
+    let content = load_flake("grouped_inputs");
+    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    let change = Change::Add {
+        id: Some(flake_edit::change::ChangeId::parse("fenix").unwrap()),
+        uri: Some("github:nix-community/fenix".to_owned()),
+        flake: true,
+    };
+    let result = flake_edit.apply_change(change).unwrap().text.unwrap();
+    assert!(
+        result.contains(
+            "crane.url = \"github:ipetkov/crane\";\n    fenix.url = \"github:nix-community/fenix\";\n  };"
+        ),
+        "{result}"
+    );
+}
+
 /// Inserting a follows after the last attribute of a block must land after a
 /// trailing `# comment` on that attribute, leaving the comment on its original
 /// statement rather than reattaching to the new follows line.
 #[test]
 fn add_follow_keeps_trailing_comment_on_sibling_attr() {
     let content = load_flake("trailing_comment_on_sibling_attr");
-    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    let mut flake_edit = FlakeEdit::from_text(&content)
+        .unwrap()
+        .with_dangling_follows(true);
     let change = Change::Follows {
         input: flake_edit::change::ChangeId::parse("dep.nixpkgs").unwrap(),
         target: flake_edit::follows::AttrPath::parse("nixpkgs").unwrap(),
@@ -367,6 +509,7 @@ fn test_change_nonexistent_input_error(#[case] fixture: &str, #[case] input_id:
     let change = Change::Change {
         id: Some(flake_edit::change::ChangeId::parse(input_id).unwrap()),
         uri: Some("github:foo/bar".to_owned()),
+        rename: None,
     };
     let result = flake_edit.apply_change(change);
     assert!(result.is_err());
@@ -387,7 +530,9 @@ fn test_remove_nonexistent_input_panics() {
 #[test]
 fn follows_fills_multiline_empty_inputs_block() {
     let content = load_flake("wrapper_with_empty_inputs_block");
-    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    let mut flake_edit = FlakeEdit::from_text(&content)
+        .unwrap()
+        .with_dangling_follows(true);
     let change = Change::Follows {
         input: flake_edit::change::ChangeId::parse("stylix.systems").unwrap(),
         target: flake_edit::follows::AttrPath::parse("systems").unwrap(),
@@ -414,7 +559,9 @@ fn follows_fills_multiline_empty_inputs_block() {
 #[test]
 fn follows_fills_compact_empty_inputs_block() {
     let content = load_flake("wrapper_with_empty_inputs_block");
-    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    let mut flake_edit = FlakeEdit::from_text(&content)
+        .unwrap()
+        .with_dangling_follows(true);
     let change = Change::Follows {
         input: flake_edit::change::ChangeId::parse("disko.systems").unwrap(),
         target: flake_edit::follows::AttrPath::parse("systems").unwrap(),
@@ -444,7 +591,9 @@ fn follows_fills_empty_block_when_dotted_sibling_present() {
     // left in place. Promoting it would be a structural rewrite outside
     // the scope of an insertion-point decision.
     let content = load_flake("wrapper_with_empty_inputs_block");
-    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    let mut flake_edit = FlakeEdit::from_text(&content)
+        .unwrap()
+        .with_dangling_follows(true);
     let change = Change::Follows {
         input: flake_edit::change::ChangeId::parse("mixed.systems").unwrap(),
         target: flake_edit::follows::AttrPath::parse("systems").unwrap(),
@@ -472,7 +621,9 @@ fn follows_fills_empty_block_when_dotted_sibling_present() {
 #[test]
 fn follows_fill_empty_block_is_idempotent() {
     let content = load_flake("wrapper_with_empty_inputs_block");
-    let mut first = FlakeEdit::from_text(&content).unwrap();
+    let mut first = FlakeEdit::from_text(&content)
+        .unwrap()
+        .with_dangling_follows(true);
     let change = Change::Follows {
         input: flake_edit::change::ChangeId::parse("stylix.systems").unwrap(),
         target: flake_edit::follows::AttrPath::parse("systems").unwrap(),
@@ -483,7 +634,9 @@ fn follows_fill_empty_block_is_idempotent() {
         .text
         .expect("walker must produce changed text");
 
-    let mut second = FlakeEdit::from_text(&after_first).unwrap();
+    let mut second = FlakeEdit::from_text(&after_first)
+        .unwrap()
+        .with_dangling_follows(true);
     let outcome = second
         .apply_change(change)
         .expect("apply Change::Follows must succeed");
@@ -510,6 +663,128 @@ fn list_reports_inputs_in_let_wrapped_flake() {
     );
 }
 
+/// A url bound in a `let` or brought in by `inherit` is not a flake
+/// reference: the input is listed with the expression's source text and
+/// marked unresolved.
+#[rstest]
+#[case("let_bound_url", "nixpkgs", "nixpkgsUrl")]
+#[case("inherited_inputs", "nixpkgs", "sources.nixpkgs")]
+#[case("inherited_inputs", "crane", "sources.crane")]
+#[case("inherited_inputs", "home-manager", "url")]
+fn list_marks_expression_urls_unresolved(
+    #[case] fixture: &str,
+    #[case] id: &str,
+    #[case] expr: &str,
+) {
+    let mut flake_edit = FlakeEdit::from_text(&load_flake(fixture)).unwrap();
+    let inputs = flake_edit.list();
+    let input = &inputs[id];
+    assert!(input.is_unresolved(), "{id} in {fixture}");
+    assert_eq!(input.url(), expr);
+    assert!(!input.has_editable_url());
+    assert!(!inputs["flake-utils"].is_unresolved());
+
+    let entry = list_entries(inputs)
+        .into_iter()
+        .find(|e| e.id == id)
+        .unwrap();
+    assert!(entry.unresolved);
+    assert_eq!(entry.ref_or_rev, None);
+}
+
+/// Rewriting an unresolved url would drop what the expression evaluates
+/// to, so `change` refuses it and leaves the source alone.
+#[rstest]
+#[case("let_bound_url", "nixpkgs")]
+#[case("inherited_inputs", "nixpkgs")]
+#[case("inherited_inputs", "home-manager")]
+fn change_refuses_unresolved_url(#[case] fixture: &str, #[case] id: &str) {
+    let content = load_flake(fixture);
+    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    let change = Change::Change {
+        id: Some(flake_edit::change::ChangeId::parse(id).unwrap()),
+        uri: Some("github:nixos/nixpkgs/nixos-24.05".into()),
+        rename: None,
+    };
+    let err = flake_edit.apply_change(change).unwrap_err();
+    assert!(
+        matches!(err, flake_edit::Error::UnresolvedUrl { id: ref got, .. } if got == id),
+        "{err:?}"
+    );
+    assert!(!flake_edit.is_dirty());
+    assert_eq!(flake_edit.source_text(), content);
+}
+
+/// A rename the outputs lambda refuses also takes back the url rewrite
+/// of the same `change`.
+#[test]
+fn change_with_refused_rename_keeps_the_source() {
+    let content = r#"{
+  inputs.nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+  outputs = { nixpkgs, ... }: { inherit nixpkgs; };
+}
+"#;
+    let mut flake_edit = FlakeEdit::from_text(content).unwrap();
+    let change = Change::Change {
+        id: Some(flake_edit::change::ChangeId::parse("nixpkgs").unwrap()),
+        uri: Some("github:nixos/nixpkgs/nixos-24.05".into()),
+        rename: Some(flake_edit::follows::Segment::from_unquoted("pkgs").unwrap()),
+    };
+    let err = flake_edit.apply_change(change).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            flake_edit::Error::Walker(flake_edit::walk::WalkerError::OutputsRename { .. })
+        ),
+        "{err:?}"
+    );
+    assert!(!flake_edit.is_dirty());
+    assert_eq!(flake_edit.source_text(), content);
+}
+
+/// A url declared both flat and in the `inputs = { ... }` block keeps the
+/// flake from loading; `duplicate_inputs` still finds it in the text.
+#[test]
+fn duplicate_inputs_reports_flat_and_block() {
+    let content = load_flake("duplicate_flat_and_block");
+    assert!(FlakeEdit::from_text(&content).is_err());
+    let duplicates = flake_edit::edit::duplicate_inputs(&content);
+    let lines: Vec<(&str, Vec<usize>)> = duplicates
+        .iter()
+        .map(|(id, locations)| (id.as_str(), locations.iter().map(|l| l.line).collect()))
+        .collect();
+    assert_eq!(lines, [("nixpkgs", vec![2, 4])]);
+}
+
+/// Two declarations in the block load fine, with the last one winning;
+/// `duplicates` counts both.
+#[test]
+fn duplicates_counts_shadowed_declarations() {
+    let content = load_flake("duplicate_in_block");
+    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    assert_eq!(flake_edit.duplicates(), [("nixpkgs".to_string(), 2)]);
+    assert_eq!(
+        flake_edit.list()["nixpkgs"].url(),
+        "github:nixos/nixpkgs/nixos-24.05"
+    );
+}
+
+#[rstest]
+#[case("root")]
+#[case("completely_flat_toplevel")]
+#[case("one_level_nesting_flat")]
+#[case("let_wrapped")]
+fn duplicates_empty_without_duplicates(#[case] fixture: &str) {
+    let content = load_flake(fixture);
+    assert!(
+        FlakeEdit::from_text(&content)
+            .unwrap()
+            .duplicates()
+            .is_empty()
+    );
+    assert!(flake_edit::edit::duplicate_inputs(&content).is_empty());
+}
+
 #[test]
 fn add_reaches_body_of_let_wrapped_flake() {
     // `add` on a let-wrapped flake must reach the body attrset rather
@@ -539,7 +814,9 @@ fn add_reaches_body_of_let_wrapped_flake() {
 #[test]
 fn follows_merges_into_existing_inputs_block() {
     let content = load_flake("inputs_block_with_follows");
-    let mut flake_edit = FlakeEdit::from_text(&content).unwrap();
+    let mut flake_edit = FlakeEdit::from_text(&content)
+        .unwrap()
+        .with_dangling_follows(true);
     let change = Change::Follows {
         input: flake_edit::change::ChangeId::parse("stylix.systems").unwrap(),
         target: flake_edit::follows::AttrPath::parse("systems").unwrap(),
@@ -1015,3 +1292,131 @@ fn toggle_follows_only_input_has_no_url_to_toggle() {
         "expected the no-url error, got: {err}",
     );
 }
+
+/// Apply a `Change::ToggleStore` to `content` and return the outcome text.
+fn apply_toggle_store(content: &str, id: &str, uri: &str) -> Option<String> {
+    let mut flake_edit = FlakeEdit::from_text(content).unwrap();
+    let change = Change::ToggleStore {
+        id: flake_edit::change::ChangeId::parse(id).unwrap(),
+        uri: uri.to_owned(),
+    };
+    flake_edit
+        .apply_change(change)
+        .expect("apply Change::ToggleStore must succeed")
+        .text
+}
+
+#[test]
+fn toggle_store_writes_alternate_below_active_and_keeps_it_active() {
+    let content = load_flake("toggle_toplevel_flat");
+    let stored = apply_toggle_store(&content, "crane", "path:../crane")
+        .expect("storing a new url must change the text");
+    let expected = r#"{
+  # inputs.crane.url = "github:a-kenji/crane";
+  inputs.crane.url = "github:ipetkov/crane"; # build tool
+  # inputs.crane.url = "path:../crane";
+  inputs.nixpkgs.url = "github:nixos/nixpkgs";
+
+  outputs = { self, ... }: { };
+}
+"#;
+    assert_eq!(stored, expected);
+
+    let mut flake_edit = FlakeEdit::from_text(&stored).unwrap();
+    let state = &flake_edit.toggle_states().unwrap()["crane"];
+    assert_eq!(state.active, "github:ipetkov/crane");
+    assert_eq!(
+        state.alternates,
+        vec!["github:a-kenji/crane", "path:../crane"]
+    );
+
+    let flipped = apply_toggle(&stored, "crane", "path:../crane", "github:ipetkov/crane");
+    assert!(flipped.contains("  inputs.crane.url = \"path:../crane\";\n"));
+    let restored = apply_toggle(&flipped, "crane", "github:ipetkov/crane", "path:../crane");
+    assert_eq!(
+        restored, stored,
+        "flipping back must restore the stored override"
+    );
+}
+
+#[test]
+fn toggle_store_known_url_is_a_noop() {
+    let content = load_flake("toggle_flat");
+    for uri in ["github:oxalica/rust-overlay", "github:a-kenji/rust-overlay"] {
+        assert_eq!(
+            apply_toggle_store(&content, "rust-overlay", uri),
+            None,
+            "{uri} is already active or stored",
+        );
+    }
+}
+
+#[test]
+fn dedup_inputs_keeps_the_first_binding() {
+    let content = r#"{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    crane.url = "github:ipetkov/crane";
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-24.05";
+    crane.url = "github:a-kenji/crane";
+  };
+  outputs = { self, ... }: { };
+}
+"#;
+    let expected = r#"{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    crane.url = "github:ipetkov/crane";
+  };
+  outputs = { self, ... }: { };
+}
+"#;
+    assert_eq!(
+        flake_edit::edit::dedup_inputs(content).as_deref(),
+        Some(expected)
+    );
+    assert_eq!(flake_edit::edit::dedup_inputs(expected), None);
+}
+
+#[test]
+fn dedup_inputs_leaves_duplicate_outputs_alone() {
+    let content = r#"{
+  inputs.nixpkgs.url = "github:nixos/nixpkgs";
+  outputs = { self, ... }: { };
+  outputs = { self, ... }: { };
+}
+"#;
+    let duplicates = flake_edit::edit::duplicate_bindings(content);
+    assert_eq!(duplicates.len(), 1);
+    assert!(!duplicates[0].1, "outputs is not an input binding");
+    assert_eq!(flake_edit::edit::dedup_inputs(content), None);
+}
+
+#[test]
+fn input_ranges_map_to_lines_and_columns() {
+    let content = load_flake("root");
+    let rope = ropey::Rope::from_str(&content);
+    let mut fe = FlakeEdit::from_text(&content).unwrap();
+    let inputs = fe.list();
+    let at = |id: &str| inputs[id].range.line_col(&rope);
+    assert_eq!(at("nixpkgs"), (5, 19));
+    assert_eq!(at("flake-utils"), (7, 23));
+    assert_eq!(at("rust-overlay"), (10, 13));
+    assert_eq!(at("crane"), (15, 13));
+}
+
+#[test]
+fn from_path_reads_any_file_name() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("machines.nix");
+    std::fs::write(&path, load_flake("root")).unwrap();
+    let mut fe = FlakeEdit::from_path(&path).unwrap();
+    let mut expected = FlakeEdit::from_text(&load_flake("root")).unwrap();
+    assert_eq!(fe.list(), expected.list());
+
+    let missing = tmp.path().join("flake.nix");
+    assert!(matches!(
+        FlakeEdit::from_path(&missing),
+        Err(flake_edit::Error::Read { path, .. }) if path == missing
+    ));
+}
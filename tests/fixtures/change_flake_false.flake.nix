@@ -0,0 +1,14 @@
+{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs";
+    flake-utils.url = "github:numtide/flake-utils/old";
+    flake-utils.flake = false;
+    vmsh = {
+      url = "github:mic92/vmsh";
+      flake = false;
+    };
+  };
+  inputs.top.url = "github:a/top";
+  inputs.top.flake = false;
+  outputs = { ... }: { };
+}
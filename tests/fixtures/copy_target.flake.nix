@@ -0,0 +1,8 @@
+{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    naersk.url = "github:nix-community/naersk/master";
+  };
+
+  outputs = { nixpkgs, ... }: { };
+}
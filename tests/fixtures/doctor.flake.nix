@@ -0,0 +1,18 @@
+{
+  description = "A flake in need of a doctor";
+
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    crane.url = "github:ipetkov/crane";
+    crane.inputs.flake-utils.follows = "flake-utils";
+    crane.inputs.nixpkgs.follows = "nixpkgs";
+    # Pinned while the unstable channel is broken.
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-24.05";
+    mprisd.url = "git+https://git.example.org/mprisd?shallow=1&ref=main";
+    treefmt-nix.url = "github:numtide/treefmt-nix";
+    treefmt-nix.inputs.nixpkgs.follows = "nixpkgs-unstable";
+    systems.url = "github:nix-systems/default";
+  };
+
+  outputs = { self, nixpkgs, crane, mprisd, treefmt-nix }: { };
+}
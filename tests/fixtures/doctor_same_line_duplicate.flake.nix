@@ -0,0 +1,5 @@
+{
+  inputs.nixpkgs.url = "github:nixos/nixpkgs"; inputs.nixpkgs.url = "github:nixos/nixpkgs/nixos-24.05";
+  inputs.utils.url = "github:numtide/flake-utils?ref=main&dir=lib";
+  outputs = { self, nixpkgs }: { };
+}
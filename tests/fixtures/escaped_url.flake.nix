@@ -0,0 +1,8 @@
+{
+  inputs = {
+    nixpkgs.url = "github:nixos/nix\pkgs/nixos-unstable";
+    templates.url = "git+https://example.com/templates?dir=\${name}";
+  };
+
+  outputs = { nixpkgs, ... }: { };
+}
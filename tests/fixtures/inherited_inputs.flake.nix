@@ -0,0 +1,14 @@
+let
+  sources = import ./sources.nix;
+  url = "github:nix-community/home-manager";
+in {
+  inputs = {
+    inherit (sources) nixpkgs crane;
+    home-manager = {
+      inherit url;
+      inputs.nixpkgs.follows = "nixpkgs";
+    };
+    flake-utils.url = "github:numtide/flake-utils";
+  };
+  outputs = { self, nixpkgs, crane, home-manager, flake-utils }: { };
+}
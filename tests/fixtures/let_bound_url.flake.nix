@@ -0,0 +1,7 @@
+let
+  nixpkgsUrl = "github:nixos/nixpkgs/nixos-unstable";
+in {
+  inputs.nixpkgs.url = nixpkgsUrl;
+  inputs.flake-utils.url = "github:numtide/flake-utils";
+  outputs = { self, nixpkgs, flake-utils }: { };
+}
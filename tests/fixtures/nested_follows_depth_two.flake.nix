@@ -0,0 +1,34 @@
+{
+  description = "Follows two levels below the owning input";
+
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    systems.url = "github:nix-systems/default";
+
+    crane = {
+      url = "github:ipetkov/crane";
+      inputs.nixpkgs.follows = "nixpkgs";
+      inputs.flake-utils.inputs.systems.follows = "systems";
+    };
+
+    naersk = {
+      url = "github:nix-community/naersk";
+      inputs = {
+        nixpkgs.follows = "nixpkgs";
+        flake-utils = {
+          inputs.systems.follows = "systems";
+          inputs.nixpkgs.follows = "nixpkgs";
+        };
+      };
+    };
+
+    fenix = {
+      url = "github:nix-community/fenix";
+      inputs.rust-analyzer = {
+        inputs.flake-utils.inputs.systems.follows = "systems";
+      };
+    };
+  };
+
+  outputs = _: { };
+}
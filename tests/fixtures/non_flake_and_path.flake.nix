@@ -0,0 +1,18 @@
+{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    vmsh = {
+      url = "github:mic92/vmsh";
+      flake = false;
+    };
+    store = {
+      url = "path:/srv/data";
+      flake = false;
+    };
+    local = {
+      url = "path:./sub";
+      inputs.nixpkgs.follows = "nixpkgs";
+    };
+  };
+  outputs = { ... }: { };
+}
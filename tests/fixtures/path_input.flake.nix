@@ -0,0 +1,10 @@
+{
+  description = "A github input next to a local path input";
+
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-23.05";
+    local.url = "path:/srv/flakes/local";
+  };
+
+  outputs = { self, nixpkgs, local }: { };
+}
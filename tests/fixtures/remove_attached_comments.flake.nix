@@ -0,0 +1,21 @@
+{
+  description = "Inputs carrying comments attached to their lines";
+
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    # Pinned until the 0.21 api settles.
+    crane.url = "github:ipetkov/crane/v0.20.0";
+    flake-utils.url = "github:numtide/flake-utils"; # only for eachDefaultSystem
+    rust-overlay.url = "github:oxalica/rust-overlay";
+  };
+
+  outputs =
+    {
+      self,
+      nixpkgs,
+      crane,
+      flake-utils,
+      rust-overlay,
+    }:
+    { };
+}
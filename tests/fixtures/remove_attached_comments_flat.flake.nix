@@ -0,0 +1,11 @@
+{
+  description = "Flat inputs carrying comments attached to their lines";
+
+  inputs.nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+  # Pinned until the 0.21 api settles.
+  inputs.crane.url = "github:ipetkov/crane/v0.20.0";
+  inputs.flake-utils.url = "github:numtide/flake-utils"; # only for eachDefaultSystem
+  inputs.rust-overlay.url = "github:oxalica/rust-overlay";
+
+  outputs = _: { };
+}
@@ -0,0 +1,15 @@
+{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+  };
+
+  outputs =
+    { self, nixpkgs }:
+    let
+      lib = nixpkgs.lib;
+    in
+    {
+      overlays.default = final: prev: { nixpkgs = prev; };
+      check = lib.id (nixpkgs: nixpkgs) self;
+    };
+}
@@ -0,0 +1,21 @@
+{
+  description = "outputs that use their inputs";
+
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    flake-utils.url = "github:numtide/flake-utils";
+  };
+
+  outputs =
+    { self, nixpkgs, flake-utils }@inputs:
+    flake-utils.lib.eachDefaultSystem (
+      system:
+      let
+        pkgs = nixpkgs.legacyPackages.${system};
+      in
+      {
+        packages.default = pkgs.hello;
+        formatter = inputs.nixpkgs.legacyPackages.${system}.nixfmt-rfc-style;
+      }
+    );
+}
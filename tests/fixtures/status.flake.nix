@@ -0,0 +1,12 @@
+{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    flake-utils.url = "github:numtide/flake-utils";
+    mprisd = {
+      url = "git+https://git.example.org/mprisd?ref=main";
+      flake = false;
+    };
+  };
+
+  outputs = { self, nixpkgs, flake-utils, mprisd }: { };
+}
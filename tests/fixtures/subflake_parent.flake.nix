@@ -0,0 +1,18 @@
+{
+  description = "Two subflakes of one repository, referenced via ?dir=";
+
+  inputs = {
+    nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+    tools.url = "github:acme/mono?dir=tools";
+    docs.url = "github:acme/mono?dir=docs";
+  };
+
+  outputs =
+    {
+      self,
+      nixpkgs,
+      tools,
+      docs,
+    }:
+    { };
+}
@@ -0,0 +1,11 @@
+{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    ## DISABLED: rust-overlay.url = "github:a-kenji/rust-overlay";
+    rust-overlay.url = "github:oxalica/rust-overlay";
+    rust-overlay.inputs.nixpkgs.follows = "nixpkgs";
+    # crane.url = "github:a-kenji/crane";
+    crane.url = "github:ipetkov/crane";
+  };
+  outputs = args: import ./nix args;
+}
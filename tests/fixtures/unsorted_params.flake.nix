@@ -0,0 +1,12 @@
+{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs/nixos-unstable";
+    mprisd.url = "git+https://git.example.org/mprisd?shallow=1&ref=main";
+    lib = {
+      url = "github:nix-community/lib?ref=main&dir=lib";
+      flake = false;
+    };
+  };
+
+  outputs = { self, nixpkgs, mprisd, lib }: { };
+}
@@ -0,0 +1,8 @@
+{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs";
+    crane.url = "github:ipetkov/crane";
+    crane.inputs.nixpkgs.follows = "nixpkgs-stable";
+  };
+  outputs = { ... }: { };
+}
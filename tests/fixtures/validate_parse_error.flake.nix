@@ -0,0 +1,6 @@
+{
+  inputs = {
+    nixpkgs.url = "github:nixos/nixpkgs"
+  };
+  outputs = _: { };
+}
@@ -88,6 +88,7 @@ fn run_follow(flake: &Path, lock: &Path, config: Option<&str>, label: &str) {
     Some("prune_empty_intermediate_inputs")
 )] // empty intermediate `inputs = { ... }` block prune must converge
 #[case("follow_dotted_input_promotion", None)]
+#[case("subflake_parent", None)] // `?dir=` subflake parents keep their own nested follows
 fn follow_is_idempotent(#[case] fixture: &str, #[case] config: Option<&str>) {
     assert_idempotent(fixture, config);
 }
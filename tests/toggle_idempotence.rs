@@ -255,7 +255,10 @@ fn remove_refreshes_lock_only_when_the_source_changes() {
     let stdout = run_remove(&flake, "github:a-kenji/rust-overlay", "remove alternate");
     assert_eq!(
         stdout.lines().collect::<Vec<_>>(),
-        vec!["Removed rust-overlay alternate: github:a-kenji/rust-overlay"],
+        vec![
+            "flake.nix | +0 -1",
+            "Removed rust-overlay alternate: github:a-kenji/rust-overlay",
+        ],
         "a comment-only removal must not refresh the lock",
     );
 
@@ -264,6 +267,7 @@ fn remove_refreshes_lock_only_when_the_source_changes() {
     assert_eq!(
         stdout.lines().collect::<Vec<_>>(),
         vec![
+            "flake.nix | +1 -2",
             "Updated flake.lock",
             "Toggled rust-overlay: github:oxalica/rust-overlay -> github:a-kenji/rust-overlay",
             "Removed rust-overlay alternate: github:oxalica/rust-overlay",
@@ -307,6 +311,7 @@ fn lock_line_prints_before_success_line() {
     assert_eq!(
         lines,
         vec![
+            "flake.nix | +2 -2",
             "Updated flake.lock",
             "Toggled rust-overlay: github:oxalica/rust-overlay -> github:a-kenji/rust-overlay",
         ],
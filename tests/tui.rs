@@ -17,7 +17,7 @@ use std::path::PathBuf;
 
 use flake_edit::cli::CliArgs;
 use flake_edit::edit::FlakeEdit;
-use flake_edit::tui::app::UpdateResult;
+use flake_edit::tui::app::{Screen, UpdateResult};
 use flake_edit::tui::{App, CacheConfig};
 use ratatui::{Terminal, backend::TestBackend, widgets::Widget};
 use rstest::rstest;
@@ -244,6 +244,56 @@ fn test_update_interactive_creates_app(#[case] fixture_name: &str) {
     assert!(app.is_some());
 }
 
+/// Absolute indices selected on the app's initial list screen
+fn initial_selected(app: &App) -> Vec<usize> {
+    let Screen::List(screen) = app.screen() else {
+        panic!("expected a list screen");
+    };
+    screen.state.selected_indices()
+}
+
+#[rstest]
+#[case("update", "")]
+#[case("update", "--select-none")]
+#[case("rm", "")]
+#[case("rm", "--select-none")]
+fn test_multi_select_starts_empty(#[case] command: &str, #[case] flag: &str) {
+    let fixture = Fixture::load("root");
+    let app = app_from_args_with_fixture(&format!("{command} {flag}"), &fixture).unwrap();
+    assert!(initial_selected(&app).is_empty());
+}
+
+#[rstest]
+#[case("update")]
+#[case("rm")]
+fn test_multi_select_select_all_preseeds_every_item(#[case] command: &str) {
+    let fixture = Fixture::load("root");
+    let app = app_from_args_with_fixture(&format!("{command} --select-all"), &fixture).unwrap();
+    let expected: Vec<usize> = (0..fixture.inputs.len()).collect();
+    assert_eq!(initial_selected(&app), expected);
+}
+
+/// `update --select` names its inputs up front, so no picker opens.
+#[test]
+fn test_update_select_skips_picker() {
+    let fixture = Fixture::load("root");
+    assert!(app_from_args_with_fixture("update --select nixpkgs,crane", &fixture).is_none());
+}
+
+#[rstest]
+#[case(&["update", "nixpkgs", "--select", "crane"])]
+#[case(&["update", "--select", "crane", "--select-all"])]
+fn test_update_select_conflicts(#[case] args: &[&str]) {
+    let args = std::iter::once("flake-edit").chain(args.iter().copied());
+    assert!(CliArgs::try_parse_from(args).is_err());
+}
+
+#[test]
+fn test_select_all_conflicts_with_select_none() {
+    let result = CliArgs::try_parse_from(["flake-edit", "update", "--select-all", "--select-none"]);
+    assert!(result.is_err());
+}
+
 #[rstest]
 #[case("root")]
 fn test_list_no_app(#[case] fixture_name: &str) {
@@ -431,6 +481,58 @@ fn test_add_workflow_with_diff_shows_confirm(#[case] fixture_name: &str) {
     });
 }
 
+/// Test that the preview names what an incomplete URI is missing
+#[rstest]
+#[case("github:", "user/repo")]
+#[case("github:user", "/repo")]
+#[case("github:user/", "repo")]
+fn test_add_uri_preview_lists_missing_parts(#[case] uri: &str, #[case] rest: &str) {
+    let fixture = Fixture::load("root");
+    let app = app_from_args_with_fixture("add", &fixture).unwrap();
+    let mut session = TestSession::new(app, "add");
+
+    session.type_text(uri);
+    insta::with_settings!({
+        snapshot_suffix => uri.replace([':', '/'], "_"),
+        description => session.description()
+    }, {
+        insta::assert_snapshot!(session.app().pending_diff());
+    });
+
+    session.type_text(rest);
+    assert!(
+        !session.app().pending_diff().contains("missing"),
+        "a complete URI previews its diff"
+    );
+}
+
+/// The preview follows the URI as it is typed, under the inferred id,
+/// and the viewport grows to make room for it.
+#[rstest]
+#[case("root")]
+fn test_add_uri_preview_updates_per_keystroke(#[case] fixture_name: &str) {
+    let fixture = Fixture::load(fixture_name);
+    let app = app_from_args_with_fixture("add", &fixture).unwrap();
+    let mut session = TestSession::new(app, "add");
+    session.ctrl('d');
+    let input_height = session.app().terminal_height();
+
+    session.type_text("github:user/my-new-input");
+    let diff = session.app().pending_diff();
+    assert!(
+        diff.contains("+    my-new-input.url = \"github:user/my-new-input\";"),
+        "{diff}"
+    );
+    assert!(session.app().terminal_height() > input_height);
+
+    session.backspace();
+    let diff = session.app().pending_diff();
+    assert!(
+        diff.contains("+    my-new-inpu.url = \"github:user/my-new-inpu\";"),
+        "{diff}"
+    );
+}
+
 /// Test that diff preview shows actual changes during Add workflow input
 #[rstest]
 #[case("root")]
@@ -785,6 +887,55 @@ fn test_input_screen_slash_is_literal(#[case] fixture_name: &str) {
     }
 }
 
+#[rstest]
+#[case("root")]
+fn test_help_overlay_keeps_list_state(#[case] fixture_name: &str) {
+    let fixture = Fixture::load(fixture_name);
+    let app = app_from_args_with_fixture("update", &fixture).unwrap();
+    let mut session = TestSession::new(app, "update");
+
+    session.nav_down();
+    session.toggle_select();
+    session.press('?');
+    assert!(session.app().help_visible());
+    let mut terminal = create_test_terminal(80, session.app().terminal_height());
+    insta::with_settings!({
+        snapshot_suffix => fixture_name,
+        description => session.description()
+    }, {
+        insta::assert_snapshot!(snapshot(&mut terminal, session.app()));
+    });
+
+    // The closing key is swallowed: `j` does not move the cursor.
+    session.press('j');
+    assert!(!session.app().help_visible());
+    let Screen::List(screen) = session.app().screen() else {
+        panic!("expected a list screen");
+    };
+    assert_eq!(screen.state.cursor(), 2);
+    assert_eq!(screen.state.selected_indices(), vec![1]);
+}
+
+#[rstest]
+#[case("root")]
+fn test_help_key_on_input_screen(#[case] fixture_name: &str) {
+    let fixture = Fixture::load(fixture_name);
+    let app = app_from_args_with_fixture("add", &fixture).unwrap();
+    let mut session = TestSession::new(app, "add");
+
+    // `?` is part of a url here, so only F1 opens the help.
+    session.type_text("github:a/b?");
+    assert!(!session.app().help_visible());
+    session.app.update(key(KeyCode::F(1)));
+    assert!(session.app().help_visible());
+    session.app.update(key(KeyCode::Esc));
+    assert!(!session.app().help_visible());
+    let Screen::Input(screen) = session.app().screen() else {
+        panic!("expected an input screen");
+    };
+    assert_eq!(screen.state.text(), "github:a/b?");
+}
+
 #[rstest]
 #[case("root")]
 fn test_multi_select_toggle(#[case] fixture_name: &str) {
@@ -1,5 +1,5 @@
 use flake_edit::edit::FlakeEdit;
-use flake_edit::forge::update::Updater;
+use flake_edit::forge::update::{RefUpdateError, Updater};
 use ropey::Rope;
 
 fn flake_with_pins() -> String {
@@ -266,3 +266,87 @@ fn pin_follows_before_url() {
 
     insta::assert_snapshot!(updater.get_changes());
 }
+
+#[test]
+fn update_input_to_ref_moves_a_github_branch() {
+    let flake = flake_with_pins();
+    let mut flake_edit = FlakeEdit::from_text(&flake).unwrap();
+    let inputs = flake_edit.list().clone();
+    let mut updater = Updater::new(Rope::from_str(&flake), inputs);
+
+    assert!(
+        updater
+            .update_input_to_ref("nixpkgs", "nixos-24.05", false)
+            .expect("a branch-tracking input takes a new ref")
+    );
+    assert_eq!(
+        updater.get_changes(),
+        flake.replace("?ref=nixos-unstable", "?ref=nixos-24.05")
+    );
+
+    let mut updater = Updater::new(Rope::from_str(&flake), flake_edit.list().clone());
+    assert!(
+        !updater
+            .update_input_to_ref("nixpkgs", "nixos-unstable", false)
+            .unwrap(),
+        "moving to the current ref is not a change"
+    );
+    assert_eq!(updater.get_changes(), flake);
+}
+
+#[test]
+fn update_input_to_ref_keeps_a_rev_pin_unless_forced() {
+    let flake = flake_with_pins();
+    let mut flake_edit = FlakeEdit::from_text(&flake).unwrap();
+    let inputs = flake_edit.list().clone();
+    let mut updater = Updater::new(Rope::from_str(&flake), inputs);
+
+    let err = updater
+        .update_input_to_ref("flake-utils", "main", false)
+        .unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            RefUpdateError::PinnedToRev { id, rev }
+                if id == "flake-utils" && rev == "0123456789abcdef0123456789abcdef01234567"
+        ),
+        "{err:?}"
+    );
+    assert_eq!(updater.get_changes(), flake);
+
+    assert!(
+        updater
+            .update_input_to_ref("flake-utils", "main", true)
+            .unwrap()
+    );
+    assert_eq!(
+        updater.get_changes(),
+        flake.replace("?rev=0123456789abcdef0123456789abcdef01234567", "?ref=main")
+    );
+}
+
+#[test]
+fn update_input_to_ref_rejects_inputs_without_a_ref_slot() {
+    let flake = r#"{
+  inputs.local.url = "path:./sub";
+  inputs.vmsh.url = "git+https://github.com/mic92/vmsh";
+  outputs = { self, local, vmsh }: { };
+}
+"#
+    .to_string();
+    let mut flake_edit = FlakeEdit::from_text(&flake).unwrap();
+    let inputs = flake_edit.list().clone();
+    let mut updater = Updater::new(Rope::from_str(&flake), inputs);
+
+    for id in ["local", "vmsh"] {
+        assert!(matches!(
+            updater.update_input_to_ref(id, "main", true),
+            Err(RefUpdateError::Unsupported { .. })
+        ));
+    }
+    assert!(matches!(
+        updater.update_input_to_ref("missing", "main", true),
+        Err(RefUpdateError::UnknownInput(id)) if id == "missing"
+    ));
+    assert_eq!(updater.get_changes(), flake);
+}